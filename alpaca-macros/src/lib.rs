@@ -0,0 +1,527 @@
+//! Procedural macros for `alpaca-rs`.
+//!
+//! See `alpaca_rs::tool_derive` for the runtime side of `#[alpaca_tool]`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Expr, ExprLit, FnArg, GenericArgument, ItemFn, Lit, LitStr, Meta, Pat,
+    PathArguments, ReturnType, Type, parse_macro_input,
+};
+
+// ===
+// alpaca_tool
+// ===
+/// Turns a plain Rust function into an Alpaca tool.
+///
+/// For a function `read_file`, this generates a `ReadFileTool` type that:
+///
+/// * implements `AlpacaFunction` and `AlpacaActionTrait`,
+/// * deserializes its arguments into the function's parameters,
+/// * provides `definition()` and `tool_proto()` built from the signature,
+/// * provides `register_function()` and `register_action()` helpers.
+///
+/// The first paragraph of the doc comment becomes the short description, and
+/// lines of the form ``* `name` - text`` describe the parameters. The tool name
+/// defaults to the function name and can be changed with `#[alpaca_tool(name = "...")]`.
+///
+/// Parameters must be owned types that implement `serde::Deserialize`, and the
+/// return type must implement `serde::Serialize`. A `Result<T, E>` return type
+/// reports `E` to the model with its `Display` implementation.
+#[proc_macro_attribute]
+pub fn alpaca_tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut tool_name: Option<LitStr> = None;
+    let attr_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            tool_name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported alpaca_tool attribute, expected `name = \"...\"`"))
+        }
+    });
+    parse_macro_input!(attr with attr_parser);
+
+    let function = parse_macro_input!(item as ItemFn);
+    match expand(tool_name, function) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+// ---
+
+/// A function parameter, as seen by the model.
+struct ToolParam {
+    ident: syn::Ident,
+    ty: Type,
+    description: Option<String>,
+    kind: ParamKind,
+    optional: bool,
+}
+
+/// The JSON type of a parameter, derived from its Rust type.
+enum ParamKind {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    Object,
+    Array(Box<ParamKind>),
+}
+
+impl ParamKind {
+    fn type_name(&self) -> &'static str {
+        match self {
+            ParamKind::String => "string",
+            ParamKind::Integer => "integer",
+            ParamKind::Float => "float",
+            ParamKind::Boolean => "boolean",
+            ParamKind::Object => "object",
+            ParamKind::Array(_) => "array",
+        }
+    }
+
+    fn example(&self) -> String {
+        match self {
+            ParamKind::String => "\"<string>\"".to_string(),
+            ParamKind::Integer => "0".to_string(),
+            ParamKind::Float => "0.0".to_string(),
+            ParamKind::Boolean => "true".to_string(),
+            ParamKind::Object => "{}".to_string(),
+            ParamKind::Array(items) => format!("[{}]", items.example()),
+        }
+    }
+
+    fn schema_tokens(&self) -> TokenStream2 {
+        let schema = quote!(::alpaca_rs::tool_model::ToolParameterSchema);
+        let kind = quote!(::alpaca_rs::tool_proto::AlpacaToolParameterType);
+        match self {
+            ParamKind::String => quote!(#schema::new(#kind::String)),
+            ParamKind::Integer => quote!(#schema::new(#kind::Integer)),
+            ParamKind::Float => quote!(#schema::new(#kind::Float)),
+            ParamKind::Boolean => quote!(#schema::new(#kind::Boolean)),
+            ParamKind::Object => quote!(#schema::new(#kind::Object)),
+            ParamKind::Array(items) => {
+                let items = items.schema_tokens();
+                quote!(#schema::array(#items))
+            }
+        }
+    }
+}
+
+fn expand(tool_name: Option<LitStr>, function: ItemFn) -> syn::Result<TokenStream2> {
+    let signature = &function.sig;
+
+    if signature.asyncness.is_some() {
+        return Err(syn::Error::new(
+            signature.asyncness.span(),
+            "alpaca_tool does not support async functions",
+        ));
+    }
+
+    if !signature.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            signature.generics.span(),
+            "alpaca_tool does not support generic functions",
+        ));
+    }
+
+    let fn_ident = &signature.ident;
+    let name = tool_name
+        .map(|name| name.value())
+        .unwrap_or_else(|| fn_ident.to_string());
+
+    let docs = doc_lines(&function.attrs);
+    let description = first_paragraph(&docs);
+    let body = doc_body(&docs);
+
+    let mut params = Vec::new();
+    for input in &signature.inputs {
+        let FnArg::Typed(typed) = input else {
+            return Err(syn::Error::new(
+                input.span(),
+                "alpaca_tool cannot be used on methods",
+            ));
+        };
+
+        let Pat::Ident(pat) = typed.pat.as_ref() else {
+            return Err(syn::Error::new(
+                typed.pat.span(),
+                "alpaca_tool parameters must be plain identifiers",
+            ));
+        };
+
+        let ident = pat.ident.clone();
+        let (kind, optional) = param_kind(&typed.ty)?;
+        let description = param_description(&docs, &ident.to_string());
+
+        params.push(ToolParam {
+            ident,
+            ty: (*typed.ty).clone(),
+            description,
+            kind,
+            optional,
+        });
+    }
+
+    let info = function_info(&name, &body, &params);
+    let action_description = action_description(&name, &body, &params);
+
+    let vis = &function.vis;
+    let tool_ident = format_ident!("{}Tool", pascal_case(&fn_ident.to_string()));
+    let args_ident = format_ident!("__{}Args", tool_ident);
+    let struct_doc = format!("Tool generated by `#[alpaca_tool]` from `{}`.", fn_ident);
+
+    let field_idents: Vec<_> = params.iter().map(|param| &param.ident).collect();
+    let field_types: Vec<_> = params.iter().map(|param| &param.ty).collect();
+    let parameter_tokens: Vec<_> = params
+        .iter()
+        .map(|param| {
+            let param_name = param.ident.to_string();
+            let mut schema = param.kind.schema_tokens();
+            if let Some(description) = &param.description {
+                schema = quote!(#schema.description(#description));
+            }
+            if param.optional {
+                schema = quote!(#schema.optional());
+            }
+            quote!(.parameter(#param_name, #schema))
+        })
+        .collect();
+
+    let description_tokens = if description.is_empty() {
+        quote!()
+    } else {
+        quote!(.description(#description))
+    };
+
+    let call = quote!(#fn_ident(#(args.#field_idents),*));
+    let output = if returns_result(&signature.output) {
+        quote! {
+            let output = #call.map_err(|e| ::alpaca_rs::tool_derive::ToolFailure::Execution(e.to_string()))?;
+        }
+    } else {
+        quote! {
+            let output = #call;
+        }
+    };
+
+    Ok(quote! {
+        #function
+
+        #[derive(::alpaca_rs::tool_derive::__private::serde::Deserialize)]
+        #[serde(crate = "::alpaca_rs::tool_derive::__private::serde")]
+        struct #args_ident {
+            #(#field_idents: #field_types,)*
+        }
+
+        #[doc = #struct_doc]
+        #[derive(Default)]
+        #vis struct #tool_ident;
+
+        impl #tool_ident {
+            /// Creates a new instance of the tool.
+            pub fn new() -> Self {
+                #tool_ident
+            }
+
+            /// Returns the typed definition of the tool, derived from the function signature.
+            pub fn definition() -> ::alpaca_rs::tool_model::ToolDefinition {
+                ::alpaca_rs::tool_model::ToolDefinition::builder(#name)
+                    #description_tokens
+                    #(#parameter_tokens)*
+                    .build()
+            }
+
+            /// Returns the tool prototype, derived from the function signature.
+            pub fn tool_proto() -> ::alpaca_rs::tool_proto::AlpacaToolProto {
+                ::alpaca_rs::tool_proto::AlpacaToolProto::from(&Self::definition())
+            }
+
+            /// Adds the tool to a collection of functions.
+            pub fn register_function(functions: &mut ::alpaca_rs::function::AlpacaFunctions) {
+                functions.add_function(::std::boxed::Box::new(Self::new()));
+            }
+
+            /// Adds the tool to a collection of actions.
+            pub fn register_action(actions: &mut ::alpaca_rs::action::AlpacaActions) {
+                actions.add_action(::std::boxed::Box::new(Self::new()));
+            }
+
+            fn run(
+                arguments: ::std::option::Option<&::alpaca_rs::tool_derive::__private::serde_json::Value>,
+            ) -> ::std::result::Result<
+                ::alpaca_rs::tool_derive::__private::serde_json::Value,
+                ::alpaca_rs::tool_derive::ToolFailure,
+            > {
+                let args: #args_ident = ::alpaca_rs::tool_derive::parse_arguments(arguments)
+                    .map_err(::alpaca_rs::tool_derive::ToolFailure::Arguments)?;
+                #output
+                ::alpaca_rs::tool_derive::to_output(output)
+                    .map_err(::alpaca_rs::tool_derive::ToolFailure::Execution)
+            }
+        }
+
+        impl ::alpaca_rs::function::AlpacaFunction for #tool_ident {
+            fn execute(
+                &self,
+                arguments: ::std::option::Option<&::alpaca_rs::tool_derive::__private::serde_json::Value>,
+            ) -> ::std::option::Option<::std::string::String> {
+                ::std::option::Option::Some(::alpaca_rs::tool_derive::function_response(
+                    #name,
+                    #info,
+                    Self::run(arguments),
+                ))
+            }
+
+            fn info(&self) -> &str {
+                #info
+            }
+
+            fn name(&self) -> &str {
+                #name
+            }
+
+            fn description(&self) -> &str {
+                #description
+            }
+        }
+
+        impl ::alpaca_rs::action::AlpacaActionTrait for #tool_ident {
+            fn name(&self) -> &str {
+                #name
+            }
+
+            fn description(&self) -> &str {
+                #action_description
+            }
+
+            fn invoke(
+                &self,
+                object: &::alpaca_rs::tool_derive::__private::serde_json::Value,
+                _context: &::alpaca_rs::action::AlpacaActions,
+            ) -> ::std::string::String {
+                ::alpaca_rs::tool_derive::action_response(
+                    #action_description,
+                    Self::run(::std::option::Option::Some(object)),
+                )
+            }
+        }
+    })
+}
+
+// ---
+
+fn doc_lines(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(text),
+                    ..
+                }) => Some(text.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| {
+            line.strip_prefix(' ')
+                .unwrap_or(&line)
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+fn first_paragraph(docs: &[String]) -> String {
+    docs.iter()
+        .skip_while(|line| line.trim().is_empty())
+        .take_while(|line| !line.trim().is_empty())
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The doc comment without the `# Arguments` and `# Returns` sections, which are
+/// replaced by the generated parameter list.
+fn doc_body(docs: &[String]) -> String {
+    let mut body = Vec::new();
+    let mut skipping = false;
+
+    for line in docs {
+        if let Some(heading) = line.strip_prefix("# ") {
+            skipping = matches!(heading.trim(), "Arguments" | "Returns");
+            if skipping {
+                continue;
+            }
+        }
+
+        if !skipping {
+            body.push(line.as_str());
+        }
+    }
+
+    body.join("\n").trim().to_string()
+}
+
+fn param_description(docs: &[String], name: &str) -> Option<String> {
+    let prefix = format!("* `{}` -", name);
+    docs.iter()
+        .find_map(|line| line.trim().strip_prefix(&prefix))
+        .map(|text| text.trim().to_string())
+}
+
+fn param_kind(ty: &Type) -> syn::Result<(ParamKind, bool)> {
+    match ty {
+        Type::Reference(_) => Err(syn::Error::new(
+            ty.span(),
+            "alpaca_tool parameters must be owned types, for example `String` instead of `&str`",
+        )),
+        Type::Array(array) => Ok((
+            ParamKind::Array(Box::new(param_kind(&array.elem)?.0)),
+            false,
+        )),
+        Type::Path(path) => {
+            let Some(segment) = path.path.segments.last() else {
+                return Ok((ParamKind::Object, false));
+            };
+
+            let kind = match segment.ident.to_string().as_str() {
+                "Option" => {
+                    let inner = generic_argument(&segment.arguments)
+                        .ok_or_else(|| syn::Error::new(ty.span(), "expected `Option<T>`"))?;
+                    return Ok((param_kind(inner)?.0, true));
+                }
+                "String" | "char" | "PathBuf" => ParamKind::String,
+                "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+                | "u128" | "usize" => ParamKind::Integer,
+                "f32" | "f64" => ParamKind::Float,
+                "bool" => ParamKind::Boolean,
+                "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => {
+                    let items = match generic_argument(&segment.arguments) {
+                        Some(inner) => param_kind(inner)?.0,
+                        None => ParamKind::Object,
+                    };
+                    ParamKind::Array(Box::new(items))
+                }
+                _ => ParamKind::Object,
+            };
+
+            Ok((kind, false))
+        }
+        _ => Ok((ParamKind::Object, false)),
+    }
+}
+
+fn generic_argument(arguments: &PathArguments) -> Option<&Type> {
+    let PathArguments::AngleBracketed(arguments) = arguments else {
+        return None;
+    };
+
+    arguments.args.iter().find_map(|argument| match argument {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+fn returns_result(output: &ReturnType) -> bool {
+    match output {
+        ReturnType::Type(_, ty) => match ty.as_ref() {
+            Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Result"),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    }
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn parameter_list(params: &[ToolParam]) -> String {
+    params
+        .iter()
+        .map(|param| {
+            let optional = if param.optional { ", optional" } else { "" };
+            let description = param
+                .description
+                .as_ref()
+                .map(|text| format!(": {}", text))
+                .unwrap_or_default();
+            format!(
+                "- `{}` ({}{}){}\n",
+                param.ident,
+                param.kind.type_name(),
+                optional,
+                description
+            )
+        })
+        .collect()
+}
+
+fn example_fields(params: &[ToolParam], indent: &str) -> Vec<String> {
+    params
+        .iter()
+        .map(|param| format!("{}\"{}\": {}", indent, param.ident, param.kind.example()))
+        .collect()
+}
+
+fn with_parameters(body: &str, params: &[ToolParam]) -> String {
+    let mut text = String::new();
+    if !body.is_empty() {
+        text.push_str(body);
+        text.push_str("\n\n");
+    }
+    if !params.is_empty() {
+        text.push_str("Parameters:\n");
+        text.push_str(&parameter_list(params));
+        text.push('\n');
+    }
+    text
+}
+
+fn function_info(name: &str, body: &str, params: &[ToolParam]) -> LitStr {
+    let arguments = example_fields(params, "        ");
+    let arguments = if arguments.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{\n{}\n    }}", arguments.join(",\n"))
+    };
+
+    let info = format!(
+        "\n# `{name}`\n\n{}example call:\n```json\n{{\n    \"action\": \"invoke_function\",\n    \"function\": \"{name}\",\n    \"arguments\": {arguments}\n}}\n```\n",
+        with_parameters(body, params),
+    );
+
+    LitStr::new(&info, Span::call_site())
+}
+
+fn action_description(name: &str, body: &str, params: &[ToolParam]) -> LitStr {
+    let mut fields = vec![format!("    \"action\": \"{}\"", name)];
+    fields.extend(example_fields(params, "    "));
+
+    let description = format!(
+        "\n# `{name}`\n\n{}Here is an example of how to invoke it:\n```json\n{{\n{}\n}}\n```\n",
+        with_parameters(body, params),
+        fields.join(",\n"),
+    );
+
+    LitStr::new(&description, Span::call_site())
+}
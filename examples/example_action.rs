@@ -151,7 +151,7 @@ Remember to escape your backslashes in JSON strings. For example, use `\\` inste
 This is especially important when using regex patterns in JSON strings.
 
 When performing string matches or filtering, make sure you use an appropriate 'action' to 
-double-check your results: for example, `regex` or `string_match`.
+double-check your results: for example, `regex` or `string_ops`.
 "#;
 
 fn streaming_print(content: &str) {
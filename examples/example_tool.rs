@@ -79,6 +79,10 @@ async fn main() {
             });
         }
 
+        for rejected in dispatch.rejected() {
+            tool_output.push_str(&rejected.correction());
+        }
+
         println!(" (( tool_output: {} ))\n\n", tool_output);
 
         session.user(&tool_output);
@@ -1,12 +1,31 @@
+use crate::action_calculate::AlpacaActionCalculate;
+use crate::action_code_outline::AlpacaActionCodeOutline;
 use crate::action_describe::AlpacaActionDescribe;
+use crate::action_find_definition::AlpacaActionFindDefinition;
+use crate::action_find_files::AlpacaActionFindFiles;
+use crate::action_find_references::AlpacaActionFindReferences;
+use crate::action_git::AlpacaActionGit;
+use crate::action_json_query::AlpacaActionJsonQuery;
 use crate::action_list::AlpacaActionList;
+use crate::action_read_config::AlpacaActionReadConfig;
 use crate::action_read_directory::AlpacaActionReadDirectory;
 use crate::action_read_file::AlpacaActionReadFile;
 use crate::action_regex::AlpacaActionRegex;
+use crate::action_rust_project::AlpacaActionRustProject;
+use crate::action_search_files::AlpacaActionSearchFiles;
+use crate::action_string_ops::AlpacaActionStringOps;
+use crate::action_tree::AlpacaActionTree;
+use crate::permission::{
+    AlpacaDenyAll, AlpacaPermission, AlpacaPermissionPolicy, AlpacaPermissionRequest,
+};
+use crate::sandbox::AlpacaSandbox;
+use crate::symbol_index::AlpacaSymbolIndex;
 use regex::Regex;
+use serde::de::DeserializeOwned;
 use serde_json::Value as JsonValue;
 use serde_json::json;
 use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 // ---
 
@@ -49,6 +68,8 @@ pub trait AlpacaActionTrait {
 
 pub struct AlpacaActions {
     actions: HashMap<String, Box<dyn AlpacaActionTrait>>,
+    sandbox: AlpacaSandbox,
+    permission_policy: Box<dyn AlpacaPermissionPolicy>,
 }
 
 // ===
@@ -59,6 +80,8 @@ impl AlpacaActions {
     pub fn new() -> Self {
         let mut actions = Self {
             actions: HashMap::new(),
+            sandbox: AlpacaSandbox::default(),
+            permission_policy: Box::new(AlpacaDenyAll),
         };
 
         actions.add_action(Box::new(AlpacaActionList::new()));
@@ -66,6 +89,19 @@ impl AlpacaActions {
         actions.add_action(Box::new(AlpacaActionReadDirectory::new()));
         actions.add_action(Box::new(AlpacaActionReadFile::new()));
         actions.add_action(Box::new(AlpacaActionRegex::new()));
+        actions.add_action(Box::new(AlpacaActionStringOps::new()));
+        actions.add_action(Box::new(AlpacaActionCalculate::new()));
+        actions.add_action(Box::new(AlpacaActionJsonQuery::new()));
+        actions.add_action(Box::new(AlpacaActionReadConfig::new()));
+        actions.add_action(Box::new(AlpacaActionRustProject::new()));
+        actions.add_action(Box::new(AlpacaActionCodeOutline::new()));
+        let symbols = Arc::new(Mutex::new(AlpacaSymbolIndex::new()));
+        actions.add_action(Box::new(AlpacaActionFindDefinition::new(symbols.clone())));
+        actions.add_action(Box::new(AlpacaActionFindReferences::new(symbols)));
+        actions.add_action(Box::new(AlpacaActionGit::new()));
+        actions.add_action(Box::new(AlpacaActionSearchFiles::new()));
+        actions.add_action(Box::new(AlpacaActionFindFiles::new()));
+        actions.add_action(Box::new(AlpacaActionTree::new()));
 
         actions
     }
@@ -79,6 +115,61 @@ impl AlpacaActions {
         self.actions.insert(action.name().to_string(), action);
     }
 
+    /// Gets the sandbox that confines the file access of actions.
+    pub fn sandbox(&self) -> &AlpacaSandbox {
+        &self.sandbox
+    }
+
+    /// Replaces the sandbox. By default, it is rooted at the process' current directory.
+    pub fn set_sandbox(&mut self, sandbox: AlpacaSandbox) {
+        self.sandbox = sandbox;
+    }
+
+    /// Sets the policy consulted by actions before they modify anything.
+    ///
+    /// By default every request is denied, so that mutating actions must be
+    /// explicitly enabled by the host.
+    pub fn set_permission_policy(&mut self, policy: Box<dyn AlpacaPermissionPolicy>) {
+        self.permission_policy = policy;
+    }
+
+    /// Asks the permission policy whether a mutating action may go ahead.
+    pub fn request_permission(&self, request: &AlpacaPermissionRequest) -> AlpacaPermission {
+        self.permission_policy.check(request)
+    }
+
+    /// Deserializes the arguments of an action invocation into a typed struct.
+    ///
+    /// Fields that the struct does not declare, such as `action`, are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The name of the action being invoked
+    /// * `object` - The JSON object the action was invoked with
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(T)` - The typed arguments
+    /// * `Err(String)` - An error response for the model, including the action's description
+    pub fn arguments<T: DeserializeOwned>(
+        &self,
+        action: &str,
+        object: &JsonValue,
+    ) -> Result<T, String> {
+        T::deserialize(object).map_err(|e| {
+            let description = self
+                .actions
+                .get(action)
+                .map(|action| action.description())
+                .unwrap_or_default();
+
+            format!(
+                "## Error\n\nInvalid arguments for action '{}': {}.\n\n## Help\n{}",
+                action, e, description
+            )
+        })
+    }
+
     pub fn invoke(&self, message: &str) -> Option<String> {
         // Check each JSON block for an action
         let json_blocks = self.parse(message);
@@ -223,3 +314,39 @@ impl AlpacaActions {
         Self::blockify(&object)
     }
 }
+
+// ===
+// AlpacaActions Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_describe::DescribeArguments;
+
+    /// Tests deserializing typed arguments from an action invocation.
+    #[test]
+    fn test_arguments() {
+        let actions = AlpacaActions::new();
+        let object = json!({"action": "describe_action", "action_name": "regex"});
+
+        let arguments: DescribeArguments = actions.arguments("describe_action", &object).unwrap();
+        assert_eq!(arguments.action_name, "regex");
+    }
+
+    /// Tests that invalid arguments produce an error response with the action description.
+    #[test]
+    fn test_arguments_invalid() {
+        let actions = AlpacaActions::new();
+        let object = json!({"action": "describe_action", "action_name": 3});
+
+        let error = actions
+            .arguments::<DescribeArguments>("describe_action", &object)
+            .err()
+            .unwrap();
+        assert!(error.starts_with("## Error"));
+        assert!(error.contains("Invalid arguments for action 'describe_action'"));
+        assert!(error.contains("## Help"));
+        assert!(error.contains("\"action_name\": \"list_actions\""));
+    }
+}
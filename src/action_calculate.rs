@@ -0,0 +1,193 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::expression::evaluate_expression;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+
+const NAME: &str = "calculate";
+const DESCRIPTION: &str = r#"
+# `calculate`
+
+The 'calculate' action evaluates arithmetic expressions exactly. Use it for any
+arithmetic, such as adding up file sizes or counts, instead of working it out
+yourself. Here is an example of how to invoke it:
+
+```json
+{
+    "action": "calculate",
+    "expression": "(1024 + 2048) / 3"
+}
+```
+
+Provide several expressions at once with 'expressions', an array of strings.
+
+Expressions can use:
+- integers and decimals, such as `42`, `0.5`, `1_000` or `1.5e3`
+- `+`, `-`, `*`, `/`, `^` (power) and parentheses
+- percentages: `15% * 80` is `12`, `200 + 10%` is `220` and `200 - 25%` is `150`
+- `min`, `max`, `abs`, `round(x)` or `round(x, places)`, `floor`, `ceil`, `trunc`,
+  `sqrt`, `pow(x, y)`, `mod(x, y)`, `log(x)` (natural), `log(x, base)`, `log10`,
+  `log2`, `exp`, `sin`, `cos`, `tan`, and the constants `pi` and `e`
+
+Integers and decimals are computed exactly: `0.1 + 0.2` is `0.3`. When a result
+has no exact decimal form, such as `1 / 3`, it is rounded to 15 significant
+digits and also given as a fraction.
+"#;
+
+/// The longest expression evaluated, in characters.
+const MAX_EXPRESSION_CHARS: usize = 1000;
+
+/// Evaluates arithmetic expressions exactly.
+#[derive(Deserialize, JsonSchema)]
+pub struct CalculateArguments {
+    /// The expression to evaluate.
+    #[serde(default)]
+    pub expression: Option<String>,
+    /// Several expressions to evaluate.
+    #[serde(default)]
+    pub expressions: Vec<String>,
+}
+
+pub struct AlpacaActionCalculate {}
+
+impl AlpacaActionCalculate {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn calculate(&self, expression: &str) -> JsonValue {
+        if expression.chars().count() > MAX_EXPRESSION_CHARS {
+            let error = format!(
+                "The expression is longer than {} characters.",
+                MAX_EXPRESSION_CHARS
+            );
+            return json!({"error": error});
+        }
+        let evaluation = match evaluate_expression(expression) {
+            Ok(evaluation) => evaluation,
+            Err(error) => return json!({"expression": expression, "error": error}),
+        };
+
+        let result = evaluation.result;
+        let mut text = result.to_string();
+        if evaluation.percent {
+            text.push('%');
+        }
+
+        let mut response = json!({
+            "expression": evaluation.expression,
+            "result": text,
+            "exact": result.is_exact() && result.is_finite_decimal(),
+        });
+        if let Some(fraction) = result.fraction_text()
+            && !result.is_finite_decimal()
+        {
+            response["fraction"] = json!(fraction);
+        }
+        response
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionCalculate {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: CalculateArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        let expressions: Vec<&String> = arguments
+            .expression
+            .iter()
+            .chain(arguments.expressions.iter())
+            .collect();
+        if expressions.is_empty() {
+            return format!(
+                "## Error\n\nMissing 'expression' parameter.\n\n## Help\n{}",
+                DESCRIPTION
+            );
+        }
+
+        let mut results: Vec<JsonValue> = expressions
+            .iter()
+            .map(|expression| self.calculate(expression))
+            .collect();
+        let failed = results.iter().any(|result| result.get("error").is_some());
+
+        let response = if results.len() == 1 {
+            results.remove(0)
+        } else {
+            json!({"results": results})
+        };
+
+        if failed && expressions.len() == 1 {
+            format!(
+                "## Error\n\n{}\n## Help\n{}",
+                AlpacaActions::blockify(&response),
+                DESCRIPTION
+            )
+        } else {
+            format!("## Success\n\n{}", AlpacaActions::blockify(&response))
+        }
+    }
+}
+
+// ===
+// AlpacaActionCalculate Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoke(object: JsonValue) -> String {
+        AlpacaActionCalculate::new().invoke(&object, &AlpacaActions::new())
+    }
+
+    /// Tests evaluating one expression, and one without an exact decimal form.
+    #[test]
+    fn test_calculate() {
+        let response = invoke(json!({"action": NAME, "expression": "(1024+2048)/4"}));
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"expression\": \"(1024 + 2048) / 4\""));
+        assert!(response.contains("\"result\": \"768\""));
+        assert!(response.contains("\"exact\": true"));
+
+        let response = invoke(json!({"action": NAME, "expression": "10 / 3"}));
+        assert!(
+            response.contains("\"result\": \"3.33333333333333\""),
+            "{}",
+            response
+        );
+        assert!(response.contains("\"fraction\": \"10/3\""));
+        assert!(response.contains("\"exact\": false"));
+    }
+
+    /// Tests several expressions, and errors.
+    #[test]
+    fn test_calculate_many_and_errors() {
+        let response = invoke(json!({"action": NAME, "expressions": ["10% + 5%", "2 +"]}));
+        assert!(response.contains("\"result\": \"15%\""), "{}", response);
+        assert!(response.contains("\"error\": \"The expression ended unexpectedly.\""));
+
+        let response = invoke(json!({"action": NAME, "expression": "import('os')"}));
+        assert!(response.starts_with("## Error"), "{}", response);
+
+        let long = "1+".repeat(MAX_EXPRESSION_CHARS / 2 - 1) + "1";
+        let response = invoke(json!({"action": NAME, "expression": long}));
+        assert!(response.contains("\"result\": \"500\""), "{}", response);
+        let long = "1+".repeat(MAX_EXPRESSION_CHARS) + "1";
+        let response = invoke(json!({"action": NAME, "expression": long}));
+        assert!(response.starts_with("## Error"), "{}", response);
+        assert!(response.contains("longer than 1000 characters"));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::rust_outline::{AlpacaOutlineItem, find_items, outline};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::fs;
+
+const NAME: &str = "code_outline";
+const DESCRIPTION: &str = r#"
+# `code_outline`
+
+The 'code_outline' action lists the items of a Rust source file: modules,
+structs, enums, traits, impl blocks, functions, consts and macros, with their
+visibility, signature, the first line of their doc comment and their line range.
+Use it to find your way around a file before reading parts of it. Here is an
+example of how to invoke it:
+
+```json
+{
+    "action": "code_outline",
+    "file_name": "src/action.rs"
+}
+```
+
+To read the full source of one item, pass its path as `item`, such as
+`AlpacaActions::invoke`, `tests::test_parse` or `Display for Point::fmt`:
+
+```json
+{
+    "action": "code_outline",
+    "file_name": "src/action.rs",
+    "item": "AlpacaActions::invoke"
+}
+```
+
+Optional arguments:
+- `include_private`: `false` to list only items with a `pub` visibility
+- `max_chars`: the most source returned for an item, 16000 characters by default
+"#;
+
+/// The largest file `code_outline` reads.
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+/// Lists the items of a Rust source file, or returns the source of one.
+#[derive(Deserialize, JsonSchema)]
+pub struct CodeOutlineArguments {
+    /// The path of the Rust file, relative to the current directory.
+    pub file_name: String,
+    /// The path of an item whose source is returned, such as `Type::method`.
+    #[serde(default)]
+    pub item: Option<String>,
+    /// Whether private items are listed.
+    #[serde(default)]
+    pub include_private: Option<bool>,
+    /// The most source returned for an item, in characters.
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+}
+
+pub struct AlpacaActionCodeOutline {}
+
+impl AlpacaActionCodeOutline {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn run(
+        &self,
+        arguments: &CodeOutlineArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let file_name = &arguments.file_name;
+        let path = context.sandbox().resolve(file_name)?;
+        if path.extension().and_then(|extension| extension.to_str()) != Some("rs") {
+            return Err(format!(
+                "'{}' is not a Rust source file. The 'code_outline' action reads '.rs' files.",
+                file_name
+            ));
+        }
+        let metadata = fs::metadata(&path)
+            .map_err(|e| format!("Failed to read file '{}': {}.", file_name, e))?;
+        if metadata.len() > MAX_FILE_SIZE {
+            return Err(format!(
+                "The file '{}' is too large to outline ({} bytes).",
+                file_name,
+                metadata.len()
+            ));
+        }
+        let source = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read file '{}': {}.", file_name, e))?;
+        let items = outline(&source)
+            .map_err(|error| format!("Failed to parse '{}': {}", file_name, error))?;
+
+        match &arguments.item {
+            Some(item) => self.item_source(file_name, &source, &items, item, arguments),
+            None => {
+                let include_private = arguments.include_private.unwrap_or(true);
+                Ok(json!({
+                    "file_name": file_name,
+                    "total_lines": source.lines().count(),
+                    "items": items
+                        .iter()
+                        .filter_map(|item| item_json(item, include_private))
+                        .collect::<Vec<_>>(),
+                }))
+            }
+        }
+    }
+
+    fn item_source(
+        &self,
+        file_name: &str,
+        source: &str,
+        items: &[AlpacaOutlineItem],
+        path: &str,
+        arguments: &CodeOutlineArguments,
+    ) -> Result<JsonValue, String> {
+        let found = find_items(items, path);
+        let Some(item) = found.first() else {
+            return Err(format!(
+                "No item '{}' was found in '{}'. {}",
+                path,
+                file_name,
+                suggestions(items, path)
+            ));
+        };
+
+        let lines: Vec<&str> = source.lines().collect();
+        let width = item.end_line.to_string().len();
+        let max_chars = arguments.max_chars.unwrap_or(16_000).clamp(500, 64_000);
+
+        let mut text = String::new();
+        let mut truncated = false;
+        for number in item.start_line..=item.end_line.min(lines.len()) {
+            let line = format!("{:>width$}\t{}\n", number, lines[number - 1]);
+            if text.len() + line.len() > max_chars {
+                truncated = true;
+                break;
+            }
+            text.push_str(&line);
+        }
+
+        let mut response = json!({
+            "file_name": file_name,
+            "item": path,
+            "kind": item.kind,
+            "lines": format!("{}-{}", item.start_line, item.end_line),
+            "source": text,
+        });
+        if truncated {
+            response["truncated"] = json!(true);
+            response["note"] = json!(
+                "The item is longer than 'max_chars'. Use 'read_file' with a line range to read the rest."
+            );
+        }
+        if found.len() > 1 {
+            response["other_matches"] = json!(
+                found[1..]
+                    .iter()
+                    .map(|other| format!(
+                        "{} (lines {}-{})",
+                        other.signature, other.start_line, other.end_line
+                    ))
+                    .collect::<Vec<_>>()
+            );
+        }
+        Ok(response)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionCodeOutline {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: CodeOutlineArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.run(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+/// Describes an item for the outline, leaving out private items if asked.
+///
+/// `impl` blocks have no visibility of their own and are kept when they have
+/// listed members, or implement a trait.
+fn item_json(item: &AlpacaOutlineItem, include_private: bool) -> Option<JsonValue> {
+    let children: Vec<JsonValue> = item
+        .children
+        .iter()
+        .filter_map(|child| item_json(child, include_private))
+        .collect();
+
+    let visible = include_private
+        || item.visibility.is_some()
+        || (item.kind == "impl" && (item.trait_name.is_some() || !children.is_empty()));
+    if !visible {
+        return None;
+    }
+
+    let mut entry = json!({
+        "kind": item.kind,
+        "name": item.name,
+        "signature": item.signature,
+        "lines": format!("{}-{}", item.start_line, item.end_line),
+    });
+    if let Some(visibility) = &item.visibility {
+        entry["visibility"] = json!(visibility);
+    }
+    if let Some(doc) = &item.doc {
+        entry["doc"] = json!(doc);
+    }
+    if !children.is_empty() {
+        entry["items"] = json!(children);
+    }
+    Some(entry)
+}
+
+/// Lists the item paths available where the lookup of `path` stopped.
+fn suggestions(items: &[AlpacaOutlineItem], path: &str) -> String {
+    let parent = path.rsplit_once("::").map(|(parent, _)| parent);
+    let (prefix, level): (String, Vec<&AlpacaOutlineItem>) = match parent {
+        Some(parent) => {
+            let parents = find_items(items, parent);
+            if parents.is_empty() {
+                return suggestions(items, parent);
+            }
+            (
+                format!("{}::", parent),
+                parents
+                    .iter()
+                    .flat_map(|item| item.children.iter())
+                    .collect(),
+            )
+        }
+        None => (String::new(), items.iter().collect()),
+    };
+
+    let mut names: Vec<String> = level
+        .iter()
+        .map(|item| format!("{}{}", prefix, item.name))
+        .collect();
+    names.dedup();
+    if names.is_empty() {
+        String::new()
+    } else {
+        format!("The available items are: {}.", names.join(", "))
+    }
+}
+
+// ===
+// AlpacaActionCodeOutline Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::AlpacaSandbox;
+
+    fn actions() -> (tempfile::TempDir, AlpacaActions) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("lib.rs"),
+            "/// A counter.\npub struct Counter {\n    count: u32,\n}\n\nimpl Counter {\n    pub fn increment(&mut self) {\n        self.count += 1;\n    }\n\n    fn reset(&mut self) {\n        self.count = 0;\n    }\n}\n",
+        )
+        .unwrap();
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(AlpacaSandbox::new(temp_dir.path()));
+        (temp_dir, actions)
+    }
+
+    /// Tests outlining a file, with and without private items.
+    #[test]
+    fn test_code_outline() {
+        let (_temp_dir, actions) = actions();
+        let action = AlpacaActionCodeOutline::new();
+
+        let response = action.invoke(&json!({"file_name": "lib.rs"}), &actions);
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"signature\": \"pub struct Counter\""));
+        assert!(response.contains("\"doc\": \"A counter.\""));
+        assert!(response.contains("\"lines\": \"7-9\""));
+        assert!(response.contains("\"name\": \"reset\""));
+
+        let response = action.invoke(
+            &json!({"file_name": "lib.rs", "include_private": false}),
+            &actions,
+        );
+        assert!(response.contains("\"name\": \"increment\""));
+        assert!(!response.contains("\"name\": \"reset\""));
+    }
+
+    /// Tests returning the source of one item, and a missing one.
+    #[test]
+    fn test_code_outline_item() {
+        let (_temp_dir, actions) = actions();
+        let action = AlpacaActionCodeOutline::new();
+
+        let response = action.invoke(
+            &json!({"file_name": "lib.rs", "item": "Counter::reset"}),
+            &actions,
+        );
+        assert!(response.contains("\"lines\": \"11-13\""), "{}", response);
+        assert!(response.contains("11\\t    fn reset(&mut self) {\\n"));
+        assert!(!response.contains("increment"));
+
+        let response = action.invoke(
+            &json!({"file_name": "lib.rs", "item": "Counter::clear"}),
+            &actions,
+        );
+        assert!(response.contains(
+            "No item 'Counter::clear' was found in 'lib.rs'. The available items are: Counter::increment, Counter::reset."
+        ));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::action::AlpacaActionTrait;
 use crate::action::AlpacaActions;
+use schemars::JsonSchema;
+use serde::Deserialize;
 use serde_json::Value as JsonValue;
 
 const DESCRIPTION: &str = r#"
@@ -15,6 +17,13 @@ Here is an example of how to invoke it:
 ```
 "#;
 
+/// Provides a detailed description of the specified action.
+#[derive(Deserialize, JsonSchema)]
+pub struct DescribeArguments {
+    /// The name of the action to describe.
+    pub action_name: String,
+}
+
 pub struct AlpacaActionDescribe {}
 
 impl AlpacaActionDescribe {
@@ -33,20 +42,12 @@ impl AlpacaActionTrait for AlpacaActionDescribe {
     }
 
     fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
-        let description = object["action_name"]
-            .as_str()
-            .map(|name| context.describe_action(name));
-
-        if let Some(description) = description {
-            let response = format!("## Success\n{}\n", &description);
-            return response;
-        }
-
-        let error_text = format!(
-            "## Error\n\n{}\n{}\n",
-            "Request is missing the 'action_name' field.", DESCRIPTION
-        );
+        let arguments: DescribeArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
 
-        error_text
+        let description = context.describe_action(&arguments.action_name);
+        format!("## Success\n{}\n", &description)
     }
 }
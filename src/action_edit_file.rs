@@ -0,0 +1,353 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::action_read_file::content_hash;
+use crate::action_write_file::write_atomic;
+use crate::diff::{apply_hunks, parse_unified_diff, unified_diff};
+use crate::permission::{AlpacaPermission, AlpacaPermissionRequest};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::fs;
+
+const NAME: &str = "edit_file";
+const DESCRIPTION: &str = r#"
+# `edit_file`
+
+The 'edit_file' action changes part of an existing file, without sending the
+whole file. Provide either `edits`, a list of exact search and replace pairs,
+or `diff`, a unified diff. Each `search` text must appear exactly once in the
+file, so include enough surrounding lines to make it unique. The change is
+shown to the user as a diff and must be approved before it is applied.
+
+Optionally, pass the `sha256` returned by `read_file` as `expected_sha256` to
+refuse the edit if the file has changed since it was read.
+
+Here is an example of how to invoke it:
+
+```json
+{
+    "action": "edit_file",
+    "file_name": "src/main.rs",
+    "edits": [
+        {
+            "search": "fn main() {\n    println!(\"Hello\");",
+            "replace": "fn main() {\n    println!(\"Hello, world!\");"
+        }
+    ]
+}
+```
+
+And with a unified diff:
+
+```json
+{
+    "action": "edit_file",
+    "file_name": "src/main.rs",
+    "diff": "@@ -1,3 +1,3 @@\n fn main() {\n-    println!(\"Hello\");\n+    println!(\"Hello, world!\");\n }\n"
+}
+```
+"#;
+
+/// An exact text replacement.
+#[derive(Deserialize, JsonSchema)]
+pub struct SearchReplace {
+    /// The text to find. It must appear exactly once in the file.
+    pub search: String,
+    /// The text to put in its place.
+    pub replace: String,
+}
+
+/// Changes part of an existing file.
+#[derive(Deserialize, JsonSchema)]
+pub struct EditFileArguments {
+    /// The path of the file to edit, relative to the current directory.
+    pub file_name: String,
+    /// Search and replace pairs, applied in order.
+    #[serde(default)]
+    pub edits: Option<Vec<SearchReplace>>,
+    /// A unified diff of the change.
+    #[serde(default)]
+    pub diff: Option<String>,
+    /// The hash of the file when it was last read.
+    #[serde(default)]
+    pub expected_sha256: Option<String>,
+}
+
+pub struct AlpacaActionEditFile {}
+
+impl AlpacaActionEditFile {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn edit_file(
+        &self,
+        arguments: &EditFileArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let path = context.sandbox().resolve(&arguments.file_name)?;
+        let original = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read file '{}': {}.", arguments.file_name, e))?;
+
+        if let Some(expected) = &arguments.expected_sha256 {
+            let actual = content_hash(original.as_bytes());
+            if !actual.eq_ignore_ascii_case(expected.trim()) {
+                return Err(format!(
+                    "The file '{}' has changed since it was read (expected sha256 {}, found {}). Read the file again before editing it.",
+                    arguments.file_name, expected, actual
+                ));
+            }
+        }
+
+        let (edited, applied) = match (&arguments.edits, &arguments.diff) {
+            (Some(edits), None) => (apply_edits(&original, edits)?, edits.len()),
+            (None, Some(diff)) => {
+                let hunks = parse_unified_diff(diff)?;
+                (apply_hunks(&original, &hunks)?, hunks.len())
+            }
+            _ => return Err("Provide either 'edits' or 'diff', but not both.".to_string()),
+        };
+
+        if edited == original {
+            return Err("The edit does not change the file.".to_string());
+        }
+
+        // Ask the host to approve the change, showing it as a diff
+        let display_name = context.sandbox().display_path(&path);
+        let diff = unified_diff(
+            &original,
+            &edited,
+            &format!("a/{}", display_name),
+            &format!("b/{}", display_name),
+            3,
+        );
+        let request =
+            AlpacaPermissionRequest::new(NAME, &format!("Edit '{}'", display_name), &diff);
+        if let AlpacaPermission::Deny(reason) = context.request_permission(&request) {
+            return Err(format!(
+                "Editing '{}' was not permitted: {}",
+                arguments.file_name, reason
+            ));
+        }
+
+        write_atomic(&path, edited.as_bytes(), false)
+            .map_err(|e| format!("Failed to write file '{}': {}.", arguments.file_name, e))?;
+
+        Ok(json!({
+            "file_name": display_name,
+            "changes_applied": applied,
+            "bytes_written": edited.len(),
+            "sha256": content_hash(edited.as_bytes()),
+        }))
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionEditFile {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: EditFileArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.edit_file(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n", error),
+        }
+    }
+}
+
+/// Applies search and replace pairs in order, requiring each search text to be unique.
+fn apply_edits(text: &str, edits: &[SearchReplace]) -> Result<String, String> {
+    let mut text = text.to_string();
+
+    for (number, edit) in edits.iter().enumerate() {
+        let number = number + 1;
+        if edit.search.is_empty() {
+            return Err(format!("Edit {}: the search text is empty.", number));
+        }
+
+        let matches: Vec<usize> = text
+            .match_indices(&edit.search)
+            .map(|(index, _)| index)
+            .collect();
+
+        match matches.as_slice() {
+            [index] => text.replace_range(*index..*index + edit.search.len(), &edit.replace),
+            [] => {
+                let hint = if whitespace_insensitive_match(&text, &edit.search) {
+                    " A similar text exists that differs only in whitespace or indentation; copy it exactly."
+                } else {
+                    " Read the file again and copy the text exactly."
+                };
+                return Err(format!(
+                    "Edit {}: the search text was not found in the file.{}",
+                    number, hint
+                ));
+            }
+            _ => {
+                let lines: Vec<String> = matches
+                    .iter()
+                    .map(|index| (text[..*index].matches('\n').count() + 1).to_string())
+                    .collect();
+                return Err(format!(
+                    "Edit {}: the search text appears {} times (at lines {}). Include more surrounding text so that it matches only once.",
+                    number,
+                    matches.len(),
+                    lines.join(", ")
+                ));
+            }
+        }
+    }
+
+    Ok(text)
+}
+
+fn whitespace_insensitive_match(text: &str, search: &str) -> bool {
+    let squash = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    squash(text).contains(&squash(search))
+}
+
+// ===
+// AlpacaActionEditFile Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::AlpacaAllowAll;
+    use crate::sandbox::AlpacaSandbox;
+    use std::path::Path;
+
+    fn actions(root: &Path) -> AlpacaActions {
+        let mut actions = AlpacaActions::new();
+        actions.add_action(Box::new(AlpacaActionEditFile::new()));
+        actions.set_sandbox(AlpacaSandbox::new(root));
+        actions.set_permission_policy(Box::new(AlpacaAllowAll));
+        actions
+    }
+
+    fn invoke(actions: &AlpacaActions, object: JsonValue) -> String {
+        AlpacaActionEditFile::new().invoke(&object, actions)
+    }
+
+    /// Tests applying search and replace edits.
+    #[test]
+    fn test_edits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        let actions = actions(temp_dir.path());
+
+        let response = invoke(
+            &actions,
+            json!({
+                "action": NAME,
+                "file_name": "a.txt",
+                "edits": [
+                    {"search": "two\n", "replace": "2\n"},
+                    {"search": "three", "replace": "3"}
+                ]
+            }),
+        );
+
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"changes_applied\": 2"));
+        let edited = fs::read_to_string(temp_dir.path().join("a.txt")).unwrap();
+        assert_eq!(edited, "one\n2\n3\n");
+    }
+
+    /// Tests that ambiguous and missing search texts are explained.
+    #[test]
+    fn test_edits_not_unique() {
+        assert_eq!(
+            apply_edits(
+                "a\nx\nb\nx\n",
+                &[SearchReplace {
+                    search: "x".to_string(),
+                    replace: "y".to_string(),
+                }]
+            )
+            .unwrap_err(),
+            "Edit 1: the search text appears 2 times (at lines 2, 4). Include more surrounding text so that it matches only once."
+        );
+
+        let error = apply_edits(
+            "fn main() {\n    run();\n}\n",
+            &[SearchReplace {
+                search: "fn main() {\nrun();".to_string(),
+                replace: String::new(),
+            }],
+        )
+        .unwrap_err();
+        assert!(error.contains("differs only in whitespace"));
+    }
+
+    /// Tests applying a unified diff.
+    #[test]
+    fn test_diff() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        let actions = actions(temp_dir.path());
+
+        let response = invoke(
+            &actions,
+            json!({
+                "action": NAME,
+                "file_name": "a.txt",
+                "diff": "--- a/a.txt\n+++ b/a.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n three\n"
+            }),
+        );
+
+        assert!(response.starts_with("## Success"), "{}", response);
+        let edited = fs::read_to_string(temp_dir.path().join("a.txt")).unwrap();
+        assert_eq!(edited, "one\n2\nthree\n");
+    }
+
+    /// Tests that a stale hash prevents the edit.
+    #[test]
+    fn test_expected_hash() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "one\n").unwrap();
+        let actions = actions(temp_dir.path());
+        let edit = |hash: &str| {
+            invoke(
+                &actions,
+                json!({
+                    "action": NAME,
+                    "file_name": "a.txt",
+                    "edits": [{"search": "one", "replace": "1"}],
+                    "expected_sha256": hash
+                }),
+            )
+        };
+
+        let response = edit(&content_hash(b"zero\n"));
+        assert!(response.contains("has changed since it was read"));
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+            "one\n"
+        );
+
+        let response = edit(&content_hash(b"one\n"));
+        assert!(response.starts_with("## Success"), "{}", response);
+    }
+
+    /// Tests that exactly one kind of change must be given.
+    #[test]
+    fn test_missing_change() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "one\n").unwrap();
+        let actions = actions(temp_dir.path());
+
+        let response = invoke(&actions, json!({"action": NAME, "file_name": "a.txt"}));
+        assert!(response.contains("Provide either 'edits' or 'diff'"));
+    }
+}
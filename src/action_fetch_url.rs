@@ -0,0 +1,424 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::fetch::{AlpacaFetchConfig, AlpacaUrl, fetch};
+use crate::html_text::html_to_markdown;
+use crate::http_transport::{AlpacaHttpRequest, AlpacaHttpTransport, AlpacaStdHttpTransport};
+use crate::permission::{AlpacaPermission, AlpacaPermissionRequest};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const NAME: &str = "fetch_url";
+const DESCRIPTION: &str = r#"
+# `fetch_url`
+
+The 'fetch_url' action fetches a web page or another resource over HTTP and
+returns its content. HTML pages are converted to markdown text, with their
+links kept as `[text](url)`. Only the domains the host allows can be reached,
+and each request must be approved before it is sent. Here is an example of how
+to invoke it:
+
+```json
+{
+    "action": "fetch_url",
+    "url": "http://docs.example.com/guide/install.html"
+}
+```
+
+`https://` URLs can only be fetched if the host has set up a transport that
+supports TLS; otherwise the error says so.
+
+Optional arguments:
+- `method`: `GET` by default, or `HEAD`, `POST`, `PUT`, `PATCH` or `DELETE`
+- `headers`: request headers, such as `{"Accept": "application/json"}`
+- `body`: the request body, as text
+- `raw`: `true` to return HTML as it is instead of converting it
+- `offset`: the character of the content to start from, to read a long page in parts
+- `max_chars`: the most characters of content returned, 20000 by default
+
+Redirects are followed. Responses with an error status are returned with their
+content; check `status`.
+"#;
+
+/// The most characters of a request body shown when asking for permission.
+const MAX_PERMISSION_BODY_CHARS: usize = 2000;
+
+/// Fetches a URL and returns its content as text.
+#[derive(Deserialize, JsonSchema)]
+pub struct FetchUrlArguments {
+    /// The `http://` or `https://` URL to fetch.
+    pub url: String,
+    /// The HTTP method, `GET` by default.
+    #[serde(default)]
+    pub method: Option<String>,
+    /// The request headers.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// The request body.
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Whether HTML is returned as it is rather than as markdown.
+    #[serde(default)]
+    pub raw: bool,
+    /// The character of the content to start from.
+    #[serde(default)]
+    pub offset: Option<usize>,
+    /// The most characters of content returned.
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+}
+
+pub struct AlpacaActionFetchUrl {
+    config: AlpacaFetchConfig,
+    transport: Arc<dyn AlpacaHttpTransport>,
+}
+
+impl AlpacaActionFetchUrl {
+    /// Creates the action with the given allowlist and limits, sending
+    /// requests with an `AlpacaStdHttpTransport` that times out after `config.timeout`.
+    ///
+    /// That transport only speaks plain `http://`. Use `with_transport` with a
+    /// transport that supports TLS to fetch `https://` URLs.
+    pub fn new(config: AlpacaFetchConfig) -> Self {
+        let transport = Arc::new(AlpacaStdHttpTransport::with_timeout(config.timeout));
+        Self::with_transport(config, transport)
+    }
+
+    /// Creates the action with the given allowlist and limits, sending requests through `transport`.
+    pub fn with_transport(
+        config: AlpacaFetchConfig,
+        transport: Arc<dyn AlpacaHttpTransport>,
+    ) -> Self {
+        Self { config, transport }
+    }
+
+    fn fetch_url(
+        &self,
+        arguments: &FetchUrlArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let method = arguments.method.as_deref().unwrap_or("GET").to_uppercase();
+        let url = AlpacaUrl::parse(&arguments.url)?;
+        if !self.config.is_allowed(url.domain()) {
+            return Err(format!(
+                "The domain '{}' is not allowed. Allowed domains: {}.",
+                url.domain(),
+                if self.config.allowed_domains.is_empty() {
+                    "none".to_string()
+                } else {
+                    self.config.allowed_domains.join(", ")
+                }
+            ));
+        }
+
+        let mut request = AlpacaHttpRequest::new(&method, &url.to_string());
+        for (name, value) in &arguments.headers {
+            request = request.header(name, value);
+        }
+        if let Some(body) = &arguments.body {
+            request = request.body(body.as_bytes().to_vec());
+        }
+
+        // Ask the host to approve the request as it is sent, and again if it
+        // is redirected to another origin
+        let approve = |request: &AlpacaHttpRequest| {
+            let mut details = format!("{} {}\n", request.method, request.url);
+            for (name, value) in &request.headers {
+                details.push_str(&format!("{}: {}\n", name, value));
+            }
+            if let Some(body) = &request.body {
+                let body = String::from_utf8_lossy(body);
+                let shown: String = body.chars().take(MAX_PERMISSION_BODY_CHARS).collect();
+                details.push_str(&format!("\n{}\n", shown));
+                if shown.len() < body.len() {
+                    details.push_str(&format!("[… {} bytes in all]\n", body.len()));
+                }
+            }
+            let summary = format!("Fetch '{} {}'", request.method, request.url);
+            let permission = AlpacaPermissionRequest::new(NAME, &summary, &details);
+            match context.request_permission(&permission) {
+                AlpacaPermission::Deny(reason) => Err(format!(
+                    "Fetching '{}' was not permitted: {}",
+                    request.url, reason
+                )),
+                AlpacaPermission::Allow => Ok(()),
+            }
+        };
+
+        let fetched = fetch(self.transport.as_ref(), &request, &self.config, &approve)?;
+        let response = &fetched.response;
+        let content_type = response
+            .header("Content-Type")
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_lowercase())
+            .unwrap_or_default();
+
+        let mut result = json!({
+            "url": request.url,
+            "status": response.status,
+            "content_type": content_type,
+            "bytes": fetched.total_bytes,
+            "duration_ms": fetched.duration.as_millis() as u64,
+        });
+        if !fetched.redirects.is_empty() {
+            result["final_url"] = json!(fetched.url.to_string());
+            result["redirects"] = json!(fetched.redirects.len());
+        }
+        if request.method == "HEAD" {
+            result["headers"] = response
+                .headers
+                .iter()
+                .map(|(name, value)| (name.to_lowercase(), json!(value)))
+                .collect::<serde_json::Map<_, _>>()
+                .into();
+            return Ok(result);
+        }
+
+        if !is_text(&content_type, &response.body) {
+            result["note"] = json!(format!(
+                "The content is binary ({}), so it is not shown.",
+                if content_type.is_empty() {
+                    "no content type"
+                } else {
+                    &content_type
+                }
+            ));
+            return Ok(result);
+        }
+        let content = if content_type.contains("html") && !arguments.raw {
+            let page = html_to_markdown(&response.text(), Some(&fetched.url.to_string()));
+            if let Some(title) = page.title {
+                result["title"] = json!(title);
+            }
+            page.text
+        } else {
+            response.text()
+        };
+
+        // Return one window of the content
+        let total_chars = content.chars().count();
+        let offset = arguments.offset.unwrap_or(0).min(total_chars);
+        let max_chars = arguments.max_chars.unwrap_or(20_000).clamp(1_000, 100_000);
+        let window: String = content.chars().skip(offset).take(max_chars).collect();
+        let end = offset + window.chars().count();
+        result["content"] = json!(window);
+        if offset > 0 || end < total_chars {
+            result["total_chars"] = json!(total_chars);
+        }
+        if end < total_chars {
+            result["truncated"] = json!(true);
+            result["note"] = json!(format!(
+                "Characters {} to {} of {} are shown. Pass 'offset' {} to read on.",
+                offset, end, total_chars, end
+            ));
+        } else if fetched.truncated {
+            result["truncated"] = json!(true);
+            result["note"] = json!(format!(
+                "The response was cut to its first {} of {} bytes.",
+                self.config.max_response_bytes, fetched.total_bytes
+            ));
+        }
+
+        Ok(result)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionFetchUrl {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: FetchUrlArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.fetch_url(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+/// Decides whether a body can be shown as text, from its content type or, without one, its bytes.
+fn is_text(content_type: &str, body: &[u8]) -> bool {
+    if content_type.is_empty() {
+        return std::str::from_utf8(body).is_ok() && !body.contains(&0);
+    }
+    content_type.starts_with("text/")
+        || ["json", "xml", "javascript", "yaml", "toml", "csv"]
+            .iter()
+            .any(|kind| content_type.contains(kind))
+}
+
+// ===
+// AlpacaActionFetchUrl Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::AlpacaAllowAll;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+    use std::thread;
+
+    /// Starts a local server that answers each request with the response for its
+    /// path, and returns its address.
+    fn serve(responses: Vec<(&'static str, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let request = String::from_utf8_lossy(&request).to_string();
+                let path = request.split(' ').nth(1).unwrap_or("/");
+                let response = responses.iter().find(|(prefix, _)| path == *prefix).map_or(
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+                    |(_, response)| response.as_str(),
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        format!("http://{}", address)
+    }
+
+    fn actions() -> AlpacaActions {
+        let mut actions = AlpacaActions::new();
+        actions.set_permission_policy(Box::new(AlpacaAllowAll));
+        actions
+    }
+
+    /// Tests fetching an HTML page through a redirect and converting it to markdown.
+    #[test]
+    fn test_fetch_url_html() {
+        let url = serve(vec![
+            (
+                "/old",
+                "HTTP/1.1 301 Moved Permanently\r\nLocation: /docs/page.html\r\nContent-Length: 0\r\n\r\n".to_string(),
+            ),
+            (
+                "/docs/page.html",
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\r\n<html><head><title>Docs</title></head><body><h2>Install</h2><p>See <a href=\"setup.html\">setup</a>.</p></body></html>".to_string(),
+            ),
+        ]);
+        let action = AlpacaActionFetchUrl::new(AlpacaFetchConfig::allowing(&["127.0.0.1"]));
+
+        let response = action.invoke(&json!({"url": format!("{}/old", url)}), &actions());
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"title\": \"Docs\""));
+        assert!(response.contains("\"redirects\": 1"));
+        assert!(response.contains(&format!(
+            "\"content\": \"## Install\\n\\nSee [setup]({}/docs/setup.html).\"",
+            url
+        )));
+
+        let response = action.invoke(
+            &json!({"url": format!("{}/docs/page.html", url), "raw": true}),
+            &actions(),
+        );
+        assert!(
+            response.contains("\"content\": \"<html><head>"),
+            "{}",
+            response
+        );
+
+        let response = action.invoke(&json!({"url": format!("{}/missing", url)}), &actions());
+        assert!(response.contains("\"status\": 404"), "{}", response);
+    }
+
+    /// Tests reading a long text response in parts.
+    #[test]
+    fn test_fetch_url_offset() {
+        let url = serve(vec![(
+            "/data.txt",
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n{}",
+                "a".repeat(1500)
+            ),
+        )]);
+        let action = AlpacaActionFetchUrl::new(AlpacaFetchConfig::allowing(&["127.0.0.1"]));
+        let data_url = format!("{}/data.txt", url);
+
+        let response = action.invoke(&json!({"url": data_url, "max_chars": 1000}), &actions());
+        assert!(
+            response.contains("Pass 'offset' 1000 to read on."),
+            "{}",
+            response
+        );
+        let response = action.invoke(
+            &json!({"url": data_url, "max_chars": 1000, "offset": 1000}),
+            &actions(),
+        );
+        assert!(response.contains(&format!("\"content\": \"{}\"", "a".repeat(500))));
+        assert!(!response.contains("truncated"));
+    }
+
+    /// Tests that domains outside the allowlist are refused, and that the
+    /// permission policy sees the request and can deny it.
+    #[test]
+    fn test_fetch_url_refused() {
+        let action = AlpacaActionFetchUrl::new(AlpacaFetchConfig::allowing(&["example.com"]));
+        let response = action.invoke(&json!({"url": "http://127.0.0.1:9/"}), &actions());
+        assert!(
+            response
+                .contains("The domain '127.0.0.1' is not allowed. Allowed domains: example.com.")
+        );
+
+        let mut actions = actions();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&seen);
+        actions.set_permission_policy(Box::new(move |request: &AlpacaPermissionRequest| {
+            recorded.lock().unwrap().push(request.details.clone());
+            AlpacaPermission::Deny("offline".to_string())
+        }));
+        let response = action.invoke(
+            &json!({
+                "url": "http://example.com/api",
+                "method": "post",
+                "headers": {"Content-Type": "application/json"},
+                "body": "{\"q\": 1}"
+            }),
+            &actions,
+        );
+        assert!(
+            response.contains("Fetching 'http://example.com/api' was not permitted: offline"),
+            "{}",
+            response
+        );
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                "POST http://example.com/api\nContent-Type: application/json\nUser-Agent: alpaca-rs\nAccept: text/html, text/plain;q=0.9, application/json;q=0.9, */*;q=0.8\n\n{\"q\": 1}\n"
+            ]
+        );
+
+        // Invalid headers are refused before the policy is asked
+        let response = action.invoke(
+            &json!({"url": "http://example.com/", "headers": {"Host": "evil.net"}}),
+            &actions,
+        );
+        assert!(response.contains("The header 'Host' is set automatically."));
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+}
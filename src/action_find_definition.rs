@@ -0,0 +1,249 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::symbol_index::{AlpacaSymbol, AlpacaSymbolIndex};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::sync::{Arc, Mutex, PoisonError};
+
+const NAME: &str = "find_definition";
+const DESCRIPTION: &str = r#"
+# `find_definition`
+
+The 'find_definition' action finds where a Rust item is defined: a function,
+method, struct, enum, trait, const, module or macro. For traits and types, it
+also lists their `impl` blocks. Every Rust file under the accessible directory
+is indexed, and the index is kept up to date as files change. Here is an
+example of how to invoke it:
+
+```json
+{
+    "action": "find_definition",
+    "name": "AlpacaActionTrait"
+}
+```
+
+The name can be qualified to narrow the search, as in `AlpacaActions::invoke`.
+
+Optional arguments:
+- `kind`: only items of this kind, such as `fn`, `struct` or `trait`
+- `path`: only definitions under this directory
+- `max_results`: the most definitions returned, 20 by default
+"#;
+
+/// Finds where a Rust item is defined.
+#[derive(Deserialize, JsonSchema)]
+pub struct FindDefinitionArguments {
+    /// The name of the item, optionally qualified, such as `Type::method`.
+    pub name: String,
+    /// Only items of this kind, such as `fn`, `struct` or `trait`.
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// Only definitions under this directory.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The most definitions returned.
+    #[serde(default)]
+    pub max_results: Option<usize>,
+}
+
+pub struct AlpacaActionFindDefinition {
+    index: Arc<Mutex<AlpacaSymbolIndex>>,
+}
+
+impl AlpacaActionFindDefinition {
+    /// Creates the action over an index, which can be shared with `find_references`.
+    pub fn new(index: Arc<Mutex<AlpacaSymbolIndex>>) -> Self {
+        Self { index }
+    }
+
+    fn find(
+        &self,
+        arguments: &FindDefinitionArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let sandbox = context.sandbox();
+        let scope = match &arguments.path {
+            Some(path) => Some(sandbox.resolve(path)?),
+            None => None,
+        };
+        let in_scope = |symbol: &&AlpacaSymbol| {
+            scope
+                .as_ref()
+                .is_none_or(|scope| symbol.file.starts_with(scope))
+        };
+
+        let mut index = self.index.lock().unwrap_or_else(PoisonError::into_inner);
+        let refresh = index.refresh(sandbox.root())?;
+
+        let name = arguments.name.trim();
+        let definitions: Vec<&AlpacaSymbol> = index
+            .definitions(name)
+            .into_iter()
+            .filter(in_scope)
+            .filter(|symbol| {
+                arguments
+                    .kind
+                    .as_ref()
+                    .is_none_or(|kind| symbol.kind == kind.as_str())
+            })
+            .collect();
+
+        // The `impl` blocks of the traits and types found
+        let mut implementations: Vec<&AlpacaSymbol> = Vec::new();
+        for definition in &definitions {
+            if matches!(
+                definition.kind,
+                "trait" | "struct" | "enum" | "union" | "type"
+            ) {
+                for implementation in index.implementations(&definition.name) {
+                    if in_scope(&implementation) && !implementations.contains(&implementation) {
+                        implementations.push(implementation);
+                    }
+                }
+            }
+        }
+
+        if definitions.is_empty() {
+            let similar = index.similar_names(name.rsplit("::").next().unwrap_or(name), 10);
+            let hint = if similar.is_empty() {
+                format!(
+                    "Use 'find_references' or 'search_files' to look for '{}' as text.",
+                    name
+                )
+            } else {
+                format!("Similar names: {}.", similar.join(", "))
+            };
+            return Err(format!(
+                "No definition of '{}' was found in the {} indexed Rust files. {}",
+                name, refresh.files, hint
+            ));
+        }
+
+        let max_results = arguments.max_results.unwrap_or(20).clamp(1, 200);
+        let describe = |symbol: &&AlpacaSymbol| {
+            let mut entry = json!({
+                "kind": symbol.kind,
+                "name": symbol.qualified_name,
+                "file": sandbox.display_path(&symbol.file),
+                "line": symbol.line,
+                "lines": format!("{}-{}", symbol.start_line, symbol.end_line),
+                "signature": symbol.signature,
+            });
+            if let Some(doc) = &symbol.doc {
+                entry["doc"] = json!(doc);
+            }
+            entry
+        };
+
+        let mut response = json!({
+            "name": name,
+            "indexed_files": refresh.files,
+            "definitions": definitions.iter().take(max_results).map(describe).collect::<Vec<_>>(),
+        });
+        if !implementations.is_empty() {
+            response["implementations"] = implementations
+                .iter()
+                .take(max_results)
+                .map(|implementation| {
+                    json!({
+                        "signature": implementation.signature,
+                        "file": sandbox.display_path(&implementation.file),
+                        "line": implementation.line,
+                        "lines": format!("{}-{}", implementation.start_line, implementation.end_line),
+                    })
+                })
+                .collect();
+        }
+        if definitions.len() > max_results || implementations.len() > max_results {
+            response["truncated"] = json!(true);
+            response["note"] = json!(format!(
+                "{} definitions and {} implementations were found. Qualify the name, or pass 'kind' or 'path', to narrow them.",
+                definitions.len(),
+                implementations.len()
+            ));
+        }
+        if !refresh.parse_errors.is_empty() {
+            response["unparsed_files"] = refresh
+                .parse_errors
+                .iter()
+                .take(10)
+                .map(|(file, _)| json!(sandbox.display_path(file)))
+                .collect();
+        }
+
+        Ok(response)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionFindDefinition {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: FindDefinitionArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.find(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+// ===
+// AlpacaActionFindDefinition Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::AlpacaSandbox;
+    use std::fs;
+
+    /// Tests finding a trait with its implementations, and a missing name.
+    #[test]
+    fn test_find_definition() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir(temp_dir.path().join("src")).unwrap();
+        fs::write(
+            temp_dir.path().join("src/lib.rs"),
+            "/// Something that greets.\npub trait Greet {\n    fn greet(&self);\n}\n\npub struct World;\n\nimpl Greet for World {\n    fn greet(&self) {}\n}\n",
+        )
+        .unwrap();
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(AlpacaSandbox::new(temp_dir.path()));
+        let action =
+            AlpacaActionFindDefinition::new(Arc::new(Mutex::new(AlpacaSymbolIndex::new())));
+
+        let response = action.invoke(&json!({"name": "Greet"}), &actions);
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"signature\": \"pub trait Greet\""));
+        assert!(response.contains("\"doc\": \"Something that greets.\""));
+        assert!(response.contains("\"file\": \"src/lib.rs\""));
+        assert!(response.contains("\"signature\": \"impl Greet for World\""));
+
+        let response = action.invoke(&json!({"name": "greet", "kind": "fn"}), &actions);
+        assert!(
+            response.contains("\"name\": \"Greet::greet\""),
+            "{}",
+            response
+        );
+        assert!(response.contains("\"name\": \"<World as Greet>::greet\""));
+
+        let response = action.invoke(&json!({"name": "Gree"}), &actions);
+        assert!(
+            response.contains("No definition of 'Gree' was found in the 1 indexed Rust files. Similar names: Greet, greet."),
+            "{}",
+            response
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::file_walk::{AlpacaWalkOptions, format_time, parse_duration};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const NAME: &str = "find_files";
+const DESCRIPTION: &str = r#"
+# `find_files`
+
+The 'find_files' action finds files and directories under the current directory
+whose names match glob patterns, and returns their paths relative to the current
+directory. Here are examples of how to invoke it:
+
+```json
+{
+    "action": "find_files",
+    "patterns": ["*.lock"]
+}
+```
+
+```json
+{
+    "action": "find_files",
+    "patterns": [".*"],
+    "type": "directory",
+    "max_depth": 1
+}
+```
+
+All of the arguments are optional:
+- `patterns`: glob patterns such as `*.rs` or `src/**/mod.rs`; a pattern without
+  a `/` is matched against the name only
+- `path`: the directory to search, relative to the current directory
+- `type`: `file`, `directory` or `symlink`
+- `max_depth`: how deep to search, where 1 is the directory itself
+- `min_size` / `max_size`: file size limits in bytes
+- `newer_than` / `older_than`: modification age limits, such as `30m`, `12h` or `7d`
+- `hidden`: whether hidden entries are included; on by default when a pattern starts with `.`
+- `respect_gitignore`: `true` by default
+- `max_results`: the maximum number of paths returned, 100 by default
+"#;
+
+/// The most paths a single search can return.
+const MAX_RESULTS_LIMIT: usize = 1000;
+
+fn default_true() -> bool {
+    true
+}
+
+/// The kind of entry `find_files` looks for.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryType {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// Finds files and directories by name and metadata.
+#[derive(Deserialize, JsonSchema)]
+pub struct FindFilesArguments {
+    /// Glob patterns matched against the name, or against the relative path if they contain a `/`.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// The directory to search, relative to the current directory.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The kind of entry to find.
+    #[serde(default, rename = "type")]
+    pub entry_type: Option<EntryType>,
+    /// How deep to search, where 1 is the directory itself.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// The minimum file size, in bytes.
+    #[serde(default)]
+    pub min_size: Option<u64>,
+    /// The maximum file size, in bytes.
+    #[serde(default)]
+    pub max_size: Option<u64>,
+    /// Only entries modified within this duration, such as `7d`.
+    #[serde(default)]
+    pub newer_than: Option<String>,
+    /// Only entries last modified longer ago than this duration.
+    #[serde(default)]
+    pub older_than: Option<String>,
+    /// Whether hidden entries are included.
+    #[serde(default)]
+    pub hidden: Option<bool>,
+    /// Whether entries ignored by `.gitignore` are skipped.
+    #[serde(default = "default_true")]
+    pub respect_gitignore: bool,
+    /// The maximum number of paths returned.
+    #[serde(default)]
+    pub max_results: Option<usize>,
+}
+
+pub struct AlpacaActionFindFiles {}
+
+impl AlpacaActionFindFiles {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn find(
+        &self,
+        arguments: &FindFilesArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let sandbox = context.sandbox();
+        let root = sandbox.resolve(arguments.path.as_deref().unwrap_or("."))?;
+        if !root.is_dir() {
+            return Err(format!(
+                "'{}' is not a directory.",
+                arguments.path.as_deref().unwrap_or(".")
+            ));
+        }
+
+        let (name_patterns, path_patterns) = build_patterns(&arguments.patterns)?;
+        let now = SystemTime::now();
+        let newer_than = match &arguments.newer_than {
+            Some(age) => Some(now.checked_sub(parse_duration(age)?).unwrap_or(UNIX_EPOCH)),
+            None => None,
+        };
+        let older_than = match &arguments.older_than {
+            Some(age) => Some(now.checked_sub(parse_duration(age)?).unwrap_or(UNIX_EPOCH)),
+            None => None,
+        };
+
+        let hidden = arguments.hidden.unwrap_or_else(|| {
+            arguments
+                .patterns
+                .iter()
+                .any(|pattern| pattern.starts_with('.'))
+        });
+        let options = AlpacaWalkOptions {
+            respect_ignore_files: arguments.respect_gitignore,
+            hidden,
+            max_depth: arguments.max_depth,
+            ..Default::default()
+        };
+        let max_results = arguments
+            .max_results
+            .unwrap_or(100)
+            .clamp(1, MAX_RESULTS_LIMIT);
+
+        let mut entries = Vec::new();
+        let mut truncated = false;
+
+        for entry in options.walk(&root)?.flatten() {
+            if entry.depth() == 0 {
+                continue;
+            }
+            let Some(file_type) = entry.file_type() else {
+                continue;
+            };
+            let kind = if file_type.is_symlink() {
+                EntryType::Symlink
+            } else if file_type.is_dir() {
+                EntryType::Directory
+            } else {
+                EntryType::File
+            };
+            if arguments.entry_type.is_some_and(|wanted| wanted != kind) {
+                continue;
+            }
+
+            let relative = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+            if !matches_patterns(relative, &name_patterns, &path_patterns) {
+                continue;
+            }
+
+            let Ok(metadata) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            let size = metadata.len();
+            if kind == EntryType::File
+                && (arguments.min_size.is_some_and(|min| size < min)
+                    || arguments.max_size.is_some_and(|max| size > max))
+            {
+                continue;
+            }
+
+            let modified = metadata.modified().ok();
+            if newer_than.is_some_and(|limit| modified.is_none_or(|time| time < limit))
+                || older_than.is_some_and(|limit| modified.is_none_or(|time| time > limit))
+            {
+                continue;
+            }
+
+            if entries.len() >= max_results {
+                truncated = true;
+                break;
+            }
+
+            let mut found = json!({
+                "path": sandbox.display_path(entry.path()),
+                "type": match kind {
+                    EntryType::File => "file",
+                    EntryType::Directory => "directory",
+                    EntryType::Symlink => "symlink",
+                },
+            });
+            if kind == EntryType::File {
+                found["size"] = json!(size);
+            }
+            if let Some(modified) = modified {
+                found["modified"] = json!(format_time(modified));
+            }
+            entries.push(found);
+        }
+
+        let mut response = json!({
+            "count": entries.len(),
+            "entries": entries,
+        });
+        if truncated {
+            response["truncated"] = json!(true);
+            response["note"] = json!(
+                "More entries match. Narrow the search with more specific patterns, `path` or `max_depth`."
+            );
+        }
+
+        Ok(response)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionFindFiles {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: FindFilesArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.find(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+// ---
+
+/// Splits the patterns into those matched against names and those matched against paths.
+fn build_patterns(patterns: &[String]) -> Result<(GlobSet, GlobSet), String> {
+    let mut names = GlobSetBuilder::new();
+    let mut paths = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        let glob = Glob::new(pattern.trim_start_matches("./"))
+            .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+        if pattern.contains('/') {
+            paths.add(glob);
+        } else {
+            names.add(glob);
+        }
+    }
+
+    let names = names.build().map_err(|e| e.to_string())?;
+    let paths = paths.build().map_err(|e| e.to_string())?;
+    Ok((names, paths))
+}
+
+fn matches_patterns(relative: &Path, names: &GlobSet, paths: &GlobSet) -> bool {
+    if names.is_empty() && paths.is_empty() {
+        return true;
+    }
+
+    relative
+        .file_name()
+        .is_some_and(|name| names.is_match(name))
+        || paths.is_match(relative)
+}
+
+// ===
+// AlpacaActionFindFiles Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::AlpacaSandbox;
+    use std::fs;
+
+    fn find(root: &Path, object: JsonValue) -> Vec<String> {
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(AlpacaSandbox::new(root));
+        let arguments: FindFilesArguments = actions.arguments(NAME, &object).unwrap();
+        let response = AlpacaActionFindFiles::new()
+            .find(&arguments, &actions)
+            .unwrap();
+
+        response["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["path"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn project() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join(".github/workflows")).unwrap();
+        fs::create_dir_all(root.join("src/bin")).unwrap();
+        fs::write(root.join("Cargo.lock"), "x".repeat(100)).unwrap();
+        fs::write(root.join("yarn.lock"), "").unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join("src/bin/main.rs"), "").unwrap();
+        temp_dir
+    }
+
+    /// Tests finding files by name.
+    #[test]
+    fn test_find_by_name() {
+        let temp_dir = project();
+        assert_eq!(
+            find(temp_dir.path(), json!({"patterns": ["*.lock"]})),
+            vec!["Cargo.lock", "yarn.lock"]
+        );
+        assert_eq!(
+            find(temp_dir.path(), json!({"patterns": ["src/**/*.rs"]})),
+            vec!["src/bin/main.rs", "src/lib.rs"]
+        );
+    }
+
+    /// Tests finding hidden directories at the top level.
+    #[test]
+    fn test_find_hidden_directories() {
+        let temp_dir = project();
+        assert_eq!(
+            find(
+                temp_dir.path(),
+                json!({"patterns": [".*"], "type": "directory", "max_depth": 1})
+            ),
+            vec![".git", ".github"]
+        );
+    }
+
+    /// Tests filtering by size and age.
+    #[test]
+    fn test_find_metadata_filters() {
+        let temp_dir = project();
+        assert_eq!(
+            find(temp_dir.path(), json!({"type": "file", "min_size": 50})),
+            vec!["Cargo.lock"]
+        );
+        assert_eq!(
+            find(temp_dir.path(), json!({"type": "file", "older_than": "1d"})),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            find(
+                temp_dir.path(),
+                json!({"patterns": ["*.rs"], "newer_than": "1h"})
+            )
+            .len(),
+            2
+        );
+    }
+}
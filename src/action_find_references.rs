@@ -0,0 +1,242 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::symbol_index::{AlpacaReference, AlpacaSymbolIndex};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+
+const NAME: &str = "find_references";
+const DESCRIPTION: &str = r#"
+# `find_references`
+
+The 'find_references' action finds where a Rust identifier is used, such as the
+callers of a function or the uses of a type, with the file, line and text of
+each. Comments and strings are skipped. Every Rust file under the accessible
+directory is indexed, and the index is kept up to date as files change. Here is
+an example of how to invoke it:
+
+```json
+{
+    "action": "find_references",
+    "name": "blockify"
+}
+```
+
+Uses are matched by name: `AlpacaActions::blockify` looks for `blockify`, and
+the uses of other items with the same name are included. Each use has a kind:
+`call` when followed by `(` or `!`, `definition`, or `reference`.
+
+Optional arguments:
+- `calls_only`: `true` to list only calls
+- `include_definitions`: `true` to also list the definitions
+- `path`: only uses under this directory
+- `max_results`: the most uses returned, 50 by default
+"#;
+
+/// The longest line of text returned with a use.
+const MAX_LINE_CHARS: usize = 200;
+
+/// Finds where a Rust identifier is used.
+#[derive(Deserialize, JsonSchema)]
+pub struct FindReferencesArguments {
+    /// The identifier, optionally qualified, such as `Type::method`.
+    pub name: String,
+    /// Whether only calls are listed.
+    #[serde(default)]
+    pub calls_only: bool,
+    /// Whether definitions are listed too.
+    #[serde(default)]
+    pub include_definitions: bool,
+    /// Only uses under this directory.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The most uses returned.
+    #[serde(default)]
+    pub max_results: Option<usize>,
+}
+
+pub struct AlpacaActionFindReferences {
+    index: Arc<Mutex<AlpacaSymbolIndex>>,
+}
+
+impl AlpacaActionFindReferences {
+    /// Creates the action over an index, which can be shared with `find_definition`.
+    pub fn new(index: Arc<Mutex<AlpacaSymbolIndex>>) -> Self {
+        Self { index }
+    }
+
+    fn find(
+        &self,
+        arguments: &FindReferencesArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let sandbox = context.sandbox();
+        let scope = match &arguments.path {
+            Some(path) => Some(sandbox.resolve(path)?),
+            None => None,
+        };
+
+        let name = arguments.name.trim();
+        let identifier = name.rsplit("::").next().unwrap_or(name).trim();
+        if identifier.is_empty() {
+            return Err("The 'name' is empty.".to_string());
+        }
+
+        let mut index = self.index.lock().unwrap_or_else(PoisonError::into_inner);
+        let refresh = index.refresh(sandbox.root())?;
+        let references: Vec<AlpacaReference> = index
+            .references(identifier.trim_start_matches("r#"))
+            .into_iter()
+            .filter(|reference| {
+                scope
+                    .as_ref()
+                    .is_none_or(|scope| reference.file.starts_with(scope))
+            })
+            .filter(|reference| arguments.include_definitions || reference.kind != "definition")
+            .filter(|reference| !arguments.calls_only || reference.kind == "call")
+            .collect();
+        drop(index);
+
+        let max_results = arguments.max_results.unwrap_or(50).clamp(1, 500);
+        let shown = &references[..references.len().min(max_results)];
+        let lines = read_lines(shown);
+
+        let files: BTreeSet<&PathBuf> =
+            references.iter().map(|reference| &reference.file).collect();
+        let mut response = json!({
+            "name": identifier,
+            "indexed_files": refresh.files,
+            "total": references.len(),
+            "files": files.len(),
+            "references": shown
+                .iter()
+                .map(|reference| {
+                    let text = lines
+                        .get(&reference.file)
+                        .and_then(|lines| lines.get(reference.line - 1))
+                        .map(|line| truncate(line.trim()))
+                        .unwrap_or_default();
+                    json!({
+                        "file": sandbox.display_path(&reference.file),
+                        "line": reference.line,
+                        "column": reference.column,
+                        "kind": reference.kind,
+                        "text": text,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        });
+        if references.len() > shown.len() {
+            response["truncated"] = json!(true);
+            response["note"] = json!(format!(
+                "Only {} of {} uses are shown. Pass 'path' or 'calls_only' to narrow them.",
+                shown.len(),
+                references.len()
+            ));
+        }
+
+        Ok(response)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionFindReferences {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: FindReferencesArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.find(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+/// Reads the lines of the files the references are in, once per file.
+fn read_lines(references: &[AlpacaReference]) -> HashMap<PathBuf, Vec<String>> {
+    let mut lines = HashMap::new();
+    for reference in references {
+        if !lines.contains_key(&reference.file) {
+            let text = fs::read_to_string(&reference.file).unwrap_or_default();
+            lines.insert(
+                reference.file.clone(),
+                text.lines().map(str::to_string).collect(),
+            );
+        }
+    }
+    lines
+}
+
+fn truncate(line: &str) -> String {
+    if line.chars().count() <= MAX_LINE_CHARS {
+        return line.to_string();
+    }
+    let mut text: String = line.chars().take(MAX_LINE_CHARS).collect();
+    text.push('…');
+    text
+}
+
+// ===
+// AlpacaActionFindReferences Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::AlpacaSandbox;
+
+    /// Tests finding the callers of a method across files.
+    #[test]
+    fn test_find_references() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("lib.rs"),
+            "pub struct Page;\n\nimpl Page {\n    pub fn render() -> String {\n        String::new()\n    }\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("main.rs"),
+            "fn main() {\n    // render is not called here\n    let text = Page::render();\n    let f = Page::render;\n}\n",
+        )
+        .unwrap();
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(AlpacaSandbox::new(temp_dir.path()));
+        let action =
+            AlpacaActionFindReferences::new(Arc::new(Mutex::new(AlpacaSymbolIndex::new())));
+
+        let response = action.invoke(&json!({"name": "Page::render"}), &actions);
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"total\": 2"), "{}", response);
+        assert!(response.contains("\"text\": \"let text = Page::render();\""));
+        assert!(response.contains("\"kind\": \"call\""));
+        assert!(!response.contains("not called"));
+
+        let response = action.invoke(
+            &json!({"name": "render", "calls_only": true, "include_definitions": true}),
+            &actions,
+        );
+        assert!(response.contains("\"total\": 1"), "{}", response);
+
+        let response = action.invoke(
+            &json!({"name": "render", "include_definitions": true}),
+            &actions,
+        );
+        assert!(response.contains("\"total\": 3"), "{}", response);
+        assert!(response.contains("\"kind\": \"definition\""));
+        assert!(response.contains("\"file\": \"lib.rs\""));
+    }
+}
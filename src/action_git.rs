@@ -0,0 +1,470 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::git::{AlpacaGitChange, AlpacaGitDiff, AlpacaGitFileStat, AlpacaGitRepository};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const NAME: &str = "git";
+const DESCRIPTION: &str = r#"
+# `git`
+
+The 'git' action inspects the git repository of the current directory. It is
+read-only: it cannot commit, check out, fetch or change the repository in any
+way, and it does not use the network. Here is an example of how to invoke it:
+
+```json
+{
+    "action": "git",
+    "command": "log",
+    "path": "src/action.rs",
+    "limit": 5
+}
+```
+
+The commands are:
+- `status`: the branch, and the staged, unstaged, untracked and conflicted files
+- `log`: the commits from `revision`, or `HEAD`, that changed `path` if given
+- `show`: the message and changes of the commit `revision`, `HEAD` by default
+- `diff`: the changes of the working tree, or of the index when `staged` is
+  `true`, compared with the commit `from` if given; or the changes from the
+  commit `from` to the commit `to`
+- `blame`: the commit that last changed each line of the file `path`, from
+  `start_line` to `end_line`
+- `branches`: the local branches, and the remote ones when `include_remote` is `true`
+
+Revisions can be branch names, tags, hashes or expressions such as `HEAD~3`.
+Diffs longer than `max_chars`, 12000 characters by default, are shortened.
+"#;
+
+/// The most files listed in each part of `status`.
+const MAX_STATUS_FILES: usize = 200;
+/// The most lines `blame` covers at once.
+const MAX_BLAME_LINES: usize = 400;
+
+/// Inspects the git repository of the current directory without changing it.
+#[derive(Deserialize, JsonSchema)]
+pub struct GitArguments {
+    /// `status`, `log`, `show`, `diff`, `blame` or `branches`.
+    pub command: String,
+    /// A file or directory to limit the command to; the file for `blame`.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The commit for `show`, where `log` starts, or the commit `blame` looks from.
+    #[serde(default)]
+    pub revision: Option<String>,
+    /// The commit `diff` compares from.
+    #[serde(default)]
+    pub from: Option<String>,
+    /// The commit `diff` compares to.
+    #[serde(default)]
+    pub to: Option<String>,
+    /// Whether `diff` compares the index rather than the working tree.
+    #[serde(default)]
+    pub staged: bool,
+    /// The first line for `blame`.
+    #[serde(default)]
+    pub start_line: Option<usize>,
+    /// The last line for `blame`.
+    #[serde(default)]
+    pub end_line: Option<usize>,
+    /// The most commits listed by `log`.
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Whether `branches` lists remote-tracking branches.
+    #[serde(default)]
+    pub include_remote: bool,
+    /// The most characters of a diff returned.
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+}
+
+pub struct AlpacaActionGit {}
+
+impl AlpacaActionGit {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn run(&self, arguments: &GitArguments, context: &AlpacaActions) -> Result<JsonValue, String> {
+        let command = arguments.command.trim();
+        if !["status", "log", "show", "diff", "blame", "branches"].contains(&command) {
+            return Err(format!(
+                "'{}' is not a supported command. The 'git' action is read-only; its commands are status, log, show, diff, blame and branches.",
+                command
+            ));
+        }
+
+        let sandbox = context.sandbox();
+        let paths: Vec<PathBuf> = match &arguments.path {
+            Some(path) => vec![sandbox.resolve(path)?],
+            None => Vec::new(),
+        };
+        let start = paths
+            .first()
+            .filter(|path| path.exists())
+            .map(PathBuf::as_path)
+            .unwrap_or(sandbox.current_dir());
+        let repository = AlpacaGitRepository::discover(start, sandbox.root())?;
+        let revision = arguments.revision.as_deref().map(str::trim);
+
+        let mut response = match command {
+            "status" => {
+                let status = repository.status(&paths)?;
+                let mut response = json!({
+                    "branch": status.branch,
+                    "commit": status.commit.as_deref().map(short),
+                    "clean": status.staged.is_empty()
+                        && status.unstaged.is_empty()
+                        && status.untracked.is_empty()
+                        && status.conflicted.is_empty(),
+                });
+                if let Some(upstream) = &status.upstream {
+                    response["upstream"] = json!(upstream);
+                    response["ahead"] = json!(status.ahead);
+                    response["behind"] = json!(status.behind);
+                }
+                let changes = |changes: &[AlpacaGitChange]| {
+                    changes
+                        .iter()
+                        .take(MAX_STATUS_FILES)
+                        .map(|change| {
+                            let mut entry = json!({
+                                "path": sandbox.display_path(&change.path),
+                                "status": change.status,
+                            });
+                            if let Some(original) = &change.original_path {
+                                entry["from"] = json!(sandbox.display_path(original));
+                            }
+                            entry
+                        })
+                        .collect::<Vec<_>>()
+                };
+                let files = |files: &[PathBuf]| {
+                    files
+                        .iter()
+                        .take(MAX_STATUS_FILES)
+                        .map(|file| json!(sandbox.display_path(file)))
+                        .collect::<Vec<_>>()
+                };
+                for (key, list, total) in [
+                    ("staged", changes(&status.staged), status.staged.len()),
+                    ("unstaged", changes(&status.unstaged), status.unstaged.len()),
+                    (
+                        "untracked",
+                        files(&status.untracked),
+                        status.untracked.len(),
+                    ),
+                    (
+                        "conflicted",
+                        files(&status.conflicted),
+                        status.conflicted.len(),
+                    ),
+                ] {
+                    if total > 0 {
+                        response[key] = json!(list);
+                    }
+                    if total > list.len() {
+                        response[format!("{}_total", key)] = json!(total);
+                    }
+                }
+                response
+            }
+            "log" => {
+                let limit = arguments.limit.unwrap_or(20).clamp(1, 200);
+                // One more commit than asked tells whether there are more
+                let commits = repository.log(revision, &paths, limit + 1)?;
+                let mut response = json!({
+                    "commits": commits
+                        .iter()
+                        .take(limit)
+                        .map(|commit| json!({
+                            "commit": short(&commit.hash),
+                            "date": commit.date,
+                            "author": commit.author,
+                            "subject": commit.subject,
+                        }))
+                        .collect::<Vec<_>>(),
+                });
+                if commits.len() > limit {
+                    response["note"] = json!(format!(
+                        "There are more commits. Raise 'limit', or pass the 'revision' '{}~{}' to continue.",
+                        short(&commits[0].hash),
+                        limit
+                    ));
+                }
+                response
+            }
+            "show" => {
+                let (commit, diff) = repository.show(revision.unwrap_or("HEAD"))?;
+                let mut message = commit.subject.clone();
+                if let Some(body) = &commit.body {
+                    message.push_str("\n\n");
+                    message.push_str(body);
+                }
+                let mut response = json!({
+                    "commit": commit.hash,
+                    "parents": commit.parents.iter().map(|parent| short(parent)).collect::<Vec<_>>(),
+                    "author": format!("{} <{}>", commit.author, commit.email),
+                    "date": commit.date,
+                    "message": message,
+                });
+                add_diff(&mut response, &diff, arguments, context);
+                response
+            }
+            "diff" => {
+                let from = arguments.from.as_deref().map(str::trim);
+                let to = arguments.to.as_deref().map(str::trim);
+                let diff = repository.diff(from, to, arguments.staged, &paths)?;
+                let compared = match (from, to) {
+                    (Some(from), Some(to)) => format!("{} to {}", from, to),
+                    (Some(from), None) if arguments.staged => format!("{} to the index", from),
+                    (Some(from), None) => format!("{} to the working tree", from),
+                    _ if arguments.staged => "HEAD to the index".to_string(),
+                    _ => "the index to the working tree".to_string(),
+                };
+                let mut response = json!({ "compared": compared });
+                add_diff(&mut response, &diff, arguments, context);
+                if diff.files.is_empty() {
+                    response["note"] = json!("There are no differences.");
+                }
+                response
+            }
+            "blame" => {
+                let Some(file) = paths
+                    .first()
+                    .filter(|path| path.is_file() || revision.is_some())
+                else {
+                    return Err("The 'blame' command needs the 'path' of a file.".to_string());
+                };
+                let start_line = arguments.start_line.unwrap_or(1).max(1);
+                let end_line = arguments
+                    .end_line
+                    .unwrap_or_else(|| start_line.saturating_add(99))
+                    .clamp(start_line, start_line.saturating_add(MAX_BLAME_LINES - 1));
+                let lines = repository.blame(file, start_line, end_line, revision)?;
+
+                let mut commits = BTreeMap::new();
+                for line in &lines {
+                    commits.entry(short(&line.commit)).or_insert_with(|| {
+                        json!({
+                            "author": line.author,
+                            "date": line.date,
+                            "summary": line.summary,
+                        })
+                    });
+                }
+                let mut response = json!({
+                    "file": sandbox.display_path(file),
+                    "commits": commits,
+                    "lines": lines
+                        .iter()
+                        .map(|line| json!({
+                            "line": line.line,
+                            "commit": short(&line.commit),
+                            "text": line.text,
+                        }))
+                        .collect::<Vec<_>>(),
+                });
+                if arguments.end_line.is_some_and(|end| end > end_line) {
+                    response["note"] = json!(format!(
+                        "Only {} lines are blamed at once. Pass 'start_line' {} to continue.",
+                        MAX_BLAME_LINES,
+                        end_line + 1
+                    ));
+                }
+                response
+            }
+            _ => {
+                let branches = repository.branches(arguments.include_remote)?;
+                json!({
+                    "branches": branches
+                        .iter()
+                        .map(|branch| {
+                            let mut entry = json!({
+                                "name": branch.name,
+                                "commit": short(&branch.commit),
+                                "date": branch.date,
+                                "subject": branch.subject,
+                            });
+                            if branch.current {
+                                entry["current"] = json!(true);
+                            }
+                            if branch.remote {
+                                entry["remote"] = json!(true);
+                            }
+                            if let Some(upstream) = &branch.upstream {
+                                entry["upstream"] = json!(upstream);
+                            }
+                            if let Some(track) = &branch.track {
+                                entry["track"] = json!(track);
+                            }
+                            entry
+                        })
+                        .collect::<Vec<_>>(),
+                })
+            }
+        };
+
+        response["command"] = json!(command);
+        response["repository"] = json!(sandbox.display_path(&repository.root));
+        Ok(response)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionGit {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: GitArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.run(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+/// Shortens a commit hash for display.
+fn short(hash: &str) -> &str {
+    &hash[..hash.len().min(12)]
+}
+
+/// Adds the changed files and the patch, shortened to `max_chars`, to a response.
+fn add_diff(
+    response: &mut JsonValue,
+    diff: &AlpacaGitDiff,
+    arguments: &GitArguments,
+    context: &AlpacaActions,
+) {
+    let sandbox = context.sandbox();
+    response["files"] = diff
+        .files
+        .iter()
+        .map(|file: &AlpacaGitFileStat| {
+            let mut entry = json!({ "path": sandbox.display_path(&file.path) });
+            if let Some(original) = &file.original_path {
+                entry["from"] = json!(sandbox.display_path(original));
+            }
+            match (file.added, file.removed) {
+                (Some(added), Some(removed)) => {
+                    entry["added"] = json!(added);
+                    entry["removed"] = json!(removed);
+                }
+                _ => entry["binary"] = json!(true),
+            }
+            entry
+        })
+        .collect();
+
+    let max_chars = arguments.max_chars.unwrap_or(12_000).clamp(1_000, 64_000);
+    if diff.patch.len() <= max_chars {
+        response["diff"] = json!(diff.patch);
+        return;
+    }
+
+    // Cut at the end of a line
+    let mut end = max_chars;
+    while !diff.patch.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = diff.patch[..end]
+        .rfind('\n')
+        .map_or(end, |newline| newline + 1);
+    response["diff"] = json!(&diff.patch[..end]);
+    response["truncated"] = json!(true);
+    response["note"] = json!(format!(
+        "Only the first {} of {} characters of the diff are shown. Pass 'path' to see the changes of one file.",
+        end,
+        diff.patch.len()
+    ));
+}
+
+// ===
+// AlpacaActionGit Tests
+// ===
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::sandbox::AlpacaSandbox;
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    fn git(directory: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(["-c", "commit.gpgsign=false"])
+            .args(args)
+            .current_dir(directory)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    /// Tests the log and status of a repository, and refusing other commands.
+    #[test]
+    fn test_git() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        git(root, &["init", "--quiet", "--initial-branch=main"]);
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "pub fn one() {}\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "--quiet", "-m", "Add one"]);
+        fs::write(root.join("README.md"), "# Test\n").unwrap();
+
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(AlpacaSandbox::new(root));
+        let action = AlpacaActionGit::new();
+
+        let response = action.invoke(&json!({"command": "log", "path": "src"}), &actions);
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"subject\": \"Add one\""));
+
+        let response = action.invoke(&json!({"command": "status"}), &actions);
+        assert!(response.contains("\"branch\": \"main\""), "{}", response);
+        assert!(
+            response.contains("\"untracked\": [\n    \"README.md\"\n  ]"),
+            "{}",
+            response
+        );
+        assert!(response.contains("\"clean\": false"));
+
+        let response = action.invoke(&json!({"command": "blame", "path": "src/lib.rs"}), &actions);
+        assert!(
+            response.contains("\"text\": \"pub fn one() {}\""),
+            "{}",
+            response
+        );
+
+        let response = action.invoke(
+            &json!({"command": "blame", "path": "src/lib.rs", "start_line": usize::MAX}),
+            &actions,
+        );
+        assert!(response.starts_with("## Error"), "{}", response);
+
+        let response = action.invoke(&json!({"command": "commit"}), &actions);
+        assert!(response.contains("'commit' is not a supported command."));
+
+        let response = action.invoke(
+            &json!({"command": "show", "revision": "--output=/tmp/x"}),
+            &actions,
+        );
+        assert!(response.contains("'--output=/tmp/x' is not a valid revision."));
+    }
+}
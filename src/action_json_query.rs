@@ -0,0 +1,307 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::json_path::AlpacaJsonPath;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::fs;
+
+const NAME: &str = "json_query";
+const DESCRIPTION: &str = r#"
+# `json_query`
+
+The 'json_query' action selects values from a JSON file, or from inline JSON,
+with a JSONPath query. Use it instead of reading a whole JSON file when you only
+need some of it. Here is an example of how to invoke it:
+
+```json
+{
+    "action": "json_query",
+    "file_name": "package.json",
+    "query": "$.dependencies"
+}
+```
+
+Provide either:
+- `file_name`: the path of a JSON file, relative to the current directory
+- `json`: the JSON value to query, inline
+
+Query syntax:
+- `$` is the whole document; `.name` or `['name']` a member; `[0]` or `[-1]` an element
+- `[*]` or `.*` every child; `[1:3]` a slice; `[0,2]` several elements
+- `..name` every `name` member at any depth
+- `[?(@.price < 10)]` the children whose member compares with a literal
+  (`==`, `!=`, `<`, `<=`, `>`, `>=`), and `[?(@.name)]` those that have the member
+- jq-style queries such as `.items[].name` also work
+
+Optional arguments:
+- `include_paths`: `true` to return the path of each value, such as `$.items[0].name`
+- `max_results`: the most values returned, 50 by default
+- `max_chars`: the size of the output, 8000 characters by default
+"#;
+
+/// The largest file `json_query` reads.
+const MAX_FILE_SIZE: u64 = 32 * 1024 * 1024;
+/// The upper bound for `max_results`.
+const MAX_RESULTS_LIMIT: usize = 1000;
+/// The upper bound for `max_chars`.
+const MAX_CHARS_LIMIT: usize = 64_000;
+
+/// Selects values from JSON with a JSONPath query.
+#[derive(Deserialize, JsonSchema)]
+pub struct JsonQueryArguments {
+    /// The JSONPath query.
+    pub query: String,
+    /// The path of a JSON file, relative to the current directory.
+    #[serde(default)]
+    pub file_name: Option<String>,
+    /// The JSON value to query.
+    #[serde(default)]
+    pub json: Option<JsonValue>,
+    /// Whether the path of each value is returned.
+    #[serde(default)]
+    pub include_paths: bool,
+    /// The most values returned.
+    #[serde(default)]
+    pub max_results: Option<usize>,
+    /// The size of the output, in characters.
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+}
+
+pub struct AlpacaActionJsonQuery {}
+
+impl AlpacaActionJsonQuery {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn document(
+        &self,
+        arguments: &JsonQueryArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        match (&arguments.file_name, &arguments.json) {
+            (Some(file_name), None) => {
+                let path = context.sandbox().resolve(file_name)?;
+                let metadata = fs::metadata(&path)
+                    .map_err(|e| format!("Failed to read file '{}': {}.", file_name, e))?;
+                if metadata.len() > MAX_FILE_SIZE {
+                    return Err(format!(
+                        "The file '{}' is too large to query ({} bytes).",
+                        file_name,
+                        metadata.len()
+                    ));
+                }
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read file '{}': {}.", file_name, e))?;
+                serde_json::from_str(&content)
+                    .map_err(|e| format!("The file '{}' is not valid JSON: {}.", file_name, e))
+            }
+            // JSON passed as a string is parsed, when it holds an object or array
+            (None, Some(JsonValue::String(text))) => Ok(serde_json::from_str(text)
+                .ok()
+                .filter(|value: &JsonValue| value.is_object() || value.is_array())
+                .unwrap_or_else(|| JsonValue::String(text.clone()))),
+            (None, Some(value)) => Ok(value.clone()),
+            (Some(_), Some(_)) => {
+                Err("Provide either 'file_name' or 'json', not both.".to_string())
+            }
+            (None, None) => Err("Provide the JSON to query as 'file_name' or 'json'.".to_string()),
+        }
+    }
+
+    fn query(
+        &self,
+        arguments: &JsonQueryArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let query = AlpacaJsonPath::parse(&arguments.query)?;
+        let document = self.document(arguments, context)?;
+        let found = query.select(&document);
+
+        let max_results = arguments
+            .max_results
+            .unwrap_or(50)
+            .clamp(1, MAX_RESULTS_LIMIT);
+        let max_chars = arguments
+            .max_chars
+            .unwrap_or(8000)
+            .clamp(200, MAX_CHARS_LIMIT);
+
+        let mut results = Vec::new();
+        let mut used = 0;
+        let mut truncated = found.len() > max_results;
+        let mut summarized = false;
+        for found in found.iter().take(max_results) {
+            let mut value = found.value.clone();
+            let mut size = serde_json::to_string(&value).unwrap_or_default().len();
+
+            // A single value larger than the budget is described instead
+            if size > max_chars {
+                value = summarize(found.value);
+                size = serde_json::to_string(&value).unwrap_or_default().len();
+                summarized = true;
+            }
+            if used + size > max_chars && !results.is_empty() {
+                truncated = true;
+                break;
+            }
+            used += size;
+
+            results.push(if arguments.include_paths {
+                json!({"path": found.path, "value": value})
+            } else {
+                value
+            });
+        }
+
+        let mut response = json!({
+            "query": arguments.query,
+            "count": found.len(),
+            "results": results,
+        });
+        if truncated {
+            response["truncated"] = json!(true);
+            response["note"] = json!(format!(
+                "Only {} of {} values are shown. Use a more specific query to see the rest.",
+                results.len(),
+                found.len()
+            ));
+        } else if summarized {
+            response["note"] =
+                json!("Values too large to show are summarized. Query their members to see them.");
+        }
+
+        Ok(response)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionJsonQuery {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: JsonQueryArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.query(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+/// Describes a value too large to return: its type, size and first keys.
+pub(crate) fn summarize(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Object(map) => json!({
+            "summary": "object",
+            "member_count": map.len(),
+            "keys": map.keys().take(50).collect::<Vec<_>>(),
+        }),
+        JsonValue::Array(array) => json!({
+            "summary": "array",
+            "length": array.len(),
+        }),
+        JsonValue::String(text) => json!({
+            "summary": "string",
+            "length": text.chars().count(),
+            "start": text.chars().take(200).collect::<String>(),
+        }),
+        other => other.clone(),
+    }
+}
+
+// ===
+// AlpacaActionJsonQuery Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::AlpacaSandbox;
+
+    fn response(object: JsonValue, actions: &AlpacaActions) -> JsonValue {
+        let response = AlpacaActionJsonQuery::new().invoke(&object, actions);
+        assert!(response.starts_with("## Success"), "{}", response);
+        let json = response.split("```json\n").nth(1).unwrap();
+        serde_json::from_str(json.split("\n```").next().unwrap()).unwrap()
+    }
+
+    /// Tests querying a file in the sandbox.
+    #[test]
+    fn test_query_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("package.json"),
+            r#"{"name": "app", "dependencies": {"left-pad": "1.0.0", "react": "18.2.0"}}"#,
+        )
+        .unwrap();
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(AlpacaSandbox::new(temp_dir.path()));
+
+        let value = response(
+            json!({"file_name": "package.json", "query": "$.dependencies.react", "include_paths": true}),
+            &actions,
+        );
+        assert_eq!(value["count"], 1);
+        assert_eq!(
+            value["results"][0],
+            json!({"path": "$.dependencies.react", "value": "18.2.0"})
+        );
+    }
+
+    /// Tests inline JSON and the result limits.
+    #[test]
+    fn test_query_inline_limits() {
+        let actions = AlpacaActions::new();
+        let items: Vec<JsonValue> = (0..20).map(|index| json!({"id": index})).collect();
+
+        let value = response(
+            json!({"json": {"items": items}, "query": ".items[].id", "max_results": 5}),
+            &actions,
+        );
+        assert_eq!(value["count"], 20);
+        assert_eq!(value["results"], json!([0, 1, 2, 3, 4]));
+        assert_eq!(value["truncated"], true);
+
+        let value = response(
+            json!({"json": "{\"a\": [1, 2]}", "query": "$.a[1]"}),
+            &actions,
+        );
+        assert_eq!(value["results"], json!([2]));
+
+        let big = "x".repeat(1000);
+        let value = response(
+            json!({"json": {"text": big}, "query": "$.text", "max_chars": 200}),
+            &actions,
+        );
+        assert_eq!(value["results"][0]["summary"], "string");
+    }
+
+    /// Tests errors for invalid queries and missing input.
+    #[test]
+    fn test_query_errors() {
+        let actions = AlpacaActions::new();
+        let action = AlpacaActionJsonQuery::new();
+
+        let response = action.invoke(&json!({"json": {}, "query": "$.a[?(@.b <)]"}), &actions);
+        assert!(
+            response.contains("Invalid query '$.a[?(@.b <)]'"),
+            "{}",
+            response
+        );
+
+        let response = action.invoke(&json!({"query": "$"}), &actions);
+        assert!(response.contains("Provide the JSON to query"));
+    }
+}
@@ -0,0 +1,324 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::action_json_query::summarize;
+use crate::config_file::AlpacaConfigFormat;
+use crate::json_path::AlpacaJsonPath;
+use crate::permission::{AlpacaPermission, AlpacaPermissionRequest};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::fs;
+
+const NAME: &str = "read_config";
+const DESCRIPTION: &str = r#"
+# `read_config`
+
+The 'read_config' action reads a configuration file (TOML, YAML, INI, `.env` or
+JSON) and returns its contents as JSON. Use it instead of 'read_file' for
+manifests such as `Cargo.toml` or `package.json`. Here is an example of how to
+invoke it:
+
+```json
+{
+    "action": "read_config",
+    "file_name": "Cargo.toml",
+    "key": "dependencies"
+}
+```
+
+Optional arguments:
+- `key`: the part of the file to return, such as `package.name`,
+  `dependencies.serde` or `servers[0].host` (any 'json_query' query works)
+- `format`: `toml`, `yaml`, `ini`, `env` or `json`, when the file name does not
+  tell
+- `redact`: `true` to hide the values. The values of `.env` files are always
+  hidden.
+- `max_chars`: the size of the output, 8000 characters by default
+"#;
+
+/// The largest file `read_config` reads.
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+/// The text that replaces redacted values.
+const REDACTED: &str = "<redacted>";
+
+/// Reads a configuration file as JSON.
+#[derive(Deserialize, JsonSchema)]
+pub struct ReadConfigArguments {
+    /// The path of the file, relative to the current directory.
+    pub file_name: String,
+    /// The part of the file to return, such as `package.name`.
+    #[serde(default)]
+    pub key: Option<String>,
+    /// The format of the file: toml, yaml, ini, env or json.
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Whether the values are hidden. `.env` files are always redacted,
+    /// unless the host approves showing them.
+    #[serde(default)]
+    pub redact: Option<bool>,
+    /// The size of the output, in characters.
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+}
+
+pub struct AlpacaActionReadConfig {}
+
+impl AlpacaActionReadConfig {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn read(
+        &self,
+        arguments: &ReadConfigArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let file_name = &arguments.file_name;
+        let path = context.sandbox().resolve(file_name)?;
+
+        let format = match &arguments.format {
+            Some(name) => AlpacaConfigFormat::from_name(name).ok_or_else(|| {
+                format!(
+                    "Unknown format '{}'. Use toml, yaml, ini, env or json.",
+                    name
+                )
+            })?,
+            None => AlpacaConfigFormat::detect(&path).ok_or_else(|| {
+                format!(
+                    "Cannot tell the format of '{}' from its name. Pass 'format'.",
+                    file_name
+                )
+            })?,
+        };
+
+        let metadata = fs::metadata(&path)
+            .map_err(|e| format!("Failed to read file '{}': {}.", file_name, e))?;
+        if metadata.len() > MAX_FILE_SIZE {
+            return Err(format!(
+                "The file '{}' is too large to read as configuration ({} bytes).",
+                file_name,
+                metadata.len()
+            ));
+        }
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read file '{}': {}.", file_name, e))?;
+        let document = format.parse(&text).map_err(|error| {
+            format!(
+                "Failed to parse '{}' as {}: {}.",
+                file_name,
+                format.name(),
+                error
+            )
+        })?;
+
+        let mut response = json!({
+            "file_name": file_name,
+            "format": format.name(),
+        });
+
+        let mut value = match &arguments.key {
+            Some(key) => {
+                response["key"] = json!(key);
+                select(&document, key).map_err(|error| {
+                    format!("{} in '{}'. {}", error, file_name, keys_hint(&document))
+                })?
+            }
+            None => document,
+        };
+
+        // The values of `.env` files are secrets, whatever format they are read as
+        let secret = format == AlpacaConfigFormat::Env
+            || AlpacaConfigFormat::detect(&path) == Some(AlpacaConfigFormat::Env);
+        let redact = arguments.redact.unwrap_or(secret);
+        if secret && !redact {
+            let display_name = context.sandbox().display_path(&path);
+            let request = AlpacaPermissionRequest::new(
+                NAME,
+                &format!("Show the values of '{}'", display_name),
+                &format!(
+                    "The values of '{}' may be secrets, such as API keys.\n",
+                    display_name
+                ),
+            );
+            if let AlpacaPermission::Deny(reason) = context.request_permission(&request) {
+                return Err(format!(
+                    "Showing the values of '{}' was not permitted: {}",
+                    file_name, reason
+                ));
+            }
+        }
+        if redact {
+            redact_values(&mut value);
+            response["redacted"] = json!(true);
+        }
+
+        let max_chars = arguments.max_chars.unwrap_or(8000).clamp(200, 64_000);
+        if serde_json::to_string(&value).unwrap_or_default().len() > max_chars {
+            value = summarize(&value);
+            response["note"] = json!(
+                "The value is too large to show and is summarized. Pass 'key' to read part of it."
+            );
+        }
+        response["value"] = value;
+
+        Ok(response)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionReadConfig {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: ReadConfigArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.read(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+/// Selects a key path. Plain paths like `package.name` are read from the root.
+fn select(document: &JsonValue, key: &str) -> Result<JsonValue, String> {
+    let query = if key.starts_with(['$', '.', '[']) {
+        key.to_string()
+    } else {
+        format!("$.{}", key)
+    };
+    let found = AlpacaJsonPath::parse(&query)?.select(document);
+
+    match found.len() {
+        0 => Err(format!("The key '{}' was not found", key)),
+        1 => Ok(found[0].value.clone()),
+        _ => Ok(found.iter().map(|found| found.value.clone()).collect()),
+    }
+}
+
+fn keys_hint(document: &JsonValue) -> String {
+    match document.as_object() {
+        Some(map) if !map.is_empty() => format!(
+            "The top-level keys are: {}.",
+            map.keys()
+                .take(50)
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => String::new(),
+    }
+}
+
+/// Replaces every non-empty scalar with a placeholder, keeping the keys.
+fn redact_values(value: &mut JsonValue) {
+    match value {
+        JsonValue::Object(map) => map.values_mut().for_each(redact_values),
+        JsonValue::Array(array) => array.iter_mut().for_each(redact_values),
+        JsonValue::Null => {}
+        JsonValue::String(text) if text.is_empty() => {}
+        other => *other = JsonValue::String(REDACTED.to_string()),
+    }
+}
+
+// ===
+// AlpacaActionReadConfig Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::AlpacaAllowAll;
+    use crate::sandbox::AlpacaSandbox;
+
+    fn actions(files: &[(&str, &str)]) -> (tempfile::TempDir, AlpacaActions) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for (name, content) in files {
+            fs::write(temp_dir.path().join(name), content).unwrap();
+        }
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(AlpacaSandbox::new(temp_dir.path()));
+        (temp_dir, actions)
+    }
+
+    /// Tests reading a manifest, narrowed with a key.
+    #[test]
+    fn test_read_config_key() {
+        let (_temp_dir, actions) = actions(&[(
+            "Cargo.toml",
+            "[package]\nname = \"app\"\n\n[dependencies]\nserde = \"1.0\"\ndev-tools = { path = \"tools\" }\n",
+        )]);
+        let action = AlpacaActionReadConfig::new();
+
+        let response = action.invoke(
+            &json!({"file_name": "Cargo.toml", "key": "dependencies"}),
+            &actions,
+        );
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"serde\": \"1.0\""));
+        assert!(response.contains("\"path\": \"tools\""));
+        assert!(!response.contains("\"app\""));
+
+        let response = action.invoke(
+            &json!({"file_name": "Cargo.toml", "key": "package.version"}),
+            &actions,
+        );
+        assert!(response.contains("The key 'package.version' was not found in 'Cargo.toml'"));
+        assert!(response.contains("The top-level keys are: dependencies, package."));
+    }
+
+    /// Tests that `.env` values are redacted unless the host approves showing them.
+    #[test]
+    fn test_read_config_redacted() {
+        let (_temp_dir, mut actions) = actions(&[(".env", "API_KEY=secret\nEMPTY=\n")]);
+        let action = AlpacaActionReadConfig::new();
+
+        let response = action.invoke(&json!({"file_name": ".env"}), &actions);
+        assert!(
+            response.contains("\"API_KEY\": \"<redacted>\""),
+            "{}",
+            response
+        );
+        assert!(response.contains("\"EMPTY\": \"\""));
+        assert!(!response.contains("secret"));
+
+        let response = action.invoke(
+            &json!({"file_name": ".env", "format": "yaml", "redact": false}),
+            &actions,
+        );
+        assert!(response.contains("was not permitted"), "{}", response);
+        assert!(!response.contains("secret"));
+
+        actions.set_permission_policy(Box::new(AlpacaAllowAll));
+        let response = action.invoke(&json!({"file_name": ".env", "redact": false}), &actions);
+        assert!(response.contains("\"API_KEY\": \"secret\""));
+    }
+
+    /// Tests that parse errors name the file and line.
+    #[test]
+    fn test_read_config_parse_error() {
+        let (_temp_dir, actions) = actions(&[("settings.conf", "a: [1, 2\n")]);
+        let action = AlpacaActionReadConfig::new();
+
+        let response = action.invoke(&json!({"file_name": "settings.conf"}), &actions);
+        assert!(response.contains("Pass 'format'"), "{}", response);
+
+        let response = action.invoke(
+            &json!({"file_name": "settings.conf", "format": "yaml"}),
+            &actions,
+        );
+        assert!(
+            response.contains("Failed to parse 'settings.conf' as yaml: line "),
+            "{}",
+            response
+        );
+    }
+}
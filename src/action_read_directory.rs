@@ -1,31 +1,36 @@
 use crate::action::AlpacaActionTrait;
 use crate::action::AlpacaActions;
+use crate::dir_listing::{AlpacaDirListing, AlpacaListArguments, LIST_OPTIONS_HELP};
 use serde_json::Value as JsonValue;
+use std::sync::LazyLock;
 
 const NAME: &str = "read_directory";
-const DESCRIPTION: &str = r#"
+const USAGE: &str = r#"
 The 'read_directory' action provides the names of the files and subdirectories
 in the current working directory.
 
 Here is an example of how to invoke it:
 ```json
 {
-    "action": "read_directory",
+    "action": "read_directory"
 }
 ```
-"#;
-
-// ---
 
-const EXAMPLE_USAGE: &str = r#"
-Here is an example of how to invoke it:
+And with options, to list the Rust files of a subdirectory by size:
 ```json
 {
     "action": "read_directory",
+    "path": "src",
+    "extensions": ["rs"],
+    "metadata": true,
+    "sort": "size"
 }
 ```
+
 "#;
 
+static DESCRIPTION: LazyLock<String> = LazyLock::new(|| format!("{}{}", USAGE, LIST_OPTIONS_HELP));
+
 // ---
 
 fn format_response(status: &str, response: &str) -> String {
@@ -50,54 +55,37 @@ impl AlpacaActionTrait for AlpacaActionReadDirectory {
     }
 
     fn description(&self) -> &str {
-        DESCRIPTION
+        &DESCRIPTION
     }
 
-    fn invoke(&self, object: &JsonValue, _context: &AlpacaActions) -> String {
-        // Return an error if the action contains too many arguments
-        if let Some(object) = object.as_object() {
-            if object.len() > 1 {
-                // return format!("{}{}", ERR_TOO_MANY_ARGS, DESCRIPTION);
-                let response = format_response(
-                    "Error",
-                    "The action `read_directory` does not take any additional arguments.",
-                );
-                return format!("{}{}", response, EXAMPLE_USAGE);
-            }
-        }
-
-        // Read the current directory
-        let current_dir = std::env::current_dir().unwrap_or_default();
-        let mut files = Vec::new();
-        let mut directories = Vec::new();
-
-        // Read directory entries
-        if let Ok(entries) = std::fs::read_dir(&current_dir) {
-            for entry in entries.flatten() {
-                if let Ok(file_type) = entry.file_type() {
-                    if let Ok(file_name) = entry.file_name().into_string() {
-                        if file_type.is_file() {
-                            files.push(file_name);
-                        } else if file_type.is_dir() {
-                            directories.push(file_name);
-                        }
-                    }
-                }
-            }
-        }
-
-        // Sort lists for consistent output
-        files.sort();
-        directories.sort();
-
-        let ok = serde_json::json!({
-            "files": files,
-            "subdirectories": directories,
-        });
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: AlpacaListArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        // Read the requested directory, or the current one
+        let path = arguments.path.as_deref().unwrap_or(".");
+        let listing = context
+            .sandbox()
+            .resolve(path)
+            .and_then(|directory| AlpacaDirListing::read(&directory, &arguments.options));
+        let listing = match listing {
+            Ok(listing) => listing,
+            Err(error) => return format_response("Error", &error),
+        };
+
+        let mut ok = serde_json::json!({});
+        listing.add_to(&mut ok, "files", "subdirectories");
 
         let directory_block = AlpacaActions::blockify(&ok);
         let response = format!(
-            "Here are the contents of the current directory:\n{}",
+            "Here are the contents of the {}:\n{}",
+            if arguments.path.is_some() {
+                format!("directory '{}'", path)
+            } else {
+                "current directory".to_string()
+            },
             &directory_block
         );
 
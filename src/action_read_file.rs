@@ -1,14 +1,21 @@
 use crate::action::AlpacaActionTrait;
 use crate::action::AlpacaActions;
+use crate::file_content::{AlpacaFileContent, base64, detect_type, hex_dump};
+use schemars::JsonSchema;
+use serde::Deserialize;
 use serde_json::Value as JsonValue;
 use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::path::Path;
 
 const NAME: &str = "read_file";
 const DESCRIPTION: &str = r#"
 # `read_file`
 
-The 'read_file' action outputs the contents of the specified file. Only files
-in the current directory can be read.  Here is an example of how to invoke it:
+The 'read_file' action outputs the contents of the specified file, with line
+numbers. Only files in the current directory can be read. The response includes
+the SHA-256 hash of the file, which can be passed to `edit_file` to make sure the
+file has not changed in the meantime. Here is an example of how to invoke it:
 
 ```json
 {
@@ -16,29 +23,306 @@ in the current directory can be read.  Here is an example of how to invoke it:
     "file_name": "example.txt"
 }
 ```
+
+Large files are truncated. To read part of a file, give the first line and
+either the last line or the number of lines:
+
+```json
+{
+    "action": "read_file",
+    "file_name": "example.txt",
+    "start_line": 200,
+    "end_line": 260
+}
+```
+
+The other optional arguments are `limit` (the number of lines to read),
+`line_numbers` (`true` by default) and `max_chars` (the most text to return).
+
+Text in UTF-16 or Latin-1 is decoded automatically. For binary files, the
+response describes the file instead; to see its bytes, add `binary_format`
+(`hex` or `base64`) with an optional `byte_offset` and `byte_length`:
+
+```json
+{
+    "action": "read_file",
+    "file_name": "logo.png",
+    "binary_format": "hex",
+    "byte_offset": 0,
+    "byte_length": 64
+}
+```
 "#;
 
-pub struct AlpacaActionReadFile {}
+/// The most text returned by a single read, in characters, unless `max_chars` is given.
+pub const DEFAULT_MAX_CHARS: usize = 16 * 1024;
+/// The upper bound for `max_chars`.
+const MAX_CHARS_LIMIT: usize = 64 * 1024;
+/// The number of bytes of a binary file shown when `byte_length` is not given.
+const DEFAULT_BYTE_LENGTH: usize = 256;
+/// The upper bound for `byte_length`.
+const MAX_BYTE_LENGTH: usize = 4096;
 
-impl AlpacaActionReadFile {
-    pub fn new() -> Self {
-        Self {}
+fn default_true() -> bool {
+    true
+}
+
+/// How the bytes of a binary file are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryFormat {
+    Hex,
+    Base64,
+}
+
+/// Outputs the contents of a file, or a range of its lines.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReadFileArguments {
+    /// The path of the file to read, relative to the current directory.
+    pub file_name: String,
+    /// The first line to read, starting at 1.
+    #[serde(default)]
+    pub start_line: Option<usize>,
+    /// The last line to read, inclusive.
+    #[serde(default)]
+    pub end_line: Option<usize>,
+    /// The number of lines to read, as an alternative to `end_line`.
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Whether each line is prefixed with its number.
+    #[serde(default = "default_true")]
+    pub line_numbers: bool,
+    /// The most text to return, in characters.
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+    /// How to show the bytes of a binary file.
+    #[serde(default)]
+    pub binary_format: Option<BinaryFormat>,
+    /// The first byte of a binary file to show.
+    #[serde(default)]
+    pub byte_offset: Option<usize>,
+    /// The number of bytes of a binary file to show.
+    #[serde(default)]
+    pub byte_length: Option<usize>,
+}
+
+/// Computes the hash reported by `read_file` and checked by `edit_file`, as lowercase hex.
+pub fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Selects the requested lines of a text file, for `read_file` responses.
+///
+/// # Arguments
+///
+/// * `display_name` - The name of the file shown to the model
+/// * `content` - The full text of the file
+/// * `arguments` - The requested range and formatting
+///
+/// # Returns
+///
+/// * `Ok(JsonValue)` - The excerpt, its line range, the total line count and a
+///   continuation hint if the output was truncated
+/// * `Err(String)` - A description of the invalid range
+pub fn read_lines(
+    display_name: &str,
+    content: &str,
+    arguments: &ReadFileArguments,
+) -> Result<JsonValue, String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let total_lines = lines.len();
+
+    let start_line = arguments.start_line.unwrap_or(1).max(1);
+    if start_line > total_lines.max(1) {
+        return Err(format!(
+            "The start line {} is past the end of '{}', which has {} lines.",
+            start_line, display_name, total_lines
+        ));
+    }
+
+    let requested_end = match (arguments.end_line, arguments.limit) {
+        (Some(end_line), _) => end_line,
+        (None, Some(limit)) => start_line.saturating_add(limit.max(1) - 1),
+        (None, None) => total_lines,
+    }
+    .min(total_lines);
+    if requested_end < start_line && total_lines > 0 {
+        return Err(format!(
+            "The end line {} is before the start line {}.",
+            requested_end, start_line
+        ));
+    }
+
+    // Stop adding lines once the output budget is used up
+    let max_chars = arguments
+        .max_chars
+        .unwrap_or(DEFAULT_MAX_CHARS)
+        .clamp(1, MAX_CHARS_LIMIT);
+    let width = requested_end.to_string().len();
+    let mut output = String::new();
+    let mut used = 0;
+    let mut end_line = requested_end;
+    let mut truncated = false;
+    let mut cut_line = None;
+
+    for number in start_line..=requested_end {
+        let Some(line) = lines.get(number - 1) else {
+            break;
+        };
+        let formatted = if arguments.line_numbers {
+            format!("{:>width$}\t{}\n", number, line, width = width)
+        } else {
+            format!("{}\n", line)
+        };
+
+        let length = formatted.chars().count();
+        if used + length > max_chars {
+            truncated = true;
+            if !output.is_empty() {
+                end_line = number - 1;
+                break;
+            }
+            // A first line longer than the budget is cut to fit
+            output.extend(formatted.chars().take(max_chars));
+            output.push('\n');
+            end_line = number;
+            cut_line = Some(number);
+            break;
+        }
+        used += length;
+        output.push_str(&formatted);
     }
 
-    fn read_file(&self, file_name: &str) -> Result<String, String> {
-        // Attempt to read file contents
-        match std::fs::read_to_string(file_name) {
-            Ok(content) => Ok(content),
-            Err(e) => {
-                // Return the error message if file reading fails
-                let error = format!(
-                    "Failed to read file '{}': {}.\nPlease ensure the file name is correct and try again.",
-                    file_name,
-                    e.to_string()
-                );
-                Err(error)
+    let mut response = json!({
+        "file_name": display_name,
+        "start_line": start_line,
+        "end_line": end_line,
+        "total_lines": total_lines,
+        "content": output,
+    });
+
+    if let Some(number) = cut_line {
+        response["line_truncated"] = json!(number);
+    }
+    if truncated || end_line < total_lines {
+        response["next_start_line"] = json!(end_line + 1);
+        response["note"] = json!(format!(
+            "Showing lines {}-{} of {}{}. To continue, read the file again with \"start_line\": {}.",
+            start_line,
+            end_line,
+            total_lines,
+            match (truncated, cut_line) {
+                (_, Some(number)) => format!(
+                    ", with line {} cut after {} characters, because the output size limit was reached",
+                    number, max_chars
+                ),
+                (true, None) => " because the output size limit was reached".to_string(),
+                (false, None) => String::new(),
+            },
+            end_line + 1
+        ));
+    }
+
+    Ok(response)
+}
+
+/// Reads a file and selects the requested lines, adding the hash of the whole file.
+///
+/// Text in other encodings is decoded first. Binary files are summarized
+/// instead, with an optional hex dump or base64 encoding of a byte range.
+pub fn read_file(
+    path: &Path,
+    display_name: &str,
+    arguments: &ReadFileArguments,
+) -> Result<JsonValue, String> {
+    let bytes = std::fs::read(path).map_err(|e| {
+        format!(
+            "Failed to read file '{}': {}.\nPlease ensure the file name is correct and try again.",
+            display_name, e
+        )
+    })?;
+
+    let mut response = match AlpacaFileContent::decode(&bytes) {
+        AlpacaFileContent::Text {
+            text,
+            encoding,
+            lossy,
+        } => {
+            let mut response = read_lines(display_name, &text, arguments)?;
+            if encoding != "utf-8" || lossy {
+                response["encoding"] = json!(encoding);
+            }
+            if lossy {
+                response["lossy"] = json!(true);
             }
+            response
         }
+        AlpacaFileContent::Binary => read_binary(display_name, &bytes, arguments)?,
+    };
+
+    response["sha256"] = json!(content_hash(&bytes));
+    Ok(response)
+}
+
+/// Summarizes a binary file, adding the requested byte range if a format is given.
+fn read_binary(
+    display_name: &str,
+    bytes: &[u8],
+    arguments: &ReadFileArguments,
+) -> Result<JsonValue, String> {
+    let magic = &bytes[..bytes.len().min(16)];
+    let mut response = json!({
+        "file_name": display_name,
+        "binary": true,
+        "size": bytes.len(),
+        "detected_type": detect_type(bytes).unwrap_or("unknown"),
+        "magic_bytes": magic.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" "),
+    });
+
+    let Some(format) = arguments.binary_format else {
+        response["note"] = json!(
+            "This is a binary file. To see its bytes, read it again with \"binary_format\": \"hex\" or \"base64\", and optionally \"byte_offset\" and \"byte_length\"."
+        );
+        return Ok(response);
+    };
+
+    let offset = arguments.byte_offset.unwrap_or(0);
+    if offset > bytes.len() {
+        return Err(format!(
+            "The byte offset {} is past the end of '{}', which has {} bytes.",
+            offset,
+            display_name,
+            bytes.len()
+        ));
+    }
+    let length = arguments
+        .byte_length
+        .unwrap_or(DEFAULT_BYTE_LENGTH)
+        .min(MAX_BYTE_LENGTH);
+    let end = (offset + length).min(bytes.len());
+    let range = &bytes[offset..end];
+
+    response["byte_offset"] = json!(offset);
+    response["byte_length"] = json!(range.len());
+    match format {
+        BinaryFormat::Hex => response["hex"] = json!(hex_dump(range, offset)),
+        BinaryFormat::Base64 => response["base64"] = json!(base64(range)),
+    }
+    if end < bytes.len() {
+        response["next_byte_offset"] = json!(end);
+    }
+
+    Ok(response)
+}
+
+pub struct AlpacaActionReadFile {}
+
+impl AlpacaActionReadFile {
+    pub fn new() -> Self {
+        Self {}
     }
 }
 
@@ -51,39 +335,163 @@ impl AlpacaActionTrait for AlpacaActionReadFile {
         DESCRIPTION
     }
 
-    fn invoke(&self, object: &JsonValue, _context: &AlpacaActions) -> String {
-        // If we don't have a 'file_name' field, return an error
-        let filename = object["file_name"].as_str();
-        if filename.is_none() {
-            // If 'file_name' is not provided, return an error
-            let error = json!({
-                "error": format!(
-                    "The 'file_name' field is missing from the request. Please review the following description and try again.\n\n{}\n",
-                    DESCRIPTION),
-            });
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: ReadFileArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        let result = context
+            .sandbox()
+            .resolve(&arguments.file_name)
+            .and_then(|path| read_file(&path, &arguments.file_name, &arguments));
 
-            return AlpacaActions::blockify(&error);
+        match result {
+            Ok(response) => AlpacaActions::blockify(&response),
+            Err(error) => AlpacaActions::blockify(&json!({ "error": error })),
         }
+    }
+}
 
-        // If 'file_name' is provided, read the file
-        let filename = filename.unwrap();
-        match self.read_file(filename) {
-            Ok(content) => {
-                // Create a JSON object with the file content
-                let response = json!({
-                    "content": content,
-                });
+// ===
+// AlpacaActionReadFile Tests
+// ===
 
-                return AlpacaActions::blockify(&response);
-            }
-            Err(error) => {
-                // Return the error message if file reading fails
-                let output = json!({
-                    "error": error,
-                });
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                return AlpacaActions::blockify(&output);
-            }
-        }
+    fn arguments(object: JsonValue) -> ReadFileArguments {
+        let mut object = object;
+        object["file_name"] = json!("a.txt");
+        serde_json::from_value(object).unwrap()
+    }
+
+    fn text(lines: usize) -> String {
+        (1..=lines).map(|i| format!("line {}\n", i)).collect()
+    }
+
+    /// Tests reading a whole file with line numbers.
+    #[test]
+    fn test_read_lines() {
+        let response = read_lines("a.txt", "a\nb\n", &arguments(json!({}))).unwrap();
+
+        assert_eq!(response["content"], "1\ta\n2\tb\n");
+        assert_eq!(response["total_lines"], 2);
+        assert!(response.get("next_start_line").is_none());
+    }
+
+    /// Tests reading a range, with and without line numbers.
+    #[test]
+    fn test_read_lines_range() {
+        let content = text(20);
+
+        let response = read_lines(
+            "a.txt",
+            &content,
+            &arguments(json!({"start_line": 9, "end_line": 10})),
+        )
+        .unwrap();
+        assert_eq!(response["content"], " 9\tline 9\n10\tline 10\n");
+        assert_eq!(response["next_start_line"], 11);
+
+        let response = read_lines(
+            "a.txt",
+            &content,
+            &arguments(json!({"start_line": 19, "limit": 5, "line_numbers": false})),
+        )
+        .unwrap();
+        assert_eq!(response["content"], "line 19\nline 20\n");
+        assert_eq!(response["end_line"], 20);
+        assert!(response.get("next_start_line").is_none());
+
+        let error = read_lines("a.txt", &content, &arguments(json!({"start_line": 30})));
+        assert!(error.unwrap_err().contains("has 20 lines"));
+
+        let response = read_lines(
+            "a.txt",
+            &content,
+            &arguments(json!({"start_line": 20, "limit": usize::MAX})),
+        )
+        .unwrap();
+        assert_eq!(response["end_line"], 20);
+    }
+
+    /// Tests that large outputs are truncated with a continuation hint.
+    #[test]
+    fn test_read_lines_truncated() {
+        let content = text(100);
+        let response = read_lines(
+            "a.txt",
+            &content,
+            &arguments(json!({"line_numbers": false, "max_chars": 40})),
+        )
+        .unwrap();
+
+        assert_eq!(response["end_line"], 5);
+        assert_eq!(response["next_start_line"], 6);
+        let note = response["note"].as_str().unwrap();
+        assert!(note.contains("Showing lines 1-5 of 100 because the output size limit"));
+        assert!(note.contains("\"start_line\": 6"));
+
+        let content = format!("{}\nshort\n", "é".repeat(100));
+        let response = read_lines(
+            "a.txt",
+            &content,
+            &arguments(json!({"line_numbers": false, "max_chars": 40})),
+        )
+        .unwrap();
+        assert_eq!(response["content"], format!("{}\n", "é".repeat(40)));
+        assert_eq!(response["line_truncated"], 1);
+        assert_eq!(response["next_start_line"], 2);
+        let note = response["note"].as_str().unwrap();
+        assert!(
+            note.contains("with line 1 cut after 40 characters"),
+            "{}",
+            note
+        );
+    }
+
+    /// Tests summarizing a binary file and dumping a byte range.
+    #[test]
+    fn test_read_binary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("logo.png");
+        let mut bytes = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR".to_vec();
+        bytes.extend([0u8; 100]);
+        std::fs::write(&path, &bytes).unwrap();
+
+        let response = read_file(&path, "logo.png", &arguments(json!({}))).unwrap();
+        assert_eq!(response["binary"], true);
+        assert_eq!(response["size"], 116);
+        assert_eq!(response["detected_type"], "PNG image");
+        assert!(
+            response["magic_bytes"]
+                .as_str()
+                .unwrap()
+                .starts_with("89 50 4e 47")
+        );
+        assert!(response.get("hex").is_none());
+
+        let response = read_file(
+            &path,
+            "logo.png",
+            &arguments(json!({"binary_format": "base64", "byte_offset": 1, "byte_length": 3})),
+        )
+        .unwrap();
+        assert_eq!(response["base64"], "UE5H");
+        assert_eq!(response["next_byte_offset"], 4);
+    }
+
+    /// Tests that text in another encoding is decoded and labelled.
+    #[test]
+    fn test_read_latin1() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        std::fs::write(&path, b"na\xefve caf\xe9\n").unwrap();
+
+        let response = read_file(&path, "a.txt", &arguments(json!({}))).unwrap();
+        assert_eq!(response["content"], "1\tnaïve café\n");
+        assert_eq!(response["encoding"], "latin-1");
     }
 }
@@ -1,16 +1,19 @@
 use crate::action::AlpacaActionTrait;
 use crate::action::AlpacaActions;
-use regex::Regex;
+use regex::{Captures, Regex, RegexBuilder};
+use schemars::JsonSchema;
+use serde::Deserialize;
 use serde_json::Value as JsonValue;
 use serde_json::json;
 
 const NAME: &str = "regex";
 const DESCRIPTION: &str = r#"
 The 'regex' action allows you to perform regular expression operations on text.
-You can search for patterns in text and extract matches.
+You can search for patterns in text, extract capture groups, replace text and
+filter lists of strings.
 
 - Provide the regular expression pattern as the 'pattern' parameter.
-- Provide an array of strings as the 'input' parameter:
+- Provide a string, or an array of strings, as the 'input' parameter:
 
 Here is an example of how to invoke it:
 ```json
@@ -25,6 +28,26 @@ Here is an example of how to invoke it:
 ```
 
 This will return all matches of the pattern in the provided input(s).
+
+Use the 'mode' parameter for other operations:
+- `find` (the default): the text of every match
+- `captures`: every match with its positional and named groups, such as `(?P<year>\d{4})`, and their character offsets
+- `replace`: replaces matches with 'replacement', where `$1` or `${name}` insert a group; set 'limit' to replace only the first matches
+- `filter`: the inputs that match, or that don't match with `"invert": true`
+- `is_match`: whether each input matches, and how many matches it has
+
+Flags: `case_insensitive`, `multiline` (`^` and `$` match at line breaks) and
+`dot_all` (`.` matches line breaks), all `false` by default.
+
+For example, to keep the Rust files of a directory listing:
+```json
+{
+    "action": "regex",
+    "pattern": "\\.rs$",
+    "mode": "filter",
+    "input": ["main.rs", "Cargo.toml", "lib.rs"]
+}
+```
 "#;
 
 fn format_response(status: &str, response: &str) -> String {
@@ -35,12 +58,98 @@ fn response_error(message: &str) -> String {
     format!("## Error\n\n{}\n\n## Help\n{}", message, DESCRIPTION)
 }
 
+/// The operation performed by the `regex` action.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegexMode {
+    #[default]
+    Find,
+    Captures,
+    Replace,
+    Filter,
+    IsMatch,
+}
+
+/// The text a regular expression is applied to.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum RegexInput {
+    One(String),
+    Many(Vec<JsonValue>),
+}
+
+/// Performs regular expression operations on text.
+#[derive(Deserialize, JsonSchema)]
+pub struct RegexArguments {
+    /// The regular expression.
+    pub pattern: String,
+    /// A string, or an array of strings.
+    pub input: RegexInput,
+    /// The operation to perform.
+    #[serde(default)]
+    pub mode: RegexMode,
+    /// The replacement text for the `replace` mode, where `$1` or `${name}` insert a group.
+    #[serde(default)]
+    pub replacement: Option<String>,
+    /// The most replacements per input in the `replace` mode; all by default.
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Keeps the inputs that don't match in the `filter` mode.
+    #[serde(default)]
+    pub invert: bool,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    #[serde(default)]
+    pub multiline: bool,
+    #[serde(default)]
+    pub dot_all: bool,
+}
+
 pub struct AlpacaActionRegex {}
 
 impl AlpacaActionRegex {
     pub fn new() -> Self {
         Self {}
     }
+
+    /// Applies the operation to one input.
+    fn apply(&self, regex: &Regex, text: &str, arguments: &RegexArguments) -> (JsonValue, usize) {
+        match arguments.mode {
+            RegexMode::Find | RegexMode::Filter => {
+                let matches: Vec<&str> = regex.find_iter(text).map(|m| m.as_str()).collect();
+                let count = matches.len();
+                (json!({"input": text, "matches": matches}), count)
+            }
+            RegexMode::Captures => {
+                let matches: Vec<JsonValue> = regex
+                    .captures_iter(text)
+                    .map(|captures| captures_json(regex, text, &captures))
+                    .collect();
+                let count = matches.len();
+                (json!({"input": text, "matches": matches}), count)
+            }
+            RegexMode::Replace => {
+                let replacement = arguments.replacement.as_deref().unwrap_or_default();
+                let limit = arguments.limit.unwrap_or(0);
+                let count = match limit {
+                    0 => regex.find_iter(text).count(),
+                    limit => regex.find_iter(text).take(limit).count(),
+                };
+                let output = regex.replacen(text, limit, replacement);
+                (
+                    json!({"input": text, "output": output, "replacements": count}),
+                    count,
+                )
+            }
+            RegexMode::IsMatch => {
+                let count = regex.find_iter(text).count();
+                (
+                    json!({"input": text, "is_match": count > 0, "count": count}),
+                    count,
+                )
+            }
+        }
+    }
 }
 
 impl AlpacaActionTrait for AlpacaActionRegex {
@@ -52,25 +161,23 @@ impl AlpacaActionTrait for AlpacaActionRegex {
         DESCRIPTION
     }
 
-    fn invoke(&self, object: &JsonValue, _context: &AlpacaActions) -> String {
-        // Check if we have the required fields
-        let pattern = object["pattern"].as_str();
-
-        // Check if the 'pattern' argument is provided.
-        if pattern.is_none() {
-            return response_error("Missing 'pattern' parameter");
-        }
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: RegexArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
 
-        // Check if the 'input' argument is provided.
-        let input = object.get("input");
-        if input.is_none() {
-            return response_error("Missing 'input' parameter.");
+        if arguments.mode == RegexMode::Replace && arguments.replacement.is_none() {
+            return response_error("The 'replace' mode needs a 'replacement' parameter.");
         }
 
-        let pattern = pattern.unwrap();
-
         // Compile the regex pattern
-        let regex = match Regex::new(pattern) {
+        let regex = match RegexBuilder::new(&arguments.pattern)
+            .case_insensitive(arguments.case_insensitive)
+            .multi_line(arguments.multiline)
+            .dot_matches_new_line(arguments.dot_all)
+            .build()
+        {
             Ok(re) => re,
             Err(e) => {
                 let error = format!("Invalid regex pattern: {}", e);
@@ -79,49 +186,193 @@ impl AlpacaActionTrait for AlpacaActionRegex {
         };
 
         // Handle both cases: input as string or as array of strings
-        if let Some(texts) = object["input"].as_array() {
-            let mut all_results = Vec::new();
-            let mut total_matches = 0;
-
-            for (index, text_value) in texts.iter().enumerate() {
-                if let Some(text) = text_value.as_str() {
-                    let matches: Vec<String> = regex
-                        .find_iter(text)
-                        .map(|m| m.as_str().to_string())
-                        .collect();
-
-                    total_matches += matches.len();
-
-                    all_results.push(json!({
-                        "input": text,
-                        "matches": matches,
-                    }));
+        let texts = match &arguments.input {
+            RegexInput::One(text) => vec![JsonValue::String(text.clone())],
+            RegexInput::Many(texts) => texts.clone(),
+        };
+
+        let mut all_results = Vec::new();
+        let mut total_matches = 0;
+        let mut matching_inputs = 0;
+
+        for (index, text_value) in texts.iter().enumerate() {
+            if let Some(text) = text_value.as_str() {
+                let (result, count) = self.apply(&regex, text, &arguments);
+                total_matches += count;
+
+                if arguments.mode == RegexMode::Filter {
+                    if (count > 0) != arguments.invert {
+                        all_results.push(json!(text));
+                    }
                 } else {
-                    // If an element in the array is not a string, include it as an error
-                    all_results.push(json!({
-                        "index": index,
-                        "error": "Not a string value"
-                    }));
+                    all_results.push(result);
+                }
+                if count > 0 {
+                    matching_inputs += 1;
                 }
+            } else {
+                // If an element in the array is not a string, include it as an error
+                all_results.push(json!({
+                    "index": index,
+                    "error": "Not a string value"
+                }));
             }
+        }
 
-            let response = json!({
+        let response = match arguments.mode {
+            RegexMode::Filter => json!({
+                "results": all_results,
+                "count": all_results.len(),
+                "total_inputs": texts.len(),
+            }),
+            RegexMode::IsMatch => json!({
                 "results": all_results,
                 "total_count": total_matches,
-            });
-
-            let regex_block = AlpacaActions::blockify(&response);
-            let response_text = format!(
-                "Regular expression results for pattern '{}' across {} text items:\n{}",
-                pattern,
-                texts.len(),
-                &regex_block
-            );
-
-            format_response("Success", &response_text)
-        } else {
-            // Neither a string nor an array was provided
-            response_error("The 'input' parameter must be an array of strings")
-        }
+                "matching_inputs": matching_inputs,
+            }),
+            _ => json!({
+                "results": all_results,
+                "total_count": total_matches,
+            }),
+        };
+
+        let regex_block = AlpacaActions::blockify(&response);
+        let response_text = format!(
+            "Regular expression results for pattern '{}' across {} text items:\n{}",
+            arguments.pattern,
+            texts.len(),
+            &regex_block
+        );
+
+        format_response("Success", &response_text)
+    }
+}
+
+/// Describes a match and its groups, with character offsets.
+fn captures_json(regex: &Regex, text: &str, captures: &Captures) -> JsonValue {
+    let span = |m: regex::Match| {
+        let start = text[..m.start()].chars().count();
+        json!({
+            "text": m.as_str(),
+            "start": start,
+            "end": start + m.as_str().chars().count(),
+        })
+    };
+
+    let whole = captures.get(0).unwrap();
+    let groups: Vec<JsonValue> = (1..captures.len())
+        .map(|index| captures.get(index).map(span).unwrap_or(JsonValue::Null))
+        .collect();
+
+    let mut value = span(whole);
+    value["groups"] = json!(groups);
+
+    let named: serde_json::Map<String, JsonValue> = regex
+        .capture_names()
+        .flatten()
+        .map(|name| {
+            let group = captures.name(name).map(span).unwrap_or(JsonValue::Null);
+            (name.to_string(), group)
+        })
+        .collect();
+    if !named.is_empty() {
+        value["named"] = JsonValue::Object(named);
+    }
+
+    value
+}
+
+// ===
+// AlpacaActionRegex Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoke(object: JsonValue) -> String {
+        AlpacaActionRegex::new().invoke(&object, &AlpacaActions::new())
+    }
+
+    /// Tests finding matches in a single string and in an array.
+    #[test]
+    fn test_find() {
+        let response = invoke(json!({"action": NAME, "pattern": "\\d+", "input": "a 1 b 22"}));
+        assert!(response.contains("\"1\",\n"), "{}", response);
+        assert!(response.contains("\"total_count\": 2"));
+
+        let response = invoke(json!({"action": NAME, "pattern": "\\d", "input": ["x1", 3]}));
+        assert!(response.contains("\"error\": \"Not a string value\""));
+    }
+
+    /// Tests positional and named groups with character offsets.
+    #[test]
+    fn test_captures() {
+        let response = invoke(json!({
+            "action": NAME,
+            "pattern": "(?P<key>\\w+)=(\\d+)?",
+            "mode": "captures",
+            "input": "é a=1 b=",
+        }));
+        let json = response.split("```json\n").nth(1).unwrap();
+        let value: JsonValue = serde_json::from_str(json.split("\n```").next().unwrap()).unwrap();
+
+        let matches = &value["results"][0]["matches"];
+        assert_eq!(matches[0]["start"], 2);
+        assert_eq!(matches[0]["groups"][1]["text"], "1");
+        assert_eq!(matches[0]["named"]["key"]["text"], "a");
+        assert_eq!(matches[1]["groups"][1], JsonValue::Null);
+    }
+
+    /// Tests replacing with group references, flags and a limit.
+    #[test]
+    fn test_replace() {
+        let response = invoke(json!({
+            "action": NAME,
+            "pattern": "(?P<word>foo)",
+            "mode": "replace",
+            "replacement": "[${word}]",
+            "case_insensitive": true,
+            "limit": 2,
+            "input": "Foo foo FOO",
+        }));
+        assert!(
+            response.contains("\"output\": \"[Foo] [foo] FOO\""),
+            "{}",
+            response
+        );
+        assert!(response.contains("\"replacements\": 2"));
+
+        let response =
+            invoke(json!({"action": NAME, "pattern": "x", "mode": "replace", "input": "x"}));
+        assert!(response.contains("needs a 'replacement'"));
+    }
+
+    /// Tests filtering inputs and counting matches.
+    #[test]
+    fn test_filter_and_is_match() {
+        let input = json!(["main.rs", "Cargo.toml", "lib.rs"]);
+        let response =
+            invoke(json!({"action": NAME, "pattern": "\\.rs$", "mode": "filter", "input": input}));
+        assert!(
+            response.contains("\"main.rs\",\n    \"lib.rs\""),
+            "{}",
+            response
+        );
+
+        let response = invoke(json!({
+            "action": NAME, "pattern": "\\.rs$", "mode": "filter", "invert": true, "input": input,
+        }));
+        assert!(
+            response.contains("\"results\": [\n    \"Cargo.toml\"\n  ]"),
+            "{}",
+            response
+        );
+
+        let response = invoke(json!({
+            "action": NAME, "pattern": "^b.", "mode": "is_match", "multiline": true, "dot_all": true,
+            "input": ["a\nb\n", "abc"],
+        }));
+        assert!(response.contains("\"matching_inputs\": 1"), "{}", response);
     }
 }
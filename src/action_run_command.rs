@@ -0,0 +1,401 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::command::{AlpacaCommandConfig, AlpacaCommandOutput, AlpacaCommandProcess, run, spawn};
+use crate::permission::{AlpacaPermission, AlpacaPermissionRequest};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const NAME: &str = "run_command";
+const DESCRIPTION: &str = r#"
+# `run_command`
+
+The 'run_command' action runs a program in the current directory and returns
+its exit code and output. The program is started directly, without a shell, so
+pass each argument separately. Only the programs the host allows can be run,
+and each command must be approved before it runs. Here is an example of how to
+invoke it:
+
+```json
+{
+    "action": "run_command",
+    "command": "cargo",
+    "args": ["test", "--lib"]
+}
+```
+
+Optional arguments:
+- `path`: the directory to run in, relative to the current directory
+- `env`: extra environment variables, such as `{"RUST_BACKTRACE": "1"}`; variables
+  such as `PATH` or `LD_PRELOAD` cannot be set
+- `timeout_seconds`: a shorter timeout than the host's limit
+- `shell`: `true` to run `command` as a `sh -c` command line, if the host allows it
+- `background`: `true` to start a long command and return at once with a `job` id
+
+Check on a command started in the background by passing its id alone, as in
+`{"action": "run_command", "job": 1}`. Its output is returned once it has
+finished; until then, the time it has been running is.
+
+Long output is shortened to its beginning and end.
+"#;
+
+/// The most commands running in the background at once.
+const MAX_BACKGROUND_COMMANDS: usize = 4;
+
+/// Runs a program in the current directory.
+#[derive(Deserialize, JsonSchema)]
+pub struct RunCommandArguments {
+    /// The program to run, or a command line when `shell` is `true`.
+    #[serde(default)]
+    pub command: String,
+    /// The arguments passed to the program.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The directory to run in, relative to the current directory.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Extra environment variables.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// A timeout shorter than the host's limit.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+    /// Whether `command` is run through `sh -c`.
+    #[serde(default)]
+    pub shell: bool,
+    /// Whether to start the command and return before it has finished.
+    #[serde(default)]
+    pub background: bool,
+    /// The id of a command started in the background, to check on it.
+    #[serde(default)]
+    pub job: Option<u64>,
+}
+
+/// A command started in the background, with the command line it was started with.
+struct AlpacaBackgroundCommand {
+    command_line: String,
+    process: AlpacaCommandProcess,
+}
+
+pub struct AlpacaActionRunCommand {
+    config: AlpacaCommandConfig,
+    /// The commands started in the background, by id, with the next id
+    jobs: Mutex<(u64, HashMap<u64, AlpacaBackgroundCommand>)>,
+}
+
+impl AlpacaActionRunCommand {
+    /// Creates the action with the given allowlist and limits.
+    pub fn new(config: AlpacaCommandConfig) -> Self {
+        Self {
+            config,
+            jobs: Mutex::new((1, HashMap::new())),
+        }
+    }
+
+    fn run_command(
+        &self,
+        arguments: &RunCommandArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        if arguments.command.is_empty() {
+            return Err("Missing 'command' parameter.".to_string());
+        }
+        let (program, args) = if arguments.shell {
+            if !self.config.allow_shell {
+                return Err(
+                    "Running commands through a shell is not allowed. Pass the program as `command` and its arguments as `args`.".to_string(),
+                );
+            }
+            let mut args = vec!["-c".to_string(), arguments.command.clone()];
+            args.extend(arguments.args.iter().cloned());
+            ("sh".to_string(), args)
+        } else {
+            if !self.config.is_allowed(&arguments.command) {
+                return Err(format!(
+                    "The program '{}' is not allowed. Allowed programs: {}.",
+                    arguments.command,
+                    if self.config.allowed_commands.is_empty() {
+                        "none".to_string()
+                    } else {
+                        self.config.allowed_commands.join(", ")
+                    }
+                ));
+            }
+            (arguments.command.clone(), arguments.args.clone())
+        };
+
+        self.config.check_env(&arguments.env)?;
+
+        let cwd = context
+            .sandbox()
+            .resolve(arguments.path.as_deref().unwrap_or("."))?;
+        if !cwd.is_dir() {
+            return Err(format!(
+                "'{}' is not a directory.",
+                arguments.path.as_deref().unwrap_or(".")
+            ));
+        }
+
+        // Ask the host to approve the exact command line
+        let command_line = std::iter::once(program.as_str())
+            .chain(args.iter().map(String::as_str))
+            .map(quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let display_dir = context.sandbox().display_path(&cwd);
+        let mut details = format!("$ {}\n\nin: {}\n", command_line, display_dir);
+        let mut env_names: Vec<&String> = arguments.env.keys().collect();
+        env_names.sort();
+        for name in env_names {
+            details.push_str(&format!("env: {}={}\n", name, arguments.env[name]));
+        }
+        let request =
+            AlpacaPermissionRequest::new(NAME, &format!("Run '{}'", command_line), &details);
+        if let AlpacaPermission::Deny(reason) = context.request_permission(&request) {
+            return Err(format!(
+                "Running '{}' was not permitted: {}",
+                command_line, reason
+            ));
+        }
+
+        let mut config = self.config.clone();
+        if let Some(seconds) = arguments.timeout_seconds {
+            config.timeout = config.timeout.min(Duration::from_secs(seconds.max(1)));
+        }
+        if arguments.background {
+            let mut jobs = self.jobs.lock().unwrap();
+            if jobs.1.len() >= MAX_BACKGROUND_COMMANDS {
+                return Err(format!(
+                    "{} commands are already running in the background. Check on them with `job` before starting another.",
+                    jobs.1.len()
+                ));
+            }
+            let process = spawn(&program, &args, &cwd, &arguments.env, &config)?;
+            let job = jobs.0;
+            jobs.0 += 1;
+            jobs.1.insert(
+                job,
+                AlpacaBackgroundCommand {
+                    command_line: command_line.clone(),
+                    process,
+                },
+            );
+            return Ok(json!({
+                "command": command_line,
+                "job": job,
+                "running": true,
+            }));
+        }
+
+        let output = run(&program, &args, &cwd, &arguments.env, &config)?;
+        Ok(output_json(&command_line, &output, config.timeout))
+    }
+
+    /// Checks on a command started in the background, forgetting it once it has finished.
+    fn check_job(&self, job: u64) -> Result<JsonValue, String> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(command) = jobs.1.get_mut(&job) else {
+            return Err(format!(
+                "There is no command running in the background with the id {}.",
+                job
+            ));
+        };
+
+        match command.process.try_wait()? {
+            Some(output) => {
+                let response =
+                    output_json(&command.command_line, &output, command.process.timeout());
+                jobs.1.remove(&job);
+                Ok(response)
+            }
+            None => Ok(json!({
+                "command": command.command_line,
+                "job": job,
+                "running": true,
+                "running_ms": command.process.elapsed().as_millis() as u64,
+            })),
+        }
+    }
+}
+
+/// Describes the outcome of a command.
+fn output_json(command_line: &str, output: &AlpacaCommandOutput, timeout: Duration) -> JsonValue {
+    let mut response = json!({
+        "command": command_line,
+        "exit_code": output.exit_code,
+        "success": output.exit_code == Some(0),
+        "duration_ms": output.duration.as_millis() as u64,
+        "stdout": output.stdout.text,
+        "stderr": output.stderr.text,
+    });
+    if let Some(report) = &output.confinement {
+        response["confinement"] = json!(report.lines());
+    }
+    if let Some(signal) = output.signal {
+        response["signal"] = json!(signal);
+    }
+    if output.timed_out {
+        response["timed_out"] = json!(true);
+        response["note"] = json!(format!(
+            "The command was killed after {} seconds.",
+            timeout.as_secs_f64()
+        ));
+    }
+    for (key, captured) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        if captured.truncated {
+            response[format!("{}_total_bytes", key)] = json!(captured.total_bytes);
+        }
+    }
+    response
+}
+
+impl AlpacaActionTrait for AlpacaActionRunCommand {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: RunCommandArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        let result = match arguments.job {
+            Some(job) => self.check_job(job),
+            None => self.run_command(&arguments, context),
+        };
+        match result {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n", error),
+        }
+    }
+}
+
+/// Quotes an argument for display when it contains spaces or shell characters.
+fn quote(argument: &str) -> String {
+    let plain = !argument.is_empty()
+        && argument
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_./=:,@+%".contains(c));
+    if plain {
+        argument.to_string()
+    } else {
+        format!("'{}'", argument.replace('\'', r"'\''"))
+    }
+}
+
+// ===
+// AlpacaActionRunCommand Tests
+// ===
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::permission::AlpacaAllowAll;
+    use crate::sandbox::AlpacaSandbox;
+    use std::sync::{Arc, Mutex};
+
+    fn actions(root: &std::path::Path) -> AlpacaActions {
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(AlpacaSandbox::new(root));
+        actions.set_permission_policy(Box::new(AlpacaAllowAll));
+        actions
+    }
+
+    /// Tests running an allowed program in the sandbox.
+    #[test]
+    fn test_run_command() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("hello.txt"), "hi").unwrap();
+        let actions = actions(temp_dir.path());
+
+        let response = AlpacaActionRunCommand::new(AlpacaCommandConfig::allowing(&["ls"])).invoke(
+            &json!({"action": NAME, "command": "ls", "args": ["-1"]}),
+            &actions,
+        );
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"stdout\": \"hello.txt\\n\""));
+        assert!(response.contains("\"exit_code\": 0"));
+    }
+
+    /// Tests that programs outside the allowlist and shells are refused.
+    #[test]
+    fn test_run_command_not_allowed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let actions = actions(temp_dir.path());
+        let action = AlpacaActionRunCommand::new(AlpacaCommandConfig::allowing(&["ls"]));
+
+        let response = action.invoke(&json!({"action": NAME, "command": "rm"}), &actions);
+        assert!(response.contains("The program 'rm' is not allowed"));
+
+        let response = action.invoke(
+            &json!({"action": NAME, "command": "ls; rm -rf /", "shell": true}),
+            &actions,
+        );
+        assert!(response.contains("not allowed"));
+    }
+
+    /// Tests that the permission policy sees the command line and can deny it.
+    #[test]
+    fn test_run_command_denied() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut actions = actions(temp_dir.path());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&seen);
+        actions.set_permission_policy(Box::new(move |request: &AlpacaPermissionRequest| {
+            recorded.lock().unwrap().push(request.summary.clone());
+            AlpacaPermission::Deny("not today".to_string())
+        }));
+
+        let response = AlpacaActionRunCommand::new(AlpacaCommandConfig::allowing(&["echo"]))
+            .invoke(
+                &json!({"action": NAME, "command": "echo", "args": ["a b"]}),
+                &actions,
+            );
+        assert!(
+            response.contains("was not permitted: not today"),
+            "{}",
+            response
+        );
+        assert_eq!(*seen.lock().unwrap(), vec!["Run 'echo 'a b''".to_string()]);
+    }
+
+    /// Tests starting a command in the background and checking on it.
+    #[test]
+    fn test_run_command_background() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let actions = actions(temp_dir.path());
+        let action = AlpacaActionRunCommand::new(AlpacaCommandConfig::allowing(&["sh"]));
+
+        let response = action.invoke(
+            &json!({"action": NAME, "command": "sh", "args": ["-c", "sleep 0.3; echo done"], "background": true}),
+            &actions,
+        );
+        assert!(response.contains("\"job\": 1"), "{}", response);
+        let check = json!({"action": NAME, "job": 1});
+        let response = action.invoke(&check, &actions);
+        assert!(response.contains("\"running\": true"), "{}", response);
+
+        let started = std::time::Instant::now();
+        let response = loop {
+            let response = action.invoke(&check, &actions);
+            if !response.contains("\"running\"") || started.elapsed().as_secs() > 5 {
+                break response;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        };
+        assert!(response.contains("\"stdout\": \"done\\n\""), "{}", response);
+        assert!(action.invoke(&check, &actions).contains("no command"));
+
+        let response = action.invoke(&json!({"action": NAME}), &actions);
+        assert!(response.contains("Missing 'command'"), "{}", response);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::cargo_project::{AlpacaCargoPackage, AlpacaCargoWorkspace};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+
+const NAME: &str = "rust_project";
+const DESCRIPTION: &str = r#"
+# `rust_project`
+
+The 'rust_project' action describes the Rust project containing a directory: its
+workspace members, the targets of each crate (library, binaries, examples, tests
+and benches), its dependencies with their versions and features, its edition,
+and its module tree. It reads `Cargo.toml` and `Cargo.lock` directly and works
+offline. Here is an example of how to invoke it:
+
+```json
+{
+    "action": "rust_project"
+}
+```
+
+Optional arguments:
+- `path`: a directory inside the project, the current directory by default
+- `package`: the name of one workspace member to describe
+- `include_modules`: `false` to leave out the module trees
+- `include_dev_dependencies`: `false` to leave out dev-dependencies
+"#;
+
+/// Workspaces with more members than this are summarized unless `package` is given.
+const MAX_DETAILED_PACKAGES: usize = 3;
+
+/// Describes the Rust project containing a directory.
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RustProjectArguments {
+    /// A directory inside the project, relative to the current directory.
+    pub path: String,
+    /// The name of one workspace member to describe.
+    pub package: Option<String>,
+    /// Whether the module trees are listed.
+    pub include_modules: bool,
+    /// Whether dev-dependencies are listed.
+    pub include_dev_dependencies: bool,
+}
+
+impl Default for RustProjectArguments {
+    fn default() -> Self {
+        Self {
+            path: ".".to_string(),
+            package: None,
+            include_modules: true,
+            include_dev_dependencies: true,
+        }
+    }
+}
+
+pub struct AlpacaActionRustProject {}
+
+impl AlpacaActionRustProject {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn describe(
+        &self,
+        arguments: &RustProjectArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let sandbox = context.sandbox();
+        let start = sandbox.resolve(&arguments.path)?;
+        if !start.exists() {
+            return Err(format!("The path '{}' does not exist.", arguments.path));
+        }
+        let workspace = AlpacaCargoWorkspace::discover(&start, sandbox.root())
+            .map_err(|error| error.replace(&*sandbox.root().to_string_lossy(), ""))?;
+
+        let mut response = json!({
+            "root": sandbox.display_path(&workspace.root),
+            "is_workspace": workspace.is_workspace,
+            "lock_file": workspace.lock_file.as_ref().map(|path| sandbox.display_path(path)),
+        });
+        if workspace.is_workspace {
+            response["members"] = json!(
+                workspace
+                    .packages
+                    .iter()
+                    .map(|package| package.name.as_str())
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        let selected: Vec<&AlpacaCargoPackage> = match &arguments.package {
+            Some(name) => {
+                let package = workspace
+                    .packages
+                    .iter()
+                    .find(|package| package.name == *name)
+                    .ok_or_else(|| format!("The workspace has no package named '{}'.", name))?;
+                vec![package]
+            }
+            None => workspace.packages.iter().collect(),
+        };
+
+        if selected.len() > MAX_DETAILED_PACKAGES {
+            response["packages"] = selected
+                .iter()
+                .map(|package| self.summarize(package, context))
+                .collect();
+            response["note"] = json!(
+                "Pass 'package' with the name of a member to see its dependencies and modules."
+            );
+        } else {
+            response["packages"] = selected
+                .iter()
+                .map(|package| self.detail(package, arguments, context))
+                .collect();
+        }
+
+        Ok(response)
+    }
+
+    fn summarize(&self, package: &AlpacaCargoPackage, context: &AlpacaActions) -> JsonValue {
+        json!({
+            "name": package.name,
+            "version": package.version,
+            "manifest": context.sandbox().display_path(&package.manifest),
+            "targets": package
+                .targets
+                .iter()
+                .map(|target| format!("{} {}", target.kind, target.name))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn detail(
+        &self,
+        package: &AlpacaCargoPackage,
+        arguments: &RustProjectArguments,
+        context: &AlpacaActions,
+    ) -> JsonValue {
+        let sandbox = context.sandbox();
+
+        let mut detail = json!({
+            "name": package.name,
+            "version": package.version,
+            "edition": package.edition,
+            "manifest": sandbox.display_path(&package.manifest),
+            "targets": package
+                .targets
+                .iter()
+                .map(|target| json!({
+                    "kind": target.kind,
+                    "name": target.name,
+                    "path": sandbox.display_path(&target.path),
+                }))
+                .collect::<Vec<_>>(),
+            "dependencies": package
+                .dependencies
+                .iter()
+                .filter(|dependency| arguments.include_dev_dependencies || dependency.kind != "dev")
+                .collect::<Vec<_>>(),
+        });
+        if !package.features.is_empty() {
+            detail["features"] = json!(package.features);
+        }
+
+        if arguments.include_modules
+            && let Some(root) = package.root_target()
+        {
+            detail["module_root"] = json!(sandbox.display_path(&root.path));
+            detail["modules"] = package
+                .modules(sandbox.root())
+                .iter()
+                .map(|module| {
+                    let mut entry = json!({"path": module.path});
+                    match &module.file {
+                        Some(file) if !module.inline => {
+                            entry["file"] = json!(sandbox.display_path(file))
+                        }
+                        Some(_) => entry["inline"] = json!(true),
+                        None => entry["file"] = json!("(not found)"),
+                    }
+                    if module.public {
+                        entry["public"] = json!(true);
+                    }
+                    entry
+                })
+                .collect();
+        }
+
+        detail
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionRustProject {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: RustProjectArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.describe(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+// ===
+// AlpacaActionRustProject Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::AlpacaSandbox;
+    use std::fs;
+
+    /// Tests describing a lone package from one of its directories.
+    #[test]
+    fn test_rust_project() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/net")).unwrap();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[dependencies]\nregex = \"1\"\n\n[dev-dependencies]\ntempfile = \"3\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/lib.rs"), "pub mod net;\n").unwrap();
+        fs::write(root.join("src/net.rs"), "mod http;\n").unwrap();
+        fs::write(root.join("src/net/http.rs"), "").unwrap();
+
+        let mut sandbox = AlpacaSandbox::new(root);
+        sandbox.set_current_dir("src").unwrap();
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(sandbox);
+        let action = AlpacaActionRustProject::new();
+
+        let response = action.invoke(&json!({"include_dev_dependencies": false}), &actions);
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"root\": \"/\""), "{}", response);
+        assert!(response.contains("\"edition\": \"2024\""));
+        assert!(response.contains("\"name\": \"regex\""));
+        assert!(!response.contains("tempfile"));
+        assert!(response.contains("\"path\": \"net::http\""));
+        assert!(response.contains("\"file\": \"net/http.rs\""));
+
+        let response = action.invoke(&json!({"package": "other"}), &actions);
+        assert!(response.contains("The workspace has no package named 'other'."));
+    }
+}
@@ -0,0 +1,349 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::file_walk::AlpacaWalkOptions;
+use regex::{Regex, RegexBuilder};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::path::Path;
+
+const NAME: &str = "search_files";
+const DESCRIPTION: &str = r#"
+# `search_files`
+
+The 'search_files' action searches the contents of the files under the current
+directory for a regular expression, and returns each match with its file, line
+and column. Files ignored by `.gitignore` and hidden files are skipped unless
+requested otherwise. Here is an example of how to invoke it:
+
+```json
+{
+    "action": "search_files",
+    "pattern": "fn\\s+main",
+    "include": ["*.rs"],
+    "case_sensitive": false,
+    "context_lines": 1,
+    "max_results": 20
+}
+```
+
+Only `pattern` is required. The other arguments are:
+- `path`: the directory to search, relative to the current directory
+- `include` / `exclude`: glob patterns of the files to search or to skip
+- `case_sensitive`: `true` by default
+- `context_lines`: the number of lines shown before and after each match (up to 5)
+- `max_results`: the maximum number of matches returned, 50 by default
+- `respect_gitignore`: `true` by default
+- `hidden`: set to `true` to also search hidden files
+"#;
+
+/// The most matches a single search can return.
+const MAX_RESULTS_LIMIT: usize = 500;
+/// The most context lines shown around a match.
+const MAX_CONTEXT_LINES: usize = 5;
+/// The longest line of text included in a result, in characters.
+const MAX_LINE_CHARS: usize = 200;
+/// The largest file that is searched, in bytes.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// The size of the response, in characters, after which no more matches are added.
+const MAX_RESPONSE_CHARS: usize = 12_000;
+
+fn default_true() -> bool {
+    true
+}
+
+/// Searches the contents of files for a regular expression.
+#[derive(Deserialize, JsonSchema)]
+pub struct SearchFilesArguments {
+    /// The regular expression to search for.
+    pub pattern: String,
+    /// The directory to search, relative to the current directory.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Glob patterns of the files to search.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Glob patterns of the files and directories to skip.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Whether the pattern is case sensitive.
+    #[serde(default = "default_true")]
+    pub case_sensitive: bool,
+    /// The number of lines shown before and after each match.
+    #[serde(default)]
+    pub context_lines: usize,
+    /// The maximum number of matches returned.
+    #[serde(default)]
+    pub max_results: Option<usize>,
+    /// Whether files ignored by `.gitignore` are skipped.
+    #[serde(default = "default_true")]
+    pub respect_gitignore: bool,
+    /// Whether hidden files are searched.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+pub struct AlpacaActionSearchFiles {}
+
+impl AlpacaActionSearchFiles {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn search(
+        &self,
+        arguments: &SearchFilesArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let regex = RegexBuilder::new(&arguments.pattern)
+            .case_insensitive(!arguments.case_sensitive)
+            .build()
+            .map_err(|e| format!("Invalid regex pattern: {}", e))?;
+
+        let sandbox = context.sandbox();
+        let root = sandbox.resolve(arguments.path.as_deref().unwrap_or("."))?;
+        if !root.is_dir() {
+            return Err(format!(
+                "'{}' is not a directory.",
+                arguments.path.as_deref().unwrap_or(".")
+            ));
+        }
+
+        let options = AlpacaWalkOptions {
+            include: arguments.include.clone(),
+            exclude: arguments.exclude.clone(),
+            respect_ignore_files: arguments.respect_gitignore,
+            hidden: arguments.hidden,
+            max_depth: None,
+        };
+        let max_results = arguments
+            .max_results
+            .unwrap_or(50)
+            .clamp(1, MAX_RESULTS_LIMIT);
+        let context_lines = arguments.context_lines.min(MAX_CONTEXT_LINES);
+
+        let mut matches = Vec::new();
+        let mut response_chars = 0;
+        let mut files_searched = 0;
+        let mut truncated = false;
+
+        for entry in options.walk(&root)?.flatten() {
+            if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+                continue;
+            }
+            if entry
+                .metadata()
+                .is_ok_and(|metadata| metadata.len() > MAX_FILE_SIZE)
+            {
+                continue;
+            }
+            let Some(text) = read_text(entry.path()) else {
+                continue;
+            };
+            files_searched += 1;
+
+            let file = sandbox.display_path(entry.path());
+            search_text(&regex, &text, context_lines, |mut result| {
+                if matches.len() >= max_results || response_chars >= MAX_RESPONSE_CHARS {
+                    truncated = true;
+                    return false;
+                }
+                result["file"] = json!(file);
+                response_chars += result.to_string().len();
+                matches.push(result);
+                true
+            });
+            if truncated {
+                break;
+            }
+        }
+
+        let mut response = json!({
+            "match_count": matches.len(),
+            "files_searched": files_searched,
+            "matches": matches,
+        });
+        if truncated {
+            response["truncated"] = json!(true);
+            response["note"] = json!(
+                "More matches exist. Narrow the search with a more specific pattern, `path` or `include`."
+            );
+        }
+
+        Ok(response)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionSearchFiles {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: SearchFilesArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.search(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+// ---
+
+/// Reads a file as UTF-8 text, skipping binary and non-UTF-8 files.
+fn read_text(path: &Path) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    if bytes.iter().take(8192).any(|&byte| byte == 0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Finds the matches in a text, with their one-based line and column.
+///
+/// Each match is passed to `accept` as soon as it is found, and the search
+/// stops once `accept` returns `false`, when the budget of the response is
+/// used up.
+fn search_text(
+    regex: &Regex,
+    text: &str,
+    context_lines: usize,
+    mut accept: impl FnMut(JsonValue) -> bool,
+) {
+    let lines: Vec<&str> = text.lines().collect();
+
+    for (index, line) in lines.iter().enumerate() {
+        for found in regex.find_iter(line) {
+            let mut result = json!({
+                "line": index + 1,
+                "column": line[..found.start()].chars().count() + 1,
+                "match": truncate(found.as_str()),
+                "text": truncate(line),
+            });
+
+            if context_lines > 0 {
+                let before = &lines[index.saturating_sub(context_lines)..index];
+                let after = &lines
+                    [(index + 1).min(lines.len())..(index + 1 + context_lines).min(lines.len())];
+                result["before"] =
+                    json!(before.iter().map(|line| truncate(line)).collect::<Vec<_>>());
+                result["after"] =
+                    json!(after.iter().map(|line| truncate(line)).collect::<Vec<_>>());
+            }
+
+            if !accept(result) {
+                return;
+            }
+        }
+    }
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_LINE_CHARS) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+// ===
+// AlpacaActionSearchFiles Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::AlpacaSandbox;
+    use std::fs;
+
+    fn search(root: &Path, object: JsonValue) -> JsonValue {
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(AlpacaSandbox::new(root));
+        let arguments: SearchFilesArguments = actions.arguments(NAME, &object).unwrap();
+        AlpacaActionSearchFiles::new()
+            .search(&arguments, &actions)
+            .unwrap()
+    }
+
+    fn project() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(
+            root.join("src/main.rs"),
+            "// Entry\nfn main() {\n    run();\n}\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/notes.md"), "Main notes\n").unwrap();
+        fs::write(root.join("target/gen.rs"), "fn main() {}\n").unwrap();
+        temp_dir
+    }
+
+    /// Tests that matches are reported with their file, line and column.
+    #[test]
+    fn test_search() {
+        let temp_dir = project();
+        let response = search(temp_dir.path(), json!({"pattern": "main"}));
+
+        assert_eq!(response["match_count"], 1);
+        let found = &response["matches"][0];
+        assert_eq!(found["file"], "src/main.rs");
+        assert_eq!(found["line"], 2);
+        assert_eq!(found["column"], 4);
+        assert_eq!(found["text"], "fn main() {");
+    }
+
+    /// Tests case-insensitive searches restricted by a glob, with context lines.
+    #[test]
+    fn test_search_options() {
+        let temp_dir = project();
+        let response = search(
+            temp_dir.path(),
+            json!({"pattern": "MAIN", "case_sensitive": false, "include": ["*.rs"], "context_lines": 1}),
+        );
+
+        assert_eq!(response["match_count"], 1);
+        assert_eq!(response["matches"][0]["before"], json!(["// Entry"]));
+        assert_eq!(response["matches"][0]["after"], json!(["    run();"]));
+
+        let response = search(
+            temp_dir.path(),
+            json!({"pattern": "main", "case_sensitive": false, "respect_gitignore": false}),
+        );
+        assert_eq!(response["match_count"], 3);
+    }
+
+    /// Tests that the number of results is limited.
+    #[test]
+    fn test_search_max_results() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "x\n".repeat(10)).unwrap();
+
+        let response = search(temp_dir.path(), json!({"pattern": "x", "max_results": 3}));
+        assert_eq!(response["match_count"], 3);
+        assert_eq!(response["truncated"], true);
+    }
+
+    /// Tests that a text stops being searched once the budget is used up.
+    #[test]
+    fn test_search_text_budget() {
+        let regex = RegexBuilder::new("x").build().unwrap();
+        let text = "x x x\n".repeat(100_000);
+        let mut found = 0;
+        search_text(&regex, &text, 2, |_| {
+            found += 1;
+            found < 5
+        });
+        assert_eq!(found, 5);
+    }
+}
@@ -0,0 +1,455 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+const NAME: &str = "string_ops";
+const DESCRIPTION: &str = r#"
+The 'string_ops' action performs exact operations on a list of strings, such as
+filtering, sorting, removing duplicates and counting. Use it instead of doing
+these by hand, so that the results are reliable.
+
+- Provide the operation as the 'operation' parameter.
+- Provide a string, or an array of strings, as the 'input' parameter.
+
+Here is an example of how to invoke it:
+```json
+{
+    "action": "string_ops",
+    "operation": "ends_with",
+    "value": ".lock",
+    "input": ["Cargo.lock", "Cargo.toml", "yarn.lock"]
+}
+```
+
+Operations:
+- `starts_with`, `ends_with`, `contains`: the inputs that match 'value'; set `"invert": true` for those that don't
+- `sort`: sorted inputs; set `"numeric": true` to compare the numbers in them by value, and `"reverse": true` for descending order
+- `unique`: the inputs without duplicates, in their original order
+- `count`: the number of inputs, and how often each distinct input occurs
+- `length`: the number of characters of each input
+- `split`: the parts of each input, split at 'separator' (whitespace by default)
+- `join`: the inputs joined with 'separator' (nothing by default)
+- `uppercase`, `lowercase`, `capitalize`, `trim`: each input converted
+- `union`, `intersection`, `difference`, `symmetric_difference`: set operations between 'input' and 'other'
+
+Set `"case_insensitive": true` to ignore case when matching, sorting, removing
+duplicates, counting and in set operations.
+"#;
+
+fn format_response(status: &str, response: &str) -> String {
+    format!("## {}\n\n{}\n", status, response)
+}
+
+fn response_error(message: &str) -> String {
+    format!("## Error\n\n{}\n\n## Help\n{}", message, DESCRIPTION)
+}
+
+/// An operation of the `string_ops` action.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StringOperation {
+    StartsWith,
+    EndsWith,
+    Contains,
+    Sort,
+    Unique,
+    Count,
+    Length,
+    Split,
+    Join,
+    Uppercase,
+    Lowercase,
+    Capitalize,
+    Trim,
+    Union,
+    Intersection,
+    Difference,
+    SymmetricDifference,
+}
+
+/// The strings an operation is applied to.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum StringOpsInput {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Performs exact operations on a list of strings.
+#[derive(Deserialize, JsonSchema)]
+pub struct StringOpsArguments {
+    /// The operation to perform.
+    pub operation: StringOperation,
+    /// A string, or an array of strings.
+    pub input: StringOpsInput,
+    /// The text matched by `starts_with`, `ends_with` and `contains`.
+    #[serde(default)]
+    pub value: Option<String>,
+    /// The separator of `split` and `join`.
+    #[serde(default)]
+    pub separator: Option<String>,
+    /// The second list of the set operations.
+    #[serde(default)]
+    pub other: Vec<String>,
+    /// Keeps the inputs that don't match.
+    #[serde(default)]
+    pub invert: bool,
+    /// Sorts in descending order.
+    #[serde(default)]
+    pub reverse: bool,
+    /// Compares the numbers in the inputs by value when sorting.
+    #[serde(default)]
+    pub numeric: bool,
+    #[serde(default)]
+    pub case_insensitive: bool,
+}
+
+pub struct AlpacaActionStringOps {}
+
+impl AlpacaActionStringOps {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn apply(
+        &self,
+        operation: &str,
+        inputs: &[String],
+        arguments: &StringOpsArguments,
+    ) -> Result<JsonValue, String> {
+        let fold = |text: &str| -> String {
+            if arguments.case_insensitive {
+                text.to_lowercase()
+            } else {
+                text.to_string()
+            }
+        };
+
+        let response = match arguments.operation {
+            StringOperation::StartsWith | StringOperation::EndsWith | StringOperation::Contains => {
+                let value = arguments.value.as_deref().ok_or_else(|| {
+                    format!("The '{}' operation needs a 'value' parameter.", operation)
+                })?;
+                let value = fold(value);
+                let results: Vec<&String> = inputs
+                    .iter()
+                    .filter(|input| {
+                        let input = fold(input);
+                        let matched = match arguments.operation {
+                            StringOperation::StartsWith => input.starts_with(&value),
+                            StringOperation::EndsWith => input.ends_with(&value),
+                            _ => input.contains(&value),
+                        };
+                        matched != arguments.invert
+                    })
+                    .collect();
+                json!({"results": results, "count": results.len(), "total_inputs": inputs.len()})
+            }
+            StringOperation::Sort => {
+                let mut results = inputs.to_vec();
+                results.sort_by(|a, b| {
+                    let (a, b) = (fold(a), fold(b));
+                    if arguments.numeric {
+                        natural_cmp(&a, &b)
+                    } else {
+                        a.cmp(&b)
+                    }
+                });
+                if arguments.reverse {
+                    results.reverse();
+                }
+                json!({"results": results, "count": results.len()})
+            }
+            StringOperation::Unique => {
+                let mut seen = HashSet::new();
+                let results: Vec<&String> = inputs
+                    .iter()
+                    .filter(|input| seen.insert(fold(input)))
+                    .collect();
+                json!({
+                    "results": results,
+                    "count": results.len(),
+                    "duplicates_removed": inputs.len() - results.len(),
+                })
+            }
+            StringOperation::Count => {
+                let mut counts: Vec<(String, usize)> = Vec::new();
+                let mut index: HashMap<String, usize> = HashMap::new();
+                for input in inputs {
+                    let key = fold(input);
+                    match index.get(&key) {
+                        Some(&position) => counts[position].1 += 1,
+                        None => {
+                            index.insert(key, counts.len());
+                            counts.push((input.clone(), 1));
+                        }
+                    }
+                }
+                counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+                let results: Vec<JsonValue> = counts
+                    .iter()
+                    .map(|(value, count)| json!({"value": value, "count": count}))
+                    .collect();
+                json!({"results": results, "count": inputs.len(), "distinct": counts.len()})
+            }
+            StringOperation::Length => {
+                let results: Vec<JsonValue> = inputs
+                    .iter()
+                    .map(|input| json!({"input": input, "length": input.chars().count()}))
+                    .collect();
+                let total: usize = inputs.iter().map(|input| input.chars().count()).sum();
+                json!({"results": results, "total_length": total})
+            }
+            StringOperation::Split => {
+                let results: Vec<&str> = inputs
+                    .iter()
+                    .flat_map(|input| -> Vec<&str> {
+                        match arguments.separator.as_deref() {
+                            Some("") | None => input.split_whitespace().collect(),
+                            Some(separator) => input.split(separator).collect(),
+                        }
+                    })
+                    .collect();
+                json!({"results": results, "count": results.len()})
+            }
+            StringOperation::Join => {
+                let separator = arguments.separator.as_deref().unwrap_or_default();
+                json!({"results": [inputs.join(separator)], "count": 1})
+            }
+            StringOperation::Uppercase
+            | StringOperation::Lowercase
+            | StringOperation::Capitalize
+            | StringOperation::Trim => {
+                let results: Vec<String> = inputs
+                    .iter()
+                    .map(|input| match arguments.operation {
+                        StringOperation::Uppercase => input.to_uppercase(),
+                        StringOperation::Lowercase => input.to_lowercase(),
+                        StringOperation::Capitalize => capitalize(input),
+                        _ => input.trim().to_string(),
+                    })
+                    .collect();
+                json!({"results": results, "count": results.len()})
+            }
+            StringOperation::Union
+            | StringOperation::Intersection
+            | StringOperation::Difference
+            | StringOperation::SymmetricDifference => {
+                let left: HashSet<String> = inputs.iter().map(|input| fold(input)).collect();
+                let right: HashSet<String> =
+                    arguments.other.iter().map(|other| fold(other)).collect();
+                let mut seen = HashSet::new();
+
+                // Keep the first spelling of each value, inputs before others
+                let results: Vec<&String> = inputs
+                    .iter()
+                    .map(|input| (input, true))
+                    .chain(arguments.other.iter().map(|other| (other, false)))
+                    .filter(|(value, from_input)| {
+                        let key = fold(value);
+                        let keep = match arguments.operation {
+                            StringOperation::Union => true,
+                            StringOperation::Intersection => *from_input && right.contains(&key),
+                            StringOperation::Difference => *from_input && !right.contains(&key),
+                            _ => {
+                                if *from_input {
+                                    !right.contains(&key)
+                                } else {
+                                    !left.contains(&key)
+                                }
+                            }
+                        };
+                        keep && seen.insert(key)
+                    })
+                    .map(|(value, _)| value)
+                    .collect();
+                json!({"results": results, "count": results.len()})
+            }
+        };
+
+        Ok(response)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionStringOps {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: StringOpsArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        let inputs = match &arguments.input {
+            StringOpsInput::One(text) => vec![text.clone()],
+            StringOpsInput::Many(texts) => texts.clone(),
+        };
+
+        let operation = object["operation"].as_str().unwrap_or_default();
+        match self.apply(operation, &inputs, &arguments) {
+            Ok(response) => {
+                let block = AlpacaActions::blockify(&response);
+                let response_text = format!(
+                    "String operation '{}' results across {} text items:\n{}",
+                    operation,
+                    inputs.len(),
+                    &block
+                );
+                format_response("Success", &response_text)
+            }
+            Err(error) => response_error(&error),
+        }
+    }
+}
+
+/// Compares strings so that runs of digits are ordered by their value, such as
+/// `file2` before `file10`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x: String = std::iter::from_fn(|| a.next_if(char::is_ascii_digit)).collect();
+                let y: String = std::iter::from_fn(|| b.next_if(char::is_ascii_digit)).collect();
+                let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+// ===
+// AlpacaActionStringOps Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(object: JsonValue) -> JsonValue {
+        let response = AlpacaActionStringOps::new().invoke(&object, &AlpacaActions::new());
+        let json = response
+            .split("```json\n")
+            .nth(1)
+            .unwrap_or_else(|| panic!("{}", response));
+        serde_json::from_str(json.split("\n```").next().unwrap()).unwrap()
+    }
+
+    /// Tests the filters.
+    #[test]
+    fn test_filters() {
+        let input = json!(["Cargo.lock", "Cargo.toml", "yarn.LOCK"]);
+        let response = results(json!({"operation": "ends_with", "value": ".lock", "input": input}));
+        assert_eq!(response["results"], json!(["Cargo.lock"]));
+
+        let response = results(json!({
+            "operation": "ends_with", "value": ".lock", "case_insensitive": true, "input": input,
+        }));
+        assert_eq!(response["results"], json!(["Cargo.lock", "yarn.LOCK"]));
+
+        let response = results(json!({
+            "operation": "contains", "value": "toml", "invert": true, "input": input,
+        }));
+        assert_eq!(response["count"], 2);
+    }
+
+    /// Tests sorting, removing duplicates and counting.
+    #[test]
+    fn test_sort_unique_count() {
+        let input = json!(["file10", "file2", "file1", "file2"]);
+        let response = results(json!({"operation": "sort", "numeric": true, "input": input}));
+        assert_eq!(
+            response["results"],
+            json!(["file1", "file2", "file2", "file10"])
+        );
+
+        let response = results(json!({"operation": "unique", "input": input}));
+        assert_eq!(response["results"], json!(["file10", "file2", "file1"]));
+
+        let response = results(json!({"operation": "count", "input": input}));
+        assert_eq!(response["count"], 4);
+        assert_eq!(response["distinct"], 3);
+        assert_eq!(
+            response["results"][0],
+            json!({"value": "file2", "count": 2})
+        );
+    }
+
+    /// Tests splitting, joining and converting strings.
+    #[test]
+    fn test_split_join_case() {
+        let response = results(json!({"operation": "split", "input": "a b\n c"}));
+        assert_eq!(response["results"], json!(["a", "b", "c"]));
+
+        let response =
+            results(json!({"operation": "join", "separator": ", ", "input": ["a", "b"]}));
+        assert_eq!(response["results"], json!(["a, b"]));
+
+        let response = results(json!({"operation": "capitalize", "input": ["hELLO"]}));
+        assert_eq!(response["results"], json!(["Hello"]));
+
+        let response = results(json!({"operation": "length", "input": ["héllo"]}));
+        assert_eq!(response["total_length"], 5);
+    }
+
+    /// Tests the set operations.
+    #[test]
+    fn test_set_operations() {
+        let sets = |operation: &str| {
+            results(json!({"operation": operation, "input": ["a", "b", "c"], "other": ["b", "d"]}))
+                ["results"]
+                .clone()
+        };
+        assert_eq!(sets("union"), json!(["a", "b", "c", "d"]));
+        assert_eq!(sets("intersection"), json!(["b"]));
+        assert_eq!(sets("difference"), json!(["a", "c"]));
+        assert_eq!(sets("symmetric_difference"), json!(["a", "c", "d"]));
+    }
+
+    /// Tests that a missing value is reported.
+    #[test]
+    fn test_missing_value() {
+        let response = AlpacaActionStringOps::new().invoke(
+            &json!({"operation": "starts_with", "input": ["a"]}),
+            &AlpacaActions::new(),
+        );
+        assert!(response.contains("The 'starts_with' operation needs a 'value' parameter."));
+    }
+}
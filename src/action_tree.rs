@@ -0,0 +1,467 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::file_walk::AlpacaWalkOptions;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+
+const NAME: &str = "tree";
+const DESCRIPTION: &str = r#"
+# `tree`
+
+The 'tree' action shows the hierarchy of files and directories under the current
+directory, down to a given depth. Files ignored by `.gitignore` or `.ignore` and
+hidden files are left out unless requested otherwise, and large directories are
+collapsed. Here is an example of how to invoke it:
+
+```json
+{
+    "action": "tree",
+    "max_depth": 2,
+    "include_sizes": true
+}
+```
+
+All of the arguments are optional:
+- `path`: the directory to show, relative to the current directory
+- `max_depth`: how many levels to show, 3 by default
+- `include_sizes`: `true` to show the size of each file
+- `max_entries`: the most entries shown per directory before collapsing it, 30 by default
+- `respect_gitignore`: `true` by default
+- `hidden`: `true` to also show hidden files and directories
+- `format`: `text`, `json` or `both` (the default)
+- `max_chars`: the size of the output, 8000 characters by default
+"#;
+
+/// The deepest tree that can be requested.
+const MAX_DEPTH_LIMIT: usize = 10;
+/// The upper bound for `max_chars`.
+const MAX_CHARS_LIMIT: usize = 32_000;
+
+fn default_true() -> bool {
+    true
+}
+
+/// Which renderings of the tree are returned.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TreeFormat {
+    Text,
+    Json,
+    #[default]
+    Both,
+}
+
+/// Shows the hierarchy of files and directories.
+#[derive(Deserialize, JsonSchema)]
+pub struct TreeArguments {
+    /// The directory to show, relative to the current directory.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// How many levels to show.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Whether the size of each file is shown.
+    #[serde(default)]
+    pub include_sizes: bool,
+    /// The most entries shown per directory before collapsing it.
+    #[serde(default)]
+    pub max_entries: Option<usize>,
+    /// Whether entries ignored by `.gitignore` and `.ignore` are skipped.
+    #[serde(default = "default_true")]
+    pub respect_gitignore: bool,
+    /// Whether hidden entries are shown.
+    #[serde(default)]
+    pub hidden: bool,
+    /// Which renderings of the tree are returned.
+    #[serde(default)]
+    pub format: TreeFormat,
+    /// The size of the output, in characters.
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+}
+
+/// A file or directory of the tree.
+struct TreeNode {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn new(name: String, is_dir: bool, size: u64) -> Self {
+        TreeNode {
+            name,
+            is_dir,
+            size,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Renders the tree as text and JSON, within a character budget shared by
+/// the renderings that are returned.
+struct TreeRenderer {
+    include_sizes: bool,
+    max_entries: usize,
+    max_chars: usize,
+    format: TreeFormat,
+    /// The characters of the budget used so far
+    used: usize,
+    text: String,
+    directories: usize,
+    files: usize,
+    truncated: bool,
+}
+
+impl TreeRenderer {
+    /// Adds a line to the text, if it and the `json_size` characters its
+    /// entry takes in the JSON fit in the budget.
+    fn line(&mut self, line: String, json_size: usize) -> bool {
+        let mut size = 0;
+        if self.format != TreeFormat::Json {
+            size += line.len() + 1;
+        }
+        if self.format != TreeFormat::Text {
+            size += json_size;
+        }
+        if self.used + size > self.max_chars {
+            self.truncated = true;
+            return false;
+        }
+        self.used += size;
+        self.text.push_str(&line);
+        self.text.push('\n');
+        true
+    }
+
+    /// Renders the children of a directory, `depth` levels below the root,
+    /// returning their JSON form.
+    fn children(&mut self, node: &TreeNode, prefix: &str, depth: usize) -> Vec<JsonValue> {
+        let mut children: Vec<&TreeNode> = node.children.iter().collect();
+        children.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+        let shown = children.len().min(self.max_entries);
+        let omitted = &children[shown..];
+        let mut output = Vec::new();
+
+        for (index, child) in children[..shown].iter().enumerate() {
+            let last = index + 1 == shown && omitted.is_empty();
+            let branch = if last { "└── " } else { "├── " };
+            let mut label = child.name.clone();
+            if child.is_dir {
+                label.push('/');
+            } else if self.include_sizes {
+                label.push_str(&format!(" ({})", format_size(child.size)));
+            }
+
+            let mut value = json!({
+                "name": child.name,
+                "type": if child.is_dir { "directory" } else { "file" },
+            });
+            if !child.is_dir && self.include_sizes {
+                value["size"] = json!(child.size);
+            }
+            let size = json_size(&value, depth, child.is_dir);
+            if !self.line(format!("{}{}{}", prefix, branch, label), size) {
+                return output;
+            }
+
+            if child.is_dir {
+                self.directories += 1;
+                let nested = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                value["children"] = json!(self.children(child, &nested, depth + 1));
+            } else {
+                self.files += 1;
+            }
+            output.push(value);
+
+            if self.truncated {
+                return output;
+            }
+        }
+
+        if !omitted.is_empty() {
+            let omitted_dirs = omitted.iter().filter(|child| child.is_dir).count();
+            let omitted_files = omitted.len() - omitted_dirs;
+            let summary = match (omitted_dirs, omitted_files) {
+                (0, files) => format!("{} more files", files),
+                (dirs, 0) => format!("{} more directories", dirs),
+                (dirs, files) => format!("{} more directories and {} more files", dirs, files),
+            };
+            let value = json!({
+                "omitted_directories": omitted_dirs,
+                "omitted_files": omitted_files,
+            });
+            let size = json_size(&value, depth, false);
+            if self.line(format!("{}└── … {}", prefix, summary), size) {
+                output.push(value);
+            }
+        }
+
+        output
+    }
+}
+
+pub struct AlpacaActionTree {}
+
+impl AlpacaActionTree {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn tree(&self, arguments: &TreeArguments, context: &AlpacaActions) -> Result<String, String> {
+        let sandbox = context.sandbox();
+        let path = arguments.path.as_deref().unwrap_or(".");
+        let root_path = sandbox.resolve(path)?;
+        if !root_path.is_dir() {
+            return Err(format!("'{}' is not a directory.", path));
+        }
+
+        let options = AlpacaWalkOptions {
+            respect_ignore_files: arguments.respect_gitignore,
+            hidden: arguments.hidden,
+            max_depth: Some(arguments.max_depth.unwrap_or(3).clamp(1, MAX_DEPTH_LIMIT)),
+            ..Default::default()
+        };
+
+        // The walk is depth first, so the open directories form a stack
+        let mut stack = vec![TreeNode::new(sandbox.display_path(&root_path), true, 0)];
+        for entry in options.walk(&root_path)?.flatten() {
+            if entry.depth() == 0 {
+                continue;
+            }
+            while stack.len() > entry.depth() {
+                let node = stack.pop().unwrap();
+                stack.last_mut().unwrap().children.push(node);
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.file_type().is_some_and(|kind| kind.is_dir());
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            if is_dir {
+                stack.push(TreeNode::new(name, true, 0));
+            } else {
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .children
+                    .push(TreeNode::new(name, false, size));
+            }
+        }
+        while stack.len() > 1 {
+            let node = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(node);
+        }
+        let root = stack.pop().unwrap();
+
+        let mut renderer = TreeRenderer {
+            include_sizes: arguments.include_sizes,
+            max_entries: arguments.max_entries.unwrap_or(30).max(1),
+            max_chars: arguments
+                .max_chars
+                .unwrap_or(8000)
+                .clamp(100, MAX_CHARS_LIMIT),
+            format: arguments.format,
+            used: 0,
+            text: String::new(),
+            directories: 0,
+            files: 0,
+            truncated: false,
+        };
+        let size = json_size(&json!({"name": root.name, "truncated": false}), 0, true);
+        renderer.line(format!("{}/", root.name), size);
+        let children = renderer.children(&root, "", 1);
+
+        let mut summary = format!(
+            "{} directories, {} files",
+            renderer.directories, renderer.files
+        );
+        if renderer.truncated {
+            summary.push_str(" shown. The output was truncated; use a smaller `max_depth` or a `path` to see the rest");
+        }
+
+        let mut response = String::from("## Success\n\n");
+        if arguments.format != TreeFormat::Json {
+            response.push_str(&format!("```text\n{}```\n\n", renderer.text));
+        }
+        if arguments.format != TreeFormat::Text {
+            let tree = json!({
+                "name": root.name,
+                "type": "directory",
+                "children": children,
+                "truncated": renderer.truncated,
+            });
+            response.push_str(&AlpacaActions::blockify(&tree));
+            response.push('\n');
+        }
+        response.push_str(&format!("{}.\n", summary));
+
+        Ok(response)
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionTree {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: TreeArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.tree(&arguments, context) {
+            Ok(response) => response,
+            Err(error) => format!("## Error\n\n{}\n\n## Help\n{}", error, DESCRIPTION),
+        }
+    }
+}
+
+// ---
+
+/// The characters an entry takes in the pretty-printed JSON, without its
+/// children, when it is `depth` levels below the root.
+fn json_size(value: &JsonValue, depth: usize, is_dir: bool) -> usize {
+    let pretty = serde_json::to_string_pretty(value).unwrap_or_default();
+    // Each level nests an array and an object, of two spaces each
+    let indent = 4 * depth;
+    let mut size = pretty.len() + indent * pretty.lines().count() + ",\n".len();
+    if is_dir {
+        // `"type": "directory"` and the lines opening and closing `"children"`
+        size += 2 * (indent + 2) + ",\n\"children\": [\n]".len();
+    }
+    size
+}
+
+/// Formats a size in bytes for people, such as `512 B` or `1.5 KB`.
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+// ===
+// AlpacaActionTree Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::AlpacaSandbox;
+    use std::fs;
+    use std::path::Path;
+
+    fn tree(root: &Path, object: JsonValue) -> String {
+        let mut actions = AlpacaActions::new();
+        actions.set_sandbox(AlpacaSandbox::new(root));
+        AlpacaActionTree::new().invoke(&object, &actions)
+    }
+
+    fn project() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/bin")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("Cargo.toml"), "x".repeat(2048)).unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join("src/bin/main.rs"), "").unwrap();
+        temp_dir
+    }
+
+    /// Tests rendering a tree as text, honouring the ignore file.
+    #[test]
+    fn test_tree_text() {
+        let temp_dir = project();
+        let response = tree(
+            temp_dir.path(),
+            json!({"action": NAME, "format": "text", "include_sizes": true}),
+        );
+
+        assert!(
+            response.contains(
+                "./\n├── src/\n│   ├── bin/\n│   │   └── main.rs (0 B)\n│   └── lib.rs (0 B)\n└── Cargo.toml (2.0 KB)\n"
+            ),
+            "{}",
+            response
+        );
+        assert!(!response.contains("target"));
+        assert!(response.contains("2 directories, 3 files."));
+    }
+
+    /// Tests the depth limit and the JSON rendering.
+    #[test]
+    fn test_tree_depth_json() {
+        let temp_dir = project();
+        let response = tree(
+            temp_dir.path(),
+            json!({"action": NAME, "format": "json", "max_depth": 1}),
+        );
+
+        assert!(!response.contains("```text"));
+        assert!(response.contains("\"name\": \"src\""));
+        assert!(!response.contains("lib.rs"));
+    }
+
+    /// Tests that large directories are collapsed.
+    #[test]
+    fn test_tree_collapsed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for index in 0..10 {
+            fs::write(temp_dir.path().join(format!("file{}.txt", index)), "").unwrap();
+        }
+
+        let response = tree(
+            temp_dir.path(),
+            json!({"action": NAME, "format": "text", "max_entries": 3}),
+        );
+        assert!(
+            response.contains("├── file2.txt\n└── … 7 more files\n"),
+            "{}",
+            response
+        );
+    }
+
+    /// Tests that the JSON rendering counts against the output size.
+    #[test]
+    fn test_tree_json_budget() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut directory = temp_dir.path().to_path_buf();
+        for level in 0..5 {
+            directory = directory.join(format!("level{}", level));
+            fs::create_dir(&directory).unwrap();
+            for index in 0..20 {
+                fs::write(directory.join(format!("file{}.txt", index)), "").unwrap();
+            }
+        }
+
+        for format in ["json", "both"] {
+            let response = tree(
+                temp_dir.path(),
+                json!({"action": NAME, "format": format, "max_depth": 6, "max_chars": 2000}),
+            );
+            let json_block = response.split("```json").nth(1).unwrap();
+            assert!(json_block.contains("\"truncated\": true"), "{}", response);
+            let rendered = response.len() - response.lines().last().unwrap().len();
+            assert!(rendered < 2000 + 100, "{}: {}", format, rendered);
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use crate::action::AlpacaActionTrait;
+use crate::action::AlpacaActions;
+use crate::diff::unified_diff;
+use crate::permission::{AlpacaPermission, AlpacaPermissionRequest};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::json;
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const NAME: &str = "write_file";
+const DESCRIPTION: &str = r#"
+# `write_file`
+
+The 'write_file' action writes text to a file inside the accessible directory.
+By default it only creates new files; set `mode` to `overwrite` to replace the
+contents of an existing file. The change is shown to the user as a diff and
+must be approved before it is applied. Here is an example of how to invoke it:
+
+```json
+{
+    "action": "write_file",
+    "file_name": "notes/todo.txt",
+    "content": "- write the tests\n",
+    "mode": "create"
+}
+```
+"#;
+
+/// How `write_file` treats a file that already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteMode {
+    /// Only create a new file, failing if it already exists.
+    #[default]
+    Create,
+    /// Create the file, or replace its contents if it exists.
+    Overwrite,
+}
+
+/// Writes text to a file inside the accessible directory.
+#[derive(Deserialize, JsonSchema)]
+pub struct WriteFileArguments {
+    /// The path of the file to write, relative to the current directory.
+    pub file_name: String,
+    /// The complete text to write to the file.
+    pub content: String,
+    /// Whether an existing file may be replaced.
+    #[serde(default)]
+    pub mode: WriteMode,
+}
+
+pub struct AlpacaActionWriteFile {}
+
+impl AlpacaActionWriteFile {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn write_file(
+        &self,
+        arguments: &WriteFileArguments,
+        context: &AlpacaActions,
+    ) -> Result<JsonValue, String> {
+        let path = context.sandbox().resolve(&arguments.file_name)?;
+        if path.is_dir() {
+            return Err(format!("'{}' is a directory.", arguments.file_name));
+        }
+
+        let existing = match fs::read(&path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read file '{}': {}.",
+                    arguments.file_name, e
+                ));
+            }
+        };
+
+        if existing.is_some() && arguments.mode == WriteMode::Create {
+            return Err(format!(
+                "The file '{}' already exists. Use the mode 'overwrite' to replace it.",
+                arguments.file_name
+            ));
+        }
+
+        // Ask the host to approve the change, showing it as a diff
+        let display_name = context.sandbox().display_path(&path);
+        let new_name = format!("b/{}", display_name);
+        let diff = match existing.as_deref().map(std::str::from_utf8) {
+            Some(Ok(text)) => unified_diff(
+                text,
+                &arguments.content,
+                &format!("a/{}", display_name),
+                &new_name,
+                3,
+            ),
+            Some(Err(_)) => format!(
+                "The existing file is not UTF-8 text ({} bytes), so it is shown as replaced whole.\n{}",
+                existing.as_ref().map_or(0, Vec::len),
+                unified_diff("", &arguments.content, "/dev/null", &new_name, 3)
+            ),
+            None => unified_diff("", &arguments.content, "/dev/null", &new_name, 3),
+        };
+        let verb = if existing.is_some() {
+            "Overwrite"
+        } else {
+            "Create"
+        };
+        let request =
+            AlpacaPermissionRequest::new(NAME, &format!("{} '{}'", verb, display_name), &diff);
+        if let AlpacaPermission::Deny(reason) = context.request_permission(&request) {
+            return Err(format!(
+                "Writing '{}' was not permitted: {}",
+                arguments.file_name, reason
+            ));
+        }
+
+        write_atomic(&path, arguments.content.as_bytes(), existing.is_none())
+            .map_err(|e| format!("Failed to write file '{}': {}.", arguments.file_name, e))?;
+
+        Ok(json!({
+            "file_name": display_name,
+            "mode": if existing.is_some() { "overwritten" } else { "created" },
+            "bytes_written": arguments.content.len(),
+        }))
+    }
+}
+
+impl AlpacaActionTrait for AlpacaActionWriteFile {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn description(&self) -> &str {
+        DESCRIPTION
+    }
+
+    fn invoke(&self, object: &JsonValue, context: &AlpacaActions) -> String {
+        let arguments: WriteFileArguments = match context.arguments(self.name(), object) {
+            Ok(arguments) => arguments,
+            Err(error) => return error,
+        };
+
+        match self.write_file(&arguments, context) {
+            Ok(response) => format!("## Success\n\n{}", AlpacaActions::blockify(&response)),
+            Err(error) => format!("## Error\n\n{}\n", error),
+        }
+    }
+}
+
+/// Writes a file so that readers never observe partial contents.
+///
+/// The data is written to a temporary file in the same directory and synced
+/// to disk before it takes the place of `path`. Missing parent directories are
+/// created, and the permissions of an existing file are kept.
+///
+/// # Arguments
+///
+/// * `path` - The file to write
+/// * `data` - The new contents
+/// * `create_new` - If `true`, fail instead of replacing a file that already exists
+pub(crate) fn write_atomic(path: &Path, data: &[u8], create_new: bool) -> std::io::Result<()> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| std::io::Error::other("the path has no parent directory"))?;
+    fs::create_dir_all(parent)?;
+
+    let (mut file, temp_path) = create_temp_file(path, parent)?;
+
+    let result = (|| {
+        file.write_all(data)?;
+        file.sync_all()?;
+
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+
+        if create_new {
+            // Linking fails if the target appeared since it was checked
+            fs::hard_link(&temp_path, path)?;
+            fs::remove_file(&temp_path)
+        } else {
+            fs::rename(&temp_path, path)
+        }
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+/// Creates a temporary file next to `path`, with a random name that no
+/// other file, or link, has yet.
+fn create_temp_file(path: &Path, parent: &Path) -> std::io::Result<(fs::File, PathBuf)> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut attempts = 0;
+    loop {
+        // Each `RandomState` is seeded differently
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        let temp_path = parent.join(format!(
+            ".{}.{}.{:016x}.tmp",
+            file_name,
+            std::process::id(),
+            hasher.finish()
+        ));
+
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((file, temp_path)),
+            Err(error) if error.kind() == ErrorKind::AlreadyExists && attempts < 16 => {
+                attempts += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+// ===
+// AlpacaActionWriteFile Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::AlpacaAllowAll;
+    use crate::sandbox::AlpacaSandbox;
+    use std::sync::{Arc, Mutex};
+
+    fn actions(root: &Path) -> AlpacaActions {
+        let mut actions = AlpacaActions::new();
+        actions.add_action(Box::new(AlpacaActionWriteFile::new()));
+        actions.set_sandbox(AlpacaSandbox::new(root));
+        actions.set_permission_policy(Box::new(AlpacaAllowAll));
+        actions
+    }
+
+    fn invoke(actions: &AlpacaActions, object: JsonValue) -> String {
+        AlpacaActionWriteFile::new().invoke(&object, actions)
+    }
+
+    /// Tests creating a new file, including its parent directory.
+    #[test]
+    fn test_create() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let actions = actions(temp_dir.path());
+
+        let response = invoke(
+            &actions,
+            json!({"action": NAME, "file_name": "notes/todo.txt", "content": "hello\n"}),
+        );
+
+        assert!(response.starts_with("## Success"), "{}", response);
+        assert!(response.contains("\"bytes_written\": 6"));
+        assert!(response.contains("\"mode\": \"created\""));
+        let written = fs::read_to_string(temp_dir.path().join("notes/todo.txt")).unwrap();
+        assert_eq!(written, "hello\n");
+    }
+
+    /// Tests that create mode refuses to replace an existing file.
+    #[test]
+    fn test_create_existing() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "old\n").unwrap();
+        let actions = actions(temp_dir.path());
+
+        let response = invoke(
+            &actions,
+            json!({"action": NAME, "file_name": "a.txt", "content": "new\n"}),
+        );
+
+        assert!(response.starts_with("## Error"));
+        assert!(response.contains("already exists"));
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+            "old\n"
+        );
+    }
+
+    /// Tests that overwriting passes the diff to the permission policy.
+    #[test]
+    fn test_overwrite() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "one\ntwo\n").unwrap();
+        let mut actions = actions(temp_dir.path());
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        actions.set_permission_policy(Box::new(move |request: &AlpacaPermissionRequest| {
+            seen.lock().unwrap().push(request.clone());
+            AlpacaPermission::Allow
+        }));
+
+        let response = invoke(
+            &actions,
+            json!({"action": NAME, "file_name": "a.txt", "content": "one\n2\n", "mode": "overwrite"}),
+        );
+
+        assert!(
+            response.contains("\"mode\": \"overwritten\""),
+            "{}",
+            response
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+            "one\n2\n"
+        );
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].summary, "Overwrite 'a.txt'");
+        assert_eq!(
+            requests[0].details,
+            "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n one\n-two\n+2\n"
+        );
+    }
+
+    /// Tests overwriting a file that is not UTF-8 text.
+    #[test]
+    fn test_overwrite_binary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.bin"), [0xff, 0xfe, 0x00, 0x01]).unwrap();
+        let mut actions = actions(temp_dir.path());
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        actions.set_permission_policy(Box::new(move |request: &AlpacaPermissionRequest| {
+            seen.lock().unwrap().push(request.clone());
+            AlpacaPermission::Allow
+        }));
+
+        let response = invoke(
+            &actions,
+            json!({"action": NAME, "file_name": "a.bin", "content": "text\n", "mode": "overwrite"}),
+        );
+
+        assert!(
+            response.contains("\"mode\": \"overwritten\""),
+            "{}",
+            response
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("a.bin")).unwrap(),
+            "text\n"
+        );
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].summary, "Overwrite 'a.bin'");
+        assert!(requests[0].details.contains("not UTF-8 text (4 bytes)"));
+        assert!(requests[0].details.ends_with("+text\n"));
+    }
+
+    /// Tests that nothing is written when the policy denies the change.
+    #[test]
+    fn test_denied() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut actions = actions(temp_dir.path());
+        actions.set_permission_policy(Box::new(|_: &AlpacaPermissionRequest| {
+            AlpacaPermission::Deny("the user declined".to_string())
+        }));
+
+        let response = invoke(
+            &actions,
+            json!({"action": NAME, "file_name": "a.txt", "content": "x"}),
+        );
+
+        assert!(response.contains("was not permitted: the user declined"));
+        assert!(!temp_dir.path().join("a.txt").exists());
+    }
+
+    /// Tests that paths outside the sandbox are rejected.
+    #[test]
+    fn test_outside_sandbox() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let actions = actions(temp_dir.path());
+
+        let response = invoke(
+            &actions,
+            json!({"action": NAME, "file_name": "../escape.txt", "content": "x"}),
+        );
+
+        assert!(response.contains("outside of the accessible directory"));
+    }
+
+    /// Tests that concurrent writes of one file use their own temporary files.
+    #[test]
+    fn test_write_atomic_concurrent() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("shared.txt");
+
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let data = format!("{}\n", i).repeat(10_000);
+                    write_atomic(&path, data.as_bytes(), false)
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+
+        let written = fs::read_to_string(&path).unwrap();
+        let first = written.lines().next().unwrap();
+        assert!(written.lines().all(|line| line == first));
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,906 @@
+use crate::rust_modules::{AlpacaRustModule, is_inside, module_tree};
+use crate::sandbox::AlpacaSandbox;
+use globset::Glob;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value as TomlValue};
+
+// ===
+// AlpacaCargoWorkspace
+// ===
+/// A Cargo workspace, or a lone package, read from its manifests and `Cargo.lock`.
+///
+/// Nothing is resolved over the network: dependency requirements come from the
+/// manifests, and the versions in use from the lock file when there is one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaCargoWorkspace {
+    /// The directory of the root manifest
+    pub root: PathBuf,
+    /// Whether the root manifest has a `[workspace]` table
+    pub is_workspace: bool,
+    /// The `Cargo.lock` of the workspace, if there is one
+    pub lock_file: Option<PathBuf>,
+    /// The packages of the workspace, the root package first
+    pub packages: Vec<AlpacaCargoPackage>,
+}
+
+/// A package and what its manifest declares.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaCargoPackage {
+    pub name: String,
+    pub version: Option<String>,
+    pub edition: String,
+    pub manifest: PathBuf,
+    pub targets: Vec<AlpacaCargoTarget>,
+    pub features: BTreeMap<String, Vec<String>>,
+    pub dependencies: Vec<AlpacaCargoDependency>,
+}
+
+/// A compilation target of a package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaCargoTarget {
+    /// `lib`, `proc-macro`, `bin`, `example`, `test` or `bench`
+    pub kind: &'static str,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// A dependency of a package.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlpacaCargoDependency {
+    /// The name the package uses for the dependency
+    pub name: String,
+    /// The name of the crate, when the dependency is renamed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// `normal`, `dev` or `build`
+    pub kind: &'static str,
+    /// The version requirement, such as `^1.0`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requirement: Option<String>,
+    /// The version chosen in `Cargo.lock`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locked: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+    #[serde(skip_serializing_if = "is_true")]
+    pub default_features: bool,
+    /// Where the dependency comes from when it is not crates.io, such as `path ../core`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// The platform the dependency is limited to, such as `cfg(unix)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+/// A package entry of `Cargo.lock`.
+#[derive(Debug, Clone, PartialEq)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    /// The dependencies, as `name` or `name version`
+    dependencies: Vec<String>,
+}
+
+impl AlpacaCargoWorkspace {
+    /// Finds the manifest closest to `start`, and the workspace it belongs to,
+    /// looking no higher than `boundary`. Workspace members outside `boundary` are skipped.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AlpacaCargoWorkspace)` - The workspace, or the lone package
+    /// * `Err(String)` - A description of the problem if there is no manifest or it is invalid
+    pub fn discover(start: &Path, boundary: &Path) -> Result<Self, String> {
+        let start = if start.is_file() {
+            start.parent().unwrap_or(start)
+        } else {
+            start
+        };
+        let ancestors: Vec<&Path> = start
+            .ancestors()
+            .take_while(|directory| directory.starts_with(boundary))
+            .collect();
+
+        let nearest = ancestors
+            .iter()
+            .find(|directory| directory.join("Cargo.toml").is_file())
+            .ok_or("No Cargo.toml was found in this directory or above it.")?;
+
+        // The closest manifest above with a `[workspace]` that lists this package
+        for directory in ancestors
+            .iter()
+            .skip_while(|directory| *directory != nearest)
+        {
+            let manifest = directory.join("Cargo.toml");
+            if !manifest.is_file() {
+                continue;
+            }
+            let table = read_manifest(&manifest)?;
+            if let Some(workspace) = table.get("workspace").and_then(TomlValue::as_table)
+                && (directory == nearest
+                    || member_directories(directory, workspace, boundary)
+                        .contains(&nearest.to_path_buf()))
+            {
+                return Self::load(directory, boundary);
+            }
+        }
+
+        Self::load(nearest, boundary)
+    }
+
+    /// Loads the workspace, or lone package, whose root manifest is in `root`.
+    ///
+    /// Members and targets that `boundary` does not contain, such as `../*`
+    /// or `path = "../../main.rs"`, are skipped.
+    pub fn load(root: &Path, boundary: &Path) -> Result<Self, String> {
+        let manifest = read_manifest(&root.join("Cargo.toml"))?;
+        let workspace = manifest.get("workspace").and_then(TomlValue::as_table);
+
+        let lock_path = root.join("Cargo.lock");
+        let lock = fs::read_to_string(&lock_path)
+            .ok()
+            .map(|text| parse_lock(&text))
+            .transpose()?;
+
+        let mut directories = Vec::new();
+        if manifest.contains_key("package") {
+            directories.push(root.to_path_buf());
+        }
+        if let Some(workspace) = workspace {
+            directories.extend(
+                member_directories(root, workspace, boundary)
+                    .into_iter()
+                    .filter(|directory| directory != root),
+            );
+        }
+
+        let mut packages = Vec::new();
+        for directory in directories {
+            let table = if directory == root {
+                manifest.clone()
+            } else {
+                read_manifest(&directory.join("Cargo.toml"))?
+            };
+            packages.push(read_package(
+                &directory,
+                &table,
+                workspace,
+                lock.as_deref(),
+                boundary,
+            )?);
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            is_workspace: workspace.is_some(),
+            lock_file: lock.is_some().then_some(lock_path),
+            packages,
+        })
+    }
+}
+
+impl AlpacaCargoPackage {
+    /// The library target, or else the binary named after the package.
+    pub fn root_target(&self) -> Option<&AlpacaCargoTarget> {
+        self.targets
+            .iter()
+            .find(|target| target.kind == "lib" || target.kind == "proc-macro")
+            .or_else(|| {
+                self.targets
+                    .iter()
+                    .find(|target| target.kind == "bin" && target.name == self.name)
+            })
+            .or_else(|| self.targets.iter().find(|target| target.kind == "bin"))
+    }
+
+    /// Lists the modules of the library, or of the main binary, reading no
+    /// file outside `boundary`.
+    pub fn modules(&self, boundary: &Path) -> Vec<AlpacaRustModule> {
+        self.root_target()
+            .map(|target| module_tree(&target.path, boundary))
+            .unwrap_or_default()
+    }
+}
+
+fn read_manifest(path: &Path) -> Result<Table, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read '{}': {}.", path.to_string_lossy(), e))?;
+    toml::from_str(&text).map_err(|e| {
+        format!(
+            "Failed to parse '{}': {}.",
+            path.to_string_lossy(),
+            e.message().trim_end()
+        )
+    })
+}
+
+/// Expands the `members` of a workspace, less its `exclude`, to the
+/// directories inside `boundary` that hold a manifest.
+fn member_directories(root: &Path, workspace: &Table, boundary: &Path) -> Vec<PathBuf> {
+    let patterns = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(TomlValue::as_array)
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(|value| value.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let excluded: Vec<PathBuf> = patterns("exclude")
+        .iter()
+        .flat_map(|pattern| expand(root, pattern, boundary))
+        .collect();
+
+    let mut directories: Vec<PathBuf> = Vec::new();
+    for pattern in patterns("members") {
+        for directory in expand(root, &pattern, boundary) {
+            if directory.join("Cargo.toml").is_file()
+                && !excluded.contains(&directory)
+                && !directories.contains(&directory)
+            {
+                directories.push(directory);
+            }
+        }
+    }
+    directories
+}
+
+/// Expands a relative path whose components may hold glob patterns, as in `crates/*`.
+///
+/// Paths that leave `boundary`, once links and `..` are resolved, are dropped
+/// before they are listed.
+fn expand(root: &Path, pattern: &str, boundary: &Path) -> Vec<PathBuf> {
+    let boundary = boundary
+        .canonicalize()
+        .unwrap_or_else(|_| boundary.to_path_buf());
+    let inside = |path: &PathBuf| {
+        path.canonicalize()
+            .is_ok_and(|path| path.starts_with(&boundary))
+    };
+
+    let mut paths = vec![root.to_path_buf()];
+    paths.retain(inside);
+    for component in pattern.split('/').filter(|c| !c.is_empty() && *c != ".") {
+        if !component.contains(['*', '?', '[']) {
+            paths = paths.into_iter().map(|path| path.join(component)).collect();
+            paths.retain(inside);
+            continue;
+        }
+
+        let Ok(glob) = Glob::new(component) else {
+            return Vec::new();
+        };
+        let matcher = glob.compile_matcher();
+        let mut expanded = Vec::new();
+        for path in paths {
+            let Ok(entries) = fs::read_dir(&path) else {
+                continue;
+            };
+            let mut matches: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|entry| entry.path().is_dir() && matcher.is_match(entry.file_name()))
+                .map(|entry| entry.path())
+                .collect();
+            matches.sort();
+            expanded.extend(matches);
+        }
+        paths = expanded;
+        paths.retain(inside);
+    }
+    paths
+}
+
+/// Reads a `[package]` field, following `field.workspace = true` to `[workspace.package]`.
+fn package_field(package: &Table, workspace: Option<&Table>, field: &str) -> Option<String> {
+    match package.get(field)? {
+        TomlValue::String(text) => Some(text.clone()),
+        TomlValue::Table(table) if table.get("workspace")?.as_bool()? => workspace?
+            .get("package")?
+            .get(field)?
+            .as_str()
+            .map(str::to_string),
+        _ => None,
+    }
+}
+
+fn read_package(
+    directory: &Path,
+    manifest: &Table,
+    workspace: Option<&Table>,
+    lock: Option<&[LockedPackage]>,
+    boundary: &Path,
+) -> Result<AlpacaCargoPackage, String> {
+    let manifest_path = directory.join("Cargo.toml");
+    let package = manifest
+        .get("package")
+        .and_then(TomlValue::as_table)
+        .ok_or_else(|| {
+            format!(
+                "'{}' has no [package] table.",
+                manifest_path.to_string_lossy()
+            )
+        })?;
+    let name = package_field(package, workspace, "name").ok_or_else(|| {
+        format!(
+            "The package in '{}' has no name.",
+            manifest_path.to_string_lossy()
+        )
+    })?;
+    let version = package_field(package, workspace, "version");
+
+    let features = manifest
+        .get("features")
+        .and_then(TomlValue::as_table)
+        .map(|table| {
+            table
+                .iter()
+                .map(|(feature, enables)| {
+                    let enables = enables
+                        .as_array()
+                        .map(|array| {
+                            array
+                                .iter()
+                                .filter_map(|value| value.as_str().map(str::to_string))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    (feature.clone(), enables)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let locked_package = lock.and_then(|lock| {
+        lock.iter().find(|locked| {
+            locked.name == name && version.as_ref().is_none_or(|v| *v == locked.version)
+        })
+    });
+    let dependencies = read_dependencies(manifest, workspace)
+        .into_iter()
+        .map(|mut dependency| {
+            if let Some(lock) = lock {
+                dependency.locked = locked_version(&dependency, locked_package, lock);
+            }
+            dependency
+        })
+        .collect();
+
+    Ok(AlpacaCargoPackage {
+        targets: read_targets(directory, package, manifest, &name, boundary),
+        edition: package_field(package, workspace, "edition").unwrap_or("2015".to_string()),
+        manifest: manifest_path,
+        name,
+        version,
+        features,
+        dependencies,
+    })
+}
+
+fn read_dependencies(manifest: &Table, workspace: Option<&Table>) -> Vec<AlpacaCargoDependency> {
+    const KINDS: [(&str, &str); 3] = [
+        ("dependencies", "normal"),
+        ("dev-dependencies", "dev"),
+        ("build-dependencies", "build"),
+    ];
+
+    let mut tables: Vec<(&Table, &'static str, Option<String>)> = Vec::new();
+    for (key, kind) in KINDS {
+        if let Some(table) = manifest.get(key).and_then(TomlValue::as_table) {
+            tables.push((table, kind, None));
+        }
+    }
+    if let Some(targets) = manifest.get("target").and_then(TomlValue::as_table) {
+        for (platform, target) in targets {
+            for (key, kind) in KINDS {
+                if let Some(table) = target.get(key).and_then(TomlValue::as_table) {
+                    tables.push((table, kind, Some(platform.clone())));
+                }
+            }
+        }
+    }
+
+    let inherited = workspace
+        .and_then(|workspace| workspace.get("dependencies"))
+        .and_then(TomlValue::as_table);
+
+    let mut dependencies = Vec::new();
+    for (table, kind, target) in tables {
+        for (name, value) in table {
+            let mut dependency = AlpacaCargoDependency {
+                name: name.clone(),
+                package: None,
+                kind,
+                requirement: None,
+                locked: None,
+                features: Vec::new(),
+                optional: false,
+                default_features: true,
+                source: None,
+                target: target.clone(),
+            };
+
+            // `name.workspace = true` starts from the workspace's declaration
+            if value.get("workspace").and_then(TomlValue::as_bool) == Some(true)
+                && let Some(base) = inherited.and_then(|inherited| inherited.get(name))
+            {
+                apply_declaration(&mut dependency, base);
+            }
+            apply_declaration(&mut dependency, value);
+            dependencies.push(dependency);
+        }
+    }
+    dependencies
+}
+
+fn apply_declaration(dependency: &mut AlpacaCargoDependency, value: &TomlValue) {
+    let table = match value {
+        TomlValue::String(requirement) => {
+            dependency.requirement = Some(requirement.clone());
+            return;
+        }
+        TomlValue::Table(table) => table,
+        _ => return,
+    };
+    let text = |key: &str| table.get(key).and_then(TomlValue::as_str);
+
+    if let Some(requirement) = text("version") {
+        dependency.requirement = Some(requirement.to_string());
+    }
+    if let Some(package) = text("package") {
+        dependency.package = Some(package.to_string());
+    }
+    if let Some(features) = table.get("features").and_then(TomlValue::as_array) {
+        for feature in features.iter().filter_map(TomlValue::as_str) {
+            if !dependency.features.iter().any(|f| f == feature) {
+                dependency.features.push(feature.to_string());
+            }
+        }
+    }
+    if let Some(optional) = table.get("optional").and_then(TomlValue::as_bool) {
+        dependency.optional = optional;
+    }
+    if let Some(default_features) = table
+        .get("default-features")
+        .or_else(|| table.get("default_features"))
+        .and_then(TomlValue::as_bool)
+    {
+        dependency.default_features = default_features;
+    }
+    if let Some(path) = text("path") {
+        dependency.source = Some(format!("path {}", path));
+    } else if let Some(git) = text("git") {
+        let reference = ["branch", "tag", "rev"]
+            .iter()
+            .find_map(|key| text(key).map(|value| format!(" ({} {})", key, value)))
+            .unwrap_or_default();
+        dependency.source = Some(format!("git {}{}", git, reference));
+    } else if let Some(registry) = text("registry") {
+        dependency.source = Some(format!("registry {}", registry));
+    }
+}
+
+/// Finds the version of a dependency chosen in `Cargo.lock`.
+///
+/// The lock entry of the package names its dependencies, with a version when
+/// several versions of the crate are locked. Without an entry, a crate locked
+/// at a single version is used.
+fn locked_version(
+    dependency: &AlpacaCargoDependency,
+    package: Option<&LockedPackage>,
+    lock: &[LockedPackage],
+) -> Option<String> {
+    let crate_name = dependency.package.as_deref().unwrap_or(&dependency.name);
+
+    if let Some(package) = package {
+        for entry in &package.dependencies {
+            let mut parts = entry.split_whitespace();
+            if parts.next() != Some(crate_name) {
+                continue;
+            }
+            if let Some(version) = parts.next() {
+                return Some(version.to_string());
+            }
+            break;
+        }
+    }
+
+    let mut versions = lock.iter().filter(|locked| locked.name == crate_name);
+    match (versions.next(), versions.next()) {
+        (Some(locked), None) => Some(locked.version.clone()),
+        _ => None,
+    }
+}
+
+fn parse_lock(text: &str) -> Result<Vec<LockedPackage>, String> {
+    let table: Table = toml::from_str(text)
+        .map_err(|e| format!("Failed to parse 'Cargo.lock': {}.", e.message().trim_end()))?;
+
+    Ok(table
+        .get("package")
+        .and_then(TomlValue::as_array)
+        .map(|packages| {
+            packages
+                .iter()
+                .filter_map(|package| {
+                    Some(LockedPackage {
+                        name: package.get("name")?.as_str()?.to_string(),
+                        version: package.get("version")?.as_str()?.to_string(),
+                        dependencies: package
+                            .get("dependencies")
+                            .and_then(TomlValue::as_array)
+                            .map(|array| {
+                                array
+                                    .iter()
+                                    .filter_map(|value| value.as_str().map(str::to_string))
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Lists the targets of a package: those declared in the manifest, and those
+/// Cargo discovers from the standard layout unless `autobins` and the like are off.
+/// Targets whose file is outside `boundary` are left out.
+fn read_targets(
+    directory: &Path,
+    package: &Table,
+    manifest: &Table,
+    name: &str,
+    boundary: &Path,
+) -> Vec<AlpacaCargoTarget> {
+    let mut targets = Vec::new();
+    let declared_path = |table: &TomlValue| {
+        table
+            .get("path")
+            .and_then(TomlValue::as_str)
+            .map(|path| directory.join(path))
+    };
+
+    // The library
+    let lib = manifest.get("lib");
+    let lib_path = lib
+        .and_then(declared_path)
+        .unwrap_or_else(|| directory.join("src").join("lib.rs"));
+    if lib.is_some() || lib_path.is_file() {
+        let proc_macro = lib
+            .and_then(|lib| lib.get("proc-macro").or_else(|| lib.get("proc_macro")))
+            .and_then(TomlValue::as_bool)
+            .unwrap_or(false);
+        targets.push(AlpacaCargoTarget {
+            kind: if proc_macro { "proc-macro" } else { "lib" },
+            name: lib
+                .and_then(|lib| lib.get("name"))
+                .and_then(TomlValue::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| name.replace('-', "_")),
+            path: lib_path,
+        });
+    }
+
+    const KINDS: [(&str, &str, &str, &str); 4] = [
+        ("bin", "bin", "src/bin", "autobins"),
+        ("example", "example", "examples", "autoexamples"),
+        ("test", "test", "tests", "autotests"),
+        ("bench", "bench", "benches", "autobenches"),
+    ];
+    for (key, kind, folder, auto) in KINDS {
+        let mut found: Vec<AlpacaCargoTarget> = Vec::new();
+
+        for declared in manifest
+            .get(key)
+            .and_then(TomlValue::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let Some(target_name) = declared.get("name").and_then(TomlValue::as_str) else {
+                continue;
+            };
+            let path = declared_path(declared).unwrap_or_else(|| {
+                if kind == "bin" && target_name == name {
+                    directory.join("src").join("main.rs")
+                } else {
+                    directory.join(folder).join(format!("{}.rs", target_name))
+                }
+            });
+            found.push(AlpacaCargoTarget {
+                kind,
+                name: target_name.to_string(),
+                path,
+            });
+        }
+
+        if package.get(auto).and_then(TomlValue::as_bool) != Some(false) {
+            let mut discovered = Vec::new();
+            if kind == "bin" && directory.join("src").join("main.rs").is_file() {
+                discovered.push((name.to_string(), directory.join("src").join("main.rs")));
+            }
+            discovered.extend(discover_targets(&directory.join(folder)));
+
+            for (target_name, path) in discovered {
+                // A declared target replaces the discovered one with its name or path
+                if !found
+                    .iter()
+                    .any(|target| target.name == target_name || target.path == path)
+                {
+                    found.push(AlpacaCargoTarget {
+                        kind,
+                        name: target_name,
+                        path,
+                    });
+                }
+            }
+        }
+
+        targets.extend(found);
+    }
+
+    let sandbox = AlpacaSandbox::new(boundary);
+    targets.retain(|target| is_inside(&sandbox, &target.path));
+    targets
+}
+
+/// Finds `name.rs` and `name/main.rs` target files in a folder.
+fn discover_targets(folder: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+
+    let mut targets: Vec<(String, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if path.is_file() {
+                let stem = file_name.strip_suffix(".rs")?;
+                Some((stem.to_string(), path))
+            } else if path.join("main.rs").is_file() {
+                Some((file_name, path.join("main.rs")))
+            } else {
+                None
+            }
+        })
+        .collect();
+    targets.sort();
+    targets
+}
+
+// ===
+// AlpacaCargoWorkspace Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn workspace() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(
+            root,
+            "Cargo.toml",
+            r#"
+[workspace]
+members = ["crates/*"]
+exclude = ["crates/skipped"]
+
+[workspace.package]
+version = "0.3.0"
+edition = "2021"
+
+[workspace.dependencies]
+serde = { version = "1.0", features = ["derive"] }
+"#,
+        );
+        write(
+            root,
+            "crates/core/Cargo.toml",
+            r#"
+[package]
+name = "demo-core"
+version.workspace = true
+edition.workspace = true
+
+[features]
+default = ["std"]
+std = []
+
+[dependencies]
+serde = { workspace = true, features = ["rc"] }
+regex = "1"
+json = { package = "serde_json", version = "1", optional = true }
+
+[target.'cfg(unix)'.dependencies]
+libc = { version = "0.2", default-features = false }
+
+[dev-dependencies]
+tempfile = "3"
+"#,
+        );
+        write(root, "crates/core/src/lib.rs", "pub mod parse;\n");
+        write(root, "crates/core/src/parse.rs", "");
+        write(root, "crates/core/src/bin/tool.rs", "fn main() {}\n");
+        write(root, "crates/core/tests/smoke.rs", "");
+        write(
+            root,
+            "crates/cli/Cargo.toml",
+            "[package]\nname = \"demo-cli\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[dependencies]\ndemo-core = { path = \"../core\" }\n",
+        );
+        write(root, "crates/cli/src/main.rs", "mod args;\nfn main() {}\n");
+        write(
+            root,
+            "crates/skipped/Cargo.toml",
+            "[package]\nname = \"skipped\"\n",
+        );
+        write(
+            root,
+            "Cargo.lock",
+            r#"
+version = 4
+
+[[package]]
+name = "demo-core"
+version = "0.3.0"
+dependencies = ["regex", "serde 1.0.210"]
+
+[[package]]
+name = "regex"
+version = "1.10.6"
+
+[[package]]
+name = "serde"
+version = "1.0.210"
+
+[[package]]
+name = "serde"
+version = "0.9.15"
+
+[[package]]
+name = "serde_json"
+version = "1.0.128"
+"#,
+        );
+        temp_dir
+    }
+
+    /// Tests finding the workspace from inside a member, and its packages.
+    #[test]
+    fn test_discover_workspace() {
+        let temp_dir = workspace();
+        let root = temp_dir.path();
+
+        let workspace =
+            AlpacaCargoWorkspace::discover(&root.join("crates/core/src"), root).unwrap();
+        assert_eq!(workspace.root, root);
+        assert!(workspace.is_workspace);
+        assert_eq!(workspace.lock_file, Some(root.join("Cargo.lock")));
+
+        let names: Vec<&str> = workspace.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["demo-cli", "demo-core"]);
+
+        let cli = &workspace.packages[0];
+        assert_eq!(cli.edition, "2024");
+        assert_eq!(cli.root_target().unwrap().kind, "bin");
+        assert_eq!(cli.modules(root)[0].path, "args");
+        assert_eq!(cli.dependencies[0].source.as_deref(), Some("path ../core"));
+    }
+
+    /// Tests the targets, features and dependencies of a package.
+    #[test]
+    fn test_read_package() {
+        let temp_dir = workspace();
+        let root = temp_dir.path();
+        let workspace = AlpacaCargoWorkspace::load(root, root).unwrap();
+        let core = &workspace.packages[1];
+
+        assert_eq!(core.version.as_deref(), Some("0.3.0"));
+        assert_eq!(core.edition, "2021");
+        assert_eq!(core.features["default"], ["std"]);
+
+        let targets: Vec<(&str, &str)> = core
+            .targets
+            .iter()
+            .map(|target| (target.kind, target.name.as_str()))
+            .collect();
+        assert_eq!(
+            targets,
+            [("lib", "demo_core"), ("bin", "tool"), ("test", "smoke")]
+        );
+        assert_eq!(core.modules(root)[0].path, "parse");
+
+        let dependency = |name: &str| {
+            core.dependencies
+                .iter()
+                .find(|dependency| dependency.name == name)
+                .unwrap()
+        };
+        let serde = dependency("serde");
+        assert_eq!(serde.requirement.as_deref(), Some("1.0"));
+        assert_eq!(serde.features, ["derive", "rc"]);
+        assert_eq!(serde.locked.as_deref(), Some("1.0.210"));
+        assert_eq!(dependency("regex").locked.as_deref(), Some("1.10.6"));
+
+        let json = dependency("json");
+        assert_eq!(json.package.as_deref(), Some("serde_json"));
+        assert!(json.optional);
+        assert_eq!(json.locked.as_deref(), Some("1.0.128"));
+
+        let libc = dependency("libc");
+        assert_eq!(libc.target.as_deref(), Some("cfg(unix)"));
+        assert!(!libc.default_features);
+        assert_eq!(dependency("tempfile").kind, "dev");
+    }
+
+    /// Tests that members outside the boundary are skipped.
+    #[test]
+    fn test_members_outside_boundary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("project");
+        write(
+            &root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"app\", \"../*\", \"app/../../outside\"]\n",
+        );
+        write(&root, "app/Cargo.toml", "[package]\nname = \"app\"\n");
+        write(
+            temp_dir.path(),
+            "outside/Cargo.toml",
+            "[package]\nname = \"outside\"\n",
+        );
+
+        let workspace = AlpacaCargoWorkspace::load(&root, &root).unwrap();
+        let names: Vec<&str> = workspace.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["app"]);
+    }
+
+    /// Tests that targets and modules outside the boundary are skipped.
+    #[test]
+    fn test_targets_outside_boundary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("project");
+        write(
+            &root,
+            "Cargo.toml",
+            "[package]\nname = \"app\"\n\n[lib]\npath = \"../outside.rs\"\n\n[[bin]]\nname = \"tool\"\npath = \"/etc/hostname\"\n",
+        );
+        write(
+            &root,
+            "src/main.rs",
+            "#[path = \"../../outside.rs\"]\nmod outside;\n",
+        );
+        write(temp_dir.path(), "outside.rs", "mod secret;\n");
+
+        let workspace = AlpacaCargoWorkspace::load(&root, &root).unwrap();
+        let package = &workspace.packages[0];
+        let targets: Vec<(&str, &str)> = package
+            .targets
+            .iter()
+            .map(|target| (target.kind, target.name.as_str()))
+            .collect();
+        assert_eq!(targets, [("bin", "app")]);
+
+        let modules = package.modules(&root);
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0].file, None);
+    }
+}
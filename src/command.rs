@@ -0,0 +1,645 @@
+use crate::confinement::{AlpacaConfinement, AlpacaConfinementReport};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The environment variables passed to commands by default.
+const DEFAULT_ENV_PASSTHROUGH: [&str; 7] =
+    ["PATH", "HOME", "USER", "LANG", "LC_ALL", "TERM", "TMPDIR"];
+
+/// The environment variables a command may not be given, because they choose
+/// which code runs: the dynamic loader, compilers, shells, interpreters,
+/// pagers and editors, and the places configuration is read from.
+const PROTECTED_ENV: [&str; 21] = [
+    "BASH_ENV",
+    "CC",
+    "CXX",
+    "EDITOR",
+    "ENV",
+    "HOME",
+    "IFS",
+    "LESSCLOSE",
+    "LESSOPEN",
+    "NODE_OPTIONS",
+    "PAGER",
+    "PATH",
+    "PERL5LIB",
+    "PERL5OPT",
+    "PYTHONPATH",
+    "PYTHONSTARTUP",
+    "RUBYOPT",
+    "RUSTFLAGS",
+    "SHELL",
+    "VISUAL",
+    "XDG_CONFIG_HOME",
+];
+
+/// The prefixes of other protected variables, such as `LD_PRELOAD` or `RUSTC_WRAPPER`.
+const PROTECTED_ENV_PREFIXES: [&str; 6] = ["CARGO_", "DYLD_", "GIT_", "LD_", "RUSTC", "RUSTDOC"];
+
+/// How long to wait for the output pipes to close once the process has exited.
+const DRAIN_GRACE: Duration = Duration::from_millis(500);
+
+// ===
+// AlpacaCommandConfig
+// ===
+/// Controls which programs `run_command` may start and how they run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaCommandConfig {
+    /// The programs that may be run, such as `cargo` or `git`; `*` allows any program
+    pub allowed_commands: Vec<String>,
+    /// Whether a command line may be run through `sh -c`
+    pub allow_shell: bool,
+    /// The longest a command may run before it is killed
+    pub timeout: Duration,
+    /// The most bytes kept from each of stdout and stderr
+    pub max_output_bytes: usize,
+    /// The environment variables inherited from the host; all others are removed
+    pub env_passthrough: Vec<String>,
+    /// Kernel-level limits for the process, if any
+    pub confinement: Option<AlpacaConfinement>,
+}
+
+impl Default for AlpacaCommandConfig {
+    fn default() -> Self {
+        AlpacaCommandConfig {
+            allowed_commands: Vec::new(),
+            allow_shell: false,
+            timeout: Duration::from_secs(60),
+            max_output_bytes: 16 * 1024,
+            env_passthrough: DEFAULT_ENV_PASSTHROUGH
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            confinement: None,
+        }
+    }
+}
+
+impl AlpacaCommandConfig {
+    /// Creates a configuration allowing the given programs.
+    pub fn allowing(commands: &[&str]) -> Self {
+        AlpacaCommandConfig {
+            allowed_commands: commands.iter().map(|command| command.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Checks a program against the allowlist.
+    ///
+    /// Programs are matched exactly as given, so `ls` does not allow `./ls`.
+    pub fn is_allowed(&self, program: &str) -> bool {
+        self.allowed_commands
+            .iter()
+            .any(|allowed| allowed == "*" || allowed == program)
+    }
+
+    /// Checks the extra environment variables requested for a command.
+    ///
+    /// Variables passed through from the host, and variables that choose which
+    /// code runs, such as `PATH`, `LD_PRELOAD` or `RUSTC_WRAPPER`, are refused,
+    /// so that an allowed program cannot be made to run another one.
+    pub fn check_env(&self, env: &HashMap<String, String>) -> Result<(), String> {
+        let mut names: Vec<&String> = env.keys().collect();
+        names.sort();
+        for name in names {
+            let upper = name.to_ascii_uppercase();
+            let protected = PROTECTED_ENV.contains(&upper.as_str())
+                || PROTECTED_ENV_PREFIXES
+                    .iter()
+                    .any(|prefix| upper.starts_with(prefix))
+                || self
+                    .env_passthrough
+                    .iter()
+                    .any(|passed| passed.eq_ignore_ascii_case(name));
+            if protected {
+                return Err(format!(
+                    "The environment variable '{}' cannot be set for a command.",
+                    name
+                ));
+            }
+            if name.is_empty() || name.contains(['=', '\0']) {
+                return Err(format!(
+                    "'{}' is not a valid environment variable name.",
+                    name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// ===
+// AlpacaCommandOutput
+// ===
+/// The outcome of a command run by `run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaCommandOutput {
+    /// The exit code, or `None` if the process was killed by a signal
+    pub exit_code: Option<i32>,
+    /// The signal that ended the process, on unix
+    pub signal: Option<i32>,
+    /// Whether the process was killed for running past the timeout
+    pub timed_out: bool,
+    /// How long the command ran
+    pub duration: Duration,
+    pub stdout: AlpacaCapturedOutput,
+    pub stderr: AlpacaCapturedOutput,
+    /// What the confinement enforced, if one was configured
+    pub confinement: Option<AlpacaConfinementReport>,
+}
+
+/// The output of a stream, keeping its beginning and end when it is too long.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AlpacaCapturedOutput {
+    /// The captured text, with a marker where bytes were dropped
+    pub text: String,
+    /// The number of bytes the process wrote
+    pub total_bytes: usize,
+    /// Whether bytes were dropped
+    pub truncated: bool,
+}
+
+/// Collects a stream, keeping the first and last half of `limit` bytes.
+struct Capture {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    total: usize,
+    limit: usize,
+}
+
+impl Capture {
+    fn new(limit: usize) -> Self {
+        Capture {
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            total: 0,
+            limit,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.total += bytes.len();
+        let head_limit = self.limit / 2;
+        let room = head_limit.saturating_sub(self.head.len());
+        let (head, rest) = bytes.split_at(room.min(bytes.len()));
+        self.head.extend_from_slice(head);
+
+        self.tail.extend(rest);
+        let tail_limit = self.limit - head_limit;
+        if self.tail.len() > tail_limit {
+            self.tail.drain(..self.tail.len() - tail_limit);
+        }
+    }
+
+    fn output(&self) -> AlpacaCapturedOutput {
+        let kept = self.head.len() + self.tail.len();
+        let truncated = kept < self.total;
+        let mut text = String::from_utf8_lossy(&self.head).to_string();
+        if truncated {
+            text.push_str(&format!("\n[… {} bytes omitted …]\n", self.total - kept));
+        }
+        let tail: Vec<u8> = self.tail.iter().copied().collect();
+        text.push_str(&String::from_utf8_lossy(&tail));
+
+        AlpacaCapturedOutput {
+            text,
+            total_bytes: self.total,
+            truncated,
+        }
+    }
+}
+
+/// Reads a stream to its end on a separate thread.
+///
+/// The returned receiver is signalled when the stream closes. The capture is
+/// shared so that partial output can be used if a background process keeps the
+/// stream open.
+fn spawn_reader(
+    mut stream: impl Read + Send + 'static,
+    limit: usize,
+) -> (Arc<Mutex<Capture>>, mpsc::Receiver<()>) {
+    let capture = Arc::new(Mutex::new(Capture::new(limit)));
+    let (sender, receiver) = mpsc::channel();
+
+    let shared = Arc::clone(&capture);
+    thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => shared.lock().unwrap().push(&buffer[..read]),
+            }
+        }
+        let _ = sender.send(());
+    });
+
+    (capture, receiver)
+}
+
+/// Runs a program without a shell, capturing its output.
+///
+/// The environment is cleared except for the variables in
+/// `config.env_passthrough` and those in `env`. Standard input is closed, and
+/// the process is killed if it runs past `config.timeout`. When
+/// `config.confinement` is set, the kernel confines the process as well.
+///
+/// This waits for the program to finish; `spawn` starts it without waiting.
+///
+/// # Arguments
+///
+/// * `program` - The program to run, looked up on the host's `PATH`
+/// * `args` - The arguments, passed as they are
+/// * `cwd` - The working directory of the process
+/// * `env` - Extra environment variables
+/// * `config` - The limits to apply
+///
+/// # Returns
+///
+/// * `Ok(AlpacaCommandOutput)` - The outcome, including failures of the program itself
+/// * `Err(String)` - A description of why the program could not be started
+pub fn run(
+    program: &str,
+    args: &[String],
+    cwd: &Path,
+    env: &HashMap<String, String>,
+    config: &AlpacaCommandConfig,
+) -> Result<AlpacaCommandOutput, String> {
+    spawn(program, args, cwd, env, config)?.wait()
+}
+
+/// Runs a program as `run` does, taking the host's environment from `parent_env`.
+pub fn run_with_parent_env(
+    program: &str,
+    args: &[String],
+    cwd: &Path,
+    env: &HashMap<String, String>,
+    parent_env: &HashMap<OsString, OsString>,
+    config: &AlpacaCommandConfig,
+) -> Result<AlpacaCommandOutput, String> {
+    spawn_with_parent_env(program, args, cwd, env, parent_env, config)?.wait()
+}
+
+/// Starts a program as `run` does, and returns while it runs.
+///
+/// The returned process is polled with `AlpacaCommandProcess::try_wait`, or
+/// waited for with `AlpacaCommandProcess::wait`; it is killed once it runs
+/// past `config.timeout`, or when it is dropped before it has finished.
+pub fn spawn(
+    program: &str,
+    args: &[String],
+    cwd: &Path,
+    env: &HashMap<String, String>,
+    config: &AlpacaCommandConfig,
+) -> Result<AlpacaCommandProcess, String> {
+    let parent_env: HashMap<OsString, OsString> = std::env::vars_os().collect();
+    spawn_with_parent_env(program, args, cwd, env, &parent_env, config)
+}
+
+/// Starts a program as `spawn` does, taking the host's environment from `parent_env`.
+///
+/// On unix the program runs in a process group of its own, which is killed
+/// when it times out or exits, so that processes it started in the
+/// background do not outlive it.
+pub fn spawn_with_parent_env(
+    program: &str,
+    args: &[String],
+    cwd: &Path,
+    env: &HashMap<String, String>,
+    parent_env: &HashMap<OsString, OsString>,
+    config: &AlpacaCommandConfig,
+) -> Result<AlpacaCommandProcess, String> {
+    // Look the program up before `env` can change the `PATH` of the child
+    let executable =
+        find_program(program, parent_env.get(&OsString::from("PATH"))).ok_or_else(|| {
+            format!(
+                "Failed to start '{}': it was not found on the PATH.",
+                program
+            )
+        })?;
+
+    let mut command = Command::new(executable);
+    command
+        .args(args)
+        .current_dir(cwd)
+        .env_clear()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    for name in &config.env_passthrough {
+        if let Some(value) = parent_env.get(&OsString::from(name)) {
+            command.env(name, value);
+        }
+    }
+    command.envs(env);
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let confinement = match &config.confinement {
+        Some(confinement) => Some(confinement.apply_to_command(&mut command)?),
+        None => None,
+    };
+
+    let started = Instant::now();
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to start '{}': {}.", program, e))?;
+
+    let stdout = spawn_reader(child.stdout.take().unwrap(), config.max_output_bytes);
+    let stderr = spawn_reader(child.stderr.take().unwrap(), config.max_output_bytes);
+
+    Ok(AlpacaCommandProcess {
+        child,
+        started,
+        timeout: config.timeout,
+        stdout,
+        stderr,
+        confinement,
+        finished: None,
+    })
+}
+
+// ===
+// AlpacaCommandProcess
+// ===
+/// A program started by `spawn`.
+pub struct AlpacaCommandProcess {
+    child: Child,
+    started: Instant,
+    timeout: Duration,
+    stdout: (Arc<Mutex<Capture>>, mpsc::Receiver<()>),
+    stderr: (Arc<Mutex<Capture>>, mpsc::Receiver<()>),
+    confinement: Option<AlpacaConfinementReport>,
+    /// The exit status, whether it timed out and how long it ran, once it has finished
+    finished: Option<(std::process::ExitStatus, bool, Duration)>,
+}
+
+impl AlpacaCommandProcess {
+    /// Checks whether the program has finished, without waiting for it.
+    ///
+    /// A program that has run past its timeout is killed first.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(AlpacaCommandOutput))` - The outcome, once the program has finished
+    /// * `Ok(None)` - The program is still running
+    /// * `Err(String)` - A description of why its state could not be read
+    pub fn try_wait(&mut self) -> Result<Option<AlpacaCommandOutput>, String> {
+        let (status, timed_out, duration) = match self.finished {
+            Some(finished) => finished,
+            None => {
+                let (status, timed_out) = match self.child.try_wait().map_err(|e| e.to_string())? {
+                    Some(status) => {
+                        kill_process_group(&self.child);
+                        (status, false)
+                    }
+                    None if self.started.elapsed() >= self.timeout => {
+                        kill_process_group(&self.child);
+                        let _ = self.child.kill();
+                        (self.child.wait().map_err(|e| e.to_string())?, true)
+                    }
+                    None => return Ok(None),
+                };
+                let finished = (status, timed_out, self.started.elapsed());
+
+                // Give the readers a moment to drain what is left in the pipes
+                let _ = self.stdout.1.recv_timeout(DRAIN_GRACE);
+                let _ = self.stderr.1.recv_timeout(DRAIN_GRACE);
+                self.finished = Some(finished);
+                finished
+            }
+        };
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Ok(Some(AlpacaCommandOutput {
+            exit_code: status.code(),
+            signal,
+            timed_out,
+            duration,
+            stdout: self.stdout.0.lock().unwrap().output(),
+            stderr: self.stderr.0.lock().unwrap().output(),
+            confinement: self.confinement.clone(),
+        }))
+    }
+
+    /// Waits for the program to finish, killing it once it runs past its timeout.
+    pub fn wait(&mut self) -> Result<AlpacaCommandOutput, String> {
+        loop {
+            if let Some(output) = self.try_wait()? {
+                return Ok(output);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// How long the program has been running.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// The longest the program may run before it is killed.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl Drop for AlpacaCommandProcess {
+    fn drop(&mut self) {
+        if self.finished.is_none() {
+            kill_process_group(&self.child);
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Finds the executable a program name refers to.
+///
+/// Names with a path separator are used as they are. Other names are looked
+/// up in the absolute directories of `path`.
+fn find_program(program: &str, path: Option<&OsString>) -> Option<PathBuf> {
+    if program.contains(std::path::MAIN_SEPARATOR) || program.contains('/') {
+        return Some(PathBuf::from(program));
+    }
+    if program.is_empty() {
+        return None;
+    }
+
+    std::env::split_paths(path?)
+        .filter(|directory| directory.is_absolute())
+        .map(|directory| directory.join(program))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Kills the process group led by a child, which `spawn` starts it in.
+#[cfg(unix)]
+fn kill_process_group(child: &Child) {
+    // SAFETY: killpg has no memory safety requirements
+    unsafe {
+        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_child: &Child) {}
+
+// ===
+// AlpacaCommand Tests
+// ===
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn run_sh(script: &str, config: &AlpacaCommandConfig) -> AlpacaCommandOutput {
+        let args = vec!["-c".to_string(), script.to_string()];
+        run("sh", &args, Path::new("."), &HashMap::new(), config).unwrap()
+    }
+
+    /// Tests capturing the output and exit code of a program.
+    #[test]
+    fn test_run_capture() {
+        let output = run_sh(
+            "echo out; echo err >&2; exit 3",
+            &AlpacaCommandConfig::default(),
+        );
+
+        assert_eq!(output.exit_code, Some(3));
+        assert_eq!(output.stdout.text, "out\n");
+        assert_eq!(output.stderr.text, "err\n");
+        assert!(!output.timed_out);
+    }
+
+    /// Tests that a program running past the timeout is killed.
+    #[test]
+    fn test_run_timeout() {
+        let config = AlpacaCommandConfig {
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let output = run_sh("sleep 5", &config);
+
+        assert!(output.timed_out);
+        assert_eq!(output.exit_code, None);
+        assert!(output.duration < Duration::from_secs(5));
+    }
+
+    /// Tests that long output keeps its beginning and end.
+    #[test]
+    fn test_run_output_cap() {
+        let config = AlpacaCommandConfig {
+            max_output_bytes: 20,
+            ..Default::default()
+        };
+        let output = run_sh("seq 1 1000", &config);
+
+        assert!(output.stdout.truncated);
+        assert!(output.stdout.text.starts_with("1\n2\n3\n"));
+        assert!(output.stdout.text.ends_with("999\n1000\n"));
+        assert!(output.stdout.text.contains("bytes omitted"));
+    }
+
+    /// Tests that the environment is scrubbed.
+    #[test]
+    fn test_run_env_scrubbed() {
+        let parent_env: HashMap<OsString, OsString> = [
+            ("PATH", std::env::var_os("PATH").unwrap()),
+            ("ALPACA_TEST_SECRET", OsString::from("hunter2")),
+        ]
+        .into_iter()
+        .map(|(name, value)| (OsString::from(name), value))
+        .collect();
+        let mut env = HashMap::new();
+        env.insert("GREETING".to_string(), "hello".to_string());
+
+        let args = vec![
+            "-c".to_string(),
+            "echo \"[$ALPACA_TEST_SECRET][$GREETING]\"".to_string(),
+        ];
+        let output = run_with_parent_env(
+            "sh",
+            &args,
+            Path::new("."),
+            &env,
+            &parent_env,
+            &AlpacaCommandConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(output.stdout.text, "[][hello]\n");
+    }
+
+    /// Tests that processes left in the background are killed with the command.
+    #[test]
+    fn test_run_kills_process_group() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = AlpacaCommandConfig {
+            timeout: Duration::from_millis(200),
+            ..Default::default()
+        };
+        for script in [
+            "(sleep 1; touch timed_out) & sleep 5",
+            "(sleep 1; touch exited) &",
+        ] {
+            let args = vec!["-c".to_string(), script.to_string()];
+            run("sh", &args, temp_dir.path(), &HashMap::new(), &config).unwrap();
+        }
+
+        thread::sleep(Duration::from_millis(1500));
+        assert!(!temp_dir.path().join("timed_out").exists());
+        assert!(!temp_dir.path().join("exited").exists());
+    }
+
+    /// Tests that variables choosing which code runs cannot be set.
+    #[test]
+    fn test_check_env() {
+        let config = AlpacaCommandConfig::allowing(&["cargo"]);
+        let env = |name: &str| HashMap::from([(name.to_string(), "x".to_string())]);
+
+        assert!(config.check_env(&env("RUST_BACKTRACE")).is_ok());
+        for name in [
+            "PATH",
+            "LD_PRELOAD",
+            "RUSTC_WRAPPER",
+            "GIT_DIR",
+            "path",
+            "LANG",
+        ] {
+            let error = config.check_env(&env(name)).unwrap_err();
+            assert!(error.contains("cannot be set"), "{}", error);
+        }
+        assert!(config.check_env(&env("A=B")).is_err());
+    }
+
+    /// Tests matching programs against the allowlist.
+    #[test]
+    fn test_is_allowed() {
+        let config = AlpacaCommandConfig::allowing(&["cargo", "git"]);
+        assert!(config.is_allowed("cargo"));
+        assert!(!config.is_allowed("./cargo"));
+        assert!(!config.is_allowed("rm"));
+        assert!(AlpacaCommandConfig::allowing(&["*"]).is_allowed("rm"));
+        assert!(!AlpacaCommandConfig::default().is_allowed("ls"));
+    }
+}
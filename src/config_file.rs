@@ -0,0 +1,382 @@
+use serde_json::Map as JsonMap;
+use serde_json::Value as JsonValue;
+use std::path::Path;
+
+// ===
+// AlpacaConfigFormat
+// ===
+/// The configuration file formats that can be read as JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlpacaConfigFormat {
+    Toml,
+    Yaml,
+    Ini,
+    /// `KEY=value` lines, as in `.env` files
+    Env,
+    Json,
+}
+
+impl AlpacaConfigFormat {
+    /// Parses a format name such as `toml` or `dotenv`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "ini" | "cfg" => Some(Self::Ini),
+            "env" | "dotenv" | ".env" => Some(Self::Env),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Detects the format of a file from its name.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if file_name == ".env" || file_name.starts_with(".env.") || file_name.ends_with(".env") {
+            return Some(Self::Env);
+        }
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "ini" | "cfg" => Some(Self::Ini),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Toml => "toml",
+            Self::Yaml => "yaml",
+            Self::Ini => "ini",
+            Self::Env => "env",
+            Self::Json => "json",
+        }
+    }
+
+    /// Parses a configuration file into JSON.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(JsonValue)` - The parsed document
+    /// * `Err(String)` - A description of the syntax error, with its line number
+    pub fn parse(&self, text: &str) -> Result<JsonValue, String> {
+        match self {
+            Self::Toml => parse_toml(text),
+            Self::Yaml => parse_yaml(text),
+            Self::Ini => parse_ini(text),
+            Self::Env => parse_env(text),
+            Self::Json => serde_json::from_str(text).map_err(|error| {
+                format!(
+                    "line {}, column {}: {}",
+                    error.line(),
+                    error.column(),
+                    error
+                        .to_string()
+                        .split(" at line ")
+                        .next()
+                        .unwrap_or_default()
+                )
+            }),
+        }
+    }
+}
+
+/// Returns the one-based line and column of a byte offset.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+// ---
+
+fn parse_toml(text: &str) -> Result<JsonValue, String> {
+    let value: toml::Table = toml::from_str(text).map_err(|error| {
+        let message = error.message().trim_end_matches('\n');
+        match error.span() {
+            Some(span) => {
+                let (line, column) = line_column(text, span.start);
+                format!("line {}, column {}: {}", line, column, message)
+            }
+            None => message.to_string(),
+        }
+    })?;
+    Ok(toml_to_json(toml::Value::Table(value)))
+}
+
+fn toml_to_json(value: toml::Value) -> JsonValue {
+    match value {
+        toml::Value::String(text) => JsonValue::String(text),
+        toml::Value::Integer(number) => JsonValue::from(number),
+        toml::Value::Float(number) => serde_json::Number::from_f64(number)
+            .map(JsonValue::Number)
+            .unwrap_or_else(|| JsonValue::String(number.to_string())),
+        toml::Value::Boolean(flag) => JsonValue::Bool(flag),
+        toml::Value::Datetime(datetime) => JsonValue::String(datetime.to_string()),
+        toml::Value::Array(array) => array.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => JsonValue::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+// ---
+
+fn parse_yaml(text: &str) -> Result<JsonValue, String> {
+    if text.trim().is_empty() {
+        return Ok(JsonValue::Null);
+    }
+    let value: serde_yaml::Value = serde_yaml::from_str(text).map_err(|error| {
+        let message = error.to_string();
+        let message = message.split(" at line ").next().unwrap_or_default();
+        match error.location() {
+            Some(location) => format!(
+                "line {}, column {}: {}",
+                location.line(),
+                location.column(),
+                message
+            ),
+            None => message.to_string(),
+        }
+    })?;
+    Ok(yaml_to_json(value))
+}
+
+fn yaml_to_json(value: serde_yaml::Value) -> JsonValue {
+    match value {
+        serde_yaml::Value::Null => JsonValue::Null,
+        serde_yaml::Value::Bool(flag) => JsonValue::Bool(flag),
+        serde_yaml::Value::Number(number) => {
+            if let Some(integer) = number.as_i64() {
+                JsonValue::from(integer)
+            } else if let Some(integer) = number.as_u64() {
+                JsonValue::from(integer)
+            } else {
+                let float = number.as_f64().unwrap_or(f64::NAN);
+                serde_json::Number::from_f64(float)
+                    .map(JsonValue::Number)
+                    .unwrap_or_else(|| JsonValue::String(number.to_string()))
+            }
+        }
+        serde_yaml::Value::String(text) => JsonValue::String(text),
+        serde_yaml::Value::Sequence(sequence) => sequence.into_iter().map(yaml_to_json).collect(),
+        serde_yaml::Value::Mapping(mapping) => JsonValue::Object(
+            mapping
+                .into_iter()
+                .map(|(key, value)| (yaml_key(key), yaml_to_json(value)))
+                .collect(),
+        ),
+        serde_yaml::Value::Tagged(tagged) => yaml_to_json(tagged.value),
+    }
+}
+
+/// JSON keys are strings, so other YAML keys are written out.
+fn yaml_key(key: serde_yaml::Value) -> String {
+    match yaml_to_json(key) {
+        JsonValue::String(text) => text,
+        other => other.to_string(),
+    }
+}
+
+// ---
+
+/// Parses INI: `[section]` headers, `key = value` or `key: value` pairs, and
+/// `;` or `#` comments. Values are kept as strings.
+fn parse_ini(text: &str) -> Result<JsonValue, String> {
+    let mut root = JsonMap::new();
+    let mut section: Option<String> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header
+                .strip_suffix(']')
+                .ok_or_else(|| format!("line {}: the section header is not closed", index + 1))?
+                .trim()
+                .to_string();
+            if !root.get(&name).is_some_and(JsonValue::is_object) {
+                root.insert(name.clone(), JsonValue::Object(JsonMap::new()));
+            }
+            section = Some(name);
+            continue;
+        }
+
+        let split = line
+            .find(['=', ':'])
+            .ok_or_else(|| format!("line {}: expected 'key = value'", index + 1))?;
+        let key = line[..split].trim().to_string();
+        if key.is_empty() {
+            return Err(format!("line {}: the key is empty", index + 1));
+        }
+        let value = JsonValue::String(unquote(line[split + 1..].trim()).to_string());
+
+        let table = match &section {
+            Some(name) => root[name.as_str()].as_object_mut().unwrap(),
+            None => &mut root,
+        };
+        table.insert(key, value);
+    }
+
+    Ok(JsonValue::Object(root))
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+// ---
+
+/// Parses `.env` files: `KEY=value` lines, optionally prefixed with `export`,
+/// with single-quoted, double-quoted (with escapes) or bare values.
+fn parse_env(text: &str) -> Result<JsonValue, String> {
+    let mut root = JsonMap::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected 'KEY=value'", index + 1))?;
+        let key = key.trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_alphanumeric() || "_.-".contains(c))
+        {
+            return Err(format!("line {}: '{}' is not a valid name", index + 1, key));
+        }
+
+        let value = env_value(value.trim())
+            .ok_or_else(|| format!("line {}: the quoted value is not closed", index + 1))?;
+        root.insert(key.to_string(), JsonValue::String(value));
+    }
+
+    Ok(JsonValue::Object(root))
+}
+
+fn env_value(value: &str) -> Option<String> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        return rest.find('\'').map(|end| rest[..end].to_string());
+    }
+
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut output = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(output),
+                '\\' => match chars.next()? {
+                    'n' => output.push('\n'),
+                    't' => output.push('\t'),
+                    'r' => output.push('\r'),
+                    other => output.push(other),
+                },
+                c => output.push(c),
+            }
+        }
+        return None;
+    }
+
+    // Bare values end at a comment
+    let end = value.find(" #").unwrap_or(value.len());
+    Some(value[..end].trim_end().to_string())
+}
+
+// ===
+// AlpacaConfigFormat Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Tests detecting formats from file names.
+    #[test]
+    fn test_detect() {
+        let detect = |name: &str| AlpacaConfigFormat::detect(Path::new(name));
+        assert_eq!(detect("Cargo.toml"), Some(AlpacaConfigFormat::Toml));
+        assert_eq!(detect("ci/deploy.YML"), Some(AlpacaConfigFormat::Yaml));
+        assert_eq!(detect("setup.cfg"), Some(AlpacaConfigFormat::Ini));
+        assert_eq!(detect(".env"), Some(AlpacaConfigFormat::Env));
+        assert_eq!(detect(".env.local"), Some(AlpacaConfigFormat::Env));
+        assert_eq!(detect("README.md"), None);
+    }
+
+    /// Tests parsing each format.
+    #[test]
+    fn test_parse() {
+        let toml = "[package]\nname = \"app\"\nreleased = 2024-01-02\n\n[dependencies]\nserde = { version = \"1\", features = [\"derive\"] }\n";
+        assert_eq!(
+            AlpacaConfigFormat::Toml.parse(toml).unwrap(),
+            json!({
+                "package": {"name": "app", "released": "2024-01-02"},
+                "dependencies": {"serde": {"version": "1", "features": ["derive"]}}
+            })
+        );
+
+        let yaml = "name: app\nports:\n  - 80\n  - 443\n1: one\n";
+        assert_eq!(
+            AlpacaConfigFormat::Yaml.parse(yaml).unwrap(),
+            json!({"name": "app", "ports": [80, 443], "1": "one"})
+        );
+
+        let ini = "; comment\nroot = 1\n[server]\nhost = \"localhost\"\nport: 8080\n";
+        assert_eq!(
+            AlpacaConfigFormat::Ini.parse(ini).unwrap(),
+            json!({"root": "1", "server": {"host": "localhost", "port": "8080"}})
+        );
+
+        let env = "# comment\nexport TOKEN=abc # note\nGREETING=\"hello\\nworld\"\nRAW='a#b'\n";
+        assert_eq!(
+            AlpacaConfigFormat::Env.parse(env).unwrap(),
+            json!({"TOKEN": "abc", "GREETING": "hello\nworld", "RAW": "a#b"})
+        );
+    }
+
+    /// Tests that syntax errors report their line.
+    #[test]
+    fn test_parse_errors() {
+        let error = AlpacaConfigFormat::Toml
+            .parse("[package]\nname = \"app\"\nversion = \n")
+            .unwrap_err();
+        assert!(error.starts_with("line 3, column "), "{}", error);
+
+        let error = AlpacaConfigFormat::Yaml
+            .parse("name: app\nlist: [1, 2\n")
+            .unwrap_err();
+        assert!(error.starts_with("line "), "{}", error);
+
+        let error = AlpacaConfigFormat::Ini.parse("[a]\nkey\n").unwrap_err();
+        assert_eq!(error, "line 2: expected 'key = value'");
+
+        let error = AlpacaConfigFormat::Env
+            .parse("A=1\nB=\"open\n")
+            .unwrap_err();
+        assert_eq!(error, "line 2: the quoted value is not closed");
+    }
+}
@@ -0,0 +1,508 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// System directories that confined programs may read and execute from.
+const SYSTEM_READ_ONLY_PATHS: [&str; 10] = [
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/opt", "/proc", "/dev",
+];
+
+// ===
+// AlpacaConfinement
+// ===
+/// Kernel-level limits for the programs started by actions.
+///
+/// The sandbox's path checks only cover the files actions open themselves. A
+/// confinement is enforced by the kernel on a child process instead:
+///
+/// - Landlock restricts the filesystem to `read_write_paths` and `read_only_paths`
+/// - rlimits bound CPU time, memory and open files
+/// - a new network namespace leaves the process without network access
+///
+/// Landlock and rlimits degrade gracefully: what the kernel does not support is
+/// described in the `AlpacaConfinementReport` rather than treated as an error.
+/// Set `strict` to refuse to run anything that cannot be fully confined.
+/// Network isolation is always strict, since the process would otherwise have
+/// the network access the host asked to remove.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AlpacaConfinement {
+    /// Directories that may be read and written, usually the sandbox root
+    pub read_write_paths: Vec<PathBuf>,
+    /// Directories that may only be read and executed from
+    pub read_only_paths: Vec<PathBuf>,
+    /// The most CPU time, in seconds
+    pub max_cpu_seconds: Option<u64>,
+    /// The most virtual memory, in bytes
+    pub max_memory_bytes: Option<u64>,
+    /// The most open file descriptors
+    pub max_open_files: Option<u64>,
+    /// Whether the process runs in its own network namespace, without network access
+    pub isolate_network: bool,
+    /// Whether to refuse to run when Landlock cannot be enforced
+    pub strict: bool,
+}
+
+impl AlpacaConfinement {
+    /// Creates a confinement giving read and write access to `root` and read
+    /// access to the system directories programs need to run.
+    pub fn for_sandbox(root: &Path) -> Self {
+        AlpacaConfinement {
+            read_write_paths: vec![root.to_path_buf(), PathBuf::from("/dev/null")],
+            read_only_paths: SYSTEM_READ_ONLY_PATHS.iter().map(PathBuf::from).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Reports which of the requested restrictions this system can enforce.
+    pub fn probe(&self) -> AlpacaConfinementReport {
+        AlpacaConfinementReport {
+            landlock: landlock_status(),
+            rlimits: if self.has_rlimits() {
+                rlimit_status()
+            } else {
+                AlpacaLayerStatus::NotRequested
+            },
+            network: if self.isolate_network {
+                network_status()
+            } else {
+                AlpacaLayerStatus::NotRequested
+            },
+        }
+    }
+
+    /// Restricts the filesystem access of the calling thread, irrevocably.
+    ///
+    /// Landlock applies to the calling thread and the threads and processes it
+    /// starts afterwards, so a host can run file actions on a dedicated, confined
+    /// thread. rlimits and network isolation are process-wide and not applied here.
+    pub fn apply_to_current_thread(&self) -> Result<AlpacaConfinementReport, String> {
+        let landlock = platform::restrict_self(self);
+        if self.strict && !matches!(landlock, AlpacaLayerStatus::Enforced(_)) {
+            return Err(format!(
+                "The thread could not be fully confined: {}",
+                landlock
+            ));
+        }
+
+        Ok(AlpacaConfinementReport {
+            landlock,
+            rlimits: AlpacaLayerStatus::NotRequested,
+            network: AlpacaLayerStatus::NotRequested,
+        })
+    }
+
+    fn has_rlimits(&self) -> bool {
+        self.max_cpu_seconds.is_some()
+            || self.max_memory_bytes.is_some()
+            || self.max_open_files.is_some()
+    }
+
+    /// Arranges for `command` to be confined when it is spawned.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AlpacaConfinementReport)` - What will be enforced on the process
+    /// * `Err(String)` - Why the command may not run, when `strict` is set
+    pub(crate) fn apply_to_command(
+        &self,
+        command: &mut std::process::Command,
+    ) -> Result<AlpacaConfinementReport, String> {
+        let report = self.probe();
+        if self.strict && !matches!(report.landlock, AlpacaLayerStatus::Enforced(_)) {
+            return Err(format!(
+                "The command could not be fully confined: {}",
+                report.landlock
+            ));
+        }
+        if let AlpacaLayerStatus::Unavailable(reason) = &report.network {
+            return Err(format!("Network isolation is not available: {}", reason));
+        }
+
+        platform::pre_exec(self, command)?;
+        Ok(report)
+    }
+}
+
+// ===
+// AlpacaConfinementReport
+// ===
+/// Whether one layer of a confinement is enforced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlpacaLayerStatus {
+    /// Enforced, with details such as the Landlock ABI version
+    Enforced(String),
+    /// Only partly enforced by this kernel, and why
+    Partial(String),
+    /// Not enforced by this system, and why
+    Unavailable(String),
+    /// Not part of the confinement
+    NotRequested,
+}
+
+impl fmt::Display for AlpacaLayerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlpacaLayerStatus::Enforced(details) => write!(f, "enforced ({})", details),
+            AlpacaLayerStatus::Partial(details) => write!(f, "partially enforced ({})", details),
+            AlpacaLayerStatus::Unavailable(reason) => write!(f, "not enforced ({})", reason),
+            AlpacaLayerStatus::NotRequested => write!(f, "not requested"),
+        }
+    }
+}
+
+/// Describes which layers of a confinement are enforced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaConfinementReport {
+    /// Filesystem restrictions
+    pub landlock: AlpacaLayerStatus,
+    /// CPU, memory and open file limits
+    pub rlimits: AlpacaLayerStatus,
+    /// Network namespace isolation
+    pub network: AlpacaLayerStatus,
+}
+
+impl AlpacaConfinementReport {
+    /// Lists the layers as `name: status` lines, for the model or the host's logs.
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("landlock: {}", self.landlock),
+            format!("rlimits: {}", self.rlimits),
+            format!("network: {}", self.network),
+        ]
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn landlock_status() -> AlpacaLayerStatus {
+    // The version query of landlock_create_ruleset(2)
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1;
+    let version = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            std::ptr::null::<libc::c_void>(),
+            0usize,
+            LANDLOCK_CREATE_RULESET_VERSION,
+        )
+    };
+
+    if version >= 5 {
+        AlpacaLayerStatus::Enforced(format!("Landlock ABI {}", version))
+    } else if version >= 1 {
+        AlpacaLayerStatus::Partial(format!(
+            "Landlock ABI {}; some file operations are not restricted",
+            version
+        ))
+    } else {
+        match std::io::Error::last_os_error().raw_os_error() {
+            Some(libc::EOPNOTSUPP) => AlpacaLayerStatus::Unavailable(
+                "Landlock is built into the kernel but disabled at boot".to_string(),
+            ),
+            _ => AlpacaLayerStatus::Unavailable(
+                "the kernel does not support Landlock (Linux 5.13 or later is required)"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn landlock_status() -> AlpacaLayerStatus {
+    AlpacaLayerStatus::Unavailable("Landlock is only available on Linux".to_string())
+}
+
+fn rlimit_status() -> AlpacaLayerStatus {
+    if cfg!(target_os = "linux") {
+        AlpacaLayerStatus::Enforced("setrlimit".to_string())
+    } else {
+        AlpacaLayerStatus::Unavailable("rlimits are only applied on Linux".to_string())
+    }
+}
+
+fn network_status() -> AlpacaLayerStatus {
+    if !cfg!(target_os = "linux") {
+        return AlpacaLayerStatus::Unavailable(
+            "network namespaces are only available on Linux".to_string(),
+        );
+    }
+
+    // Unprivileged user namespaces can be disabled or denied in many ways, so
+    // a namespace is created once in a throwaway child to find out
+    static PROBE: OnceLock<Result<(), String>> = OnceLock::new();
+    match PROBE.get_or_init(platform::probe_network_namespace) {
+        Ok(()) => AlpacaLayerStatus::Enforced("network namespace".to_string()),
+        Err(reason) => AlpacaLayerStatus::Unavailable(reason.clone()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{AlpacaConfinement, AlpacaLayerStatus};
+    use landlock::{
+        ABI, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreated, RulesetCreatedAttr,
+        RulesetStatus, path_beneath_rules,
+    };
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    #[cfg(target_env = "gnu")]
+    type Resource = libc::__rlimit_resource_t;
+    #[cfg(not(target_env = "gnu"))]
+    type Resource = libc::c_int;
+
+    /// The newest Landlock ABI the rules are written for.
+    const ABI_VERSION: ABI = ABI::V5;
+
+    fn ruleset(confinement: &AlpacaConfinement) -> Result<RulesetCreated, String> {
+        Ruleset::default()
+            .handle_access(AccessFs::from_all(ABI_VERSION))
+            .and_then(|ruleset| ruleset.create())
+            .and_then(|ruleset| {
+                ruleset.add_rules(path_beneath_rules(
+                    &confinement.read_write_paths,
+                    AccessFs::from_all(ABI_VERSION),
+                ))
+            })
+            .and_then(|ruleset| {
+                ruleset.add_rules(path_beneath_rules(
+                    &confinement.read_only_paths,
+                    AccessFs::from_read(ABI_VERSION),
+                ))
+            })
+            .map_err(|e| format!("Failed to build the Landlock rules: {}", e))
+    }
+
+    pub(super) fn restrict_self(confinement: &AlpacaConfinement) -> AlpacaLayerStatus {
+        let status = match ruleset(confinement).and_then(|ruleset| {
+            ruleset
+                .restrict_self()
+                .map_err(|e| format!("Failed to enforce the Landlock rules: {}", e))
+        }) {
+            Ok(status) => status,
+            Err(error) => return AlpacaLayerStatus::Unavailable(error),
+        };
+
+        match (status.ruleset, super::landlock_status()) {
+            (RulesetStatus::FullyEnforced, kernel) => kernel,
+            (RulesetStatus::PartiallyEnforced, AlpacaLayerStatus::Enforced(details)) => {
+                AlpacaLayerStatus::Partial(details)
+            }
+            (RulesetStatus::PartiallyEnforced, kernel) => kernel,
+            (RulesetStatus::NotEnforced, AlpacaLayerStatus::Unavailable(reason)) => {
+                AlpacaLayerStatus::Unavailable(reason)
+            }
+            (RulesetStatus::NotEnforced, _) => AlpacaLayerStatus::Unavailable(
+                "the kernel did not enforce the Landlock rules".to_string(),
+            ),
+        }
+    }
+
+    pub(super) fn pre_exec(
+        confinement: &AlpacaConfinement,
+        command: &mut Command,
+    ) -> Result<(), String> {
+        // The rules open the allowed directories, so they are built before forking
+        let mut ruleset = Some(ruleset(confinement)?);
+        let limits = [
+            (libc::RLIMIT_CPU, confinement.max_cpu_seconds),
+            (libc::RLIMIT_AS, confinement.max_memory_bytes),
+            (libc::RLIMIT_NOFILE, confinement.max_open_files),
+        ];
+        let isolate_network = confinement.isolate_network;
+
+        // SAFETY: the closure runs in the child between fork and exec, and only
+        // makes system calls without allocating or taking locks
+        unsafe {
+            command.pre_exec(move || {
+                for (resource, limit) in limits {
+                    if let Some(limit) = limit {
+                        set_rlimit(resource, limit)?;
+                    }
+                }
+                if isolate_network && libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if let Some(ruleset) = ruleset.take() {
+                    ruleset
+                        .restrict_self()
+                        .map_err(|_| std::io::Error::from_raw_os_error(libc::EPERM))?;
+                }
+                Ok(())
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Creates a user and network namespace in a forked child, which exits at once.
+    pub(super) fn probe_network_namespace() -> Result<(), String> {
+        // SAFETY: the child only makes system calls before it exits
+        let pid = unsafe { libc::fork() };
+        if pid < 0 {
+            return Err(format!(
+                "the namespace probe could not start: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        if pid == 0 {
+            unsafe {
+                let code = if libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) == 0 {
+                    0
+                } else {
+                    *libc::__errno_location()
+                };
+                libc::_exit(code);
+            }
+        }
+
+        let mut status = 0;
+        // SAFETY: `pid` is the child forked above
+        if unsafe { libc::waitpid(pid, &mut status, 0) } != pid || !libc::WIFEXITED(status) {
+            return Err("the namespace probe did not exit normally".to_string());
+        }
+        match libc::WEXITSTATUS(status) {
+            0 => Ok(()),
+            errno => Err(format!(
+                "the kernel refused to create a network namespace: {}",
+                std::io::Error::from_raw_os_error(errno)
+            )),
+        }
+    }
+
+    /// Lowers a resource limit, never above the current hard limit.
+    unsafe fn set_rlimit(resource: Resource, limit: u64) -> std::io::Result<()> {
+        let mut current = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { libc::getrlimit(resource, &mut current) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let limit = (limit as libc::rlim_t).min(current.rlim_max);
+        let new = libc::rlimit {
+            rlim_cur: limit,
+            rlim_max: limit,
+        };
+        if unsafe { libc::setrlimit(resource, &new) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use super::{AlpacaConfinement, AlpacaLayerStatus};
+    use std::process::Command;
+
+    pub(super) fn restrict_self(_confinement: &AlpacaConfinement) -> AlpacaLayerStatus {
+        super::landlock_status()
+    }
+
+    pub(super) fn probe_network_namespace() -> Result<(), String> {
+        Err("network namespaces are only available on Linux".to_string())
+    }
+
+    pub(super) fn pre_exec(
+        _confinement: &AlpacaConfinement,
+        _command: &mut Command,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// ===
+// AlpacaConfinement Tests
+// ===
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::command::{AlpacaCommandConfig, run};
+    use std::collections::HashMap;
+
+    fn run_sh(script: &str, cwd: &Path, confinement: AlpacaConfinement) -> (String, String) {
+        let config = AlpacaCommandConfig {
+            confinement: Some(confinement),
+            ..Default::default()
+        };
+        let args = vec!["-c".to_string(), script.to_string()];
+        let output = run("sh", &args, cwd, &HashMap::new(), &config).unwrap();
+        (output.stdout.text, output.stderr.text)
+    }
+
+    /// Tests that the report describes every layer.
+    #[test]
+    fn test_probe_report() {
+        let confinement = AlpacaConfinement {
+            max_open_files: Some(64),
+            ..Default::default()
+        };
+        let lines = confinement.probe().lines();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("landlock: "));
+        assert_eq!(lines[1], "rlimits: enforced (setrlimit)");
+        assert_eq!(lines[2], "network: not requested");
+    }
+
+    /// Tests that rlimits are applied to the child process.
+    #[test]
+    fn test_rlimits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut confinement = AlpacaConfinement::for_sandbox(temp_dir.path());
+        confinement.max_open_files = Some(64);
+
+        let (stdout, _) = run_sh("ulimit -n", temp_dir.path(), confinement);
+        assert_eq!(stdout, "64\n");
+    }
+
+    /// Tests that Landlock keeps the process inside the sandbox, when the kernel supports it.
+    #[test]
+    fn test_landlock() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+        std::fs::write(temp_dir.path().join("inside.txt"), "inside").unwrap();
+
+        let confinement = AlpacaConfinement::for_sandbox(temp_dir.path());
+        if matches!(
+            confinement.probe().landlock,
+            AlpacaLayerStatus::Unavailable(_)
+        ) {
+            return;
+        }
+
+        let script = format!(
+            "cat inside.txt; cat {}/secret.txt",
+            outside.path().display()
+        );
+        let (stdout, stderr) = run_sh(&script, temp_dir.path(), confinement);
+        assert_eq!(stdout, "inside");
+        assert!(stderr.contains("Permission denied"), "{}", stderr);
+    }
+
+    /// Tests that network isolation is reported as it behaves on this kernel.
+    #[test]
+    fn test_network_probe() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut confinement = AlpacaConfinement::for_sandbox(temp_dir.path());
+        confinement.isolate_network = true;
+        let config = AlpacaCommandConfig {
+            confinement: Some(confinement.clone()),
+            ..Default::default()
+        };
+        let args = vec!["-c".to_string(), "echo isolated".to_string()];
+        let result = run("sh", &args, temp_dir.path(), &HashMap::new(), &config);
+
+        match confinement.probe().network {
+            AlpacaLayerStatus::Enforced(_) => {
+                assert_eq!(result.unwrap().stdout.text, "isolated\n");
+            }
+            AlpacaLayerStatus::Unavailable(reason) => {
+                assert!(result.unwrap_err().contains(&reason));
+            }
+            status => panic!("unexpected status {}", status),
+        }
+    }
+}
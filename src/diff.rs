@@ -0,0 +1,616 @@
+// ===
+// DiffOp
+// ===
+/// A single line-level edit produced by `diff_lines`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp<'a> {
+    /// The line is present in both texts
+    Equal(&'a str),
+    /// The line is only present in the old text
+    Delete(&'a str),
+    /// The line is only present in the new text
+    Insert(&'a str),
+}
+
+/// Splits text into lines, keeping the line terminators.
+///
+/// Keeping the terminators lets a diff tell a missing final newline apart
+/// from an identical line.
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// The most lines `diff_lines` looks for to insert and delete; texts further
+/// apart are diffed as a single replacement.
+const MAX_EDIT_DISTANCE: usize = 8192;
+
+/// Computes the shortest edit script between two lists of lines (Myers'
+/// algorithm, in its linear space form).
+///
+/// When more than `MAX_EDIT_DISTANCE` lines would have to be inserted and
+/// deleted, the lines between the common start and end are replaced whole.
+///
+/// # Arguments
+///
+/// * `old` - The lines of the original text
+/// * `new` - The lines of the changed text
+///
+/// # Returns
+///
+/// The list of operations that turns `old` into `new`, in order.
+pub fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffOp<'a>> {
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    diff_range(old, new, &mut ops);
+    ops
+}
+
+/// Appends the edit script between two ranges of lines to `ops`.
+fn diff_range<'a>(old: &[&'a str], new: &[&'a str], ops: &mut Vec<DiffOp<'a>>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let changed_old = &old[prefix..old.len() - suffix];
+    let changed_new = &new[prefix..new.len() - suffix];
+
+    ops.extend(old[..prefix].iter().map(|line| DiffOp::Equal(line)));
+    match middle_snake(changed_old, changed_new) {
+        // The snake splits the changes in two halves, each diffed on its own
+        Some((x, y, u, v)) => {
+            diff_range(&changed_old[..x], &changed_new[..y], ops);
+            ops.extend(changed_old[x..u].iter().map(|line| DiffOp::Equal(line)));
+            diff_range(&changed_old[u..], &changed_new[v..], ops);
+        }
+        None => {
+            ops.extend(changed_old.iter().map(|line| DiffOp::Delete(line)));
+            ops.extend(changed_new.iter().map(|line| DiffOp::Insert(line)));
+        }
+    }
+    ops.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| DiffOp::Equal(line)),
+    );
+}
+
+/// Finds the middle snake of the shortest edit script between two ranges of
+/// lines that differ in their first and in their last line: a run of equal
+/// lines, from `old[x]` and `new[y]` to `old[u]` and `new[v]`, that splits
+/// the script into two halves.
+///
+/// Returns `None` when either range is empty, since there is nothing to
+/// split, or when the script is longer than `MAX_EDIT_DISTANCE`.
+fn middle_snake(old: &[&str], new: &[&str]) -> Option<(usize, usize, usize, usize)> {
+    if old.is_empty() || new.is_empty() {
+        return None;
+    }
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let delta = n - m;
+    // Each search covers half of the script
+    let max = ((n + m + 1) / 2).min(MAX_EDIT_DISTANCE.div_ceil(2) as isize);
+    let offset = max + 1;
+
+    // `forward[k + offset]` holds the furthest x reached on diagonal k from
+    // the start, and `backward[k + offset]` how far from the end x got on
+    // diagonal `delta - k` when searching back from the end
+    let mut forward = vec![0isize; (2 * max + 3) as usize];
+    let mut backward = vec![0isize; (2 * max + 3) as usize];
+
+    for d in 0..=max {
+        let mut k = -d;
+        while k <= d {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && forward[index - 1] < forward[index + 1]) {
+                forward[index + 1]
+            } else {
+                forward[index - 1] + 1
+            };
+            let mut y = x - k;
+            let (start_x, start_y) = (x, y);
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[index] = x;
+
+            // With an odd difference in length, the searches meet after the
+            // forward one has made its move
+            let reverse = delta - k;
+            if delta % 2 != 0
+                && (-(d - 1)..=d - 1).contains(&reverse)
+                && x + backward[(reverse + offset) as usize] >= n
+            {
+                return Some((start_x as usize, start_y as usize, x as usize, y as usize));
+            }
+            k += 2;
+        }
+
+        let mut k = -d;
+        while k <= d {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && backward[index - 1] < backward[index + 1]) {
+                backward[index + 1]
+            } else {
+                backward[index - 1] + 1
+            };
+            let mut y = x - k;
+            let (start_x, start_y) = (x, y);
+            while x < n && y < m && old[(n - x - 1) as usize] == new[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[index] = x;
+
+            let reverse = delta - k;
+            if delta % 2 == 0
+                && (-d..=d).contains(&reverse)
+                && x + forward[(reverse + offset) as usize] >= n
+            {
+                return Some((
+                    (n - x) as usize,
+                    (m - y) as usize,
+                    (n - start_x) as usize,
+                    (m - start_y) as usize,
+                ));
+            }
+            k += 2;
+        }
+    }
+
+    None
+}
+
+/// Produces a unified diff between two texts.
+///
+/// # Arguments
+///
+/// * `old` - The original text
+/// * `new` - The changed text
+/// * `old_name` - The name shown on the `---` line, for example `a/src/lib.rs` or `/dev/null`
+/// * `new_name` - The name shown on the `+++` line
+/// * `context` - The number of unchanged lines shown around each change
+///
+/// # Returns
+///
+/// The unified diff, or an empty string if the texts are identical.
+pub fn unified_diff(
+    old: &str,
+    new: &str,
+    old_name: &str,
+    new_name: &str,
+    context: usize,
+) -> String {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Group the changes into hunks, merging those whose context would overlap
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &index in &changes {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    // The line numbers at which each op starts, in the old and new text
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut old_line, mut new_line) = (0, 0);
+    for op in &ops {
+        positions.push((old_line, new_line));
+        match op {
+            DiffOp::Equal(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            DiffOp::Delete(_) => old_line += 1,
+            DiffOp::Insert(_) => new_line += 1,
+        }
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in hunks {
+        let hunk = &ops[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Insert(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Delete(_)))
+            .count();
+        let (old_start, new_start) = positions[start];
+
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+
+        for op in hunk {
+            let (prefix, line) = match op {
+                DiffOp::Equal(line) => (' ', line),
+                DiffOp::Delete(line) => ('-', line),
+                DiffOp::Insert(line) => ('+', line),
+            };
+            output.push(prefix);
+            output.push_str(line);
+            if !line.ends_with('\n') {
+                output.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    output
+}
+
+/// Formats the `start,count` part of a hunk header.
+///
+/// An empty range refers to the line before it, as in `-0,0` for an insertion
+/// at the start of a file.
+fn hunk_range(start: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", start)
+    } else if count == 1 {
+        format!("{}", start + 1)
+    } else {
+        format!("{},{}", start + 1, count)
+    }
+}
+
+// ===
+// DiffHunk
+// ===
+/// A hunk parsed from a unified diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    /// The zero-based line at which the hunk claims to start in the old text
+    pub old_start: usize,
+    /// The context and deleted lines, with their terminators
+    pub old_lines: Vec<String>,
+    /// The context and inserted lines, with their terminators
+    pub new_lines: Vec<String>,
+}
+
+/// Parses the hunks of a unified diff for a single file.
+///
+/// File headers (`---`/`+++`) and other lines outside of hunks are ignored.
+/// The line counts in the hunk headers are not trusted, since models often get
+/// them wrong; a hunk ends at the next header or at the end of the diff.
+///
+/// # Returns
+///
+/// * `Ok(Vec<DiffHunk>)` - The hunks, in order
+/// * `Err(String)` - A description of the malformed line
+pub fn parse_unified_diff(diff: &str) -> Result<Vec<DiffHunk>, String> {
+    let mut hunks: Vec<DiffHunk> = Vec::new();
+    let lines = split_lines(diff);
+
+    for (index, line) in lines.iter().enumerate() {
+        if line.starts_with("@@") {
+            let old_start = line
+                .trim_start_matches('@')
+                .split_whitespace()
+                .next()
+                .and_then(|range| range.strip_prefix('-'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse::<usize>().ok())
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid hunk header on line {}: {}",
+                        index + 1,
+                        line.trim_end()
+                    )
+                })?;
+
+            hunks.push(DiffHunk {
+                old_start: old_start.saturating_sub(1),
+                old_lines: Vec::new(),
+                new_lines: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some(hunk) = hunks.last_mut() else {
+            continue;
+        };
+
+        // A new file header ends the hunk; only a single file is supported
+        if line.starts_with("--- ")
+            && lines
+                .get(index + 1)
+                .is_some_and(|next| next.starts_with("+++ "))
+        {
+            return Err("The diff changes more than one file. Send one diff per file.".to_string());
+        }
+
+        if line.starts_with('\\') {
+            // "\ No newline at end of file" applies to the previous line
+            let previous = match lines[index - 1].chars().next() {
+                Some('-') => hunk.old_lines.last_mut(),
+                Some('+') => hunk.new_lines.last_mut(),
+                _ => {
+                    strip_newline(hunk.old_lines.last_mut());
+                    hunk.new_lines.last_mut()
+                }
+            };
+            strip_newline(previous);
+            continue;
+        }
+
+        match line.chars().next() {
+            Some(' ') => {
+                hunk.old_lines.push(line[1..].to_string());
+                hunk.new_lines.push(line[1..].to_string());
+            }
+            Some('-') => hunk.old_lines.push(line[1..].to_string()),
+            Some('+') => hunk.new_lines.push(line[1..].to_string()),
+            // Blank context lines often lose their leading space
+            Some('\n') | Some('\r') => {
+                hunk.old_lines.push(line.to_string());
+                hunk.new_lines.push(line.to_string());
+            }
+            _ => {
+                return Err(format!(
+                    "Unexpected line {} in hunk {}: {}",
+                    index + 1,
+                    hunks.len(),
+                    line.trim_end()
+                ));
+            }
+        }
+    }
+
+    if hunks.is_empty() {
+        return Err("The diff does not contain any hunks starting with '@@'.".to_string());
+    }
+
+    Ok(hunks)
+}
+
+/// Applies parsed hunks to a text.
+///
+/// Each hunk is located by its context and deleted lines. When they occur more
+/// than once, the occurrence at the line given in the hunk header is used; if
+/// none is there, the hunk is rejected as ambiguous.
+///
+/// # Returns
+///
+/// * `Ok(String)` - The patched text
+/// * `Err(String)` - A description of the hunk that could not be applied
+pub fn apply_hunks(text: &str, hunks: &[DiffHunk]) -> Result<String, String> {
+    let lines = split_lines(text);
+    let mut output: Vec<&str> = Vec::new();
+    let mut position = 0;
+
+    for (number, hunk) in hunks.iter().enumerate() {
+        let number = number + 1;
+        let old: Vec<&str> = hunk.old_lines.iter().map(String::as_str).collect();
+
+        let start = if old.is_empty() {
+            // A pure insertion can only be placed by its line number
+            hunk.old_start.max(position).min(lines.len())
+        } else {
+            let matches: Vec<usize> = (position..=lines.len().saturating_sub(old.len()))
+                .filter(|&start| lines[start..].starts_with(&old))
+                .collect();
+
+            match matches.as_slice() {
+                [] => {
+                    return Err(format!(
+                        "Hunk {} does not match the file: its context and removed lines were not found{}. Read the file again and make sure they are copied exactly.",
+                        number,
+                        if position > 0 {
+                            " after the previous hunk"
+                        } else {
+                            ""
+                        }
+                    ));
+                }
+                [start] => *start,
+                _ if matches.contains(&hunk.old_start) => hunk.old_start,
+                _ => {
+                    let found: Vec<String> = matches
+                        .iter()
+                        .map(|start| (start + 1).to_string())
+                        .collect();
+                    return Err(format!(
+                        "Hunk {} matches the file in {} places (lines {}). Include more context lines so that it matches only once.",
+                        number,
+                        matches.len(),
+                        found.join(", ")
+                    ));
+                }
+            }
+        };
+
+        output.extend_from_slice(&lines[position..start]);
+        output.extend(hunk.new_lines.iter().map(String::as_str));
+        position = start + old.len();
+    }
+
+    output.extend_from_slice(&lines[position..]);
+    Ok(output.concat())
+}
+
+fn strip_newline(line: Option<&mut String>) {
+    if let Some(line) = line.filter(|line| line.ends_with('\n')) {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+}
+
+// ===
+// Diff Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the edit script reproduces both texts.
+    #[test]
+    fn test_diff_lines() {
+        let old = split_lines("a\nb\nc\nd\n");
+        let new = split_lines("a\nc\nd\ne\n");
+        let ops = diff_lines(&old, &new);
+
+        assert_eq!(
+            ops,
+            vec![
+                DiffOp::Equal("a\n"),
+                DiffOp::Delete("b\n"),
+                DiffOp::Equal("c\n"),
+                DiffOp::Equal("d\n"),
+                DiffOp::Insert("e\n"),
+            ]
+        );
+    }
+
+    /// Tests that the edit script is the shortest one, and that texts too far
+    /// apart are replaced whole.
+    #[test]
+    fn test_diff_lines_large() {
+        let old_text: String = (0..3000).map(|i| format!("{}\n", i)).collect();
+        let new_text: String = (0..3000)
+            .map(|i| match i % 3 {
+                0 => format!("{}\n", i),
+                1 => format!("changed {}\n", i),
+                _ => String::new(),
+            })
+            .collect();
+        let (old, new) = (split_lines(&old_text), split_lines(&new_text));
+        let ops = diff_lines(&old, &new);
+        let edits = ops
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Equal(_)))
+            .count();
+        assert_eq!(edits, 3000);
+        let rebuilt: Vec<&str> = ops
+            .iter()
+            .filter_map(|op| match op {
+                DiffOp::Equal(line) | DiffOp::Insert(line) => Some(*line),
+                DiffOp::Delete(_) => None,
+            })
+            .collect();
+        assert_eq!(rebuilt, new);
+
+        let old_text: String = (0..5000).map(|i| format!("{}\n", i)).collect();
+        let new_text: String = (0..5000).map(|i| format!("new {}\n", i)).collect();
+        let old_text = format!("start\n{}end\n", old_text);
+        let new_text = format!("start\n{}end\n", new_text);
+        let (old, new) = (split_lines(&old_text), split_lines(&new_text));
+        let ops = diff_lines(&old, &new);
+        assert_eq!(ops.len(), 10_002);
+        assert_eq!(ops[0], DiffOp::Equal("start\n"));
+        assert_eq!(ops[1], DiffOp::Delete("0\n"));
+        assert_eq!(ops[5001], DiffOp::Insert("new 0\n"));
+        assert_eq!(ops[10_001], DiffOp::Equal("end\n"));
+    }
+
+    /// Tests the unified diff of a small change.
+    #[test]
+    fn test_unified_diff() {
+        let diff = unified_diff("one\ntwo\nthree\n", "one\n2\nthree\n", "a/x", "b/x", 3);
+        assert_eq!(
+            diff,
+            "--- a/x\n+++ b/x\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n three\n"
+        );
+    }
+
+    /// Tests that distant changes produce separate hunks.
+    #[test]
+    fn test_unified_diff_hunks() {
+        let old: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        let new: String = (1..=20)
+            .map(|i| match i {
+                2 => "two\n".to_string(),
+                19 => "nineteen\n".to_string(),
+                i => format!("{}\n", i),
+            })
+            .collect();
+        let diff = unified_diff(&old, &new, "a/x", "b/x", 1);
+
+        assert!(diff.contains("@@ -1,3 +1,3 @@\n 1\n-2\n+two\n 3\n"));
+        assert!(diff.contains("@@ -18,3 +18,3 @@\n 18\n-19\n+nineteen\n 20\n"));
+    }
+
+    /// Tests the diff of a new file without a trailing newline.
+    #[test]
+    fn test_unified_diff_new_file() {
+        let diff = unified_diff("", "hello", "/dev/null", "b/x", 3);
+        assert_eq!(
+            diff,
+            "--- /dev/null\n+++ b/x\n@@ -0,0 +1 @@\n+hello\n\\ No newline at end of file\n"
+        );
+    }
+
+    /// Tests that identical texts produce an empty diff.
+    #[test]
+    fn test_unified_diff_identical() {
+        assert_eq!(unified_diff("a\n", "a\n", "a/x", "b/x", 3), "");
+    }
+
+    /// Tests that a generated diff can be parsed and applied back.
+    #[test]
+    fn test_apply_round_trip() {
+        let old: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        let new = old
+            .replace("\n5\n", "\nfive\n")
+            .replace("\n20\n", "\ntwenty");
+        let diff = unified_diff(&old, &new, "a/x", "b/x", 3);
+
+        let hunks = parse_unified_diff(&diff).unwrap();
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(apply_hunks(&old, &hunks).unwrap(), new);
+    }
+
+    /// Tests that hunk headers with wrong line numbers still apply when the context is unique.
+    #[test]
+    fn test_apply_offset() {
+        let diff = "@@ -10,2 +10,2 @@\n b\n-c\n+C\n";
+        let hunks = parse_unified_diff(diff).unwrap();
+        assert_eq!(apply_hunks("a\nb\nc\nd\n", &hunks).unwrap(), "a\nb\nC\nd\n");
+    }
+
+    /// Tests that ambiguous and missing hunks are rejected.
+    #[test]
+    fn test_apply_errors() {
+        let text = "x\ny\nx\ny\n";
+
+        let hunks = parse_unified_diff("@@ -9 +9 @@\n-x\n+z\n").unwrap();
+        let error = apply_hunks(text, &hunks).unwrap_err();
+        assert!(error.contains("matches the file in 2 places (lines 1, 3)"));
+
+        let hunks = parse_unified_diff("@@ -3 +3 @@\n-x\n+z\n").unwrap();
+        assert_eq!(apply_hunks(text, &hunks).unwrap(), "x\ny\nz\ny\n");
+
+        let hunks = parse_unified_diff("@@ -1 +1 @@\n-w\n+z\n").unwrap();
+        assert!(
+            apply_hunks(text, &hunks)
+                .unwrap_err()
+                .contains("Hunk 1 does not match")
+        );
+
+        assert!(parse_unified_diff("-x\n+z\n").is_err());
+    }
+}
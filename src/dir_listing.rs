@@ -0,0 +1,456 @@
+use crate::file_walk::format_time;
+use globset::{Glob, GlobMatcher};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// The most entries returned in a single page, whatever `limit` says.
+const MAX_PAGE_SIZE: usize = 1000;
+
+// ===
+// AlpacaListOptions
+// ===
+/// The options shared by `read_directory`, `list_directory` and `dir`.
+///
+/// With the default options the listing is the plain `files`/`directories`
+/// name lists these actions have always returned.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct AlpacaListOptions {
+    /// Whether each entry is returned with its size, modification time, permissions and link target.
+    #[serde(default)]
+    pub metadata: bool,
+    /// A glob pattern the entry names must match, such as `*.toml`.
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// File extensions to keep, such as `["rs", "md"]`.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Whether entries whose name starts with a dot are included; `true` by default.
+    #[serde(default)]
+    pub hidden: Option<bool>,
+    /// The order of the entries.
+    #[serde(default)]
+    pub sort: AlpacaListSort,
+    /// Whether the order is reversed.
+    #[serde(default)]
+    pub reverse: bool,
+    /// The number of entries to skip, for paging through large directories.
+    #[serde(default)]
+    pub offset: usize,
+    /// The maximum number of entries returned.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// The arguments of the directory listing actions and functions.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct AlpacaListArguments {
+    /// The directory to list, relative to the current directory.
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(flatten)]
+    pub options: AlpacaListOptions,
+}
+
+/// Describes the optional listing arguments, for the usage text of the listing actions.
+pub const LIST_OPTIONS_HELP: &str = r#"All of the arguments are optional:
+- `path`: the directory to list, relative to the current directory
+- `metadata`: `true` to get the size, modification time, permissions and link target of each entry
+- `pattern`: a glob pattern the names must match, such as `*.toml`
+- `extensions`: the file extensions to keep, such as `["rs", "md"]`
+- `hidden`: `false` to leave out names starting with a dot
+- `sort`: `name` (the default), `size`, `modified` or `type`, with `reverse` to invert it
+- `offset` / `limit`: to page through large directories, following `next_offset`
+"#;
+
+/// The order of a directory listing.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AlpacaListSort {
+    #[default]
+    Name,
+    Size,
+    Modified,
+    Type,
+}
+
+// ===
+// AlpacaDirEntry
+// ===
+/// The kind of a directory entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlpacaEntryKind {
+    Directory,
+    File,
+    Symlink,
+    Other,
+}
+
+impl AlpacaEntryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlpacaEntryKind::Directory => "directory",
+            AlpacaEntryKind::File => "file",
+            AlpacaEntryKind::Symlink => "symlink",
+            AlpacaEntryKind::Other => "other",
+        }
+    }
+}
+
+/// An entry of a directory listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaDirEntry {
+    /// The entry name; names that are not valid UTF-8 are converted lossily
+    pub name: String,
+    pub kind: AlpacaEntryKind,
+    /// The size of a file in bytes, or 0 for other kinds of entries
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// The permissions, as `rwxr-xr-x` on Unix, or `r--`/`rw-` elsewhere
+    pub permissions: String,
+    /// The target of a symbolic link
+    pub symlink_target: Option<String>,
+    /// Whether the name starts with a dot
+    pub hidden: bool,
+}
+
+impl AlpacaDirEntry {
+    fn to_json(&self) -> Value {
+        let mut entry = json!({
+            "name": self.name,
+            "type": self.kind.as_str(),
+            "permissions": self.permissions,
+            "hidden": self.hidden,
+        });
+        if self.kind == AlpacaEntryKind::File {
+            entry["size"] = json!(self.size);
+        }
+        if let Some(modified) = self.modified {
+            entry["modified"] = json!(format_time(modified));
+        }
+        if let Some(target) = &self.symlink_target {
+            entry["symlink_target"] = json!(target);
+        }
+        entry
+    }
+}
+
+// ===
+// AlpacaDirListing
+// ===
+/// A page of a directory listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaDirListing {
+    /// The entries of this page
+    pub entries: Vec<AlpacaDirEntry>,
+    /// The number of entries that matched the filters, across all pages
+    pub total: usize,
+    /// The number of entries skipped before this page
+    pub offset: usize,
+    metadata: bool,
+}
+
+impl AlpacaDirListing {
+    /// Lists a directory, following the filters, order and paging of `options`.
+    ///
+    /// Symbolic links are listed as such and never followed.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AlpacaDirListing)` - The requested page
+    /// * `Err(String)` - A description of the problem if the directory cannot be read
+    pub fn read(path: &Path, options: &AlpacaListOptions) -> Result<Self, String> {
+        let matcher: Option<GlobMatcher> = match &options.pattern {
+            Some(pattern) => Some(
+                Glob::new(pattern)
+                    .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?
+                    .compile_matcher(),
+            ),
+            None => None,
+        };
+        let extensions: Vec<String> = options
+            .extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_lowercase())
+            .collect();
+
+        let read_dir = fs::read_dir(path).map_err(|e| {
+            format!(
+                "Failed to read directory '{}': {}.",
+                path.to_string_lossy(),
+                e
+            )
+        })?;
+
+        let mut entries: Vec<AlpacaDirEntry> = read_dir
+            .flatten()
+            .filter_map(|entry| read_entry(&entry.path()))
+            .filter(|entry| options.hidden.unwrap_or(true) || !entry.hidden)
+            .filter(|entry| matcher.as_ref().is_none_or(|m| m.is_match(&entry.name)))
+            .filter(|entry| {
+                extensions.is_empty()
+                    || Path::new(&entry.name).extension().is_some_and(|extension| {
+                        extensions.contains(&extension.to_string_lossy().to_lowercase())
+                    })
+            })
+            .collect();
+
+        entries.sort_by(|a, b| {
+            let order = match options.sort {
+                AlpacaListSort::Name => std::cmp::Ordering::Equal,
+                AlpacaListSort::Size => a.size.cmp(&b.size),
+                AlpacaListSort::Modified => a.modified.cmp(&b.modified),
+                AlpacaListSort::Type => a.kind.cmp(&b.kind),
+            };
+            order.then_with(|| a.name.cmp(&b.name))
+        });
+        if options.reverse {
+            entries.reverse();
+        }
+
+        let total = entries.len();
+        let limit = options.limit.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+        let entries = entries
+            .into_iter()
+            .skip(options.offset)
+            .take(limit)
+            .collect();
+
+        Ok(AlpacaDirListing {
+            entries,
+            total,
+            offset: options.offset,
+            metadata: options.metadata,
+        })
+    }
+
+    /// Gets the names of the entries of a kind.
+    pub fn names(&self, kind: AlpacaEntryKind) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    /// Adds the listing to a JSON response object.
+    ///
+    /// Without metadata, the entries are added as name lists under the given
+    /// keys for files and directories, plus `symlinks` as `name -> target` when
+    /// there are any. With metadata, they are added as `entries` objects. Paging
+    /// details are only added when the listing does not fit in one page.
+    pub fn add_to(&self, response: &mut Value, files_key: &str, directories_key: &str) {
+        if self.metadata {
+            response["entries"] =
+                json!(self.entries.iter().map(|e| e.to_json()).collect::<Vec<_>>());
+        } else {
+            response[files_key] = json!(self.names(AlpacaEntryKind::File));
+            response[directories_key] = json!(self.names(AlpacaEntryKind::Directory));
+
+            let symlinks: Vec<String> = self
+                .entries
+                .iter()
+                .filter(|entry| entry.kind == AlpacaEntryKind::Symlink)
+                .map(|entry| {
+                    format!(
+                        "{} -> {}",
+                        entry.name,
+                        entry.symlink_target.as_deref().unwrap_or("?")
+                    )
+                })
+                .collect();
+            if !symlinks.is_empty() {
+                response["symlinks"] = json!(symlinks);
+            }
+        }
+
+        let end = self.offset + self.entries.len();
+        if self.offset > 0 || end < self.total {
+            response["total_entries"] = json!(self.total);
+            response["offset"] = json!(self.offset);
+            if end < self.total {
+                response["next_offset"] = json!(end);
+            }
+        }
+    }
+}
+
+fn read_entry(path: &Path) -> Option<AlpacaDirEntry> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let file_type = metadata.file_type();
+
+    let kind = if file_type.is_symlink() {
+        AlpacaEntryKind::Symlink
+    } else if file_type.is_dir() {
+        AlpacaEntryKind::Directory
+    } else if file_type.is_file() {
+        AlpacaEntryKind::File
+    } else {
+        AlpacaEntryKind::Other
+    };
+
+    let symlink_target = match kind {
+        AlpacaEntryKind::Symlink => fs::read_link(path)
+            .ok()
+            .map(|target| target.to_string_lossy().to_string()),
+        _ => None,
+    };
+
+    Some(AlpacaDirEntry {
+        hidden: name.starts_with('.'),
+        name,
+        kind,
+        size: match kind {
+            AlpacaEntryKind::File => metadata.len(),
+            _ => 0,
+        },
+        modified: metadata.modified().ok(),
+        permissions: permissions(&metadata),
+        symlink_target,
+    })
+}
+
+#[cfg(unix)]
+fn permissions(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode();
+    let flags = ['r', 'w', 'x'];
+    (0..9)
+        .map(|bit| {
+            if mode & (1 << (8 - bit)) != 0 {
+                flags[bit % 3]
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn permissions(metadata: &fs::Metadata) -> String {
+    if metadata.permissions().readonly() {
+        "r--".to_string()
+    } else {
+        "rw-".to_string()
+    }
+}
+
+// ===
+// AlpacaDirListing Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(root.join("README.md"), "# Readme\n").unwrap();
+        fs::write(root.join("big.rs"), "x".repeat(1000)).unwrap();
+        temp_dir
+    }
+
+    fn listed(path: &Path, options: AlpacaListOptions) -> Value {
+        let mut response = json!({});
+        AlpacaDirListing::read(path, &options).unwrap().add_to(
+            &mut response,
+            "files",
+            "directories",
+        );
+        response
+    }
+
+    /// Tests that the default listing keeps the plain name lists.
+    #[test]
+    fn test_default_listing() {
+        let temp_dir = directory();
+        assert_eq!(
+            listed(temp_dir.path(), AlpacaListOptions::default()),
+            json!({
+                "files": ["Cargo.toml", "README.md", "big.rs"],
+                "directories": [".git", "src"],
+            })
+        );
+    }
+
+    /// Tests filtering, sorting and paging.
+    #[test]
+    fn test_filter_sort_page() {
+        let temp_dir = directory();
+
+        let options = AlpacaListOptions {
+            extensions: vec![".RS".to_string(), "md".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            listed(temp_dir.path(), options)["files"],
+            json!(["README.md", "big.rs"])
+        );
+
+        let options = AlpacaListOptions {
+            pattern: Some("*.toml".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            listed(temp_dir.path(), options)["files"],
+            json!(["Cargo.toml"])
+        );
+
+        let options = AlpacaListOptions {
+            hidden: Some(false),
+            sort: AlpacaListSort::Size,
+            reverse: true,
+            offset: 1,
+            limit: Some(2),
+            metadata: true,
+            ..Default::default()
+        };
+        let response = listed(temp_dir.path(), options);
+        let names: Vec<&str> = response["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Cargo.toml", "README.md"]);
+        assert_eq!(response["total_entries"], 4);
+        assert_eq!(response["next_offset"], 3);
+    }
+
+    /// Tests that symbolic links are listed with their targets.
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks() {
+        let temp_dir = directory();
+        std::os::unix::fs::symlink("Cargo.toml", temp_dir.path().join("link.toml")).unwrap();
+
+        let response = listed(temp_dir.path(), AlpacaListOptions::default());
+        assert_eq!(response["symlinks"], json!(["link.toml -> Cargo.toml"]));
+
+        let options = AlpacaListOptions {
+            metadata: true,
+            pattern: Some("*.toml".to_string()),
+            ..Default::default()
+        };
+        let response = listed(temp_dir.path(), options);
+        assert_eq!(response["entries"][0]["type"], "file");
+        assert_eq!(
+            response["entries"][0]["permissions"]
+                .as_str()
+                .unwrap()
+                .len(),
+            9
+        );
+        assert_eq!(response["entries"][1]["type"], "symlink");
+        assert_eq!(response["entries"][1]["symlink_target"], "Cargo.toml");
+    }
+}
@@ -1,3 +1,6 @@
+use crate::dir_listing::{AlpacaDirListing, AlpacaListArguments};
+use crate::sandbox::AlpacaSandbox;
+use serde::Deserialize;
 use serde_json::{Value, json};
 use std::path::PathBuf;
 
@@ -49,7 +52,7 @@ impl AlpacaEnvironment {
         // Match function name and call appropriate method
         match function_name {
             "get_current_directory" => self.invoke_get_current_directory(),
-            "list_directory" => self.invoke_list_directory(),
+            "list_directory" => self.invoke_list_directory(arguments),
             "change_directory" => match self.invoke_change_directory(arguments) {
                 Ok(result) => result,
                 Err(error) => error,
@@ -149,41 +152,51 @@ impl AlpacaEnvironment {
 
     /// Lists files and directories in the current directory
     ///
+    /// # Arguments
+    ///
+    /// * `arguments` - A JSON Value with the optional `path` and listing options of `AlpacaListOptions`
+    ///
     /// # Returns
     ///
     /// * `Value` - A JSON object containing sorted lists of files and directories and the current directory path
-    fn invoke_list_directory(&self) -> Value {
-        let mut files = Vec::new();
-        let mut directories = Vec::new();
-
-        // Read directory entries
-        if let Ok(entries) = std::fs::read_dir(&self.current_dir) {
-            for entry in entries.flatten() {
-                if let Ok(file_type) = entry.file_type() {
-                    if let Ok(file_name) = entry.file_name().into_string() {
-                        if file_type.is_file() {
-                            files.push(file_name);
-                        } else if file_type.is_dir() {
-                            directories.push(file_name);
-                        }
-                    }
-                }
+    fn invoke_list_directory(&self, arguments: &Value) -> Value {
+        let mut output = json!({
+            "function": "list_directory",
+        });
+
+        let arguments = match AlpacaListArguments::deserialize(arguments) {
+            Ok(arguments) => arguments,
+            Err(err) => {
+                output["error"] = json!(format!("Invalid arguments: {}.", err));
+                return output;
             }
-        }
+        };
 
-        // Sort lists for consistent output
-        files.sort();
-        directories.sort();
+        // The path may not leave the current directory
+        let sandbox = AlpacaSandbox::new(&self.current_dir);
+        let directory = match sandbox.resolve(arguments.path.as_deref().unwrap_or(".")) {
+            Ok(directory) => directory,
+            Err(err) => {
+                output["error"] = json!(err);
+                return output;
+            }
+        };
+        let listing = match AlpacaDirListing::read(&directory, &arguments.options) {
+            Ok(listing) => listing,
+            Err(err) => {
+                output["error"] = json!(err);
+                return output;
+            }
+        };
 
         // Return as JSON object with current directory included
-        json!({
-            "function": "list_directory",
-            "ok": {
-                "current_dir": self.current_dir.to_string_lossy(),
-                "files": files,
-                "directories": directories
-            }
-        })
+        let mut ok = json!({
+            "current_dir": self.current_dir.to_string_lossy(),
+        });
+        listing.add_to(&mut ok, "files", "directories");
+        output["ok"] = ok;
+
+        output
     }
 }
 
@@ -242,7 +255,7 @@ mod tests {
         let mut env = AlpacaEnvironment::new();
         env.set_current_dir(temp_dir.path().to_path_buf());
 
-        let result = env.invoke_list_directory();
+        let result = env.invoke_list_directory(&json!({}));
         assert_eq!(
             result,
             json!({
@@ -256,6 +269,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_list_dir_options() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.rs"), "fn main() {}").unwrap();
+        fs::write(temp_dir.path().join("b.txt"), "test content").unwrap();
+
+        let mut env = AlpacaEnvironment::new();
+        env.set_current_dir(temp_dir.path().to_path_buf());
+
+        let result = env.invoke_list_directory(&json!({"extensions": ["rs"], "metadata": true}));
+        let entries = result["ok"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["name"], "a.rs");
+        assert_eq!(entries[0]["size"], 12);
+
+        let result = env.invoke_list_directory(&json!({"path": "../.."}));
+        assert!(
+            result["error"]
+                .as_str()
+                .unwrap()
+                .contains("outside of the accessible directory")
+        );
+
+        let result = env.invoke_list_directory(&json!({"sort": "sideways"}));
+        assert!(
+            result["error"]
+                .as_str()
+                .unwrap()
+                .contains("Invalid arguments")
+        );
+    }
+
     #[test]
     fn test_current_dir() {
         let env = AlpacaEnvironment::new();
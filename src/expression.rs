@@ -0,0 +1,973 @@
+use std::fmt;
+
+// ===
+// AlpacaNumber
+// ===
+/// A number that stays exact while it can.
+///
+/// Integers and decimals are kept as fractions, so `0.1 + 0.2` is exactly
+/// `0.3`. Functions without exact results, such as `sqrt(2)`, and fractions
+/// that no longer fit in 128 bits produce floating point approximations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlpacaNumber {
+    /// A reduced fraction with a positive denominator
+    Exact(i128, i128),
+    Approximate(f64),
+}
+
+impl AlpacaNumber {
+    fn integer(value: i128) -> Self {
+        AlpacaNumber::Exact(value, 1)
+    }
+
+    fn fraction(numerator: i128, denominator: i128) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator);
+        let (numerator, denominator) = (numerator / divisor, denominator / divisor);
+        if denominator < 0 {
+            Some(AlpacaNumber::Exact(
+                numerator.checked_neg()?,
+                denominator.checked_neg()?,
+            ))
+        } else {
+            Some(AlpacaNumber::Exact(numerator, denominator))
+        }
+    }
+
+    /// Converts to floating point.
+    pub fn to_f64(self) -> f64 {
+        match self {
+            AlpacaNumber::Exact(numerator, denominator) => numerator as f64 / denominator as f64,
+            AlpacaNumber::Approximate(value) => value,
+        }
+    }
+
+    /// Whether the number is exact.
+    pub fn is_exact(self) -> bool {
+        matches!(self, AlpacaNumber::Exact(..))
+    }
+
+    /// The fraction, such as `1/3`, for exact numbers that are not integers.
+    pub fn fraction_text(self) -> Option<String> {
+        match self {
+            AlpacaNumber::Exact(numerator, denominator) if denominator != 1 => {
+                Some(format!("{}/{}", numerator, denominator))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the number is written exactly by `Display`, which is the case
+    /// for approximations and fractions with a finite decimal expansion.
+    pub fn is_finite_decimal(self) -> bool {
+        match self {
+            AlpacaNumber::Exact(_, mut denominator) => {
+                for factor in [2, 5] {
+                    while denominator % factor == 0 {
+                        denominator /= factor;
+                    }
+                }
+                denominator == 1
+            }
+            AlpacaNumber::Approximate(_) => true,
+        }
+    }
+
+    fn checked(
+        self,
+        other: Self,
+        exact: impl Fn(i128, i128, i128, i128) -> Option<Self>,
+        approximate: impl Fn(f64, f64) -> f64,
+    ) -> Self {
+        if let (AlpacaNumber::Exact(a, b), AlpacaNumber::Exact(c, d)) = (self, other)
+            && let Some(result) = exact(a, b, c, d)
+        {
+            return result;
+        }
+        AlpacaNumber::Approximate(approximate(self.to_f64(), other.to_f64()))
+    }
+
+    fn add(self, other: Self) -> Self {
+        self.checked(
+            other,
+            |a, b, c, d| {
+                Self::fraction(
+                    a.checked_mul(d)?.checked_add(c.checked_mul(b)?)?,
+                    b.checked_mul(d)?,
+                )
+            },
+            |x, y| x + y,
+        )
+    }
+
+    fn sub(self, other: Self) -> Self {
+        self.add(other.neg())
+    }
+
+    fn mul(self, other: Self) -> Self {
+        self.checked(
+            other,
+            |a, b, c, d| Self::fraction(a.checked_mul(c)?, b.checked_mul(d)?),
+            |x, y| x * y,
+        )
+    }
+
+    fn div(self, other: Self) -> Result<Self, String> {
+        if other.to_f64() == 0.0 {
+            return Err("Division by zero.".to_string());
+        }
+        Ok(self.checked(
+            other,
+            |a, b, c, d| Self::fraction(a.checked_mul(d)?, b.checked_mul(c)?),
+            |x, y| x / y,
+        ))
+    }
+
+    fn neg(self) -> Self {
+        match self {
+            AlpacaNumber::Exact(numerator, denominator) => match numerator.checked_neg() {
+                Some(numerator) => AlpacaNumber::Exact(numerator, denominator),
+                None => AlpacaNumber::Approximate(-self.to_f64()),
+            },
+            AlpacaNumber::Approximate(value) => AlpacaNumber::Approximate(-value),
+        }
+    }
+
+    fn pow(self, exponent: Self) -> Result<Self, String> {
+        if let (AlpacaNumber::Exact(a, b), AlpacaNumber::Exact(n, 1)) = (self, exponent) {
+            if a == 0 && n < 0 {
+                return Err("Division by zero.".to_string());
+            }
+            if let Ok(power) = u32::try_from(n.unsigned_abs()) {
+                let exact = a
+                    .checked_pow(power)
+                    .zip(b.checked_pow(power))
+                    .and_then(|(a, b)| {
+                        if n < 0 {
+                            Self::fraction(b, a)
+                        } else {
+                            Self::fraction(a, b)
+                        }
+                    });
+                if let Some(exact) = exact {
+                    return Ok(exact);
+                }
+            }
+        }
+
+        let value = self.to_f64().powf(exponent.to_f64());
+        approximate(value)
+    }
+}
+
+impl fmt::Display for AlpacaNumber {
+    /// Writes the number as a decimal: exactly when it has a finite decimal
+    /// expansion, otherwise rounded to 15 significant digits.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let AlpacaNumber::Exact(numerator, 1) = *self {
+            return write!(f, "{}", numerator);
+        }
+        if let AlpacaNumber::Exact(numerator, denominator) = *self
+            && self.is_finite_decimal()
+            && let Some((digits, scale)) = decimal_digits(numerator, denominator)
+        {
+            let text = format!("{:0>width$}", digits, width = scale as usize + 1);
+            let (whole, decimals) = text.split_at(text.len() - scale as usize);
+            let sign = if numerator < 0 { "-" } else { "" };
+            return write!(f, "{}{}.{}", sign, whole, decimals);
+        }
+
+        let value = self.to_f64();
+        let rounded = format!("{:.*e}", 14, value).parse::<f64>().unwrap_or(value);
+        write!(f, "{}", rounded)
+    }
+}
+
+/// Scales a fraction with a finite decimal expansion to a power of ten,
+/// giving its digits and the number of decimal places, if they fit in 128 bits.
+fn decimal_digits(numerator: i128, denominator: i128) -> Option<(u128, u32)> {
+    let mut scale = 0u32;
+    let mut power = 1i128;
+    while power % denominator != 0 {
+        power = power.checked_mul(10)?;
+        scale += 1;
+    }
+    let digits = numerator
+        .unsigned_abs()
+        .checked_mul((power / denominator) as u128)?;
+    Some((digits, scale))
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1) as i128
+}
+
+fn approximate(value: f64) -> Result<AlpacaNumber, String> {
+    if value.is_finite() {
+        Ok(AlpacaNumber::Approximate(value))
+    } else {
+        Err("The result is not a finite number.".to_string())
+    }
+}
+
+// ===
+// Parsing
+// ===
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Identifier(String),
+    Operator(char),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_digit() || c == '.' {
+                        number.push(c);
+                    } else if c == '_' {
+                        // Digit separators, as in 1_000_000
+                    } else if (c == 'e' || c == 'E') && !number.contains('e') {
+                        number.push('e');
+                        chars.next();
+                        if let Some(&sign) = chars.peek() {
+                            if sign == '-' || sign == '+' {
+                                number.push(sign);
+                            } else {
+                                continue;
+                            }
+                        }
+                    } else {
+                        break;
+                    }
+                    chars.next();
+                }
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        name.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Identifier(name.to_lowercase()));
+            }
+            '*' => {
+                chars.next();
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    tokens.push(Token::Operator('^'));
+                } else {
+                    tokens.push(Token::Operator('*'));
+                }
+            }
+            '+' | '-' | '/' | '^' | '%' => {
+                chars.next();
+                tokens.push(Token::Operator(c));
+            }
+            '×' | '·' => {
+                chars.next();
+                tokens.push(Token::Operator('*'));
+            }
+            '÷' => {
+                chars.next();
+                tokens.push(Token::Operator('/'));
+            }
+            '−' => {
+                chars.next();
+                tokens.push(Token::Operator('-'));
+            }
+            '(' | '[' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' | ']' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            _ => return Err(format!("Unexpected character '{}'.", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// A parsed expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Number(AlpacaNumber, String),
+    Constant(String),
+    Percent(Box<Expression>),
+    Negate(Box<Expression>),
+    Binary(char, Box<Expression>, Box<Expression>),
+    Call(String, Vec<Expression>),
+    Group(Box<Expression>),
+}
+
+/// The most operands a parser may be inside of at once, counting parentheses,
+/// function calls, signs and exponents, so that the recursion stays shallow.
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// The operands being parsed
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect_close(&mut self) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Close) => Ok(()),
+            _ => Err("Missing closing parenthesis.".to_string()),
+        }
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut left = self.product()?;
+        while let Some(Token::Operator(operator @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            let right = self.product()?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    // product := unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut left = self.unary()?;
+        while let Some(Token::Operator(operator @ ('*' | '/'))) = self.peek().cloned() {
+            self.next();
+            let right = self.unary()?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    // Every nested operand goes through `unary`, so its depth is counted here
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err("The expression is nested too deeply.".to_string());
+        }
+        self.depth += 1;
+        let unary = self.signed();
+        self.depth -= 1;
+        unary
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn signed(&mut self) -> Result<Expression, String> {
+        match self.peek() {
+            Some(Token::Operator('-')) => {
+                self.next();
+                Ok(Expression::Negate(Box::new(self.unary()?)))
+            }
+            Some(Token::Operator('+')) => {
+                self.next();
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    // power := postfix ('^' unary)?
+    fn power(&mut self) -> Result<Expression, String> {
+        let base = self.postfix()?;
+        if let Some(Token::Operator('^')) = self.peek() {
+            self.next();
+            let exponent = self.unary()?;
+            return Ok(Expression::Binary('^', Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    // postfix := primary '%'?
+    fn postfix(&mut self) -> Result<Expression, String> {
+        let primary = self.primary()?;
+        if let Some(Token::Operator('%')) = self.peek() {
+            self.next();
+            return Ok(Expression::Percent(Box::new(primary)));
+        }
+        Ok(primary)
+    }
+
+    // primary := number | constant | name '(' arguments ')' | '(' sum ')'
+    fn primary(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some(Token::Number(text)) => {
+                parse_number(&text).map(|number| Expression::Number(number, text))
+            }
+            Some(Token::Identifier(name)) => {
+                if self.peek() != Some(&Token::Open) {
+                    return match name.as_str() {
+                        "pi" | "e" => Ok(Expression::Constant(name)),
+                        _ => Err(format!("Unknown name '{}'.", name)),
+                    };
+                }
+                self.next();
+                let mut arguments = Vec::new();
+                if self.peek() != Some(&Token::Close) {
+                    loop {
+                        arguments.push(self.sum()?);
+                        if self.peek() == Some(&Token::Comma) {
+                            self.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                self.expect_close()?;
+                Ok(Expression::Call(name, arguments))
+            }
+            Some(Token::Open) => {
+                let inner = self.sum()?;
+                self.expect_close()?;
+                Ok(Expression::Group(Box::new(inner)))
+            }
+            Some(token) => Err(format!("Unexpected {}.", describe(&token))),
+            None => Err("The expression ended unexpectedly.".to_string()),
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(text) => format!("number '{}'", text),
+        Token::Identifier(name) => format!("name '{}'", name),
+        Token::Operator(operator) => format!("operator '{}'", operator),
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+        Token::Comma => "','".to_string(),
+    }
+}
+
+fn parse_number(text: &str) -> Result<AlpacaNumber, String> {
+    let invalid = || format!("Invalid number '{}'.", text);
+    let (mantissa, exponent) = match text.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().map_err(|_| invalid())?),
+        None => (text, 0),
+    };
+    let (whole, decimals) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if (whole.is_empty() && decimals.is_empty()) || decimals.contains('.') {
+        return Err(invalid());
+    }
+
+    let digits = format!("{}{}", whole, decimals);
+    let exponent = exponent - decimals.len() as i32;
+    let exact = digits.parse::<i128>().ok().and_then(|numerator| {
+        let scale = 10i128.checked_pow(exponent.unsigned_abs())?;
+        if exponent >= 0 {
+            AlpacaNumber::fraction(numerator.checked_mul(scale)?, 1)
+        } else {
+            AlpacaNumber::fraction(numerator, scale)
+        }
+    });
+
+    match exact {
+        Some(number) => Ok(number),
+        None => text
+            .parse::<f64>()
+            .map_err(|_| invalid())
+            .and_then(approximate),
+    }
+}
+
+// ===
+// Evaluation
+// ===
+
+/// A value and whether it is a percentage, such as `15%`.
+struct Value {
+    number: AlpacaNumber,
+    percent: bool,
+}
+
+impl Value {
+    fn plain(number: AlpacaNumber) -> Self {
+        Value {
+            number,
+            percent: false,
+        }
+    }
+
+    /// The number a percentage stands for on its own, such as `0.15` for `15%`.
+    fn resolved(&self) -> AlpacaNumber {
+        if self.percent {
+            self.number.div(AlpacaNumber::integer(100)).unwrap()
+        } else {
+            self.number
+        }
+    }
+}
+
+fn evaluate(expression: &Expression) -> Result<Value, String> {
+    match expression {
+        Expression::Number(number, _) => Ok(Value::plain(*number)),
+        Expression::Constant(name) => {
+            Ok(Value::plain(AlpacaNumber::Approximate(if name == "pi" {
+                std::f64::consts::PI
+            } else {
+                std::f64::consts::E
+            })))
+        }
+        Expression::Percent(inner) => {
+            let inner = evaluate(inner)?;
+            if inner.percent {
+                return Err("A percentage cannot be taken of a percentage.".to_string());
+            }
+            Ok(Value {
+                number: inner.number,
+                percent: true,
+            })
+        }
+        Expression::Negate(inner) => {
+            let inner = evaluate(inner)?;
+            Ok(Value {
+                number: inner.number.neg(),
+                percent: inner.percent,
+            })
+        }
+        Expression::Group(inner) => evaluate(inner),
+        Expression::Binary(operator, left, right) => {
+            let (left, right) = (evaluate(left)?, evaluate(right)?);
+            binary(*operator, left, right)
+        }
+        Expression::Call(name, arguments) => {
+            let values = arguments
+                .iter()
+                .map(|argument| evaluate(argument).map(|value| value.resolved()))
+                .collect::<Result<Vec<_>, _>>()?;
+            call(name, &values).map(Value::plain)
+        }
+    }
+}
+
+/// Applies an operator. Adding or subtracting a percentage changes the other
+/// side by that share, so `200 + 10%` is `220`; percentages of the same kind
+/// add up to a percentage, so `10% + 5%` is `15%`.
+fn binary(operator: char, left: Value, right: Value) -> Result<Value, String> {
+    let one = AlpacaNumber::integer(1);
+
+    let number = match (operator, left.percent, right.percent) {
+        ('+' | '-', true, true) => {
+            let number = if operator == '+' {
+                left.number.add(right.number)
+            } else {
+                left.number.sub(right.number)
+            };
+            return Ok(Value {
+                number,
+                percent: true,
+            });
+        }
+        ('+', false, true) => left.number.mul(one.add(right.resolved())),
+        ('-', false, true) => left.number.mul(one.sub(right.resolved())),
+        ('+', _, _) => left.resolved().add(right.resolved()),
+        ('-', _, _) => left.resolved().sub(right.resolved()),
+        ('*', _, _) => left.resolved().mul(right.resolved()),
+        ('/', _, _) => left.resolved().div(right.resolved())?,
+        ('^', _, _) => left.resolved().pow(right.resolved())?,
+        _ => return Err(format!("Unknown operator '{}'.", operator)),
+    };
+
+    Ok(Value::plain(number))
+}
+
+fn call(name: &str, arguments: &[AlpacaNumber]) -> Result<AlpacaNumber, String> {
+    let expect = |count: usize| -> Result<(), String> {
+        if arguments.len() == count {
+            Ok(())
+        } else {
+            Err(format!(
+                "The function '{}' takes {} argument{}, not {}.",
+                name,
+                count,
+                if count == 1 { "" } else { "s" },
+                arguments.len()
+            ))
+        }
+    };
+    let float = |function: fn(f64) -> f64| -> Result<AlpacaNumber, String> {
+        expect(1)?;
+        approximate(function(arguments[0].to_f64()))
+    };
+
+    match name {
+        "min" | "max" => {
+            if arguments.is_empty() {
+                return Err(format!(
+                    "The function '{}' needs at least one argument.",
+                    name
+                ));
+            }
+            let mut best = arguments[0];
+            for &argument in &arguments[1..] {
+                let better = if name == "min" {
+                    argument.to_f64() < best.to_f64()
+                } else {
+                    argument.to_f64() > best.to_f64()
+                };
+                if better {
+                    best = argument;
+                }
+            }
+            Ok(best)
+        }
+        "abs" => {
+            expect(1)?;
+            Ok(if arguments[0].to_f64() < 0.0 {
+                arguments[0].neg()
+            } else {
+                arguments[0]
+            })
+        }
+        "round" | "floor" | "ceil" | "trunc" => round(name, arguments),
+        "sqrt" => {
+            expect(1)?;
+            if arguments[0].to_f64() < 0.0 {
+                return Err(
+                    "The square root of a negative number is not a real number.".to_string()
+                );
+            }
+            // Keep perfect squares exact
+            if let AlpacaNumber::Exact(numerator, denominator) = arguments[0]
+                && let (Some(n), Some(d)) = (exact_sqrt(numerator), exact_sqrt(denominator))
+            {
+                return Ok(AlpacaNumber::Exact(n, d));
+            }
+            approximate(arguments[0].to_f64().sqrt())
+        }
+        "pow" => {
+            expect(2)?;
+            arguments[0].pow(arguments[1])
+        }
+        "mod" => {
+            expect(2)?;
+            let divisor = arguments[1];
+            let quotient = arguments[0].div(divisor)?;
+            let floored = round("floor", &[quotient])?;
+            Ok(arguments[0].sub(floored.mul(divisor)))
+        }
+        "log" if arguments.len() == 2 => {
+            positive(name, arguments)?;
+            approximate(arguments[0].to_f64().ln() / arguments[1].to_f64().ln())
+        }
+        "log" | "ln" => {
+            positive(name, arguments)?;
+            float(f64::ln)
+        }
+        "log10" => {
+            positive(name, arguments)?;
+            float(f64::log10)
+        }
+        "log2" => {
+            positive(name, arguments)?;
+            float(f64::log2)
+        }
+        "exp" => float(f64::exp),
+        "sin" => float(f64::sin),
+        "cos" => float(f64::cos),
+        "tan" => float(f64::tan),
+        _ => Err(format!(
+            "Unknown function '{}'. The functions are: min, max, abs, round, floor, ceil, trunc, sqrt, pow, mod, log, ln, log10, log2, exp, sin, cos, tan.",
+            name
+        )),
+    }
+}
+
+fn positive(name: &str, arguments: &[AlpacaNumber]) -> Result<(), String> {
+    if arguments.iter().any(|argument| argument.to_f64() <= 0.0) {
+        return Err(format!("The function '{}' needs positive arguments.", name));
+    }
+    Ok(())
+}
+
+/// Rounds to a number of decimal places, the second argument of `round`.
+/// Halves are rounded away from zero.
+fn round(name: &str, arguments: &[AlpacaNumber]) -> Result<AlpacaNumber, String> {
+    let places = match arguments {
+        [_] => 0,
+        [_, AlpacaNumber::Exact(places, 1)] if name == "round" && (0..=18).contains(places) => {
+            *places as u32
+        }
+        [_, _] if name == "round" => {
+            return Err(
+                "The decimal places of 'round' must be a whole number from 0 to 18.".to_string(),
+            );
+        }
+        _ => {
+            return Err(format!(
+                "The function '{}' takes {} argument{}.",
+                name,
+                if name == "round" { "1 or 2" } else { "1" },
+                if name == "round" { "s" } else { "" }
+            ));
+        }
+    };
+
+    let scale = 10i128.pow(places);
+    match arguments[0] {
+        AlpacaNumber::Exact(numerator, denominator) => {
+            let scaled = numerator.checked_mul(scale);
+            if let Some(scaled) = scaled {
+                let quotient = scaled.div_euclid(denominator);
+                let remainder = scaled.rem_euclid(denominator);
+                let round_up = match name {
+                    "floor" => false,
+                    "ceil" => remainder != 0,
+                    "trunc" => numerator < 0 && remainder != 0,
+                    _ => {
+                        // Compare the remainder with half the denominator without doubling it
+                        let half = denominator - remainder;
+                        remainder > half || (remainder == half && numerator > 0)
+                    }
+                };
+                let rounded = if round_up {
+                    quotient.checked_add(1)
+                } else {
+                    Some(quotient)
+                };
+                return rounded
+                    .and_then(|rounded| AlpacaNumber::fraction(rounded, scale))
+                    .ok_or_else(|| format!("The result of '{}' is too large.", name));
+            }
+            round(name, &[AlpacaNumber::Approximate(arguments[0].to_f64())])
+        }
+        AlpacaNumber::Approximate(value) => {
+            let scaled = value * scale as f64;
+            let rounded = match name {
+                "floor" => scaled.floor(),
+                "ceil" => scaled.ceil(),
+                "trunc" => scaled.trunc(),
+                _ => scaled.round(),
+            };
+            approximate(rounded / scale as f64)
+        }
+    }
+}
+
+fn exact_sqrt(value: i128) -> Option<i128> {
+    let root = (value as f64).sqrt().round() as i128;
+    (root.checked_mul(root) == Some(value)).then_some(root)
+}
+
+// ===
+// Formatting
+// ===
+
+/// Writes an expression with consistent spacing and operators.
+fn normalize(expression: &Expression) -> String {
+    match expression {
+        Expression::Number(_, text) => text.clone(),
+        Expression::Constant(name) => name.clone(),
+        Expression::Percent(inner) => format!("{}%", normalize(inner)),
+        Expression::Negate(inner) => format!("-{}", normalize(inner)),
+        Expression::Binary('^', left, right) => {
+            format!("{}^{}", normalize(left), normalize(right))
+        }
+        Expression::Binary(operator, left, right) => {
+            format!("{} {} {}", normalize(left), operator, normalize(right))
+        }
+        Expression::Call(name, arguments) => {
+            let arguments: Vec<String> = arguments.iter().map(normalize).collect();
+            format!("{}({})", name, arguments.join(", "))
+        }
+        Expression::Group(inner) => format!("({})", normalize(inner)),
+    }
+}
+
+// ===
+// AlpacaEvaluation
+// ===
+/// The result of evaluating an arithmetic expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaEvaluation {
+    /// The expression with consistent spacing and operators
+    pub expression: String,
+    /// The value of the expression
+    pub result: AlpacaNumber,
+    /// Whether the value of a trailing percentage, such as `25%`, is a percentage
+    pub percent: bool,
+}
+
+/// Evaluates an arithmetic expression.
+///
+/// Expressions combine numbers with `+`, `-`, `*`, `/`, `^` (or `**`),
+/// parentheses, percentages such as `15%`, the constants `pi` and `e` and the
+/// functions listed in the error for an unknown function. Nothing else can be
+/// named or called.
+///
+/// # Returns
+///
+/// * `Ok(AlpacaEvaluation)` - The normalized expression and its value
+/// * `Err(String)` - A description of the syntax or arithmetic error
+pub fn evaluate_expression(expression: &str) -> Result<AlpacaEvaluation, String> {
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        return Err("The expression is empty.".to_string());
+    }
+
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        depth: 0,
+    };
+    let parsed = parser.sum()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("Unexpected {}.", describe(token)));
+    }
+
+    let value = evaluate(&parsed)?;
+    Ok(AlpacaEvaluation {
+        expression: normalize(&parsed),
+        result: value.number,
+        percent: value.percent,
+    })
+}
+
+// ===
+// Expression Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(expression: &str) -> String {
+        let evaluation = evaluate_expression(expression).unwrap();
+        evaluation.result.to_string()
+    }
+
+    /// Tests exact arithmetic with integers and decimals.
+    #[test]
+    fn test_exact_arithmetic() {
+        assert_eq!(result("0.1 + 0.2"), "0.3");
+        assert_eq!(result("2 + 3 * (4 - 1)"), "11");
+        assert_eq!(result("-2 ^ 2"), "-4");
+        assert_eq!(result("2 ** 10 / 4"), "256");
+        assert_eq!(result("1_000 * 1.5e3"), "1500000");
+        assert_eq!(result("7 / 4"), "1.75");
+
+        let third = evaluate_expression("1 / 3").unwrap().result;
+        assert_eq!(third.fraction_text().as_deref(), Some("1/3"));
+        assert!(!third.is_finite_decimal());
+        assert_eq!(third.to_string(), "0.333333333333333");
+
+        // Too many decimal places to scale exactly, and too small to stay exact
+        assert_eq!(result("2^-40"), "0.000000000000909494701772928");
+        assert_eq!(
+            result("0.5^130"),
+            "0.00000000000000000000000000000000000000073468396926393"
+        );
+    }
+
+    /// Tests the functions and constants.
+    #[test]
+    fn test_functions() {
+        assert_eq!(result("max(3, 7.5, -1) + min(2, 4)"), "9.5");
+        assert_eq!(result("round(2.345, 2)"), "2.35");
+        assert_eq!(result("round(-2.5)"), "-3");
+        assert_eq!(result("sqrt(2.25)"), "1.5");
+        assert_eq!(result("pow(2, -2)"), "0.25");
+        assert_eq!(result("mod(-7, 3)"), "2");
+        assert_eq!(result("log(1000, 10)"), "3");
+        assert_eq!(result("round(pi, 4)"), "3.1416");
+    }
+
+    /// Tests percentages.
+    #[test]
+    fn test_percentages() {
+        assert_eq!(result("200 + 10%"), "220");
+        assert_eq!(result("200 - 25%"), "150");
+        assert_eq!(result("15% * 80"), "12");
+        assert_eq!(result("1500 / 60%"), "2500");
+
+        let sum = evaluate_expression("10% + 5%").unwrap();
+        assert!(sum.percent);
+        assert_eq!(sum.result.to_string(), "15");
+    }
+
+    /// Tests the normalized expression and errors.
+    #[test]
+    fn test_normalize_and_errors() {
+        let evaluation = evaluate_expression("2×(3+SQRT(16))**2").unwrap();
+        assert_eq!(evaluation.expression, "2 * (3 + sqrt(16))^2");
+        assert_eq!(evaluation.result.to_string(), "98");
+
+        assert_eq!(
+            evaluate_expression("1 / 0").unwrap_err(),
+            "Division by zero."
+        );
+        assert_eq!(
+            evaluate_expression("system(1)")
+                .unwrap_err()
+                .split('.')
+                .next(),
+            Some("Unknown function 'system'")
+        );
+        assert_eq!(
+            evaluate_expression("x + 1").unwrap_err(),
+            "Unknown name 'x'."
+        );
+        assert_eq!(
+            evaluate_expression("(1 + 2").unwrap_err(),
+            "Missing closing parenthesis."
+        );
+        assert_eq!(
+            evaluate_expression("1 2").unwrap_err(),
+            "Unexpected number '2'."
+        );
+
+        let nested = format!("{}1{}", "(".repeat(60), ")".repeat(60));
+        assert_eq!(result(&nested), "1");
+        for deep in [
+            format!("{}1{}", "(".repeat(300), ")".repeat(300)),
+            format!("{}1", "-".repeat(10_000)),
+            format!("2{}", "^2".repeat(300)),
+            format!("{}1{}", "abs(".repeat(300), ")".repeat(300)),
+        ] {
+            assert_eq!(
+                evaluate_expression(&deep).unwrap_err(),
+                "The expression is nested too deeply."
+            );
+        }
+    }
+}
@@ -0,0 +1,324 @@
+// ===
+// AlpacaFileContent
+// ===
+/// The contents of a file, decoded for the model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlpacaFileContent {
+    /// Text, together with the name of the encoding it was decoded from
+    Text {
+        text: String,
+        encoding: &'static str,
+        /// `true` if invalid sequences were replaced with `U+FFFD`
+        lossy: bool,
+    },
+    /// Data that is not text
+    Binary,
+}
+
+/// The share of invalid UTF-8 or control bytes tolerated in text.
+const NEAR_TEXT_RATIO: f64 = 0.05;
+/// The number of bytes inspected when deciding whether data is text.
+const SAMPLE_SIZE: usize = 8192;
+
+impl AlpacaFileContent {
+    /// Decodes the raw bytes of a file.
+    ///
+    /// UTF-8 and UTF-16 (with a byte order mark, or detected from the layout of
+    /// zero bytes) are decoded exactly. Data that is nearly valid UTF-8 is
+    /// decoded lossily, and other data without control characters is read as
+    /// Latin-1. Anything else is considered binary.
+    pub fn decode(bytes: &[u8]) -> Self {
+        if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+            return Self::utf8(rest, "utf-8-bom");
+        }
+        if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+            return Self::utf16(rest, false);
+        }
+        if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+            return Self::utf16(rest, true);
+        }
+        if let Some(big_endian) = detect_utf16(bytes) {
+            return Self::utf16(bytes, big_endian);
+        }
+
+        let sample = &bytes[..bytes.len().min(SAMPLE_SIZE)];
+        if sample.contains(&0) {
+            return AlpacaFileContent::Binary;
+        }
+
+        if std::str::from_utf8(bytes).is_ok() {
+            return Self::utf8(bytes, "utf-8");
+        }
+
+        // Count the bytes that belong to invalid UTF-8 sequences
+        let lossy = String::from_utf8_lossy(sample);
+        let invalid = lossy.chars().filter(|&c| c == '\u{FFFD}').count();
+        if (invalid as f64) <= sample.len() as f64 * NEAR_TEXT_RATIO {
+            return Self::utf8(bytes, "utf-8");
+        }
+
+        let controls = sample
+            .iter()
+            .filter(|&&byte| is_control(byte) || (0x80..0xA0).contains(&byte))
+            .count();
+        if (controls as f64) <= sample.len() as f64 * NEAR_TEXT_RATIO {
+            return AlpacaFileContent::Text {
+                text: bytes.iter().map(|&byte| byte as char).collect(),
+                encoding: "latin-1",
+                lossy: false,
+            };
+        }
+
+        AlpacaFileContent::Binary
+    }
+
+    fn utf8(bytes: &[u8], encoding: &'static str) -> Self {
+        let text = String::from_utf8_lossy(bytes);
+        AlpacaFileContent::Text {
+            lossy: matches!(text, std::borrow::Cow::Owned(_)),
+            text: text.into_owned(),
+            encoding,
+        }
+    }
+
+    fn utf16(bytes: &[u8], big_endian: bool) -> Self {
+        let units = bytes.chunks_exact(2).map(|pair| {
+            if big_endian {
+                u16::from_be_bytes([pair[0], pair[1]])
+            } else {
+                u16::from_le_bytes([pair[0], pair[1]])
+            }
+        });
+
+        let mut lossy = !bytes.len().is_multiple_of(2);
+        let text = char::decode_utf16(units)
+            .map(|unit| {
+                unit.unwrap_or_else(|_| {
+                    lossy = true;
+                    '\u{FFFD}'
+                })
+            })
+            .collect();
+
+        AlpacaFileContent::Text {
+            text,
+            encoding: if big_endian { "utf-16be" } else { "utf-16le" },
+            lossy,
+        }
+    }
+}
+
+/// Detects UTF-16 text without a byte order mark, from ASCII characters whose
+/// high byte is zero. Returns `Some(true)` for big endian.
+fn detect_utf16(bytes: &[u8]) -> Option<bool> {
+    let sample = &bytes[..bytes.len().min(SAMPLE_SIZE) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|&&byte| byte == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|&&byte| byte == 0)
+        .count();
+
+    if odd_zeros * 10 >= pairs * 9 && even_zeros == 0 {
+        Some(false)
+    } else if even_zeros * 10 >= pairs * 9 && odd_zeros == 0 {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+fn is_control(byte: u8) -> bool {
+    byte < 0x20 && !matches!(byte, b'\n' | b'\r' | b'\t' | 0x0C)
+}
+
+/// Names the format of binary data from its leading magic bytes.
+pub fn detect_type(bytes: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "PNG image"),
+        (b"\xFF\xD8\xFF", "JPEG image"),
+        (b"GIF87a", "GIF image"),
+        (b"GIF89a", "GIF image"),
+        (b"BM", "BMP image"),
+        (b"%PDF-", "PDF document"),
+        (b"PK\x03\x04", "ZIP archive (also jar, docx, xlsx)"),
+        (b"\x1F\x8B", "gzip archive"),
+        (b"BZh", "bzip2 archive"),
+        (b"\xFD7zXZ\x00", "xz archive"),
+        (b"7z\xBC\xAF\x27\x1C", "7-Zip archive"),
+        (b"\x28\xB5\x2F\xFD", "Zstandard archive"),
+        (b"\x7FELF", "ELF executable"),
+        (b"MZ", "Windows executable"),
+        (b"\xCF\xFA\xED\xFE", "Mach-O executable"),
+        (b"\xCA\xFE\xBA\xBE", "Java class or Mach-O universal binary"),
+        (b"\x00asm", "WebAssembly module"),
+        (b"SQLite format 3\x00", "SQLite database"),
+        (b"OggS", "Ogg media"),
+        (b"ID3", "MP3 audio"),
+        (b"fLaC", "FLAC audio"),
+        (b"wOFF", "WOFF font"),
+        (b"wOF2", "WOFF2 font"),
+        (b"!<arch>\n", "ar archive (also .rlib, .a)"),
+    ];
+
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" {
+        return match &bytes[8..12] {
+            b"WEBP" => Some("WebP image"),
+            b"WAVE" => Some("WAV audio"),
+            b"AVI " => Some("AVI video"),
+            _ => Some("RIFF container"),
+        };
+    }
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        return Some("MP4/QuickTime media");
+    }
+    if bytes.len() > 262 && &bytes[257..262] == b"ustar" {
+        return Some("tar archive");
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+        .map(|(_, name)| *name)
+}
+
+/// Formats bytes as a hex dump, with 16 bytes per line and their offsets.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to format
+/// * `offset` - The file offset of the first byte, used for the line labels
+pub fn hex_dump(bytes: &[u8], offset: usize) -> String {
+    let mut output = String::new();
+
+    for (index, chunk) in bytes.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        output.push_str(&format!(
+            "{:08x}  {:<47}  |{}|\n",
+            offset + index * 16,
+            hex.join(" "),
+            ascii
+        ));
+    }
+
+    output
+}
+
+/// Encodes bytes as standard base64, with padding.
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let value = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                output.push(ALPHABET[(value >> (18 - 6 * index)) as usize & 63] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+
+    output
+}
+
+// ===
+// AlpacaFileContent Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(content: &AlpacaFileContent) -> (&str, &'static str, bool) {
+        match content {
+            AlpacaFileContent::Text {
+                text,
+                encoding,
+                lossy,
+            } => (text.as_str(), *encoding, *lossy),
+            AlpacaFileContent::Binary => panic!("expected text"),
+        }
+    }
+
+    /// Tests decoding the supported text encodings.
+    #[test]
+    fn test_decode_text() {
+        let utf8 = AlpacaFileContent::decode("héllo\n".as_bytes());
+        assert_eq!(text(&utf8), ("héllo\n", "utf-8", false));
+
+        let bom = AlpacaFileContent::decode(b"\xEF\xBB\xBFhi");
+        assert_eq!(text(&bom), ("hi", "utf-8-bom", false));
+
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain("hé".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        assert_eq!(
+            text(&AlpacaFileContent::decode(&utf16)),
+            ("hé", "utf-16le", false)
+        );
+
+        let utf16_be: Vec<u8> = "plain".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(
+            text(&AlpacaFileContent::decode(&utf16_be)),
+            ("plain", "utf-16be", false)
+        );
+
+        let latin1 = AlpacaFileContent::decode(b"caf\xe9 cr\xe8me br\xfbl\xe9e");
+        assert_eq!(text(&latin1), ("café crème brûlée", "latin-1", false));
+    }
+
+    /// Tests that nearly valid UTF-8 is decoded lossily.
+    #[test]
+    fn test_decode_near_text() {
+        let mut bytes = "valid text ".repeat(20).into_bytes();
+        bytes.push(0xFF);
+        let content = AlpacaFileContent::decode(&bytes);
+
+        let (decoded, encoding, lossy) = text(&content);
+        assert!(decoded.ends_with('\u{FFFD}'));
+        assert_eq!(encoding, "utf-8");
+        assert!(lossy);
+    }
+
+    /// Tests that binary data is detected and identified.
+    #[test]
+    fn test_decode_binary() {
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+        assert_eq!(AlpacaFileContent::decode(png), AlpacaFileContent::Binary);
+        assert_eq!(detect_type(png), Some("PNG image"));
+        assert_eq!(detect_type(b"\x01\x02"), None);
+    }
+
+    /// Tests the hex dump and base64 formats.
+    #[test]
+    fn test_hex_dump_and_base64() {
+        assert_eq!(
+            hex_dump(b"AB\x00", 32),
+            "00000020  41 42 00                                         |AB.|\n"
+        );
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+    }
+}
@@ -0,0 +1,233 @@
+use ignore::WalkBuilder;
+use ignore::overrides::OverrideBuilder;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// ===
+// AlpacaWalkOptions
+// ===
+/// Controls how the file actions walk a directory tree.
+///
+/// Symbolic links are never followed, so a walk cannot leave the sandbox.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaWalkOptions {
+    /// Glob patterns a file must match, such as `*.rs` or `src/**/*.toml`; empty for any file
+    pub include: Vec<String>,
+    /// Glob patterns of files and directories to skip
+    pub exclude: Vec<String>,
+    /// Whether `.gitignore`, `.ignore` and `.git/info/exclude` files are honoured
+    pub respect_ignore_files: bool,
+    /// Whether hidden files and directories are visited
+    pub hidden: bool,
+    /// The maximum depth below the root, where 1 is the root's own entries
+    pub max_depth: Option<usize>,
+}
+
+impl Default for AlpacaWalkOptions {
+    fn default() -> Self {
+        AlpacaWalkOptions {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_ignore_files: true,
+            hidden: false,
+            max_depth: None,
+        }
+    }
+}
+
+impl AlpacaWalkOptions {
+    /// Builds a walker over `root`, visiting entries sorted by name.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ignore::Walk)` - The walker, which yields `root` itself first
+    /// * `Err(String)` - A description of the invalid glob pattern
+    pub fn walk(&self, root: &Path) -> Result<ignore::Walk, String> {
+        let mut overrides = OverrideBuilder::new(root);
+        for pattern in &self.include {
+            overrides
+                .add(pattern)
+                .map_err(|e| format!("Invalid include pattern '{}': {}", pattern, e))?;
+        }
+        for pattern in &self.exclude {
+            overrides
+                .add(&format!("!{}", pattern))
+                .map_err(|e| format!("Invalid exclude pattern '{}': {}", pattern, e))?;
+        }
+        let overrides = overrides.build().map_err(|e| e.to_string())?;
+
+        let walk = WalkBuilder::new(root)
+            .overrides(overrides)
+            .standard_filters(self.respect_ignore_files)
+            .hidden(!self.hidden)
+            .require_git(false)
+            .follow_links(false)
+            .max_depth(self.max_depth)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+
+        Ok(walk)
+    }
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp, such as `2025-04-01T12:30:00Z`.
+pub fn format_time(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(error) => -(error.duration().as_secs() as i64),
+    };
+    let days = seconds.div_euclid(86_400);
+    let time_of_day = seconds.rem_euclid(86_400);
+
+    // Convert days since the epoch to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60
+    )
+}
+
+/// Parses a duration such as `90s`, `30m`, `12h`, `7d` or `2w`.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+
+    let number: u64 = number.parse().map_err(|_| {
+        format!(
+            "Invalid duration '{}'. Use a number followed by s, m, h, d or w, such as '7d'.",
+            text
+        )
+    })?;
+    let seconds = match unit.trim() {
+        "s" | "" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => {
+            return Err(format!(
+                "Invalid duration unit in '{}'. Use s, m, h, d or w.",
+                text
+            ));
+        }
+    };
+
+    let seconds = number
+        .checked_mul(seconds)
+        .ok_or_else(|| format!("The duration '{}' is too large.", text))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+// ===
+// AlpacaWalkOptions Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn walked(root: &Path, options: &AlpacaWalkOptions) -> Vec<String> {
+        options
+            .walk(root)
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.depth() > 0)
+            .map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    /// Tests that ignore files, hidden entries and glob patterns are honoured.
+    #[test]
+    fn test_walk() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::create_dir_all(root.join(".cache")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join("src/notes.md"), "").unwrap();
+        fs::write(root.join("target/out.rs"), "").unwrap();
+        fs::write(root.join(".cache/x.rs"), "").unwrap();
+
+        let options = AlpacaWalkOptions::default();
+        assert_eq!(
+            walked(root, &options),
+            vec!["src", "src/lib.rs", "src/notes.md"]
+        );
+
+        let options = AlpacaWalkOptions {
+            include: vec!["*.rs".to_string()],
+            respect_ignore_files: false,
+            hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            walked(root, &options),
+            vec![
+                ".cache",
+                ".cache/x.rs",
+                "src",
+                "src/lib.rs",
+                "target",
+                "target/out.rs"
+            ]
+        );
+
+        let options = AlpacaWalkOptions {
+            exclude: vec!["*.md".to_string()],
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(walked(root, &options), vec!["src"]);
+    }
+
+    /// Tests formatting timestamps.
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(format_time(time), "2024-02-29T12:34:56Z");
+    }
+
+    /// Tests parsing durations.
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("7d").unwrap(), Duration::from_secs(604_800));
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("99999999999999999d").is_err());
+    }
+}
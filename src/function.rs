@@ -1,3 +1,4 @@
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use serde_json::json;
 use std::collections::HashMap;
@@ -52,22 +53,22 @@ pub trait AlpacaFunction {
     ///
     /// # Returns
     ///
-    /// A static string containing detailed information about the function
-    fn info(&self) -> &'static str;
+    /// A string containing detailed information about the function
+    fn info(&self) -> &str;
 
     /// Return the name of the function
     ///
     /// # Returns
     ///
-    /// A static string containing the name of the function
-    fn name(&self) -> &'static str;
+    /// A string containing the name of the function
+    fn name(&self) -> &str;
 
     /// Return the description of the function
     ///
     /// # Returns
     ///
-    /// A static string containing a brief description of what the function does
-    fn description(&self) -> &'static str;
+    /// A string containing a brief description of what the function does
+    fn description(&self) -> &str;
 }
 
 // ===
@@ -75,7 +76,7 @@ pub trait AlpacaFunction {
 // ===
 /// A collection of Alpaca functions that can be called by name
 pub struct AlpacaFunctions {
-    functions: HashMap<&'static str, Box<dyn AlpacaFunction>>,
+    functions: HashMap<String, Box<dyn AlpacaFunction>>,
 }
 
 impl AlpacaFunctions {
@@ -96,7 +97,7 @@ impl AlpacaFunctions {
     ///
     /// * `function` - The function to add to the collection
     pub fn add_function(&mut self, function: Box<dyn AlpacaFunction>) {
-        self.functions.insert(function.name(), function);
+        self.functions.insert(function.name().to_string(), function);
     }
 
     /// Lists all available functions in a formatted JSON string
@@ -172,6 +173,35 @@ impl AlpacaFunctions {
         }
     }
 
+    /// Deserializes the arguments of a function call into a typed struct
+    ///
+    /// # Arguments
+    ///
+    /// * `function` - The function being called
+    /// * `arguments` - Optional JSON arguments passed to the function; a missing
+    ///   value is treated as an empty object
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(T)` - The typed arguments
+    /// * `Err(String)` - An error response for the model, followed by the function's usage info
+    pub fn arguments<T: DeserializeOwned>(
+        function: &dyn AlpacaFunction,
+        arguments: Option<&Value>,
+    ) -> Result<T, String> {
+        let empty_args = json!({});
+        let arguments = arguments.unwrap_or(&empty_args);
+
+        T::deserialize(arguments).map_err(|e| {
+            let error = format!("Invalid arguments: {}.", e);
+            format!(
+                "{}{}\n",
+                Self::error(function.name(), &error),
+                function.info()
+            )
+        })
+    }
+
     /// Returns the introductory text explaining how to use functions
     ///
     /// # Returns
@@ -242,15 +272,15 @@ mod tests {
             Some(self.return_value.to_string())
         }
 
-        fn info(&self) -> &'static str {
+        fn info(&self) -> &str {
             "Mock function for testing"
         }
 
-        fn name(&self) -> &'static str {
+        fn name(&self) -> &str {
             self.name
         }
 
-        fn description(&self) -> &'static str {
+        fn description(&self) -> &str {
             self.description
         }
     }
@@ -325,6 +355,27 @@ mod tests {
         assert_eq!(result.unwrap(), "test result");
     }
 
+    #[test]
+    fn test_arguments() {
+        #[derive(serde::Deserialize)]
+        struct Arguments {
+            param: String,
+        }
+
+        let mock = MockFunction::new("test", "Test function", "test result");
+
+        let args = serde_json::json!({"param": "value"});
+        let arguments: Arguments = AlpacaFunctions::arguments(&mock, Some(&args)).unwrap();
+        assert_eq!(arguments.param, "value");
+
+        // A missing argument produces an error followed by the usage info
+        let error = AlpacaFunctions::arguments::<Arguments>(&mock, None)
+            .err()
+            .unwrap();
+        assert!(error.contains("missing field `param`"));
+        assert!(error.contains("Mock function for testing"));
+    }
+
     #[test]
     fn test_call_function_with_arguments() {
         let mut functions = AlpacaFunctions::new();
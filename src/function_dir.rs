@@ -1,6 +1,9 @@
+use crate::dir_listing::{AlpacaDirListing, AlpacaListArguments, LIST_OPTIONS_HELP};
 use crate::function::{AlpacaFunction, AlpacaFunctions};
+use crate::sandbox::AlpacaSandbox;
+use std::sync::LazyLock;
 
-const FUNCTION_DIR_INFO: &str = r#"
+const FUNCTION_DIR_USAGE: &str = r#"
 # `dir`
 
 This function lists the files & directories in the current directory.
@@ -29,8 +32,13 @@ example response:
         ]
     }
 }
+```
+
 "#;
 
+static FUNCTION_DIR_INFO: LazyLock<String> =
+    LazyLock::new(|| format!("{}{}", FUNCTION_DIR_USAGE, LIST_OPTIONS_HELP));
+
 // ===
 // AlpacaFunctionDir
 // ===
@@ -44,48 +52,40 @@ impl AlpacaFunctionDir {
 
 // Implement the AlpacaFunction trait for AlpacaFunctionDir
 impl AlpacaFunction for AlpacaFunctionDir {
-    fn execute(&self, _arguments: Option<&serde_json::Value>) -> Option<String> {
-        // Read the current directory
-        let current_dir = std::env::current_dir().unwrap_or_default();
-        let mut files = Vec::new();
-        let mut directories = Vec::new();
-
-        // Read directory entries
-        if let Ok(entries) = std::fs::read_dir(&current_dir) {
-            for entry in entries.flatten() {
-                if let Ok(file_type) = entry.file_type() {
-                    if let Ok(file_name) = entry.file_name().into_string() {
-                        if file_type.is_file() {
-                            files.push(file_name);
-                        } else if file_type.is_dir() {
-                            directories.push(file_name);
-                        }
-                    }
-                }
-            }
-        }
+    fn execute(&self, arguments: Option<&serde_json::Value>) -> Option<String> {
+        let arguments: AlpacaListArguments = match AlpacaFunctions::arguments(self, arguments) {
+            Ok(arguments) => arguments,
+            Err(error) => return Some(error),
+        };
 
-        // Sort lists for consistent output
-        files.sort();
-        directories.sort();
+        // Read the requested directory inside the current one, or the current one
+        let current_dir = std::env::current_dir().unwrap_or_default();
+        let path = match AlpacaSandbox::new(&current_dir)
+            .resolve(arguments.path.as_deref().unwrap_or("."))
+        {
+            Ok(path) => path,
+            Err(error) => return Some(AlpacaFunctions::error(self.name(), &error)),
+        };
+        let listing = match AlpacaDirListing::read(&path, &arguments.options) {
+            Ok(listing) => listing,
+            Err(error) => return Some(AlpacaFunctions::error(self.name(), &error)),
+        };
 
-        let ok = serde_json::json!({
-            "files": files,
-            "directories": directories,
-        });
+        let mut ok = serde_json::json!({});
+        listing.add_to(&mut ok, "files", "directories");
 
         Some(AlpacaFunctions::ok(self.name(), &ok))
     }
 
-    fn info(&self) -> &'static str {
-        FUNCTION_DIR_INFO
+    fn info(&self) -> &str {
+        &FUNCTION_DIR_INFO
     }
 
-    fn name(&self) -> &'static str {
+    fn name(&self) -> &str {
         "dir"
     }
 
-    fn description(&self) -> &'static str {
+    fn description(&self) -> &str {
         "Lists the files & directories in the current directory."
     }
 }
@@ -0,0 +1,264 @@
+use crate::function::{AlpacaFunction, AlpacaFunctions};
+use crate::http_transport::{AlpacaHttpRequest, AlpacaHttpTransport};
+use crate::openapi::{AlpacaOpenApiLocation, AlpacaOpenApiOperation, BODY_PARAMETER};
+use crate::tool_model::ToolParameter;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+// ===
+// AlpacaFunctionOpenApi
+// ===
+/// A function that invokes a single operation imported from an OpenAPI document.
+pub struct AlpacaFunctionOpenApi {
+    operation: AlpacaOpenApiOperation,
+    base_url: String,
+    transport: Arc<dyn AlpacaHttpTransport>,
+    info: String,
+}
+
+impl AlpacaFunctionOpenApi {
+    /// Creates a function for an operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - The imported operation
+    /// * `base_url` - The URL the operation path is appended to
+    /// * `transport` - The transport used to send the request
+    pub fn new(
+        operation: AlpacaOpenApiOperation,
+        base_url: &str,
+        transport: Arc<dyn AlpacaHttpTransport>,
+    ) -> Self {
+        let info = operation_info(&operation);
+
+        AlpacaFunctionOpenApi {
+            operation,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            transport,
+            info,
+        }
+    }
+
+    /// Gets the operation invoked by this function.
+    pub fn operation(&self) -> &AlpacaOpenApiOperation {
+        &self.operation
+    }
+
+    /// Builds the HTTP request for a call with the given arguments.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AlpacaHttpRequest)` - The request to send
+    /// * `Err(String)` - A description of the missing or invalid argument
+    pub fn build_request(&self, arguments: &Value) -> Result<AlpacaHttpRequest, String> {
+        let mut path = self.operation.path.clone();
+        let mut query = Vec::new();
+        let mut headers = Vec::new();
+
+        for parameter in &self.operation.parameters {
+            let value = match arguments.get(&parameter.name) {
+                Some(Value::Null) | None if parameter.required => {
+                    return Err(format!("Missing required argument '{}'.", parameter.name));
+                }
+                Some(Value::Null) | None => continue,
+                Some(value) => value,
+            };
+
+            match parameter.location {
+                AlpacaOpenApiLocation::Path => {
+                    let placeholder = format!("{{{}}}", parameter.name);
+                    path = path.replace(&placeholder, &percent_encode(&argument_text(value)));
+                }
+                AlpacaOpenApiLocation::Query => {
+                    // Arrays are sent as repeated keys, the OpenAPI default for query parameters
+                    let values = match value {
+                        Value::Array(values) => values.iter().collect(),
+                        value => vec![value],
+                    };
+                    for value in values {
+                        query.push(format!(
+                            "{}={}",
+                            percent_encode(&parameter.name),
+                            percent_encode(&argument_text(value))
+                        ));
+                    }
+                }
+                AlpacaOpenApiLocation::Header => {
+                    headers.push((parameter.name.clone(), argument_text(value)));
+                }
+            }
+        }
+
+        let mut url = format!("{}{}", self.base_url, path);
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&query.join("&"));
+        }
+
+        let mut request = AlpacaHttpRequest::new(&self.operation.method, &url);
+        request.headers = headers;
+        request = request.header("Accept", "application/json");
+
+        if let Some(body_schema) = &self.operation.body {
+            match arguments.get(BODY_PARAMETER) {
+                Some(Value::Null) | None if body_schema.is_required() => {
+                    return Err(format!("Missing required argument '{}'.", BODY_PARAMETER));
+                }
+                Some(Value::Null) | None => {}
+                Some(body) => {
+                    let body = serde_json::to_vec(body).map_err(|e| e.to_string())?;
+                    request = request
+                        .header("Content-Type", "application/json")
+                        .body(body);
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+impl AlpacaFunction for AlpacaFunctionOpenApi {
+    fn execute(&self, arguments: Option<&Value>) -> Option<String> {
+        let empty_args = json!({});
+        let arguments = arguments.unwrap_or(&empty_args);
+
+        let request = match self.build_request(arguments) {
+            Ok(request) => request,
+            Err(error) => {
+                let error = AlpacaFunctions::error(self.name(), &error);
+                return Some(format!("{}{}\n", error, self.info()));
+            }
+        };
+
+        let response = match self.transport.send(&request) {
+            Ok(response) => response,
+            Err(error) => {
+                let error = format!("The request could not be sent: {}.", error);
+                return Some(AlpacaFunctions::error(self.name(), &error));
+            }
+        };
+
+        // Return JSON bodies as JSON, and anything else as text
+        let body = serde_json::from_slice::<Value>(&response.body)
+            .unwrap_or_else(|_| Value::String(response.text()));
+
+        if !response.is_success() {
+            let error = format!(
+                "The request failed with status {}: {}",
+                response.status,
+                response.text()
+            );
+            return Some(AlpacaFunctions::error(self.name(), &error));
+        }
+
+        let output = json!({
+            "status": response.status,
+            "body": body,
+        });
+
+        Some(AlpacaFunctions::ok(self.name(), &output))
+    }
+
+    fn info(&self) -> &str {
+        &self.info
+    }
+
+    fn name(&self) -> &str {
+        &self.operation.name
+    }
+
+    fn description(&self) -> &str {
+        &self.operation.description
+    }
+}
+
+// ---
+
+fn operation_info(operation: &AlpacaOpenApiOperation) -> String {
+    let mut info = format!("\n# `{}`\n\n", operation.name);
+    if !operation.description.is_empty() {
+        info.push_str(&format!("{}\n\n", operation.description));
+    }
+    info.push_str(&format!("`{} {}`\n\n", operation.method, operation.path));
+
+    let mut parameters: Vec<(&str, &str, &ToolParameter)> = operation
+        .parameters
+        .iter()
+        .map(|parameter| {
+            (
+                parameter.name.as_str(),
+                parameter.location.as_str(),
+                &parameter.schema,
+            )
+        })
+        .collect();
+    if let Some(body) = &operation.body {
+        parameters.push((BODY_PARAMETER, "body", body));
+    }
+
+    if !parameters.is_empty() {
+        info.push_str("Parameters:\n");
+        for (name, location, parameter) in &parameters {
+            let optional = if parameter.is_required() {
+                ""
+            } else {
+                ", optional"
+            };
+            info.push_str(&format!(
+                "- `{}` ({}, {}{})\n",
+                name,
+                parameter.kind().to_string(),
+                location,
+                optional
+            ));
+        }
+        info.push('\n');
+    }
+
+    if !operation.responses.is_empty() {
+        info.push_str("Responses:\n");
+        for (status, description) in &operation.responses {
+            info.push_str(&format!("- `{}`: {}\n", status, description));
+        }
+        info.push('\n');
+    }
+
+    let example_arguments: serde_json::Map<String, Value> = parameters
+        .iter()
+        .filter(|(_, _, parameter)| parameter.is_required())
+        .map(|(name, _, parameter)| (name.to_string(), parameter.to_json_schema()["type"].clone()))
+        .collect();
+    let example = json!({
+        "action": "invoke_function",
+        "function": operation.name,
+        "arguments": example_arguments,
+    });
+
+    info.push_str(&format!(
+        "example call (replace each type with a value):\n```json\n{}\n```\n",
+        serde_json::to_string_pretty(&example).unwrap_or_default()
+    ));
+
+    info
+}
+
+fn argument_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
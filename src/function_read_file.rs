@@ -1,9 +1,13 @@
+use crate::action_read_file::{ReadFileArguments, read_file};
 use crate::function::{AlpacaFunction, AlpacaFunctions};
+use std::path::Path;
 
 const READ_FILE_INFO: &str = r#"
 # `read_file` usage
 
-This function outputs the contents of the specified text file.
+This function outputs the contents of the specified text file, with line numbers.
+Large files are truncated; use `start_line` with `end_line` or `limit` to read
+the rest of the file, as suggested by `next_start_line` in the output.
 
 example call:
 ```json
@@ -11,7 +15,9 @@ example call:
     "action": "invoke_function",
     "function": "read_file",
     "arguments": {
-        "file_name": "example.txt"
+        "file_name": "example.txt",
+        "start_line": 1,
+        "limit": 100
     }
 }
 ```
@@ -32,59 +38,27 @@ impl AlpacaFunctionReadFile {
 // Implement the AlpacaFunction trait for AlpacaFunctionReadFile
 impl AlpacaFunction for AlpacaFunctionReadFile {
     fn execute(&self, arguments: Option<&serde_json::Value>) -> Option<String> {
-        // Extract file_name from arguments
-        if let Some(args) = arguments {
-            if let Some(file_name) = args.get("file_name").and_then(|v| v.as_str()) {
-                // Attempt to read file contents
-                match std::fs::read_to_string(file_name) {
-                    Ok(content) => {
-                        // Update the response with the file content
-                        // response["output"] = serde_json::Value::String(content);
-                        let output = serde_json::Value::String(content);
-                        return Some(AlpacaFunctions::ok(self.name(), &output));
-                    }
-                    Err(e) => {
-                        // Return the eror message if file reading fails
-                        let error = format!(
-                            "Failed to read file '{}': {}.\nPlease ensure the file name is correct and try again.",
-                            file_name,
-                            e.to_string()
-                        );
-                        return Some(AlpacaFunctions::error(self.name(), &error));
-                    }
-                }
-            }
+        let arguments: ReadFileArguments = match AlpacaFunctions::arguments(self, arguments) {
+            Ok(arguments) => arguments,
+            Err(error) => return Some(error),
+        };
 
-            // if the 'file_name' field is not provided, return an error
-            let error = AlpacaFunctions::error(
-                self.name(),
-                "The 'file_name' field is missing from the request. Please review the usage and try again.",
-            );
-            let usage = self.info();
-            let response = format!("{}{}\n", error, usage);
-            return Some(response);
+        let path = Path::new(&arguments.file_name);
+        match read_file(path, &arguments.file_name, &arguments) {
+            Ok(output) => Some(AlpacaFunctions::ok(self.name(), &output)),
+            Err(error) => Some(AlpacaFunctions::error(self.name(), &error)),
         }
-
-        // If 'arguments' is not provided, return an error
-        let error = AlpacaFunctions::error(
-            self.name(),
-            "The 'arguments' field is missing from the request. Please review the usage and try again.",
-        );
-
-        let usage = self.info();
-        let response = format!("{}{}\n", error, usage);
-        Some(response)
     }
 
-    fn info(&self) -> &'static str {
+    fn info(&self) -> &str {
         READ_FILE_INFO
     }
 
-    fn name(&self) -> &'static str {
+    fn name(&self) -> &str {
         "read_file"
     }
 
-    fn description(&self) -> &'static str {
+    fn description(&self) -> &str {
         "Outputs the contents of the specified text file."
     }
 }
@@ -0,0 +1,940 @@
+use crate::command::{AlpacaCommandConfig, run};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The git commands `AlpacaGitRepository` runs. None of them write to the repository.
+const READ_ONLY_COMMANDS: [&str; 7] = [
+    "blame",
+    "diff",
+    "for-each-ref",
+    "log",
+    "rev-parse",
+    "show",
+    "status",
+];
+
+/// Options given to every git command: no pager, pathspecs taken literally,
+/// no file system monitor process, and no transport, so that a partial clone
+/// cannot fetch missing objects.
+const GLOBAL_OPTIONS: [&str; 12] = [
+    "--no-pager",
+    "--literal-pathspecs",
+    "-c",
+    "core.fsmonitor=false",
+    "-c",
+    "core.quotePath=false",
+    "-c",
+    "color.ui=never",
+    "-c",
+    "protocol.allow=never",
+    "-c",
+    "log.showSignature=false",
+];
+
+/// The format of a commit in `git log` and `git show`, one field per unit separator.
+const COMMIT_FORMAT: &str = "--format=%H%x1f%P%x1f%an%x1f%ae%x1f%aI%x1f%s%x1f%b%x1e";
+
+/// The longest a git command may run.
+const GIT_TIMEOUT: Duration = Duration::from_secs(30);
+/// The most bytes read from the output of a git command.
+const MAX_OUTPUT_BYTES: usize = 8 * 1024 * 1024;
+
+// ===
+// AlpacaGitRepository
+// ===
+/// A local git repository, inspected by running read-only `git` commands.
+///
+/// Only the commands in `READ_ONLY_COMMANDS` are run, and never with options
+/// that write, so the repository, its index and its refs are left untouched.
+/// Nothing is fetched over the network, and git runs none of the commands the
+/// repository configures for its filters, external diffs or submodules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaGitRepository {
+    /// The top-level directory of the working tree
+    pub root: PathBuf,
+    /// The directory git may not search above
+    boundary: PathBuf,
+}
+
+/// The state of the working tree and the index, from `git status`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AlpacaGitStatus {
+    /// The checked out branch, or `None` when the `HEAD` is detached
+    pub branch: Option<String>,
+    /// The commit of the `HEAD`, or `None` before the first commit
+    pub commit: Option<String>,
+    pub upstream: Option<String>,
+    /// The commits the branch is ahead of its upstream
+    pub ahead: usize,
+    /// The commits the branch is behind its upstream
+    pub behind: usize,
+    /// The changes in the index
+    pub staged: Vec<AlpacaGitChange>,
+    /// The changes in the working tree that are not in the index
+    pub unstaged: Vec<AlpacaGitChange>,
+    pub untracked: Vec<PathBuf>,
+    /// The files with merge conflicts
+    pub conflicted: Vec<PathBuf>,
+}
+
+/// A changed file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaGitChange {
+    pub path: PathBuf,
+    /// `modified`, `added`, `deleted`, `renamed`, `copied` or `type changed`
+    pub status: &'static str,
+    /// The path before a rename or copy
+    pub original_path: Option<PathBuf>,
+}
+
+/// A commit, from `git log` or `git show`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaGitCommit {
+    pub hash: String,
+    pub parents: Vec<String>,
+    pub author: String,
+    pub email: String,
+    /// The author date, in the strict ISO 8601 format
+    pub date: String,
+    pub subject: String,
+    /// The message after the subject, if any
+    pub body: Option<String>,
+}
+
+/// The lines added and removed in a file, from `git diff --numstat`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaGitFileStat {
+    pub path: PathBuf,
+    /// The path before a rename or copy
+    pub original_path: Option<PathBuf>,
+    /// The lines added, or `None` for a binary file
+    pub added: Option<usize>,
+    /// The lines removed, or `None` for a binary file
+    pub removed: Option<usize>,
+}
+
+/// The changes between two states of the repository.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AlpacaGitDiff {
+    pub files: Vec<AlpacaGitFileStat>,
+    /// The unified diff
+    pub patch: String,
+}
+
+/// A line of a file with the commit that last changed it, from `git blame`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaGitBlameLine {
+    /// The one-based line number
+    pub line: usize,
+    /// The commit, or zeros for a change that is not committed yet
+    pub commit: String,
+    pub author: String,
+    /// The author date, as `YYYY-MM-DD`
+    pub date: String,
+    pub summary: String,
+    pub text: String,
+}
+
+/// A local or remote-tracking branch, from `git for-each-ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaGitBranch {
+    /// The short name, such as `main` or `origin/main`
+    pub name: String,
+    pub remote: bool,
+    /// Whether the branch is checked out
+    pub current: bool,
+    pub commit: String,
+    pub upstream: Option<String>,
+    /// How the branch compares with its upstream, such as `ahead 1, behind 2`
+    pub track: Option<String>,
+    /// The committer date of the last commit, in the strict ISO 8601 format
+    pub date: String,
+    pub subject: String,
+}
+
+impl AlpacaGitRepository {
+    /// Finds the repository containing `start`.
+    ///
+    /// git does not search above `boundary`, and a working tree whose top
+    /// level is above it is refused, so that the files and history outside
+    /// `boundary` stay out of reach.
+    pub fn discover(start: &Path, boundary: &Path) -> Result<Self, String> {
+        let start = if start.is_file() {
+            start.parent().unwrap_or(start)
+        } else {
+            start
+        };
+        let output =
+            run_git(start, boundary, &["rev-parse", "--show-toplevel"]).map_err(|error| {
+                // git itself could not be started
+                if !error.starts_with("'git") {
+                    return error;
+                }
+                "No git repository was found in this directory or above it.".to_string()
+            })?;
+        let root = PathBuf::from(output.trim_end_matches('\n'));
+        let root = root.canonicalize().unwrap_or(root);
+        if !root.starts_with(boundary) {
+            return Err(
+                "The git repository starts above the accessible directory, so it cannot be inspected."
+                    .to_string(),
+            );
+        }
+
+        Ok(AlpacaGitRepository {
+            root,
+            boundary: boundary.to_path_buf(),
+        })
+    }
+
+    /// Resolves a revision, such as `HEAD~2`, `main` or a short hash, to the hash of a commit.
+    pub fn resolve_commit(&self, revision: &str) -> Result<String, String> {
+        check_revision(revision)?;
+        let object = format!("{}^{{commit}}", revision);
+        self.git(&["rev-parse", "--verify", "--quiet", &object])
+            .ok()
+            .map(|output| output.trim().to_string())
+            .filter(|hash| !hash.is_empty())
+            .ok_or_else(|| format!("'{}' is not a commit in this repository.", revision))
+    }
+
+    /// Reads the state of the working tree and the index, optionally under `paths`.
+    pub fn status(&self, paths: &[PathBuf]) -> Result<AlpacaGitStatus, String> {
+        let pathspecs = self.pathspecs(paths)?;
+        // Submodules are left out, as git would inspect them with their own configuration
+        let mut args = vec![
+            "status",
+            "--porcelain=v2",
+            "--branch",
+            "-z",
+            "--ignore-submodules=all",
+        ];
+        push_pathspecs(&mut args, &pathspecs);
+        let output = self.git(&args)?;
+
+        let mut status = AlpacaGitStatus::default();
+        let mut records = output.split('\0');
+        while let Some(record) = records.next() {
+            if let Some(header) = record.strip_prefix("# ") {
+                let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+                match key {
+                    "branch.oid" if value != "(initial)" => status.commit = Some(value.to_string()),
+                    "branch.head" if value != "(detached)" => {
+                        status.branch = Some(value.to_string())
+                    }
+                    "branch.upstream" => status.upstream = Some(value.to_string()),
+                    "branch.ab" => {
+                        for count in value.split(' ') {
+                            if let Some(ahead) = count.strip_prefix('+') {
+                                status.ahead = ahead.parse().unwrap_or(0);
+                            } else if let Some(behind) = count.strip_prefix('-') {
+                                status.behind = behind.parse().unwrap_or(0);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            let (kind, rest) = record.split_at(record.len().min(1));
+            let rest = rest.strip_prefix(' ').unwrap_or(rest);
+            match kind {
+                "1" | "2" => {
+                    // `1 XY sub mH mI mW hH hI path`, and `2` adds a score
+                    // before the path, then the original path as the next record
+                    let fields = if kind == "1" { 8 } else { 9 };
+                    let parts: Vec<&str> = rest.splitn(fields, ' ').collect();
+                    let (Some(codes), Some(path)) = (parts.first(), parts.get(fields - 1)) else {
+                        continue;
+                    };
+                    let original_path = if kind == "2" {
+                        records.next().map(|original| self.root.join(original))
+                    } else {
+                        None
+                    };
+                    let mut codes = codes.chars();
+                    let (staged, unstaged) = (codes.next(), codes.next());
+                    for (code, changes) in [
+                        (staged, &mut status.staged),
+                        (unstaged, &mut status.unstaged),
+                    ] {
+                        if let Some(change) = code.and_then(change_status) {
+                            changes.push(AlpacaGitChange {
+                                path: self.root.join(path),
+                                status: change,
+                                original_path: original_path
+                                    .clone()
+                                    .filter(|_| matches!(code, Some('R' | 'C'))),
+                            });
+                        }
+                    }
+                }
+                "u" => {
+                    if let Some(path) = rest.splitn(10, ' ').nth(9) {
+                        status.conflicted.push(self.root.join(path));
+                    }
+                }
+                "?" => status.untracked.push(self.root.join(rest)),
+                _ => {}
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Lists the commits reachable from `revision`, or from `HEAD`, newest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `revision` - Where to start, `HEAD` if `None`
+    /// * `paths` - Only the commits changing these files or directories, if any
+    /// * `limit` - The most commits returned
+    pub fn log(
+        &self,
+        revision: Option<&str>,
+        paths: &[PathBuf],
+        limit: usize,
+    ) -> Result<Vec<AlpacaGitCommit>, String> {
+        let start = match revision {
+            Some(revision) => self.resolve_commit(revision)?,
+            None => match self.resolve_commit("HEAD") {
+                Ok(hash) => hash,
+                // No commit yet
+                Err(_) => return Ok(Vec::new()),
+            },
+        };
+        let pathspecs = self.pathspecs(paths)?;
+        let count = format!("--max-count={}", limit);
+        let mut args = vec!["log", COMMIT_FORMAT, count.as_str(), start.as_str()];
+        push_pathspecs(&mut args, &pathspecs);
+
+        Ok(self
+            .git(&args)?
+            .split('\x1e')
+            .filter_map(parse_commit)
+            .collect())
+    }
+
+    /// Reads a commit with the changes it made.
+    pub fn show(&self, revision: &str) -> Result<(AlpacaGitCommit, AlpacaGitDiff), String> {
+        let hash = self.resolve_commit(revision)?;
+        let commit = self
+            .git(&["show", "--no-patch", COMMIT_FORMAT, hash.as_str()])?
+            .split('\x1e')
+            .find_map(parse_commit)
+            .ok_or_else(|| format!("Failed to read the commit '{}'.", revision))?;
+
+        let options = [
+            "show",
+            "--format=",
+            "--find-renames",
+            "--no-ext-diff",
+            "--no-textconv",
+            "--ignore-submodules=all",
+        ];
+        let mut numstat = options.to_vec();
+        numstat.extend(["--numstat", "-z", hash.as_str()]);
+        let mut patch = options.to_vec();
+        patch.extend(["--patch", hash.as_str()]);
+
+        let diff = AlpacaGitDiff {
+            files: self.parse_numstat(&self.git(&numstat)?),
+            patch: self.git(&patch)?,
+        };
+        Ok((commit, diff))
+    }
+
+    /// Compares two states of the repository.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The commit to compare from; without it, the index, or `HEAD` when `staged`
+    /// * `to` - The commit to compare to; without it, the working tree, or the index when `staged`
+    /// * `staged` - Whether the index is compared instead of the working tree
+    /// * `paths` - Only the changes to these files or directories, if any
+    pub fn diff(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        staged: bool,
+        paths: &[PathBuf],
+    ) -> Result<AlpacaGitDiff, String> {
+        let mut revisions = Vec::new();
+        match (from, to) {
+            (None, Some(_)) => {
+                return Err("Comparing to a commit needs a commit to compare from.".to_string());
+            }
+            (Some(_), Some(_)) if staged => {
+                return Err("The index cannot be compared when comparing two commits.".to_string());
+            }
+            _ => {}
+        }
+        for revision in [from, to].into_iter().flatten() {
+            revisions.push(self.resolve_commit(revision)?);
+        }
+        let pathspecs = self.pathspecs(paths)?;
+
+        let mut options = vec![
+            "diff",
+            "--find-renames",
+            "--no-ext-diff",
+            "--no-textconv",
+            "--ignore-submodules=all",
+        ];
+        if staged {
+            options.push("--cached");
+        }
+        options.extend(revisions.iter().map(String::as_str));
+
+        let mut numstat = options.clone();
+        numstat.extend(["--numstat", "-z"]);
+        push_pathspecs(&mut numstat, &pathspecs);
+        let mut patch = options;
+        patch.push("--patch");
+        push_pathspecs(&mut patch, &pathspecs);
+
+        Ok(AlpacaGitDiff {
+            files: self.parse_numstat(&self.git(&numstat)?),
+            patch: self.git(&patch)?,
+        })
+    }
+
+    /// Finds the commit that last changed each line of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The file
+    /// * `start_line` - The first line, one-based
+    /// * `end_line` - The last line
+    /// * `revision` - The commit to start from, or the working tree if `None`
+    pub fn blame(
+        &self,
+        file: &Path,
+        start_line: usize,
+        end_line: usize,
+        revision: Option<&str>,
+    ) -> Result<Vec<AlpacaGitBlameLine>, String> {
+        let hash = match revision {
+            Some(revision) => Some(self.resolve_commit(revision)?),
+            None => None,
+        };
+        let pathspecs = self.pathspecs(&[file.to_path_buf()])?;
+        let range = format!("-L{},{}", start_line, end_line);
+        let mut args = vec!["blame", "--porcelain", "--no-textconv", range.as_str()];
+        if let Some(hash) = &hash {
+            args.push(hash.as_str());
+        }
+        push_pathspecs(&mut args, &pathspecs);
+        let output = self.git(&args)?;
+
+        // Details are given the first time a commit appears
+        let mut commits: HashMap<String, (String, String, String)> = HashMap::new();
+        let mut lines = Vec::new();
+        let mut current: Option<(String, usize)> = None;
+        let (mut author, mut time, mut zone, mut summary) = (None, None, None, None);
+        for line in output.lines() {
+            if let Some(text) = line.strip_prefix('\t') {
+                let Some((commit, number)) = current.take() else {
+                    continue;
+                };
+                if let (Some(author), Some(summary)) = (author.take(), summary.take()) {
+                    let date = format_date(time.take().unwrap_or(0), zone.take().unwrap_or(""));
+                    commits.insert(commit.clone(), (author, date, summary));
+                }
+                let (author, date, summary) = commits.get(&commit).cloned().unwrap_or_default();
+                lines.push(AlpacaGitBlameLine {
+                    line: number,
+                    commit,
+                    author,
+                    date,
+                    summary,
+                    text: text.to_string(),
+                });
+            } else if current.is_none() {
+                // `<commit> <original line> <final line> [<lines in group>]`
+                let mut fields = line.split(' ');
+                if let (Some(commit), Some(_), Some(number)) =
+                    (fields.next(), fields.next(), fields.next())
+                {
+                    current = Some((commit.to_string(), number.parse().unwrap_or(0)));
+                }
+            } else if let Some((key, value)) = line.split_once(' ') {
+                match key {
+                    "author" => author = Some(value.to_string()),
+                    "author-time" => time = value.parse().ok(),
+                    "author-tz" => zone = Some(value),
+                    "summary" => summary = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(lines)
+    }
+
+    /// Lists the local branches, and the remote-tracking ones if asked, the most recent first.
+    pub fn branches(&self, include_remote: bool) -> Result<Vec<AlpacaGitBranch>, String> {
+        let mut args = vec![
+            "for-each-ref",
+            "--sort=-committerdate",
+            "--format=%(HEAD)%1f%(refname)%1f%(objectname)%1f%(upstream:short)%1f%(upstream:track,nobracket)%1f%(committerdate:iso-strict)%1f%(contents:subject)",
+            "refs/heads",
+        ];
+        if include_remote {
+            args.push("refs/remotes");
+        }
+
+        let output = self.git(&args)?;
+        let branches = output
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split('\x1f').collect();
+                let [head, reference, commit, upstream, track, date, subject] = fields[..] else {
+                    return None;
+                };
+                let (name, remote) = match reference.strip_prefix("refs/heads/") {
+                    Some(name) => (name, false),
+                    None => (reference.strip_prefix("refs/remotes/")?, true),
+                };
+                // `origin/HEAD` only points to another branch
+                if remote && name.ends_with("/HEAD") {
+                    return None;
+                }
+                Some(AlpacaGitBranch {
+                    name: name.to_string(),
+                    remote,
+                    current: head == "*",
+                    commit: commit.to_string(),
+                    upstream: (!upstream.is_empty()).then(|| upstream.to_string()),
+                    track: (!track.is_empty()).then(|| track.to_string()),
+                    date: date.to_string(),
+                    subject: subject.to_string(),
+                })
+            })
+            .collect();
+        Ok(branches)
+    }
+
+    /// Runs a read-only git command in the top-level directory.
+    fn git(&self, args: &[&str]) -> Result<String, String> {
+        run_git(&self.root, &self.boundary, args)
+    }
+
+    /// Converts absolute paths to pathspecs relative to the top-level directory.
+    fn pathspecs(&self, paths: &[PathBuf]) -> Result<Vec<String>, String> {
+        paths
+            .iter()
+            .map(|path| match path.strip_prefix(&self.root) {
+                Ok(relative) if relative.as_os_str().is_empty() => Ok(".".to_string()),
+                Ok(relative) => Ok(relative.to_string_lossy().to_string()),
+                Err(_) => Err(format!(
+                    "'{}' is outside the git repository.",
+                    path.display()
+                )),
+            })
+            .collect()
+    }
+
+    /// Reads the output of `--numstat -z`, where a rename is given as an
+    /// empty path followed by the original and the new path.
+    fn parse_numstat(&self, output: &str) -> Vec<AlpacaGitFileStat> {
+        let mut files = Vec::new();
+        let mut records = output.split('\0');
+        while let Some(record) = records.next() {
+            let mut fields = record.trim_start_matches('\n').splitn(3, '\t');
+            let (Some(added), Some(removed), Some(path)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let (path, original_path) = if path.is_empty() {
+                let original = records.next().unwrap_or_default();
+                let path = records.next().unwrap_or_default();
+                (path, Some(self.root.join(original)))
+            } else {
+                (path, None)
+            };
+            files.push(AlpacaGitFileStat {
+                path: self.root.join(path),
+                original_path,
+                added: added.parse().ok(),
+                removed: removed.parse().ok(),
+            });
+        }
+        files
+    }
+}
+
+/// Runs a git command from the list of read-only commands.
+fn run_git(cwd: &Path, boundary: &Path, args: &[&str]) -> Result<String, String> {
+    let command = args.first().copied().unwrap_or_default();
+    if !READ_ONLY_COMMANDS.contains(&command) {
+        return Err(format!("'git {}' is not a read-only command.", command));
+    }
+
+    let mut env = HashMap::new();
+    env.insert("GIT_TERMINAL_PROMPT".to_string(), "0".to_string());
+    env.insert("GIT_OPTIONAL_LOCKS".to_string(), "0".to_string());
+    // Only the repository's own configuration is read
+    env.insert("GIT_CONFIG_NOSYSTEM".to_string(), "1".to_string());
+    env.insert("GIT_CONFIG_GLOBAL".to_string(), "/dev/null".to_string());
+    if let Some(parent) = boundary.parent() {
+        env.insert(
+            "GIT_CEILING_DIRECTORIES".to_string(),
+            parent.to_string_lossy().to_string(),
+        );
+    }
+    let config = AlpacaCommandConfig {
+        timeout: GIT_TIMEOUT,
+        max_output_bytes: MAX_OUTPUT_BYTES,
+        ..AlpacaCommandConfig::default()
+    };
+
+    let mut options: Vec<String> = GLOBAL_OPTIONS.iter().map(|arg| arg.to_string()).collect();
+    options.extend(filter_overrides(cwd, &env, &config)?);
+    options.extend(args.iter().map(|arg| arg.to_string()));
+
+    let output = run("git", &options, cwd, &env, &config)?;
+    if output.timed_out {
+        return Err(format!(
+            "'git {}' took longer than {} seconds and was stopped.",
+            command,
+            GIT_TIMEOUT.as_secs()
+        ));
+    }
+    if output.exit_code != Some(0) {
+        let message = output.stderr.text.trim();
+        return Err(if message.is_empty() {
+            format!("'git {}' failed.", command)
+        } else {
+            format!("'git {}' failed: {}", command, message)
+        });
+    }
+    Ok(output.stdout.text)
+}
+
+/// Lists the options that turn off every filter driver in the configuration
+/// of the repository at `cwd`.
+///
+/// A filter runs the commands set in `filter.<name>.clean`, `smudge` and
+/// `process` whenever git reads a file of the working tree, so a repository
+/// could otherwise make `git status` or `git diff` run anything.
+fn filter_overrides(
+    cwd: &Path,
+    env: &HashMap<String, String>,
+    config: &AlpacaCommandConfig,
+) -> Result<Vec<String>, String> {
+    let args: Vec<String> = [
+        "config",
+        "--null",
+        "--name-only",
+        "--get-regexp",
+        r"^filter\.",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    let output = run("git", &args, cwd, env, config)?;
+    match output.exit_code {
+        Some(0) => {}
+        // Nothing matched
+        Some(1) => return Ok(Vec::new()),
+        _ => {
+            return Err(format!(
+                "Failed to read the git configuration: {}",
+                output.stderr.text.trim()
+            ));
+        }
+    }
+
+    let mut names: Vec<&str> = output
+        .stdout
+        .text
+        .split('\0')
+        .filter_map(|key| key.strip_prefix("filter.")?.rsplit_once('.'))
+        .map(|(name, _)| name)
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let mut options = Vec::new();
+    for name in names {
+        // `-c` takes the name up to the first `=`
+        if name.contains('=') {
+            return Err(format!(
+                "The git configuration has a filter named '{}', which cannot be turned off.",
+                name
+            ));
+        }
+        for key in ["clean", "smudge", "process"] {
+            options.push("-c".to_string());
+            options.push(format!("filter.{}.{}=", name, key));
+        }
+        options.push("-c".to_string());
+        options.push(format!("filter.{}.required=false", name));
+    }
+    Ok(options)
+}
+
+/// Refuses revisions that git would take as options.
+fn check_revision(revision: &str) -> Result<(), String> {
+    if revision.is_empty()
+        || revision.starts_with('-')
+        || revision
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(format!("'{}' is not a valid revision.", revision));
+    }
+    Ok(())
+}
+
+fn push_pathspecs<'a>(args: &mut Vec<&'a str>, pathspecs: &'a [String]) {
+    if !pathspecs.is_empty() {
+        args.push("--");
+        args.extend(pathspecs.iter().map(String::as_str));
+    }
+}
+
+/// Reads a commit written with `COMMIT_FORMAT`.
+fn parse_commit(record: &str) -> Option<AlpacaGitCommit> {
+    let fields: Vec<&str> = record.trim_start_matches('\n').split('\x1f').collect();
+    let [hash, parents, author, email, date, subject, body] = fields[..] else {
+        return None;
+    };
+    let body = body.trim();
+    Some(AlpacaGitCommit {
+        hash: hash.to_string(),
+        parents: parents.split_whitespace().map(str::to_string).collect(),
+        author: author.to_string(),
+        email: email.to_string(),
+        date: date.to_string(),
+        subject: subject.to_string(),
+        body: (!body.is_empty()).then(|| body.to_string()),
+    })
+}
+
+/// Describes a status code of `git status --porcelain=v2`.
+fn change_status(code: char) -> Option<&'static str> {
+    match code {
+        'M' => Some("modified"),
+        'T' => Some("type changed"),
+        'A' => Some("added"),
+        'D' => Some("deleted"),
+        'R' => Some("renamed"),
+        'C' => Some("copied"),
+        _ => None,
+    }
+}
+
+/// Formats a unix time as `YYYY-MM-DD` in a time zone such as `+0200`.
+fn format_date(time: i64, zone: &str) -> String {
+    let offset = match zone.len() {
+        5 => {
+            let hours: i64 = zone[1..3].parse().unwrap_or(0);
+            let minutes: i64 = zone[3..5].parse().unwrap_or(0);
+            let offset = hours * 3600 + minutes * 60;
+            if zone.starts_with('-') {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => 0,
+    };
+
+    // The proleptic Gregorian calendar, from the number of days since 1970-01-01
+    let days = (time + offset).div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// ===
+// AlpacaGitRepository Tests
+// ===
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process::Command;
+
+    /// Runs git in `directory` to set up a test repository.
+    fn git(directory: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args([
+                "-c",
+                "user.name=Test",
+                "-c",
+                "user.email=test@example.com",
+                "-c",
+                "commit.gpgsign=false",
+            ])
+            .args(args)
+            .current_dir(directory)
+            .env("GIT_AUTHOR_DATE", "2024-03-01T12:00:00+00:00")
+            .env("GIT_COMMITTER_DATE", "2024-03-01T12:00:00+00:00")
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    fn repository() -> (tempfile::TempDir, AlpacaGitRepository) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        git(&root, &["init", "--quiet", "--initial-branch=main"]);
+        fs::write(root.join("notes.txt"), "one\ntwo\nthree\nfour\nfive\n").unwrap();
+        git(&root, &["add", "notes.txt"]);
+        git(&root, &["commit", "--quiet", "-m", "Add notes"]);
+        fs::write(root.join("notes.txt"), "one\n2\nthree\nfour\nfive\n").unwrap();
+        git(
+            &root,
+            &["commit", "--quiet", "-am", "Number two\n\nWith a digit."],
+        );
+
+        let repository = AlpacaGitRepository::discover(&root, &root).unwrap();
+        (temp_dir, repository)
+    }
+
+    /// Tests reading the history and the changes of a commit.
+    #[test]
+    fn test_log_and_show() {
+        let (_temp_dir, repository) = repository();
+
+        let commits = repository.log(None, &[], 10).unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].subject, "Number two");
+        assert_eq!(commits[0].body.as_deref(), Some("With a digit."));
+        assert_eq!(commits[0].parents, vec![commits[1].hash.clone()]);
+        assert_eq!(commits[1].author, "Test");
+
+        let (commit, diff) = repository.show("HEAD").unwrap();
+        assert_eq!(commit.hash, commits[0].hash);
+        assert_eq!(diff.files.len(), 1);
+        assert_eq!(diff.files[0].path, repository.root.join("notes.txt"));
+        assert_eq!(
+            (diff.files[0].added, diff.files[0].removed),
+            (Some(1), Some(1))
+        );
+        assert!(diff.patch.contains("-two\n+2\n"));
+
+        assert!(repository.show("--output=x").is_err());
+        assert!(repository.show("missing").is_err());
+    }
+
+    /// Tests the status and the differences of the working tree.
+    #[test]
+    fn test_status_and_diff() {
+        let (_temp_dir, repository) = repository();
+        let root = repository.root.clone();
+        fs::write(root.join("notes.txt"), "zero\none\n2\nthree\nfour\nfive\n").unwrap();
+        fs::write(root.join("new.txt"), "new\n").unwrap();
+        git(&root, &["mv", "notes.txt", "renamed.txt"]);
+
+        let status = repository.status(&[]).unwrap();
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.untracked, vec![root.join("new.txt")]);
+        assert_eq!(status.staged.len(), 1);
+        assert_eq!(status.staged[0].status, "renamed");
+        assert_eq!(status.staged[0].path, root.join("renamed.txt"));
+        assert_eq!(status.staged[0].original_path, Some(root.join("notes.txt")));
+        assert_eq!(status.unstaged.len(), 1);
+        assert_eq!(status.unstaged[0].status, "modified");
+        assert_eq!(status.unstaged[0].original_path, None);
+
+        let diff = repository.diff(Some("HEAD"), None, false, &[]).unwrap();
+        assert_eq!(diff.files.len(), 1);
+        assert_eq!(diff.files[0].original_path, Some(root.join("notes.txt")));
+        assert!(diff.patch.contains("+zero\n"));
+
+        let diff = repository
+            .diff(Some("HEAD~1"), Some("HEAD"), false, &[])
+            .unwrap();
+        assert!(diff.patch.contains("+2\n"));
+        assert!(repository.diff(None, Some("HEAD"), false, &[]).is_err());
+    }
+
+    /// Tests that the filters configured in the repository are not run.
+    #[test]
+    fn test_filters_not_run() {
+        let (temp_dir, repository) = repository();
+        let root = repository.root.clone();
+        let marker = temp_dir.path().join("marker");
+        let command = format!("touch '{}'; cat", marker.display());
+        git(&root, &["config", "filter.evil.clean", &command]);
+        git(&root, &["config", "filter.evil.smudge", &command]);
+        git(&root, &["config", "filter.evil.required", "true"]);
+        fs::write(root.join(".gitattributes"), "*.txt filter=evil\n").unwrap();
+        fs::write(root.join("notes.txt"), "one\ntwo\n").unwrap();
+
+        let status = repository.status(&[]).unwrap();
+        assert_eq!(status.unstaged.len(), 1);
+        let diff = repository.diff(None, None, false, &[]).unwrap();
+        assert!(diff.patch.contains("-2\n"));
+        repository
+            .blame(&root.join("notes.txt"), 1, 2, None)
+            .unwrap();
+        assert!(!marker.exists());
+
+        // git itself runs the filter
+        git(&root, &["diff", "HEAD"]);
+        assert!(marker.exists());
+    }
+
+    /// Tests finding the commits that changed each line.
+    #[test]
+    fn test_blame_and_branches() {
+        let (_temp_dir, repository) = repository();
+        let file = repository.root.join("notes.txt");
+
+        let lines = repository.blame(&file, 1, 3, None).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].summary, "Add notes");
+        assert_eq!(lines[0].text, "one");
+        assert_eq!(lines[1].summary, "Number two");
+        assert_eq!(lines[1].date, "2024-03-01");
+        assert_eq!(lines[1].author, "Test");
+        assert_eq!(lines[2].line, 3);
+        assert_eq!(lines[2].summary, "Add notes");
+
+        let branches = repository.branches(true).unwrap();
+        assert_eq!(branches.len(), 1);
+        assert_eq!(branches[0].name, "main");
+        assert!(branches[0].current);
+        assert_eq!(branches[0].subject, "Number two");
+    }
+
+    /// Tests that repositories above the boundary are not used.
+    #[test]
+    fn test_discover_boundary() {
+        let (_temp_dir, repository) = repository();
+        let inner = repository.root.join("inner");
+        fs::create_dir(&inner).unwrap();
+
+        assert!(AlpacaGitRepository::discover(&inner, &repository.root).is_ok());
+        assert!(AlpacaGitRepository::discover(&inner, &inner).is_err());
+    }
+
+    /// Tests converting unix times to dates.
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0, "+0000"), "1970-01-01");
+        assert_eq!(format_date(1_709_294_400, "+0000"), "2024-03-01");
+        assert_eq!(format_date(1_709_251_200, "-0100"), "2024-02-29");
+    }
+}
@@ -0,0 +1,579 @@
+use crate::fetch::AlpacaUrl;
+use std::borrow::Cow;
+
+/// Elements whose content is not shown as text.
+const SKIPPED_ELEMENTS: [&str; 8] = [
+    "iframe", "noscript", "object", "script", "select", "style", "svg", "template",
+];
+
+/// Elements that start and end a paragraph.
+const BLOCK_ELEMENTS: [&str; 27] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "caption",
+    "center",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "header",
+    "html",
+    "main",
+    "nav",
+    "p",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+];
+
+// ===
+// AlpacaHtmlText
+// ===
+/// The readable text of an HTML page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaHtmlText {
+    /// The content of the `<title>` element, if any
+    pub title: Option<String>,
+    /// The text as markdown: headings, paragraphs, lists, code blocks, table rows and links
+    pub text: String,
+}
+
+/// Converts an HTML page to markdown text.
+///
+/// Scripts, styles and comments are dropped, and whitespace is collapsed
+/// except in `<pre>` blocks. Links are kept as `[text](url)`, with relative
+/// URLs resolved against `base_url` when it is given. This is a lenient
+/// reading of the markup rather than a full HTML parser, so unclosed
+/// elements and stray `<` characters are tolerated.
+pub fn html_to_markdown(html: &str, base_url: Option<&str>) -> AlpacaHtmlText {
+    let base = base_url.and_then(|url| AlpacaUrl::parse(url).ok());
+    let mut writer = Writer::default();
+    let mut title = None;
+
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        writer.text(&decode_entities(&rest[..start]));
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(tag) = Tag::parse(rest) else {
+            // A `<` that does not start a tag
+            writer.text("<");
+            rest = &rest[1..];
+            continue;
+        };
+        rest = &rest[tag.length..];
+
+        if !tag.closing
+            && !tag.self_closing
+            && (tag.name == "title" || SKIPPED_ELEMENTS.contains(&tag.name.as_str()))
+        {
+            // The raw content, up to the closing tag
+            let end = find_closing(rest, &tag.name).unwrap_or(rest.len());
+            if tag.name == "title" && title.is_none() {
+                title = Some(collapse_whitespace(&decode_entities(&rest[..end])));
+            }
+            rest = &rest[end..];
+            rest = rest.find('>').map_or("", |index| &rest[index + 1..]);
+            continue;
+        }
+
+        if tag.closing {
+            writer.close(&tag.name);
+        } else {
+            writer.open(&tag, base.as_ref());
+        }
+    }
+    writer.text(&decode_entities(rest));
+
+    AlpacaHtmlText {
+        title: title.filter(|title| !title.is_empty()),
+        text: writer.finish(),
+    }
+}
+
+// ---
+
+/// A start or end tag.
+struct Tag {
+    /// The name in lower case, or empty for `<!DOCTYPE …>` and `<?…?>`
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    attributes: Vec<(String, String)>,
+    /// The length of the tag in the source, up to and including `>`
+    length: usize,
+}
+
+impl Tag {
+    /// Reads the tag at the start of `text`, which starts with `<`.
+    fn parse(text: &str) -> Option<Tag> {
+        let bytes = text.as_bytes();
+        let closing = bytes.get(1) == Some(&b'/');
+        let name_start = if closing { 2 } else { 1 };
+
+        // Declarations and processing instructions are skipped whole
+        if !closing && matches!(bytes.get(1), Some(b'!' | b'?')) {
+            let end = text.find('>')?;
+            return Some(Tag {
+                name: String::new(),
+                closing: false,
+                self_closing: true,
+                attributes: Vec::new(),
+                length: end + 1,
+            });
+        }
+
+        let name_length = text[name_start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(text.len() - name_start);
+        if name_length == 0 || !bytes[name_start].is_ascii_alphabetic() {
+            return None;
+        }
+        let name = text[name_start..name_start + name_length].to_ascii_lowercase();
+
+        // The attributes, with quoted values that may contain `>`
+        let mut attributes = Vec::new();
+        let mut index = name_start + name_length;
+        let mut self_closing = false;
+        loop {
+            let rest = &text[index..];
+            let skipped = rest.len() - rest.trim_start().len();
+            index += skipped;
+            let rest = &text[index..];
+            match rest.chars().next()? {
+                '>' => {
+                    index += 1;
+                    break;
+                }
+                '/' => {
+                    self_closing = true;
+                    index += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let name_end = rest
+                .find(|c: char| c.is_whitespace() || "=>/".contains(c))
+                .unwrap_or(rest.len());
+            let attribute = rest[..name_end].to_ascii_lowercase();
+            index += name_end.max(1);
+            let rest = &text[index..];
+            let value = if let Some(value) = rest.trim_start().strip_prefix('=') {
+                let value_start = value.trim_start();
+                index += rest.len() - value_start.len();
+                let (value, length) = match value_start.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = value_start[1..].find(quote)?;
+                        (&value_start[1..end + 1], end + 2)
+                    }
+                    _ => {
+                        let end = value_start
+                            .find(|c: char| c.is_whitespace() || c == '>')
+                            .unwrap_or(value_start.len());
+                        (&value_start[..end], end)
+                    }
+                };
+                index += length;
+                decode_entities(value).to_string()
+            } else {
+                String::new()
+            };
+            if !attribute.is_empty() {
+                attributes.push((attribute, value));
+            }
+        }
+
+        Some(Tag {
+            name,
+            closing,
+            self_closing,
+            attributes,
+            length: index,
+        })
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Builds the markdown text as elements open and close.
+#[derive(Default)]
+struct Writer {
+    text: String,
+    /// The line breaks owed before the next text: 1 for a new line, 2 for a new paragraph
+    breaks: usize,
+    /// Whether a space is owed before the next text
+    space: bool,
+    /// What to write at the start of the next line, such as a list marker
+    prefix: String,
+    /// The depth of `<pre>` elements
+    pre: usize,
+    /// The open lists, with the next number of ordered ones
+    lists: Vec<Option<usize>>,
+    /// The open links, with where their text starts and their URL if they are kept
+    links: Vec<Option<(usize, String)>>,
+    /// The cells written in the current table row
+    cells: usize,
+}
+
+impl Writer {
+    fn text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if self.pre > 0 {
+            self.flush();
+            self.text.push_str(text);
+            return;
+        }
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.space = true;
+            } else {
+                self.flush();
+                self.text.push(c);
+            }
+        }
+    }
+
+    /// Writes the line breaks and the prefix, or the space, owed before the next text.
+    fn flush(&mut self) {
+        if self.breaks > 0 {
+            if !self.text.is_empty() {
+                let trimmed = self.text.trim_end_matches(' ').len();
+                self.text.truncate(trimmed);
+                // Links that started in the trimmed spaces start where they were
+                for (start, _) in self.links.iter_mut().flatten() {
+                    *start = (*start).min(trimmed);
+                }
+                let written = self.text.len() - self.text.trim_end_matches('\n').len();
+                for _ in written..self.breaks {
+                    self.text.push('\n');
+                }
+            }
+            self.text.push_str(&std::mem::take(&mut self.prefix));
+        } else if self.space && !self.text.is_empty() && !self.text.ends_with([' ', '\n']) {
+            self.text.push(' ');
+        }
+        self.breaks = 0;
+        self.space = false;
+    }
+
+    fn block(&mut self, breaks: usize) {
+        self.breaks = self.breaks.max(breaks);
+    }
+
+    fn open(&mut self, tag: &Tag, base: Option<&AlpacaUrl>) {
+        let name = tag.name.as_str();
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block(2);
+                let level = name[1..].parse().unwrap_or(1);
+                self.prefix = format!("{} ", "#".repeat(level));
+            }
+            "ul" | "ol" => {
+                self.block(1);
+                let start = tag.attribute("start").and_then(|start| start.parse().ok());
+                self.lists
+                    .push((name == "ol").then_some(start.unwrap_or(1)));
+            }
+            "li" => {
+                self.block(1);
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.prefix = format!("{}{}", indent, marker);
+            }
+            "pre" => {
+                self.block(2);
+                self.flush();
+                self.text.push_str("```\n");
+                self.pre += 1;
+            }
+            "code" if self.pre == 0 => {
+                self.flush();
+                self.text.push('`');
+            }
+            "a" => {
+                self.flush();
+                let target = tag
+                    .attribute("href")
+                    .map(str::trim)
+                    .filter(|href| !href.is_empty() && !href.starts_with('#'))
+                    .filter(|href| !href.to_ascii_lowercase().starts_with("javascript:"))
+                    .map(|href| match base {
+                        Some(base) => base
+                            .join(href)
+                            .map_or_else(|_| href.to_string(), |url| url.to_string()),
+                        None => href.to_string(),
+                    });
+                self.links
+                    .push(target.map(|target| (self.text.len(), target)));
+            }
+            "br" => self.block(1),
+            "hr" => {
+                self.block(2);
+                self.flush();
+                self.text.push_str("---");
+                self.block(2);
+            }
+            "tr" => {
+                self.block(1);
+                self.cells = 0;
+            }
+            "td" | "th" => {
+                if self.cells > 0 {
+                    self.flush();
+                    self.text.push_str(" | ");
+                }
+                self.cells += 1;
+            }
+            "img" => {
+                if let Some(alt) = tag.attribute("alt").map(str::trim)
+                    && !alt.is_empty()
+                {
+                    self.flush();
+                    self.text.push_str(&format!("[image: {}]", alt));
+                }
+            }
+            _ if BLOCK_ELEMENTS.contains(&name) => self.block(2),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.block(2),
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.block(if self.lists.is_empty() { 2 } else { 1 });
+            }
+            "li" | "tr" => self.block(1),
+            "pre" if self.pre > 0 => {
+                self.pre -= 1;
+                if !self.text.ends_with('\n') {
+                    self.text.push('\n');
+                }
+                self.text.push_str("```");
+                self.block(2);
+            }
+            "code" if self.pre == 0 => self.text.push('`'),
+            "a" => {
+                let Some(Some((start, target))) = self.links.pop() else {
+                    return;
+                };
+                let Some(label) = self.text.get(start..).map(str::trim) else {
+                    return;
+                };
+                if !label.is_empty() && !label.contains('\n') && label != target {
+                    let link = format!("[{}]({})", label, target);
+                    self.text.truncate(start);
+                    self.text.push_str(&link);
+                }
+            }
+            _ if BLOCK_ELEMENTS.contains(&name) => self.block(2),
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> String {
+        let lines: Vec<&str> = self.text.lines().map(str::trim_end).collect();
+        lines.join("\n").trim().to_string()
+    }
+}
+
+/// Finds the closing tag of a raw text element, such as `</script>`, ignoring case.
+fn find_closing(text: &str, name: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(index) = text[offset..].find("</") {
+        let start = offset + index;
+        let candidate = &text.as_bytes()[start + 2..];
+        if candidate.len() >= name.len()
+            && candidate[..name.len()].eq_ignore_ascii_case(name.as_bytes())
+        {
+            return Some(start);
+        }
+        offset = start + 2;
+    }
+    None
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Decodes character references, such as `&amp;`, `&#8212;` and `&#x2014;`.
+///
+/// Unknown references are left as they are.
+fn decode_entities(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('&') {
+        decoded.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        let character = rest
+            .bytes()
+            .skip(1)
+            .take(11)
+            .position(|byte| byte == b';')
+            .and_then(|end| {
+                let reference = &rest[1..end + 1];
+                let character = match reference.strip_prefix('#') {
+                    Some(number) => match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => number.parse().ok(),
+                    }
+                    .and_then(char::from_u32),
+                    None => named_entity(reference),
+                };
+                character.map(|character| (character, end + 2))
+            });
+        match character {
+            Some((character, length)) => {
+                decoded.push(character);
+                rest = &rest[length..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+fn named_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "bull" => '•',
+        "middot" => '·',
+        "times" => '×',
+        "euro" => '€',
+        _ => return None,
+    })
+}
+
+// ===
+// AlpacaHtmlText Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests converting a page with headings, paragraphs, links and lists.
+    #[test]
+    fn test_html_to_markdown() {
+        let html = r##"<!DOCTYPE html>
+<html>
+<head>
+  <title>The  Guide &amp; More</title>
+  <style>body { color: red; }</style>
+  <script>if (a < b) { document.write("<p>no</p>"); }</script>
+</head>
+<body>
+  <h1>Getting   started</h1>
+  <!-- a comment -->
+  <p>Read the <a href="setup.html">setup
+     page</a> or <a href="#top">jump</a>, then <code>cargo build</code>.</p>
+  <ul>
+    <li>One &mdash; first</li>
+    <li>Two<ol start="3"><li>Three</li><li>Four</li></ol></li>
+  </ul>
+  <p>Mail <a href="mailto:team@example.com">us</a><br>today.</p>
+</body>
+</html>"##;
+
+        let page = html_to_markdown(html, Some("https://example.com/docs/index.html"));
+        assert_eq!(page.title.as_deref(), Some("The Guide & More"));
+        assert_eq!(
+            page.text,
+            "# Getting started\n\nRead the [setup page](https://example.com/docs/setup.html) or jump, then `cargo build`.\n\n- One — first\n- Two\n  3. Three\n  4. Four\n\nMail [us](mailto:team@example.com)\ntoday."
+        );
+    }
+
+    /// Tests keeping preformatted text and reading tables.
+    #[test]
+    fn test_html_pre_and_tables() {
+        let html = "<p>Example:</p><pre><code>fn main() {\n    println!(\"&lt;hi&gt;\");\n}</code></pre>\
+            <table><tr><th>Name</th><th>Size</th></tr><tr><td>a.rs</td><td>12</td></tr></table>";
+
+        let page = html_to_markdown(html, None);
+        assert_eq!(page.title, None);
+        assert_eq!(
+            page.text,
+            "Example:\n\n```\nfn main() {\n    println!(\"<hi>\");\n}\n```\n\nName | Size\na.rs | 12"
+        );
+    }
+
+    /// Tests that broken markup and references do not lose text.
+    #[test]
+    fn test_html_lenient() {
+        assert_eq!(
+            html_to_markdown("1 < 2 &unknown; &#65;&#x42; <b>bold", None).text,
+            "1 < 2 &unknown; AB bold"
+        );
+        assert_eq!(
+            html_to_markdown("<p title='a > b'>text</p><script>never", None).text,
+            "text"
+        );
+
+        // Links starting in spaces that a line break trims away
+        assert_eq!(
+            html_to_markdown("<pre>a\n\n   <a href=\"x\"><br>b</a></pre>", None).text,
+            "```\na\n\n[b](x)\n```"
+        );
+        assert_eq!(
+            html_to_markdown("<pre>a\n <a href=\"x\"><br>é</a></pre>", None).text,
+            "```\na\n[é](x)\n```"
+        );
+    }
+}
@@ -0,0 +1,596 @@
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// The most bytes read of the status line and headers of a response.
+const MAX_HEAD_BYTES: u64 = 64 * 1024;
+
+/// The headers set by the transport, which requests may not set themselves.
+const RESERVED_HEADERS: [&str; 4] = ["connection", "content-length", "host", "transfer-encoding"];
+
+// ===
+// AlpacaHttpRequest
+// ===
+/// An HTTP request to be sent through an `AlpacaHttpTransport`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaHttpRequest {
+    /// The HTTP method, for example `GET` or `POST`
+    pub method: String,
+    /// The absolute URL of the request, including the query string
+    pub url: String,
+    /// The request headers
+    pub headers: Vec<(String, String)>,
+    /// The request body, if any
+    pub body: Option<Vec<u8>>,
+    /// The time by which the whole response must have been read, if any
+    pub deadline: Option<Instant>,
+    /// The most bytes of the response body wanted, if limited; the transport
+    /// stops reading one byte past it, so that a longer body can be told apart
+    pub max_response_bytes: Option<usize>,
+}
+
+impl AlpacaHttpRequest {
+    /// Creates a request with no headers and no body.
+    pub fn new(method: &str, url: &str) -> Self {
+        AlpacaHttpRequest {
+            method: method.to_uppercase(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+            deadline: None,
+            max_response_bytes: None,
+        }
+    }
+
+    /// Adds a header to the request.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the body of the request.
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Sets the time by which the whole response must have been read.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Limits the response body to `max_response_bytes`, plus the one byte
+    /// that shows it was longer.
+    pub fn max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = Some(max_response_bytes);
+        self
+    }
+
+    /// Checks that the method, the URL and the headers can be written into the
+    /// request head as they are, so that none of them can end a line and add
+    /// headers or a second request.
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_token(&self.method) {
+            return Err(format!("The method '{}' is not valid.", self.method));
+        }
+        if self
+            .url
+            .contains(|c: char| c.is_ascii_control() || c == ' ')
+        {
+            return Err(format!(
+                "The URL '{}' is not valid.",
+                self.url.escape_debug()
+            ));
+        }
+        for (name, value) in &self.headers {
+            if RESERVED_HEADERS.contains(&name.to_lowercase().as_str()) {
+                return Err(format!("The header '{}' is set automatically.", name));
+            }
+            if !is_token(name) || value.contains(['\r', '\n', '\0']) {
+                return Err(format!(
+                    "The header '{}' is not valid.",
+                    name.escape_debug()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// ===
+// AlpacaHttpResponse
+// ===
+/// The response returned by an `AlpacaHttpTransport`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaHttpResponse {
+    /// The HTTP status code
+    pub status: u16,
+    /// The response headers
+    pub headers: Vec<(String, String)>,
+    /// The raw response body
+    pub body: Vec<u8>,
+}
+
+impl AlpacaHttpResponse {
+    /// Gets the value of a header, ignoring the case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns `true` if the status code is in the 2xx range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Gets the body as text, replacing invalid UTF-8 sequences.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+}
+
+// ===
+// AlpacaHttpTransport
+// ===
+/// Sends HTTP requests on behalf of tools that reach the network.
+///
+/// Hosts can provide their own implementation, for example to add TLS,
+/// authentication or a proxy, and tests can point it at a local stand-in server.
+pub trait AlpacaHttpTransport {
+    /// Sends a request and waits for the complete response.
+    ///
+    /// Implementations should refuse requests that `AlpacaHttpRequest::validate` rejects.
+    /// Implementations should fail once `request.deadline` has passed, and stop
+    /// reading the body one byte past `request.max_response_bytes`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AlpacaHttpResponse)` - The response, whatever its status code
+    /// * `Err(String)` - A description of the failure if no response was received
+    fn send(&self, request: &AlpacaHttpRequest) -> Result<AlpacaHttpResponse, String>;
+}
+
+// ===
+// AlpacaStdHttpTransport
+// ===
+/// A minimal HTTP/1.1 transport built on `std::net::TcpStream`.
+///
+/// Only plain `http://` URLs are supported. Use a custom `AlpacaHttpTransport`
+/// to reach `https://` endpoints.
+pub struct AlpacaStdHttpTransport {
+    timeout: Duration,
+}
+
+impl AlpacaStdHttpTransport {
+    /// Creates a transport with a 30 second timeout.
+    pub fn new() -> Self {
+        Self::with_timeout(Duration::from_secs(30))
+    }
+
+    /// Creates a transport with the given connect, read and write timeout.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        AlpacaStdHttpTransport { timeout }
+    }
+}
+
+impl Default for AlpacaStdHttpTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlpacaHttpTransport for AlpacaStdHttpTransport {
+    fn send(&self, request: &AlpacaHttpRequest) -> Result<AlpacaHttpResponse, String> {
+        request.validate()?;
+        let (host, port, target) = split_url(&request.url)?;
+        let timeout = || remaining(self.timeout, request.deadline);
+
+        let address = (host.as_str(), port)
+            .to_socket_addrs()
+            .map_err(|e| format!("Failed to resolve '{}': {}", host, e))?
+            .next()
+            .ok_or_else(|| format!("Failed to resolve '{}'", host))?;
+
+        let mut stream = timeout()
+            .and_then(|timeout| TcpStream::connect_timeout(&address, timeout))
+            .map_err(|e| format!("Failed to connect to '{}': {}", host, e))?;
+        timeout()
+            .and_then(|timeout| stream.set_write_timeout(Some(timeout)))
+            .map_err(|e| format!("Failed to send the request: {}", e))?;
+
+        // Write the request head, followed by the body
+        let mut head = format!("{} {} HTTP/1.1\r\n", request.method, target);
+        let host_header = if port == 80 {
+            host.clone()
+        } else {
+            format!("{}:{}", host, port)
+        };
+        head.push_str(&format!("Host: {}\r\nConnection: close\r\n", host_header));
+        for (name, value) in &request.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if let Some(body) = &request.body {
+            head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        head.push_str("\r\n");
+
+        stream
+            .write_all(head.as_bytes())
+            .and_then(|_| stream.write_all(request.body.as_deref().unwrap_or_default()))
+            .map_err(|e| format!("Failed to send the request: {}", e))?;
+
+        // The connection is closed by the server once the response is complete
+        let mut reader = BufReader::new(DeadlineReader {
+            stream,
+            timeout: self.timeout,
+            deadline: request.deadline,
+        });
+        let limit = request
+            .max_response_bytes
+            .map_or(u64::MAX, |limit| (limit as u64).saturating_add(1));
+        read_response(&mut reader, limit).map_err(|e| format!("Failed to read the response: {}", e))
+    }
+}
+
+// ---
+
+/// The time left for one network operation: `timeout`, or less if `deadline` is closer.
+fn remaining(timeout: Duration, deadline: Option<Instant>) -> std::io::Result<Duration> {
+    let Some(deadline) = deadline else {
+        return Ok(timeout);
+    };
+    match deadline.checked_duration_since(Instant::now()) {
+        Some(left) if !left.is_zero() => Ok(timeout.min(left)),
+        _ => Err(std::io::Error::new(
+            ErrorKind::TimedOut,
+            "the deadline of the request has passed",
+        )),
+    }
+}
+
+/// A stream whose reads time out after `timeout`, and fail once `deadline` has passed.
+struct DeadlineReader {
+    stream: TcpStream,
+    timeout: Duration,
+    deadline: Option<Instant>,
+}
+
+impl Read for DeadlineReader {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let timeout = remaining(self.timeout, self.deadline)?;
+        self.stream.set_read_timeout(Some(timeout))?;
+        match self.stream.read(buffer) {
+            // A socket timeout is reported as `WouldBlock` on some systems
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                remaining(self.timeout, self.deadline)?;
+                Err(std::io::Error::new(
+                    ErrorKind::TimedOut,
+                    "the server sent nothing before the timeout",
+                ))
+            }
+            read => read,
+        }
+    }
+}
+
+/// Whether `text` is a method or header name made of letters, digits, `-` and `_`.
+fn is_token(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_".contains(c))
+}
+
+/// Splits an `http://` URL into its host, port and request target.
+fn split_url(url: &str) -> Result<(String, u16, String), String> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        format!(
+            "Unsupported URL '{}'. Only http:// URLs are supported by this transport.",
+            url
+        )
+    })?;
+
+    let (authority, target) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port
+                .parse::<u16>()
+                .map_err(|_| format!("Invalid port in URL '{}'", url))?;
+            (host, port)
+        }
+        None => (authority, 80),
+    };
+
+    if host.is_empty() {
+        return Err(format!("Missing host in URL '{}'", url));
+    }
+
+    Ok((host.to_string(), port, target.to_string()))
+}
+
+/// Reads a response, keeping at most `limit` bytes of its body.
+fn read_response(reader: &mut impl BufRead, limit: u64) -> Result<AlpacaHttpResponse, String> {
+    let mut head = Vec::new();
+    loop {
+        let read = reader
+            .by_ref()
+            .take(MAX_HEAD_BYTES - head.len() as u64)
+            .read_until(b'\n', &mut head)
+            .map_err(|e| e.to_string())?;
+        if head.ends_with(b"\r\n\r\n") {
+            break;
+        }
+        if read == 0 || head.len() as u64 >= MAX_HEAD_BYTES {
+            return Err("Malformed HTTP response: missing header terminator".to_string());
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or("Malformed HTTP response: invalid status line")?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let mut response = AlpacaHttpResponse {
+        status,
+        headers,
+        body: Vec::new(),
+    };
+
+    if response
+        .header("Transfer-Encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        response.body = read_chunked(reader, limit)?;
+    } else {
+        let length = response
+            .header("Content-Length")
+            .and_then(|length| length.parse::<u64>().ok())
+            .unwrap_or(u64::MAX);
+        reader
+            .take(length.min(limit))
+            .read_to_end(&mut response.body)
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(response)
+}
+
+/// Reads a chunked body, stopping once `limit` bytes have been read.
+fn read_chunked(reader: &mut impl BufRead, limit: u64) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+
+    loop {
+        let mut size_line = Vec::new();
+        reader
+            .by_ref()
+            .take(1024)
+            .read_until(b'\n', &mut size_line)
+            .map_err(|e| e.to_string())?;
+        if !size_line.ends_with(b"\n") {
+            return Err("Malformed chunked response".to_string());
+        }
+        let size_line = String::from_utf8_lossy(&size_line);
+        let size_text = size_line.split(';').next().unwrap_or_default().trim();
+        let size = u64::from_str_radix(size_text, 16)
+            .map_err(|_| format!("Invalid chunk size '{}'", size_text))?;
+        if size == 0 {
+            break;
+        }
+
+        let wanted = size.min(limit - body.len() as u64);
+        let read = reader
+            .by_ref()
+            .take(wanted)
+            .read_to_end(&mut body)
+            .map_err(|e| e.to_string())?;
+        if (read as u64) < wanted {
+            return Err("Truncated chunked response".to_string());
+        }
+        if body.len() as u64 >= limit {
+            break;
+        }
+
+        // The line break that ends the chunk
+        let mut line_break = [0u8; 2];
+        reader
+            .read_exact(&mut line_break)
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(body)
+}
+
+// ===
+// AlpacaHttpTransport Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Starts a server that answers a single request with `response` and returns
+    /// the raw request it received through the join handle.
+    fn serve_once(response: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+
+            // Read until the end of the headers and the announced body
+            loop {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(head_end) = text.find("\r\n\r\n") {
+                    let length = text
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Content-Length: "))
+                        .and_then(|length| length.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if request.len() >= head_end + 4 + length {
+                        break;
+                    }
+                }
+                if read == 0 {
+                    break;
+                }
+            }
+
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        (format!("http://{}", address), handle)
+    }
+
+    /// Tests sending a request with a body to a local server.
+    #[test]
+    fn test_send_request() {
+        let (url, handle) = serve_once(
+            "HTTP/1.1 201 Created\r\nContent-Type: application/json\r\nContent-Length: 11\r\n\r\n{\"id\": 42}\n",
+        );
+
+        let transport = AlpacaStdHttpTransport::new();
+        let request = AlpacaHttpRequest::new("post", &format!("{}/items?draft=true", url))
+            .header("Content-Type", "application/json")
+            .body(b"{\"name\":\"x\"}".to_vec());
+        let response = transport.send(&request).unwrap();
+
+        assert_eq!(response.status, 201);
+        assert!(response.is_success());
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        assert_eq!(response.text(), "{\"id\": 42}\n");
+
+        let received = handle.join().unwrap();
+        assert!(received.starts_with("POST /items?draft=true HTTP/1.1\r\n"));
+        assert!(received.ends_with("{\"name\":\"x\"}"));
+    }
+
+    /// Tests decoding a chunked response.
+    #[test]
+    fn test_chunked_response() {
+        let (url, handle) = serve_once(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+        );
+
+        let response = AlpacaStdHttpTransport::new()
+            .send(&AlpacaHttpRequest::new("GET", &url))
+            .unwrap();
+        handle.join().unwrap();
+
+        assert_eq!(response.text(), "hello world");
+    }
+
+    /// Starts a server that answers a single request with `head`, then writes
+    /// `chunk` every `pause` until the client goes away.
+    fn serve_endless(head: &'static str, chunk: Vec<u8>, pause: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0u8; 1024];
+            let _ = stream.read(&mut buffer);
+            let _ = stream.write_all(head.as_bytes());
+            let started = Instant::now();
+            while started.elapsed() < Duration::from_secs(10) && stream.write_all(&chunk).is_ok() {
+                thread::sleep(pause);
+            }
+        });
+
+        format!("http://{}", address)
+    }
+
+    /// Tests that endless and slow bodies stop at the size limit and the deadline.
+    #[test]
+    fn test_response_limits() {
+        let transport = AlpacaStdHttpTransport::new();
+
+        let url = serve_endless("HTTP/1.1 200 OK\r\n\r\n", vec![b'a'; 8192], Duration::ZERO);
+        let request = AlpacaHttpRequest::new("GET", &url).max_response_bytes(100_000);
+        assert_eq!(transport.send(&request).unwrap().body.len(), 100_001);
+
+        let mut chunk = b"2000\r\n".to_vec();
+        chunk.extend([b'b'; 8192]);
+        chunk.extend(b"\r\n");
+        let url = serve_endless(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n",
+            chunk,
+            Duration::ZERO,
+        );
+        let request = AlpacaHttpRequest::new("GET", &url).max_response_bytes(100_000);
+        assert_eq!(transport.send(&request).unwrap().body.len(), 100_001);
+
+        let started = Instant::now();
+        let url = serve_endless(
+            "HTTP/1.1 200 OK\r\n\r\n",
+            b"c".to_vec(),
+            Duration::from_millis(20),
+        );
+        let request =
+            AlpacaHttpRequest::new("GET", &url).deadline(started + Duration::from_millis(300));
+        let error = transport.send(&request).unwrap_err();
+        assert!(error.contains("deadline"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    /// Tests that requests which would change the request head are refused before connecting.
+    #[test]
+    fn test_invalid_request() {
+        let transport = AlpacaStdHttpTransport::new();
+        // Nothing listens on the discard port
+        let url = "http://127.0.0.1:9/";
+
+        let request = AlpacaHttpRequest::new("GET", url).header("X-A", "1\r\nX-B: 2");
+        assert_eq!(
+            transport.send(&request).unwrap_err(),
+            "The header 'X-A' is not valid."
+        );
+        let request = AlpacaHttpRequest::new("GET", url).header("X-A\r\nX-B", "2");
+        assert!(
+            transport
+                .send(&request)
+                .unwrap_err()
+                .contains("is not valid")
+        );
+        let request = AlpacaHttpRequest::new("GET", url).header("Transfer-Encoding", "chunked");
+        assert_eq!(
+            transport.send(&request).unwrap_err(),
+            "The header 'Transfer-Encoding' is set automatically."
+        );
+        let request = AlpacaHttpRequest::new("GET / HTTP/1.1\r\nX:", url);
+        assert!(transport.send(&request).unwrap_err().contains("method"));
+        let request = AlpacaHttpRequest::new("GET", "http://127.0.0.1:9/ HTTP/1.1\r\nX: y");
+        assert!(transport.send(&request).unwrap_err().contains("URL"));
+    }
+
+    /// Tests that URLs which are not plain HTTP are rejected.
+    #[test]
+    fn test_unsupported_url() {
+        let result =
+            AlpacaStdHttpTransport::new().send(&AlpacaHttpRequest::new("GET", "https://x.dev"));
+        assert!(
+            result
+                .unwrap_err()
+                .contains("Only http:// URLs are supported")
+        );
+    }
+}
@@ -0,0 +1,715 @@
+use serde_json::Value as JsonValue;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+// ===
+// AlpacaJsonPath
+// ===
+/// A compiled JSONPath query.
+///
+/// The supported syntax covers what is needed to pick values out of manifests,
+/// configuration files and API responses:
+///
+/// - `$` the root, `.name` or `['name']` a member, `.*` or `[*]` every child
+/// - `[0]`, `[-1]` an element, `[1:3]` or `[::2]` a slice, `[0,2]` or `['a','b']` a union
+/// - `..name` or `..*` a recursive descent
+/// - `[?(@.price < 10)]` a filter comparing a member with a literal, or
+///   `[?(@.name)]` testing that it exists
+///
+/// jq-style queries such as `.dependencies.serde` or `.items[].name` are also
+/// accepted: a leading `.` stands for `$`, and `[]` for `[*]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaJsonPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    selectors: Vec<Selector>,
+    /// Whether the selectors apply to every descendant, as in `..name`
+    descendant: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Index(i64),
+    Wildcard,
+    Slice(Option<i64>, Option<i64>, i64),
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    /// The members and indices leading from `@` to the tested value
+    path: Vec<Selector>,
+    comparison: Option<(Comparison, JsonValue)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// A value selected by a query, with its location in the document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaJsonMatch<'a> {
+    /// The normalized path of the value, such as `$.items[0].name`
+    pub path: String,
+    pub value: &'a JsonValue,
+}
+
+impl AlpacaJsonPath {
+    /// Compiles a query.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AlpacaJsonPath)` - The compiled query
+    /// * `Err(String)` - A description of the syntax error and its position
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: query.chars().collect(),
+            position: 0,
+        };
+        parser.query().map_err(|error| {
+            format!(
+                "Invalid query '{}' at position {}: {}",
+                query, parser.position, error
+            )
+        })
+    }
+
+    /// Selects the values matching the query, in document order.
+    pub fn select<'a>(&self, root: &'a JsonValue) -> Vec<AlpacaJsonMatch<'a>> {
+        let mut nodes = vec![AlpacaJsonMatch {
+            path: "$".to_string(),
+            value: root,
+        }];
+
+        for segment in &self.segments {
+            if segment.descendant {
+                nodes = descendants(nodes);
+            }
+            nodes = nodes
+                .iter()
+                .flat_map(|node| {
+                    segment
+                        .selectors
+                        .iter()
+                        .flat_map(move |selector| apply(selector, node))
+                })
+                .collect();
+        }
+
+        nodes
+    }
+}
+
+/// Lists the nodes and all of their descendants, parents first.
+///
+/// Each node is listed once, even when it is also the descendant of another
+/// node, so that chained descents such as `$..*..*` stay linear in the size of
+/// the document.
+fn descendants(nodes: Vec<AlpacaJsonMatch<'_>>) -> Vec<AlpacaJsonMatch<'_>> {
+    let mut output = Vec::new();
+    let mut seen = HashSet::new();
+
+    for node in nodes {
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if !seen.insert(std::ptr::from_ref(node.value)) {
+                // Listed already, with all of its descendants
+                continue;
+            }
+            let children = apply(&Selector::Wildcard, &node);
+            output.push(node);
+            stack.extend(children.into_iter().rev());
+        }
+    }
+
+    output
+}
+
+fn apply<'a>(selector: &Selector, node: &AlpacaJsonMatch<'a>) -> Vec<AlpacaJsonMatch<'a>> {
+    let child = |key: &str, value: &'a JsonValue| AlpacaJsonMatch {
+        path: format!("{}{}", node.path, member_path(key)),
+        value,
+    };
+    let element = |index: usize, value: &'a JsonValue| AlpacaJsonMatch {
+        path: format!("{}[{}]", node.path, index),
+        value,
+    };
+
+    match (selector, node.value) {
+        (Selector::Name(name), JsonValue::Object(map)) => map
+            .get(name)
+            .map(|value| vec![child(name, value)])
+            .unwrap_or_default(),
+        (Selector::Index(index), JsonValue::Array(array)) => resolve_index(*index, array.len())
+            .map(|index| vec![element(index, &array[index])])
+            .unwrap_or_default(),
+        (Selector::Wildcard, JsonValue::Object(map)) => {
+            map.iter().map(|(key, value)| child(key, value)).collect()
+        }
+        (Selector::Wildcard, JsonValue::Array(array)) => array
+            .iter()
+            .enumerate()
+            .map(|(index, value)| element(index, value))
+            .collect(),
+        (Selector::Slice(start, end, step), JsonValue::Array(array)) => {
+            slice_indices(*start, *end, *step, array.len())
+                .into_iter()
+                .map(|index| element(index, &array[index]))
+                .collect()
+        }
+        (Selector::Filter(filter), JsonValue::Array(array)) => array
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| filter.test(value))
+            .map(|(index, value)| element(index, value))
+            .collect(),
+        (Selector::Filter(filter), JsonValue::Object(map)) => map
+            .iter()
+            .filter(|(_, value)| filter.test(value))
+            .map(|(key, value)| child(key, value))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn member_path(key: &str) -> String {
+    let identifier = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if identifier {
+        format!(".{}", key)
+    } else {
+        format!("[{}]", serde_json::to_string(key).unwrap())
+    }
+}
+
+fn resolve_index(index: i64, length: usize) -> Option<usize> {
+    let index = if index < 0 {
+        length as i64 + index
+    } else {
+        index
+    };
+    (0..length as i64)
+        .contains(&index)
+        .then_some(index as usize)
+}
+
+fn slice_indices(start: Option<i64>, end: Option<i64>, step: i64, length: usize) -> Vec<usize> {
+    let length = length as i64;
+    let bound = |value: i64| {
+        if value < 0 {
+            (length + value).max(0)
+        } else {
+            value.min(length)
+        }
+    };
+
+    if step > 0 {
+        let (start, end) = (bound(start.unwrap_or(0)), bound(end.unwrap_or(length)));
+        (start..end)
+            .step_by(step as usize)
+            .map(|index| index as usize)
+            .collect()
+    } else {
+        let start = start
+            .map(|start| bound(start).min(length - 1))
+            .unwrap_or(length - 1);
+        let end = end.map(bound).unwrap_or(-1);
+        let mut indices = Vec::new();
+        let mut index = start;
+        while index > end && index >= 0 {
+            indices.push(index as usize);
+            index += step;
+        }
+        indices
+    }
+}
+
+impl Filter {
+    fn test(&self, value: &JsonValue) -> bool {
+        let mut current = value;
+        for selector in &self.path {
+            let node = AlpacaJsonMatch {
+                path: String::new(),
+                value: current,
+            };
+            match apply(selector, &node).into_iter().next() {
+                Some(next) => current = next.value,
+                None => return false,
+            }
+        }
+
+        let Some((comparison, literal)) = &self.comparison else {
+            return true;
+        };
+        let ordering = match (current, literal) {
+            (JsonValue::Number(a), JsonValue::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+            (JsonValue::String(a), JsonValue::String(b)) => Some(a.cmp(b)),
+            (a, b) if a == b => Some(Ordering::Equal),
+            _ => None,
+        };
+
+        match comparison {
+            Comparison::Equal => ordering == Some(Ordering::Equal),
+            Comparison::NotEqual => ordering != Some(Ordering::Equal),
+            Comparison::Less => ordering == Some(Ordering::Less),
+            Comparison::LessOrEqual => {
+                matches!(ordering, Some(Ordering::Less | Ordering::Equal))
+            }
+            Comparison::Greater => ordering == Some(Ordering::Greater),
+            Comparison::GreaterOrEqual => {
+                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+            }
+        }
+    }
+}
+
+// ===
+// Parsing
+// ===
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            match self.peek() {
+                Some(found) => Err(format!("expected '{}' but found '{}'", c, found)),
+                None => Err(format!("expected '{}' but the query ended", c)),
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn query(&mut self) -> Result<AlpacaJsonPath, String> {
+        self.skip_whitespace();
+        // A leading '.' or nothing at all stands for the root, as in jq
+        self.eat('$');
+        if self.peek() == Some('.') && self.peek_at(1).is_none() {
+            self.position += 1;
+        }
+
+        let mut segments = Vec::new();
+        while self.peek().is_some() {
+            segments.push(self.segment()?);
+            self.skip_whitespace();
+        }
+
+        Ok(AlpacaJsonPath { segments })
+    }
+
+    fn segment(&mut self) -> Result<Segment, String> {
+        match self.peek() {
+            Some('.') if self.peek_at(1) == Some('.') => {
+                self.position += 2;
+                let selectors = if self.peek() == Some('[') {
+                    self.bracket()?
+                } else {
+                    vec![self.dot_member()?]
+                };
+                Ok(Segment {
+                    selectors,
+                    descendant: true,
+                })
+            }
+            Some('.') => {
+                self.position += 1;
+                // jq's `.[0]` and `.["name"]`
+                let selectors = if self.peek() == Some('[') {
+                    self.bracket()?
+                } else {
+                    vec![self.dot_member()?]
+                };
+                Ok(Segment {
+                    selectors,
+                    descendant: false,
+                })
+            }
+            Some('[') => Ok(Segment {
+                selectors: self.bracket()?,
+                descendant: false,
+            }),
+            Some(c) => Err(format!("expected '.' or '[' but found '{}'", c)),
+            None => Err("the query ended unexpectedly".to_string()),
+        }
+    }
+
+    fn dot_member(&mut self) -> Result<Selector, String> {
+        if self.eat('*') {
+            return Ok(Selector::Wildcard);
+        }
+        let name = self.identifier();
+        if name.is_empty() {
+            return Err("expected a member name".to_string());
+        }
+        Ok(Selector::Name(name))
+    }
+
+    fn identifier(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '-' || c == '$' {
+                name.push(c);
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+        name
+    }
+
+    fn bracket(&mut self) -> Result<Vec<Selector>, String> {
+        self.expect('[')?;
+        self.skip_whitespace();
+
+        // jq's `[]` selects every element
+        if self.eat(']') {
+            return Ok(vec![Selector::Wildcard]);
+        }
+        if self.eat('?') {
+            let filter = self.filter()?;
+            self.skip_whitespace();
+            self.expect(']')?;
+            return Ok(vec![Selector::Filter(filter)]);
+        }
+
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.bracket_selector()?);
+            self.skip_whitespace();
+            if !self.eat(',') {
+                break;
+            }
+        }
+        self.expect(']')?;
+        Ok(selectors)
+    }
+
+    fn bracket_selector(&mut self) -> Result<Selector, String> {
+        match self.peek() {
+            Some('*') => {
+                self.position += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('\'' | '"') => Ok(Selector::Name(self.string()?)),
+            _ => {
+                let start = self.optional_integer()?;
+                if !self.eat(':') {
+                    return start
+                        .map(Selector::Index)
+                        .ok_or_else(|| "expected an index, a name in quotes or '*'".to_string());
+                }
+                let end = self.optional_integer()?;
+                let step = if self.eat(':') {
+                    self.optional_integer()?.unwrap_or(1)
+                } else {
+                    1
+                };
+                if step == 0 {
+                    return Err("the step of a slice cannot be 0".to_string());
+                }
+                Ok(Selector::Slice(start, end, step))
+            }
+        }
+    }
+
+    fn optional_integer(&mut self) -> Result<Option<i64>, String> {
+        self.skip_whitespace();
+        let start = self.position;
+        self.eat('-');
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        self.skip_whitespace();
+        match text.as_str() {
+            "" => Ok(None),
+            text => text
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid index '{}'", text)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.peek().unwrap();
+        self.position += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                Some('\\') => {
+                    self.position += 1;
+                    if let Some(c) = self.peek() {
+                        text.push(c);
+                        self.position += 1;
+                    }
+                }
+                Some(c) if c == quote => {
+                    self.position += 1;
+                    return Ok(text);
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.position += 1;
+                }
+                None => return Err("the string is not closed".to_string()),
+            }
+        }
+    }
+
+    // filter := '(' '@' path (operator literal)? ')'
+    fn filter(&mut self) -> Result<Filter, String> {
+        self.skip_whitespace();
+        let parenthesized = self.eat('(');
+        self.skip_whitespace();
+        self.expect('@')?;
+
+        let mut path = Vec::new();
+        loop {
+            match self.peek() {
+                Some('.') => {
+                    self.position += 1;
+                    path.push(self.dot_member()?);
+                }
+                Some('[') => {
+                    let mut selectors = self.bracket()?;
+                    if selectors.len() != 1
+                        || !matches!(selectors[0], Selector::Name(_) | Selector::Index(_))
+                    {
+                        return Err("filters can only use members and indices".to_string());
+                    }
+                    path.push(selectors.remove(0));
+                }
+                _ => break,
+            }
+        }
+        if path.contains(&Selector::Wildcard) {
+            return Err("filters can only use members and indices".to_string());
+        }
+
+        self.skip_whitespace();
+        let comparison = match self.operator() {
+            Some(comparison) => {
+                self.skip_whitespace();
+                Some((comparison, self.literal()?))
+            }
+            None => None,
+        };
+
+        self.skip_whitespace();
+        if parenthesized {
+            self.expect(')')?;
+        }
+        Ok(Filter { path, comparison })
+    }
+
+    fn operator(&mut self) -> Option<Comparison> {
+        let two: String = self.chars[self.position..].iter().take(2).collect();
+        let (comparison, length) = match two.as_str() {
+            "==" => (Comparison::Equal, 2),
+            "!=" => (Comparison::NotEqual, 2),
+            "<=" => (Comparison::LessOrEqual, 2),
+            ">=" => (Comparison::GreaterOrEqual, 2),
+            _ if two.starts_with('<') => (Comparison::Less, 1),
+            _ if two.starts_with('>') => (Comparison::Greater, 1),
+            _ => return None,
+        };
+        self.position += length;
+        Some(comparison)
+    }
+
+    fn literal(&mut self) -> Result<JsonValue, String> {
+        if let Some('\'' | '"') = self.peek() {
+            return Ok(JsonValue::String(self.string()?));
+        }
+
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || "-+.".contains(c))
+        {
+            self.position += 1;
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        serde_json::from_str(&text).map_err(|_| {
+            format!(
+                "expected a number, a string, true, false or null, not '{}'",
+                text
+            )
+        })
+    }
+}
+
+// ===
+// AlpacaJsonPath Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn select(query: &str, document: &JsonValue) -> Vec<JsonValue> {
+        AlpacaJsonPath::parse(query)
+            .unwrap()
+            .select(document)
+            .into_iter()
+            .map(|found| found.value.clone())
+            .collect()
+    }
+
+    fn store() -> JsonValue {
+        json!({
+            "name": "store",
+            "books": [
+                {"title": "A", "price": 8, "tags": ["x"]},
+                {"title": "B", "price": 12},
+                {"title": "C", "price": 5, "isbn": "123"}
+            ],
+            "owner": {"name": "Ann", "address key": "here"}
+        })
+    }
+
+    /// Tests members, indices, wildcards and slices.
+    #[test]
+    fn test_select() {
+        let store = store();
+        assert_eq!(select("$.name", &store), vec![json!("store")]);
+        assert_eq!(select("$.books[-1].title", &store), vec![json!("C")]);
+        assert_eq!(
+            select("$.books[*].price", &store),
+            vec![json!(8), json!(12), json!(5)]
+        );
+        assert_eq!(
+            select("$.books[0:2].title", &store),
+            vec![json!("A"), json!("B")]
+        );
+        assert_eq!(
+            select("$.books[::-2].title", &store),
+            vec![json!("C"), json!("A")]
+        );
+        assert_eq!(
+            select("$.owner['address key']", &store),
+            vec![json!("here")]
+        );
+        assert_eq!(
+            select("$.books[0,2].title", &store),
+            vec![json!("A"), json!("C")]
+        );
+        assert!(select("$.missing.deeper", &store).is_empty());
+    }
+
+    /// Tests recursive descent and filters.
+    #[test]
+    fn test_descent_and_filters() {
+        let store = store();
+        assert_eq!(
+            select("$..name", &store),
+            vec![json!("store"), json!("Ann")]
+        );
+        assert_eq!(
+            select("$.books[?(@.price < 10)].title", &store),
+            vec![json!("A"), json!("C")]
+        );
+        assert_eq!(select("$.books[?(@.isbn)].title", &store), vec![json!("C")]);
+        assert_eq!(
+            select("$.books[?(@.title == 'B')].price", &store),
+            vec![json!(12)]
+        );
+    }
+
+    /// Tests jq-style queries and the normalized paths.
+    #[test]
+    fn test_jq_style_and_paths() {
+        let store = store();
+        assert_eq!(select(".books[].title", &store).len(), 3);
+        assert_eq!(select(".", &store), vec![store.clone()]);
+
+        let query = AlpacaJsonPath::parse("$..tags[0]").unwrap();
+        let found = query.select(&store);
+        assert_eq!(found[0].path, "$.books[0].tags[0]");
+
+        let query = AlpacaJsonPath::parse("$.owner['address key']").unwrap();
+        assert_eq!(query.select(&store)[0].path, "$.owner[\"address key\"]");
+    }
+
+    /// Tests that chained descents list each node once.
+    #[test]
+    fn test_chained_descent() {
+        let store = store();
+        // Every value below the members of the root
+        assert_eq!(select("$..*..*", &store).len(), 14);
+        assert_eq!(select("$..books..title", &store).len(), 3);
+
+        let mut document = json!(0);
+        for _ in 0..128 {
+            document = json!({"a": document, "b": [1, 2]});
+        }
+        let found = AlpacaJsonPath::parse("$..*..*..*..*")
+            .unwrap()
+            .select(&document);
+        // Every value at least four levels down, two at the first and four at
+        // each of the others
+        let all = select("$..*", &document).len();
+        assert_eq!(found.len(), all - 2 - 4 - 4);
+    }
+
+    /// Tests errors for invalid queries.
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            AlpacaJsonPath::parse("$.books[0").unwrap_err(),
+            "Invalid query '$.books[0' at position 9: expected ']' but the query ended"
+        );
+        assert!(
+            AlpacaJsonPath::parse("$.books[::0]")
+                .unwrap_err()
+                .contains("step")
+        );
+        assert!(
+            AlpacaJsonPath::parse("$.a b")
+                .unwrap_err()
+                .contains("found 'b'")
+        );
+    }
+}
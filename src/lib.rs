@@ -1,13 +1,56 @@
+// Lets the code generated by `#[alpaca_tool]` refer to `::alpaca_rs` from inside this crate.
+extern crate self as alpaca_rs;
+
 pub mod action;
+pub mod action_calculate;
+pub mod action_code_outline;
 pub mod action_describe;
+pub mod action_edit_file;
+pub mod action_fetch_url;
+pub mod action_find_definition;
+pub mod action_find_files;
+pub mod action_find_references;
+pub mod action_git;
+pub mod action_json_query;
 pub mod action_list;
+pub mod action_read_config;
 pub mod action_read_directory;
 pub mod action_read_file;
 pub mod action_regex;
+pub mod action_run_command;
+pub mod action_rust_project;
+pub mod action_search_files;
+pub mod action_string_ops;
+pub mod action_tree;
+pub mod action_write_file;
+pub mod cargo_project;
+pub mod command;
+pub mod config_file;
+pub mod confinement;
+pub mod diff;
+pub mod dir_listing;
 pub mod environment;
+pub mod expression;
+pub mod fetch;
+pub mod file_content;
+pub mod file_walk;
 pub mod function;
 pub mod function_dir;
+pub mod function_openapi;
 pub mod function_read_file;
+pub mod git;
+pub mod html_text;
+pub mod http_transport;
+pub mod json_path;
+pub mod openapi;
+pub mod permission;
+pub mod rust_modules;
+pub mod rust_outline;
+pub mod sandbox;
+pub mod symbol_index;
 pub mod tool_call;
+pub mod tool_derive;
 pub mod tool_dispatch;
+pub mod tool_error;
+pub mod tool_model;
 pub mod tool_proto;
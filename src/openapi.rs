@@ -0,0 +1,691 @@
+use crate::function::AlpacaFunctions;
+use crate::function_openapi::AlpacaFunctionOpenApi;
+use crate::http_transport::AlpacaHttpTransport;
+use crate::tool_model::{ToolDefinition, ToolParameter};
+use crate::tool_proto::AlpacaToolProto;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+const METHODS: [&str; 7] = ["get", "post", "put", "patch", "delete", "head", "options"];
+
+/// The name of the tool parameter that carries the JSON request body.
+pub const BODY_PARAMETER: &str = "body";
+
+/// The maximum number of nested `$ref` lookups, which guards against cycles.
+const MAX_REF_DEPTH: usize = 16;
+
+// ===
+// AlpacaOpenApiLocation
+// ===
+/// Where an operation parameter is placed in the HTTP request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlpacaOpenApiLocation {
+    /// Substituted into the URL path, for example `/pets/{petId}`
+    Path,
+    /// Appended to the query string
+    Query,
+    /// Sent as a request header
+    Header,
+}
+
+impl AlpacaOpenApiLocation {
+    /// Converts the location to its OpenAPI name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlpacaOpenApiLocation::Path => "path",
+            AlpacaOpenApiLocation::Query => "query",
+            AlpacaOpenApiLocation::Header => "header",
+        }
+    }
+}
+
+// ===
+// AlpacaOpenApiParameter
+// ===
+/// A path, query or header parameter of an operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaOpenApiParameter {
+    pub name: String,
+    pub location: AlpacaOpenApiLocation,
+    pub required: bool,
+    pub schema: ToolParameter,
+}
+
+// ===
+// AlpacaOpenApiOperation
+// ===
+/// A single operation of an OpenAPI document, described as a tool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaOpenApiOperation {
+    /// The tool name, taken from `operationId` or derived from the method and path
+    pub name: String,
+    /// The upper-case HTTP method
+    pub method: String,
+    /// The path template, for example `/pets/{petId}`
+    pub path: String,
+    /// The summary or description of the operation
+    pub description: String,
+    /// The path, query and header parameters
+    pub parameters: Vec<AlpacaOpenApiParameter>,
+    /// The schema of the JSON request body, if the operation accepts one
+    pub body: Option<ToolParameter>,
+    /// The documented responses, as status code and description pairs
+    pub responses: Vec<(String, String)>,
+}
+
+impl AlpacaOpenApiOperation {
+    /// Builds the typed tool definition of the operation.
+    pub fn definition(&self) -> ToolDefinition {
+        let mut builder = ToolDefinition::builder(&self.name).description(&self.description);
+
+        for parameter in &self.parameters {
+            builder = builder.parameter(&parameter.name, parameter.schema.clone());
+        }
+
+        if let Some(body) = &self.body {
+            builder = builder.parameter(BODY_PARAMETER, body.clone());
+        }
+
+        builder.build()
+    }
+
+    /// Builds the tool prototype of the operation.
+    pub fn tool_proto(&self) -> AlpacaToolProto {
+        AlpacaToolProto::from(&self.definition())
+    }
+}
+
+// ===
+// AlpacaOpenApi
+// ===
+/// The tools imported from an OpenAPI 3 document.
+///
+/// Each operation becomes an `AlpacaFunctionOpenApi` that sends its requests
+/// through the given `AlpacaHttpTransport`.
+pub struct AlpacaOpenApi {
+    base_url: String,
+    operations: Vec<AlpacaOpenApiOperation>,
+    transport: Arc<dyn AlpacaHttpTransport>,
+}
+
+impl AlpacaOpenApi {
+    /// Imports the operations of an OpenAPI 3 document in JSON format.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The OpenAPI document as a JSON string
+    /// * `transport` - The transport used to send the requests of the imported tools
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AlpacaOpenApi)` - If the document could be imported
+    /// * `Err(String)` - A description of the problem otherwise
+    pub fn from_string(
+        document: &str,
+        transport: Arc<dyn AlpacaHttpTransport>,
+    ) -> Result<Self, String> {
+        let document: Value = serde_json::from_str(document)
+            .map_err(|e| format!("Failed to parse the OpenAPI document: {}", e))?;
+
+        Self::from_value(&document, transport)
+    }
+
+    /// Imports the operations of an already parsed OpenAPI 3 document.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The OpenAPI document
+    /// * `transport` - The transport used to send the requests of the imported tools
+    pub fn from_value(
+        document: &Value,
+        transport: Arc<dyn AlpacaHttpTransport>,
+    ) -> Result<Self, String> {
+        let version = document["openapi"].as_str().unwrap_or_default();
+        if !version.starts_with("3.") {
+            return Err(format!(
+                "Unsupported OpenAPI version '{}'. Only OpenAPI 3 documents are supported.",
+                version
+            ));
+        }
+
+        let base_url = document["servers"][0]["url"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        let paths = document["paths"]
+            .as_object()
+            .ok_or("The OpenAPI document has no 'paths' object.")?;
+
+        let mut operations = Vec::new();
+        for (path, path_item) in paths {
+            let path_item = resolve(document, path_item)?;
+            let shared_parameters = path_item["parameters"].as_array();
+
+            for method in METHODS {
+                if let Some(operation) = path_item.get(method) {
+                    let operation =
+                        parse_operation(document, path, method, operation, shared_parameters)?;
+                    operations.push(operation);
+                }
+            }
+        }
+
+        Ok(AlpacaOpenApi {
+            base_url,
+            operations,
+            transport,
+        })
+    }
+
+    /// Gets the base URL the requests are sent to.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Overrides the base URL taken from the document's `servers` list.
+    pub fn set_base_url(&mut self, base_url: &str) {
+        self.base_url = base_url.to_string();
+    }
+
+    /// Gets the imported operations.
+    pub fn operations(&self) -> &Vec<AlpacaOpenApiOperation> {
+        &self.operations
+    }
+
+    /// Builds the tool prototype of every imported operation.
+    pub fn tool_protos(&self) -> Vec<AlpacaToolProto> {
+        self.operations
+            .iter()
+            .map(|operation| operation.tool_proto())
+            .collect()
+    }
+
+    /// Creates an executable function for every imported operation.
+    pub fn functions(&self) -> Vec<AlpacaFunctionOpenApi> {
+        self.operations
+            .iter()
+            .map(|operation| {
+                AlpacaFunctionOpenApi::new(
+                    operation.clone(),
+                    &self.base_url,
+                    Arc::clone(&self.transport),
+                )
+            })
+            .collect()
+    }
+
+    /// Adds a function for every imported operation to a collection of functions.
+    pub fn register_functions(&self, functions: &mut AlpacaFunctions) {
+        for function in self.functions() {
+            functions.add_function(Box::new(function));
+        }
+    }
+}
+
+// ---
+
+fn parse_operation(
+    document: &Value,
+    path: &str,
+    method: &str,
+    operation: &Value,
+    shared_parameters: Option<&Vec<Value>>,
+) -> Result<AlpacaOpenApiOperation, String> {
+    let name = match operation["operationId"].as_str() {
+        Some(operation_id) => operation_id.to_string(),
+        None => operation_name(method, path),
+    };
+
+    let description = operation["summary"]
+        .as_str()
+        .or(operation["description"].as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    // Operation parameters override path-level parameters with the same name and location
+    let mut parameters: Vec<AlpacaOpenApiParameter> = Vec::new();
+    let operation_parameters = operation["parameters"].as_array();
+    for parameter in shared_parameters
+        .into_iter()
+        .chain(operation_parameters)
+        .flatten()
+    {
+        if let Some(parameter) = parse_parameter(document, parameter)? {
+            parameters.retain(|existing| {
+                existing.name != parameter.name || existing.location != parameter.location
+            });
+            parameters.push(parameter);
+        }
+    }
+
+    let mut body = None;
+    if let Some(request_body) = operation.get("requestBody") {
+        let request_body = resolve(document, request_body)?;
+        if let Some(schema) = request_body["content"]["application/json"].get("schema") {
+            let schema = inline_refs(document, schema, &mut Vec::new())?;
+            let required = request_body["required"].as_bool().unwrap_or(false);
+            body = Some(with_description(
+                ToolParameter::from_json_schema(&schema, required),
+                request_body["description"].as_str(),
+            ));
+        }
+    }
+
+    let responses = operation["responses"]
+        .as_object()
+        .map(|responses| {
+            responses
+                .iter()
+                .map(|(status, response)| {
+                    let description = resolve(document, response)
+                        .ok()
+                        .and_then(|response| response["description"].as_str())
+                        .unwrap_or_default();
+                    (status.clone(), description.to_string())
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(AlpacaOpenApiOperation {
+        name,
+        method: method.to_uppercase(),
+        path: path.to_string(),
+        description,
+        parameters,
+        body,
+        responses,
+    })
+}
+
+fn parse_parameter(
+    document: &Value,
+    parameter: &Value,
+) -> Result<Option<AlpacaOpenApiParameter>, String> {
+    let parameter = resolve(document, parameter)?;
+
+    let name = parameter["name"]
+        .as_str()
+        .ok_or("An OpenAPI parameter is missing its 'name'.")?;
+
+    // Cookie parameters cannot be expressed by a tool call and are skipped
+    let location = match parameter["in"].as_str() {
+        Some("path") => AlpacaOpenApiLocation::Path,
+        Some("query") => AlpacaOpenApiLocation::Query,
+        Some("header") => AlpacaOpenApiLocation::Header,
+        _ => return Ok(None),
+    };
+
+    // Path parameters are always required
+    let required =
+        location == AlpacaOpenApiLocation::Path || parameter["required"].as_bool().unwrap_or(false);
+
+    let schema = match parameter.get("schema") {
+        Some(schema) => inline_refs(document, schema, &mut Vec::new())?,
+        None => serde_json::json!({ "type": "string" }),
+    };
+
+    Ok(Some(AlpacaOpenApiParameter {
+        name: name.to_string(),
+        location,
+        required,
+        schema: with_description(
+            ToolParameter::from_json_schema(&schema, required),
+            parameter["description"].as_str(),
+        ),
+    }))
+}
+
+/// Adds a description to a parameter whose schema did not carry one.
+fn with_description(parameter: ToolParameter, description: Option<&str>) -> ToolParameter {
+    let Some(description) = description else {
+        return parameter;
+    };
+
+    match parameter {
+        ToolParameter::Type(kind) => {
+            let schema = crate::tool_model::ToolParameterSchema::new(kind).description(description);
+            ToolParameter::Schema(schema)
+        }
+        ToolParameter::Schema(schema) if schema.get_description().is_none() => {
+            ToolParameter::Schema(schema.description(description))
+        }
+        parameter => parameter,
+    }
+}
+
+/// Derives a tool name such as `get_pets_pet_id` from the method and path.
+fn operation_name(method: &str, path: &str) -> String {
+    let mut name = method.to_string();
+    for part in path.split(|c: char| !c.is_ascii_alphanumeric()) {
+        if part.is_empty() {
+            continue;
+        }
+
+        name.push('_');
+        // Split camel case words, so that `petId` becomes `pet_id`
+        for (index, c) in part.chars().enumerate() {
+            if c.is_ascii_uppercase() && index > 0 {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+    }
+
+    name
+}
+
+/// Follows a top-level `$ref` to the object it points to.
+fn resolve<'a>(document: &'a Value, value: &'a Value) -> Result<&'a Value, String> {
+    let mut value = value;
+
+    for _ in 0..MAX_REF_DEPTH {
+        let Some(reference) = value.get("$ref").and_then(Value::as_str) else {
+            return Ok(value);
+        };
+
+        let pointer = reference
+            .strip_prefix('#')
+            .ok_or_else(|| format!("Unsupported external reference '{}'.", reference))?;
+        value = document
+            .pointer(pointer)
+            .ok_or_else(|| format!("Unresolved reference '{}'.", reference))?;
+    }
+
+    Err("Too many nested references in the OpenAPI document.".to_string())
+}
+
+/// Replaces every `$ref` inside a schema with the schema it points to.
+///
+/// `references` holds the references being inlined around `schema`. A
+/// reference to one of them is recursive, such as a tree node whose children
+/// are tree nodes, and is inlined as a plain object instead.
+fn inline_refs(
+    document: &Value,
+    schema: &Value,
+    references: &mut Vec<String>,
+) -> Result<Value, String> {
+    match schema {
+        Value::Object(object) => {
+            if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+                if references.iter().any(|outer| outer == reference) {
+                    return Ok(serde_json::json!({ "type": "object" }));
+                }
+                if references.len() >= MAX_REF_DEPTH {
+                    return Err("Too many nested references in the OpenAPI document.".to_string());
+                }
+
+                let pointer = reference
+                    .strip_prefix('#')
+                    .ok_or_else(|| format!("Unsupported external reference '{}'.", reference))?;
+                let target = document
+                    .pointer(pointer)
+                    .ok_or_else(|| format!("Unresolved reference '{}'.", reference))?;
+                references.push(reference.to_string());
+                let inlined = inline_refs(document, target, references);
+                references.pop();
+                return inlined;
+            }
+
+            let mut inlined = Map::new();
+            for (key, value) in object {
+                inlined.insert(key.clone(), inline_refs(document, value, references)?);
+            }
+            Ok(Value::Object(inlined))
+        }
+        Value::Array(values) => values
+            .iter()
+            .map(|value| inline_refs(document, value, references))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        value => Ok(value.clone()),
+    }
+}
+
+// ===
+// AlpacaOpenApi Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_transport::{AlpacaHttpRequest, AlpacaHttpResponse, AlpacaStdHttpTransport};
+    use crate::tool_proto::AlpacaToolParameterType;
+    use serde_json::json;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    const PETSTORE: &str = r##"{
+        "openapi": "3.0.3",
+        "servers": [{ "url": "http://pets.example" }],
+        "paths": {
+            "/pets": {
+                "post": {
+                    "operationId": "create_pet",
+                    "summary": "Creates a pet.",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": { "$ref": "#/components/schemas/NewPet" }
+                            }
+                        }
+                    },
+                    "responses": { "201": { "description": "The created pet." } }
+                }
+            },
+            "/pets/{petId}": {
+                "parameters": [{ "$ref": "#/components/parameters/PetId" }],
+                "get": {
+                    "summary": "Gets a pet by id.",
+                    "parameters": [
+                        {
+                            "name": "fields",
+                            "in": "query",
+                            "description": "The fields to return.",
+                            "schema": { "type": "array", "items": { "type": "string" } }
+                        },
+                        { "name": "session", "in": "cookie", "schema": { "type": "string" } }
+                    ],
+                    "responses": { "200": { "description": "The pet." } }
+                }
+            }
+        },
+        "components": {
+            "parameters": {
+                "PetId": {
+                    "name": "petId",
+                    "in": "path",
+                    "schema": { "type": "integer" }
+                }
+            },
+            "schemas": {
+                "NewPet": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "tag": { "type": "string" }
+                    }
+                }
+            }
+        }
+    }"##;
+
+    /// A transport that records requests and answers them with a fixed response.
+    struct RecordingTransport {
+        requests: Mutex<Vec<AlpacaHttpRequest>>,
+        response: AlpacaHttpResponse,
+    }
+
+    impl AlpacaHttpTransport for RecordingTransport {
+        fn send(&self, request: &AlpacaHttpRequest) -> Result<AlpacaHttpResponse, String> {
+            self.requests.lock().unwrap().push(request.clone());
+            Ok(self.response.clone())
+        }
+    }
+
+    /// Tests importing the operations and parameters of a document.
+    #[test]
+    fn test_import_operations() {
+        let transport = Arc::new(AlpacaStdHttpTransport::new());
+        let openapi = AlpacaOpenApi::from_string(PETSTORE, transport).unwrap();
+        assert_eq!(openapi.base_url(), "http://pets.example");
+
+        let names: Vec<&str> = openapi
+            .operations()
+            .iter()
+            .map(|operation| operation.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["create_pet", "get_pets_pet_id"]);
+
+        let get_pet = &openapi.operations()[1];
+        assert_eq!(get_pet.method, "GET");
+        assert_eq!(get_pet.parameters.len(), 2);
+        assert_eq!(get_pet.parameters[0].location, AlpacaOpenApiLocation::Path);
+        assert!(get_pet.parameters[0].required);
+        assert_eq!(
+            get_pet.parameters[1].schema.kind(),
+            AlpacaToolParameterType::Array
+        );
+
+        let protos = openapi.tool_protos();
+        let body = &protos[0].parameters().unwrap()[BODY_PARAMETER];
+        assert_eq!(body["type"], json!("object"));
+        assert_eq!(body["properties"]["name"], json!("string"));
+    }
+
+    /// Tests that deeply nested schemas and recursive references are inlined.
+    #[test]
+    fn test_inline_refs() {
+        let mut deep = json!({ "type": "string" });
+        for _ in 0..40 {
+            deep = json!({ "type": "object", "properties": { "child": deep } });
+        }
+        let document = json!({
+            "components": {
+                "schemas": {
+                    "Node": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "children": {
+                                "type": "array",
+                                "items": { "$ref": "#/components/schemas/Node" }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        let inlined = inline_refs(&document, &deep, &mut Vec::new()).unwrap();
+        assert_eq!(inlined, deep);
+
+        let node = json!({ "$ref": "#/components/schemas/Node" });
+        let inlined = inline_refs(&document, &node, &mut Vec::new()).unwrap();
+        assert_eq!(inlined["properties"]["name"], json!({ "type": "string" }));
+        assert_eq!(
+            inlined["properties"]["children"]["items"],
+            json!({ "type": "object" })
+        );
+    }
+
+    /// Tests that invoking an operation builds the expected request.
+    #[test]
+    fn test_execute_request() {
+        let transport = Arc::new(RecordingTransport {
+            requests: Mutex::new(Vec::new()),
+            response: AlpacaHttpResponse {
+                status: 201,
+                headers: Vec::new(),
+                body: br#"{"id": 7, "name": "Rex"}"#.to_vec(),
+            },
+        });
+
+        let openapi = AlpacaOpenApi::from_string(PETSTORE, transport.clone()).unwrap();
+        let mut functions = AlpacaFunctions::new();
+        openapi.register_functions(&mut functions);
+
+        let args = json!({"body": {"name": "Rex"}});
+        let output = functions.call_function("create_pet", Some(&args)).unwrap();
+        assert!(output.contains("\"status\": 201"));
+        assert!(output.contains("\"name\": \"Rex\""));
+
+        let args = json!({"petId": 7, "fields": ["name", "tag"]});
+        functions.call_function("get_pets_pet_id", Some(&args));
+
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].url, "http://pets.example/pets");
+        assert_eq!(requests[0].body.as_deref(), Some(&br#"{"name":"Rex"}"#[..]));
+        assert_eq!(
+            requests[1].url,
+            "http://pets.example/pets/7?fields=name&fields=tag"
+        );
+    }
+
+    /// Tests that a missing required parameter is reported without sending a request.
+    #[test]
+    fn test_execute_missing_parameter() {
+        let transport = Arc::new(RecordingTransport {
+            requests: Mutex::new(Vec::new()),
+            response: AlpacaHttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: Vec::new(),
+            },
+        });
+
+        let openapi = AlpacaOpenApi::from_string(PETSTORE, transport.clone()).unwrap();
+        let mut functions = AlpacaFunctions::new();
+        openapi.register_functions(&mut functions);
+
+        let output = functions
+            .call_function("get_pets_pet_id", Some(&json!({})))
+            .unwrap();
+        assert!(output.contains("Missing required argument 'petId'"));
+        assert!(transport.requests.lock().unwrap().is_empty());
+    }
+
+    /// Tests calling an imported operation against a local stand-in server.
+    #[test]
+    fn test_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0u8; 4096];
+            let read = stream.read(&mut buffer).unwrap();
+            let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+
+            let body = r#"{"id": 3, "name": "Tom"}"#;
+            let response = format!(
+                "HTTP/1.1 404 Not Found\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            request
+        });
+
+        let transport = Arc::new(AlpacaStdHttpTransport::new());
+        let mut openapi = AlpacaOpenApi::from_string(PETSTORE, transport).unwrap();
+        openapi.set_base_url(&base_url);
+
+        let mut functions = AlpacaFunctions::new();
+        openapi.register_functions(&mut functions);
+
+        let output = functions
+            .call_function("get_pets_pet_id", Some(&json!({"petId": 3})))
+            .unwrap();
+        assert!(output.contains("\"error\""));
+        assert!(output.contains("404"));
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /pets/3 HTTP/1.1\r\n"));
+    }
+}
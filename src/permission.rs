@@ -0,0 +1,81 @@
+// ===
+// AlpacaPermissionRequest
+// ===
+/// Describes a change or side effect an action wants to make.
+///
+/// Actions that modify files, run programs or reach the network build one of
+/// these and ask the host's `AlpacaPermissionPolicy` before going ahead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaPermissionRequest {
+    /// The name of the action asking for permission
+    pub action: String,
+    /// A one-line summary of what the action is about to do
+    pub summary: String,
+    /// Details for the host to review, such as a unified diff or a command line
+    pub details: String,
+}
+
+impl AlpacaPermissionRequest {
+    /// Creates a permission request.
+    pub fn new(action: &str, summary: &str, details: &str) -> Self {
+        AlpacaPermissionRequest {
+            action: action.to_string(),
+            summary: summary.to_string(),
+            details: details.to_string(),
+        }
+    }
+}
+
+// ===
+// AlpacaPermission
+// ===
+/// The decision of an `AlpacaPermissionPolicy`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlpacaPermission {
+    /// The action may go ahead
+    Allow,
+    /// The action must not go ahead, for the given reason
+    Deny(String),
+}
+
+// ===
+// AlpacaPermissionPolicy
+// ===
+/// Decides whether a mutating action may go ahead.
+///
+/// Any `Fn(&AlpacaPermissionRequest) -> AlpacaPermission` closure can be used as a policy,
+/// which makes it easy to prompt the user or consult a configuration.
+pub trait AlpacaPermissionPolicy {
+    /// Reviews a request and returns the decision.
+    fn check(&self, request: &AlpacaPermissionRequest) -> AlpacaPermission;
+}
+
+impl<F> AlpacaPermissionPolicy for F
+where
+    F: Fn(&AlpacaPermissionRequest) -> AlpacaPermission,
+{
+    fn check(&self, request: &AlpacaPermissionRequest) -> AlpacaPermission {
+        self(request)
+    }
+}
+
+// ===
+// AlpacaAllowAll / AlpacaDenyAll
+// ===
+/// A policy that allows every request.
+pub struct AlpacaAllowAll;
+
+impl AlpacaPermissionPolicy for AlpacaAllowAll {
+    fn check(&self, _request: &AlpacaPermissionRequest) -> AlpacaPermission {
+        AlpacaPermission::Allow
+    }
+}
+
+/// A policy that denies every request. This is the default for `AlpacaActions`.
+pub struct AlpacaDenyAll;
+
+impl AlpacaPermissionPolicy for AlpacaDenyAll {
+    fn check(&self, _request: &AlpacaPermissionRequest) -> AlpacaPermission {
+        AlpacaPermission::Deny("The host has not granted permission for this action.".to_string())
+    }
+}
@@ -0,0 +1,371 @@
+use crate::sandbox::AlpacaSandbox;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// The most modules listed for one crate.
+const MAX_MODULES: usize = 500;
+/// The deepest module nesting followed.
+const MAX_DEPTH: usize = 12;
+
+static MOD_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^[ \t]*(?:#\[[^\]]*\][ \t]*)*(pub(?:\s*\([^)]*\))?\s+)?mod\s+(r#)?([A-Za-z_][A-Za-z0-9_]*)\s*([;{])")
+        .unwrap()
+});
+static PATH_ATTRIBUTE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"#\[\s*path\s*=\s*"([^"]*)"\s*\]\s*$"#).unwrap());
+
+// ===
+// AlpacaRustModule
+// ===
+/// A module declared in a crate, found by following `mod` declarations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaRustModule {
+    /// The path of the module in the crate, such as `action::tests`
+    pub path: String,
+    /// The file holding the module, or `None` if it could not be found
+    pub file: Option<PathBuf>,
+    pub public: bool,
+    /// Whether the module is written inline, as `mod name { ... }`
+    pub inline: bool,
+}
+
+/// Lists the modules of a crate, starting from its root file (`lib.rs` or `main.rs`).
+///
+/// The `mod` declarations are read without compiling the crate, so modules
+/// declared by macros are not found. `#[path]` attributes are followed, but
+/// files outside `boundary` are not read, and their modules have no file.
+pub fn module_tree(root_file: &Path, boundary: &Path) -> Vec<AlpacaRustModule> {
+    let sandbox = AlpacaSandbox::new(boundary);
+    let mut modules = Vec::new();
+    if is_inside(&sandbox, root_file)
+        && let Ok(text) = fs::read_to_string(root_file)
+    {
+        let directory = root_file.parent().unwrap_or(Path::new("")).to_path_buf();
+        scan(&text, root_file, &directory, "", 0, &sandbox, &mut modules);
+    }
+    modules
+}
+
+/// Whether `path` is inside the sandbox once links and `..` are resolved.
+pub(crate) fn is_inside(sandbox: &AlpacaSandbox, path: &Path) -> bool {
+    sandbox.resolve(&path.to_string_lossy()).is_ok()
+}
+
+/// Scans one file, or the body of an inline module, for `mod` declarations.
+///
+/// `directory` is where the files of child modules are looked for.
+fn scan(
+    text: &str,
+    file: &Path,
+    directory: &Path,
+    prefix: &str,
+    depth: usize,
+    sandbox: &AlpacaSandbox,
+    modules: &mut Vec<AlpacaRustModule>,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+
+    let code = blank_non_code(text);
+    let mut skip_until = 0;
+    for captures in MOD_PATTERN.captures_iter(&code) {
+        let whole = captures.get(0).unwrap();
+        if whole.start() < skip_until {
+            continue;
+        }
+        if modules.len() >= MAX_MODULES {
+            return;
+        }
+
+        let name = &captures[3];
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}::{}", prefix, name)
+        };
+        let public = captures.get(1).is_some();
+
+        if &captures[4] == "{" {
+            let body_start = whole.end();
+            let body_end = closing_brace(&code, body_start);
+            skip_until = body_end;
+            modules.push(AlpacaRustModule {
+                path: path.clone(),
+                file: Some(file.to_path_buf()),
+                public,
+                inline: true,
+            });
+            scan(
+                &text[body_start..body_end],
+                file,
+                &directory.join(name),
+                &path,
+                depth + 1,
+                sandbox,
+                modules,
+            );
+            continue;
+        }
+
+        // A `#[path]` attribute on the lines just before the declaration
+        let attribute = PATH_ATTRIBUTE
+            .captures(text[..whole.start()].trim_end())
+            .map(|attribute| attribute[1].to_string());
+        let child = match attribute {
+            Some(relative) => Some(file.parent().unwrap_or(Path::new("")).join(relative)),
+            None => [
+                directory.join(format!("{}.rs", name)),
+                directory.join(name).join("mod.rs"),
+            ]
+            .into_iter()
+            .find(|candidate| candidate.is_file()),
+        }
+        .filter(|child| is_inside(sandbox, child));
+
+        modules.push(AlpacaRustModule {
+            path: path.clone(),
+            file: child.clone(),
+            public,
+            inline: false,
+        });
+
+        if let Some(child) = child
+            && let Ok(child_text) = fs::read_to_string(&child)
+        {
+            scan(
+                &child_text,
+                &child,
+                &child_directory(&child),
+                &path,
+                depth + 1,
+                sandbox,
+                modules,
+            );
+        }
+    }
+}
+
+/// The directory holding the children of a module file: its own directory for
+/// `mod.rs`, `lib.rs` and `main.rs`, and a directory named after it otherwise.
+fn child_directory(file: &Path) -> PathBuf {
+    let parent = file.parent().unwrap_or(Path::new(""));
+    match file.file_stem().and_then(|stem| stem.to_str()) {
+        Some("mod") | Some("lib") | Some("main") | None => parent.to_path_buf(),
+        Some(stem) => parent.join(stem),
+    }
+}
+
+/// Finds the end of the block whose `{` ends just before `start`.
+fn closing_brace(code: &str, start: usize) -> usize {
+    let mut depth = 1;
+    for (index, c) in code[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return start + index;
+                }
+            }
+            _ => {}
+        }
+    }
+    code.len()
+}
+
+/// Replaces comments and the contents of string and character literals with
+/// spaces, keeping every byte offset and line break, so that Rust source can
+/// be scanned with patterns without matching inside them.
+pub fn blank_non_code(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut output = bytes.to_vec();
+    let blank = |output: &mut Vec<u8>, from: usize, to: usize| {
+        for byte in &mut output[from..to] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    };
+
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'/' if bytes.get(index + 1) == Some(&b'/') => {
+                let end = text[index..]
+                    .find('\n')
+                    .map_or(bytes.len(), |end| index + end);
+                blank(&mut output, index, end);
+                index = end;
+            }
+            b'/' if bytes.get(index + 1) == Some(&b'*') => {
+                let mut depth = 0;
+                let mut end = index;
+                while end < bytes.len() {
+                    if bytes[end..].starts_with(b"/*") {
+                        depth += 1;
+                        end += 2;
+                    } else if bytes[end..].starts_with(b"*/") {
+                        depth -= 1;
+                        end += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        end += 1;
+                    }
+                }
+                blank(&mut output, index, end);
+                index = end;
+            }
+            b'r' if is_raw_string_start(bytes, index) => {
+                let hashes = bytes[index + 1..]
+                    .iter()
+                    .take_while(|&&byte| byte == b'#')
+                    .count();
+                let open = index + 1 + hashes + 1;
+                let terminator = format!("\"{}", "#".repeat(hashes));
+                let end = text[open..]
+                    .find(&terminator)
+                    .map_or(bytes.len(), |end| open + end);
+                blank(&mut output, open, end);
+                index = (end + terminator.len()).min(bytes.len());
+            }
+            b'"' => {
+                let mut end = index + 1;
+                while end < bytes.len() && bytes[end] != b'"' {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                let end = end.min(bytes.len());
+                blank(&mut output, index + 1, end);
+                index = end + 1;
+            }
+            b'\'' => {
+                // A character literal, rather than a lifetime, closes within a few bytes
+                let end = if bytes.get(index + 1) == Some(&b'\\') {
+                    bytes[index + 2..]
+                        .iter()
+                        .take(10)
+                        .position(|&byte| byte == b'\'')
+                        .map(|offset| index + 2 + offset)
+                } else {
+                    text[index + 1..]
+                        .chars()
+                        .next()
+                        .map(|c| index + 1 + c.len_utf8())
+                        .filter(|&end| bytes.get(end) == Some(&b'\''))
+                };
+                match end {
+                    Some(end) => {
+                        blank(&mut output, index + 1, end);
+                        index = end + 1;
+                    }
+                    None => index += 1,
+                }
+            }
+            _ => index += 1,
+        }
+    }
+
+    // Only ASCII bytes were replaced, and whole characters at that
+    String::from_utf8(output).unwrap_or_else(|_| text.to_string())
+}
+
+fn is_raw_string_start(bytes: &[u8], index: usize) -> bool {
+    let preceded_by_identifier =
+        index > 0 && (bytes[index - 1].is_ascii_alphanumeric() || bytes[index - 1] == b'_');
+    let hashes = bytes[index + 1..]
+        .iter()
+        .take_while(|&&byte| byte == b'#')
+        .count();
+    !preceded_by_identifier && bytes.get(index + 1 + hashes) == Some(&b'"')
+}
+
+// ===
+// AlpacaRustModule Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that comments and literals are blanked without moving offsets.
+    #[test]
+    fn test_blank_non_code() {
+        let text = "let a = \"mod x;\"; // mod y;\nlet c = '{'; /* mod\nz; */ mod w;\nlet r = r#\"}\"#; fn f<'a>() {}";
+        let code = blank_non_code(text);
+
+        assert_eq!(code.len(), text.len());
+        assert_eq!(code.lines().count(), text.lines().count());
+        assert!(!code.contains("mod x") && !code.contains("mod y") && !code.contains('z'));
+        assert!(code.contains("mod w;"));
+        assert!(code.contains("fn f<'a>() {}"));
+        assert_eq!(code.matches(['{', '}']).count(), 2);
+    }
+
+    /// Tests following file, directory, inline and `#[path]` modules.
+    #[test]
+    fn test_module_tree() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("src");
+        fs::create_dir_all(src.join("net")).unwrap();
+        fs::create_dir_all(src.join("util")).unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "pub mod net;\nmod util;\n// mod commented;\n#[path = \"other/special.rs\"]\npub(crate) mod special;\n#[cfg(test)]\nmod tests {\n    mod nested {}\n}\n",
+        )
+        .unwrap();
+        fs::write(src.join("net.rs"), "pub mod http;\n").unwrap();
+        fs::write(src.join("net").join("http.rs"), "").unwrap();
+        fs::write(src.join("util").join("mod.rs"), "mod missing;\n").unwrap();
+
+        let modules = module_tree(&src.join("lib.rs"), temp_dir.path());
+        let paths: Vec<&str> = modules.iter().map(|module| module.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "net",
+                "net::http",
+                "util",
+                "util::missing",
+                "special",
+                "tests",
+                "tests::nested"
+            ]
+        );
+
+        assert_eq!(modules[1].file, Some(src.join("net").join("http.rs")));
+        assert!(modules[0].public && !modules[2].public);
+        assert_eq!(modules[3].file, None);
+        assert_eq!(modules[4].file, Some(src.join("other/special.rs")));
+        assert!(modules[4].public);
+        assert!(modules[5].inline);
+    }
+
+    /// Tests that files outside the boundary are not read.
+    #[test]
+    fn test_module_tree_boundary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("project");
+        let src = root.join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "#[path = \"../../outside.rs\"]\nmod outside;\n#[path = \"/etc/hostname\"]\nmod absolute;\nmod inner;\n",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("outside.rs"), "mod secret;\n").unwrap();
+        fs::write(src.join("inner.rs"), "").unwrap();
+
+        let modules = module_tree(&src.join("lib.rs"), &root);
+        let paths: Vec<&str> = modules.iter().map(|module| module.path.as_str()).collect();
+        assert_eq!(paths, ["outside", "absolute", "inner"]);
+        assert_eq!(modules[0].file, None);
+        assert_eq!(modules[1].file, None);
+        assert_eq!(modules[2].file, Some(src.join("inner.rs")));
+
+        assert!(module_tree(&temp_dir.path().join("outside.rs"), &root).is_empty());
+    }
+}
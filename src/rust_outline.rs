@@ -0,0 +1,511 @@
+use proc_macro2::{LineColumn, Span};
+use syn::spanned::Spanned;
+use syn::{AttrStyle, Attribute, Expr, ImplItem, Item, Lit, Meta, TraitItem, Visibility};
+
+/// The longest signature kept, in characters.
+const MAX_SIGNATURE: usize = 240;
+
+// ===
+// AlpacaOutlineItem
+// ===
+/// An item of a Rust source file, such as a function or an `impl` block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaOutlineItem {
+    /// `mod`, `struct`, `enum`, `union`, `trait`, `impl`, `fn`, `const`,
+    /// `static`, `type` or `macro`
+    pub kind: &'static str,
+    /// The name of the item; for an `impl` block, the implementing type
+    pub name: String,
+    /// The trait of an `impl` block
+    pub trait_name: Option<String>,
+    /// The visibility as written, such as `pub` or `pub(crate)`
+    pub visibility: Option<String>,
+    /// The declaration up to its body, such as `pub fn new() -> Self`
+    pub signature: String,
+    /// The first line of the doc comment
+    pub doc: Option<String>,
+    /// The first line of the item, including its doc comment and attributes
+    pub start_line: usize,
+    pub end_line: usize,
+    /// The items of a module, trait or `impl` block
+    pub children: Vec<AlpacaOutlineItem>,
+}
+
+impl AlpacaOutlineItem {
+    /// Whether a segment of an item path, such as `AlpacaActions` in
+    /// `AlpacaActions::new`, names this item. Generic parameters may be left
+    /// out, and `impl` blocks also answer to `Trait for Type`.
+    pub fn answers_to(&self, segment: &str) -> bool {
+        let bare = |name: &str| {
+            name.split('<')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+
+        self.name == segment
+            || bare(&self.name) == segment
+            || self.trait_name.as_ref().is_some_and(|trait_name| {
+                segment == format!("{} for {}", trait_name, self.name)
+                    || segment == format!("{} for {}", bare(trait_name), bare(&self.name))
+            })
+    }
+}
+
+/// Lists the items of a Rust source file.
+///
+/// # Returns
+///
+/// * `Ok(Vec<AlpacaOutlineItem>)` - The top-level items, with their children
+/// * `Err(String)` - A description of the syntax error and its line
+pub fn outline(source: &str) -> Result<Vec<AlpacaOutlineItem>, String> {
+    let result = match syn::parse_file(source) {
+        Ok(file) => {
+            let map = SourceMap::new(source);
+            Ok(file
+                .items
+                .iter()
+                .filter_map(|item| map.item(item))
+                .collect())
+        }
+        Err(error) => {
+            let start = error.span().start();
+            Err(format!(
+                "line {}, column {}: {}",
+                start.line,
+                start.column + 1,
+                error
+            ))
+        }
+    };
+
+    // Span locations are recorded per thread; release them once no span is left
+    proc_macro2::extra::invalidate_current_thread_spans();
+    result
+}
+
+/// Finds the items at a path such as `AlpacaActions::invoke` or `tests::test_parse`.
+///
+/// Several items can match, for example a method defined in two `impl` blocks
+/// of the same type. A segment may itself contain `::`, as in
+/// `fmt::Display for Point::fmt`.
+pub fn find_items<'a>(items: &'a [AlpacaOutlineItem], path: &str) -> Vec<&'a AlpacaOutlineItem> {
+    let segments: Vec<&str> = path.split("::").map(str::trim).collect();
+    let mut found = Vec::new();
+    find_in(items.iter().collect(), &segments, &mut found);
+    found.sort_by_key(|item| item.start_line);
+    found
+}
+
+fn find_in<'a>(
+    level: Vec<&'a AlpacaOutlineItem>,
+    segments: &[&str],
+    found: &mut Vec<&'a AlpacaOutlineItem>,
+) {
+    for length in 1..=segments.len() {
+        let name = segments[..length].join("::");
+        for item in level.iter().filter(|item| item.answers_to(&name)) {
+            if length == segments.len() {
+                if !found.iter().any(|other| std::ptr::eq(*other, *item)) {
+                    found.push(item);
+                }
+            } else {
+                find_in(item.children.iter().collect(), &segments[length..], found);
+            }
+        }
+    }
+}
+
+// ---
+
+struct SourceMap<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    fn new(text: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self { text, line_starts }
+    }
+
+    /// Converts a line and character column to a byte offset.
+    fn offset(&self, position: LineColumn) -> usize {
+        let Some(&line_start) = self.line_starts.get(position.line.saturating_sub(1)) else {
+            return self.text.len();
+        };
+        self.text[line_start..]
+            .char_indices()
+            .nth(position.column)
+            .map_or(self.text.len(), |(index, _)| line_start + index)
+    }
+
+    fn slice(&self, span: Span) -> &'a str {
+        let start = self.offset(span.start());
+        let end = self.offset(span.end()).max(start);
+        &self.text[start..end]
+    }
+
+    fn item(&self, item: &Item) -> Option<AlpacaOutlineItem> {
+        let entry = |kind, name: String, attrs: &[Attribute], vis: Option<&Visibility>, end| {
+            self.entry(kind, name, attrs, vis, item.span(), end)
+        };
+
+        Some(match item {
+            Item::Fn(function) => entry(
+                "fn",
+                function.sig.ident.to_string(),
+                &function.attrs,
+                Some(&function.vis),
+                Some(function.block.brace_token.span.open()),
+            ),
+            Item::Struct(structure) => {
+                let body = match &structure.fields {
+                    syn::Fields::Named(fields) => Some(fields.brace_token.span.open()),
+                    _ => None,
+                };
+                entry(
+                    "struct",
+                    structure.ident.to_string(),
+                    &structure.attrs,
+                    Some(&structure.vis),
+                    body,
+                )
+            }
+            Item::Enum(enumeration) => entry(
+                "enum",
+                enumeration.ident.to_string(),
+                &enumeration.attrs,
+                Some(&enumeration.vis),
+                Some(enumeration.brace_token.span.open()),
+            ),
+            Item::Union(union) => entry(
+                "union",
+                union.ident.to_string(),
+                &union.attrs,
+                Some(&union.vis),
+                Some(union.fields.brace_token.span.open()),
+            ),
+            Item::Const(constant) => entry(
+                "const",
+                constant.ident.to_string(),
+                &constant.attrs,
+                Some(&constant.vis),
+                Some(constant.eq_token.span),
+            ),
+            Item::Static(value) => entry(
+                "static",
+                value.ident.to_string(),
+                &value.attrs,
+                Some(&value.vis),
+                Some(value.eq_token.span),
+            ),
+            Item::Type(alias) => entry(
+                "type",
+                alias.ident.to_string(),
+                &alias.attrs,
+                Some(&alias.vis),
+                None,
+            ),
+            Item::Macro(mac) => {
+                let name = mac.ident.as_ref()?.to_string();
+                let mut outline = entry("macro", name.clone(), &mac.attrs, None, None);
+                outline.signature = format!("macro_rules! {}", name);
+                outline
+            }
+            Item::Trait(definition) => {
+                let mut outline = entry(
+                    "trait",
+                    definition.ident.to_string(),
+                    &definition.attrs,
+                    Some(&definition.vis),
+                    Some(definition.brace_token.span.open()),
+                );
+                outline.children = definition
+                    .items
+                    .iter()
+                    .filter_map(|member| self.trait_item(member))
+                    .collect();
+                outline
+            }
+            Item::Impl(block) => {
+                let mut outline = entry(
+                    "impl",
+                    collapse(self.slice(block.self_ty.span())),
+                    &block.attrs,
+                    None,
+                    Some(block.brace_token.span.open()),
+                );
+                outline.trait_name = block
+                    .trait_
+                    .as_ref()
+                    .map(|(_, path, _)| collapse(self.slice(path.span())));
+                outline.children = block
+                    .items
+                    .iter()
+                    .filter_map(|member| self.impl_item(member))
+                    .collect();
+                outline
+            }
+            Item::Mod(module) => {
+                let mut outline = entry(
+                    "mod",
+                    module.ident.to_string(),
+                    &module.attrs,
+                    Some(&module.vis),
+                    module.content.as_ref().map(|(brace, _)| brace.span.open()),
+                );
+                if let Some((_, items)) = &module.content {
+                    outline.children = items.iter().filter_map(|item| self.item(item)).collect();
+                }
+                outline
+            }
+            _ => return None,
+        })
+    }
+
+    fn impl_item(&self, member: &ImplItem) -> Option<AlpacaOutlineItem> {
+        let span = member.span();
+        Some(match member {
+            ImplItem::Fn(function) => self.entry(
+                "fn",
+                function.sig.ident.to_string(),
+                &function.attrs,
+                Some(&function.vis),
+                span,
+                Some(function.block.brace_token.span.open()),
+            ),
+            ImplItem::Const(constant) => self.entry(
+                "const",
+                constant.ident.to_string(),
+                &constant.attrs,
+                Some(&constant.vis),
+                span,
+                Some(constant.eq_token.span),
+            ),
+            ImplItem::Type(alias) => self.entry(
+                "type",
+                alias.ident.to_string(),
+                &alias.attrs,
+                Some(&alias.vis),
+                span,
+                None,
+            ),
+            _ => return None,
+        })
+    }
+
+    fn trait_item(&self, member: &TraitItem) -> Option<AlpacaOutlineItem> {
+        let span = member.span();
+        Some(match member {
+            TraitItem::Fn(function) => self.entry(
+                "fn",
+                function.sig.ident.to_string(),
+                &function.attrs,
+                None,
+                span,
+                function
+                    .default
+                    .as_ref()
+                    .map(|block| block.brace_token.span.open()),
+            ),
+            TraitItem::Const(constant) => self.entry(
+                "const",
+                constant.ident.to_string(),
+                &constant.attrs,
+                None,
+                span,
+                constant.default.as_ref().map(|(eq, _)| eq.span),
+            ),
+            TraitItem::Type(alias) => self.entry(
+                "type",
+                alias.ident.to_string(),
+                &alias.attrs,
+                None,
+                span,
+                None,
+            ),
+            _ => return None,
+        })
+    }
+
+    /// Builds an item. The signature runs from after the outer attributes to
+    /// `body`, the start of the body, or to the end of the item.
+    fn entry(
+        &self,
+        kind: &'static str,
+        name: String,
+        attrs: &[Attribute],
+        vis: Option<&Visibility>,
+        span: Span,
+        body: Option<Span>,
+    ) -> AlpacaOutlineItem {
+        let outer: Vec<&Attribute> = attrs
+            .iter()
+            .filter(|attribute| matches!(attribute.style, AttrStyle::Outer))
+            .collect();
+
+        let item_start = self.offset(span.start());
+        let signature_start = outer
+            .last()
+            .map_or(item_start, |attribute| self.offset(attribute.span().end()));
+        let signature_end = body.map_or(self.offset(span.end()), |body| self.offset(body.start()));
+        let signature = collapse(&self.text[signature_start..signature_end.max(signature_start)]);
+
+        let visibility = match vis {
+            Some(Visibility::Public(_)) => Some("pub".to_string()),
+            Some(restricted @ Visibility::Restricted(_)) => {
+                Some(collapse(self.slice(restricted.span())))
+            }
+            _ => None,
+        };
+
+        AlpacaOutlineItem {
+            kind,
+            name,
+            trait_name: None,
+            visibility,
+            signature: truncate(signature),
+            doc: doc_line(&outer),
+            start_line: span.start().line,
+            end_line: span.end().line,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// The first non-empty line of the doc comment.
+fn doc_line(attrs: &[&Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("doc"))
+        .filter_map(|attribute| match &attribute.meta {
+            Meta::NameValue(pair) => match &pair.value {
+                Expr::Lit(literal) => match &literal.lit {
+                    Lit::Str(text) => Some(text.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .flat_map(|text| {
+            text.lines()
+                .map(|line| line.trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .find(|line| !line.is_empty())
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate(text: String) -> String {
+    if text.chars().count() <= MAX_SIGNATURE {
+        return text;
+    }
+    let mut text: String = text.chars().take(MAX_SIGNATURE).collect();
+    text.push('…');
+    text
+}
+
+// ===
+// AlpacaOutlineItem Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"use std::fmt;
+
+/// A point on the plane.
+///
+/// More details.
+#[derive(Debug)]
+pub struct Point<T> {
+    x: T,
+    y: T,
+}
+
+pub(crate) const ORIGIN: &str = "0,0";
+
+impl<T: Copy> Point<T> {
+    /// Creates a point.
+    pub fn new(x: T, y: T) -> Self {
+        Self { x, y }
+    }
+}
+
+impl<T> fmt::Display for Point<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "point")
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_new() {}
+}
+"#;
+
+    /// Tests listing items with their signatures, docs and lines.
+    #[test]
+    fn test_outline() {
+        let items = outline(SOURCE).unwrap();
+        let kinds: Vec<(&str, &str)> = items
+            .iter()
+            .map(|item| (item.kind, item.name.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("struct", "Point"),
+                ("const", "ORIGIN"),
+                ("impl", "Point<T>"),
+                ("impl", "Point<T>"),
+                ("mod", "tests")
+            ]
+        );
+
+        let point = &items[0];
+        assert_eq!(point.signature, "pub struct Point<T>");
+        assert_eq!(point.doc.as_deref(), Some("A point on the plane."));
+        assert_eq!((point.start_line, point.end_line), (3, 10));
+
+        assert_eq!(items[1].signature, "pub(crate) const ORIGIN: &str");
+        assert_eq!(items[1].visibility.as_deref(), Some("pub(crate)"));
+
+        let new = &items[2].children[0];
+        assert_eq!(new.signature, "pub fn new(x: T, y: T) -> Self");
+        assert_eq!((new.start_line, new.end_line), (15, 18));
+        assert_eq!(items[3].trait_name.as_deref(), Some("fmt::Display"));
+        assert_eq!(items[4].children[0].name, "test_new");
+    }
+
+    /// Tests finding items by path.
+    #[test]
+    fn test_find_items() {
+        let items = outline(SOURCE).unwrap();
+
+        let found = find_items(&items, "Point::new");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].start_line, 15);
+
+        assert_eq!(find_items(&items, "Point").len(), 3);
+        assert_eq!(find_items(&items, "fmt::Display for Point::fmt").len(), 1);
+        assert_eq!(find_items(&items, "tests::test_new")[0].start_line, 28);
+        assert!(find_items(&items, "Point::missing").is_empty());
+    }
+
+    /// Tests that syntax errors report their line.
+    #[test]
+    fn test_outline_error() {
+        let error = outline("fn ok() {}\n\nfn broken( {}\n").unwrap_err();
+        assert!(error.starts_with("line 3, column "), "{}", error);
+    }
+}
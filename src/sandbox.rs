@@ -0,0 +1,212 @@
+use std::path::{Component, Path, PathBuf};
+
+// ===
+// AlpacaSandbox
+// ===
+/// Confines the file access of actions to a root directory.
+///
+/// The sandbox also keeps a virtual current directory, so that the model can
+/// use relative paths without the host process changing its own working
+/// directory. Every path given by the model is resolved against it and then
+/// checked to stay inside the root, including through symbolic links.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaSandbox {
+    root: PathBuf,
+    current_dir: PathBuf,
+}
+
+impl AlpacaSandbox {
+    /// Creates a sandbox rooted at `root`, with the current directory set to the root.
+    ///
+    /// The root is canonicalized when it exists, so that symbolic links in its
+    /// own path do not cause false escapes.
+    pub fn new(root: &Path) -> Self {
+        let root = root.canonicalize().unwrap_or_else(|_| normalize(root));
+
+        AlpacaSandbox {
+            current_dir: root.clone(),
+            root,
+        }
+    }
+
+    /// Gets the root directory of the sandbox.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Gets the virtual current directory.
+    pub fn current_dir(&self) -> &Path {
+        &self.current_dir
+    }
+
+    /// Changes the virtual current directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path relative to the current directory, or an absolute path inside the root
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the directory exists inside the sandbox
+    /// * `Err(String)` - A description of the problem otherwise
+    pub fn set_current_dir(&mut self, path: &str) -> Result<(), String> {
+        let resolved = self.resolve(path)?;
+        if !resolved.is_dir() {
+            return Err(format!("'{}' is not a directory.", path));
+        }
+
+        self.current_dir = resolved;
+        Ok(())
+    }
+
+    /// Resolves a path given by the model to an absolute path inside the sandbox.
+    ///
+    /// The path does not need to exist. When it does, or when one of its parent
+    /// directories does, symbolic links are followed before the check is made.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(PathBuf)` - The absolute path
+    /// * `Err(String)` - A description of the problem if the path leaves the sandbox
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let joined = normalize(&self.current_dir.join(path));
+
+        // Canonicalize the deepest existing ancestor, so that links are followed
+        let mut existing = joined.as_path();
+        let mut remainder = Vec::new();
+        let resolved = loop {
+            match existing.canonicalize() {
+                Ok(canonical) => {
+                    let mut resolved = canonical;
+                    for part in remainder.iter().rev() {
+                        resolved.push(part);
+                    }
+                    break resolved;
+                }
+                Err(_) => match (existing.parent(), existing.file_name()) {
+                    (Some(parent), Some(name)) => {
+                        remainder.push(name.to_os_string());
+                        existing = parent;
+                    }
+                    _ => break joined.clone(),
+                },
+            }
+        };
+
+        if !resolved.starts_with(&self.root) {
+            return Err(format!(
+                "The path '{}' is outside of the accessible directory.",
+                path
+            ));
+        }
+
+        Ok(resolved)
+    }
+
+    /// Converts an absolute path inside the sandbox to a path relative to the current directory.
+    ///
+    /// Paths outside the current directory are returned relative to the root,
+    /// prefixed with `/`, so that the model can still refer to them.
+    pub fn display_path(&self, path: &Path) -> String {
+        if let Ok(relative) = path.strip_prefix(&self.current_dir) {
+            let text = relative.to_string_lossy().to_string();
+            return if text.is_empty() {
+                ".".to_string()
+            } else {
+                text
+            };
+        }
+
+        match path.strip_prefix(&self.root) {
+            Ok(relative) => format!("/{}", relative.to_string_lossy()),
+            Err(_) => path.to_string_lossy().to_string(),
+        }
+    }
+}
+
+impl Default for AlpacaSandbox {
+    /// Creates a sandbox rooted at the process' current directory.
+    fn default() -> Self {
+        Self::new(&std::env::current_dir().unwrap_or_default())
+    }
+}
+
+/// Removes `.` and `..` components without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// ===
+// AlpacaSandbox Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Tests resolving relative paths, including ones that do not exist yet.
+    #[test]
+    fn test_resolve() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir(temp_dir.path().join("src")).unwrap();
+        let sandbox = AlpacaSandbox::new(temp_dir.path());
+        let root = temp_dir.path().canonicalize().unwrap();
+
+        assert_eq!(sandbox.resolve("src").unwrap(), root.join("src"));
+        assert_eq!(
+            sandbox.resolve("src/../new/file.txt").unwrap(),
+            root.join("new/file.txt")
+        );
+    }
+
+    /// Tests that paths leaving the root are rejected.
+    #[test]
+    fn test_resolve_escape() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sandbox = AlpacaSandbox::new(temp_dir.path());
+
+        assert!(sandbox.resolve("../outside.txt").is_err());
+        assert!(sandbox.resolve("/etc/passwd").is_err());
+    }
+
+    /// Tests that symbolic links pointing outside the root are rejected.
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_symlink_escape() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), temp_dir.path().join("link")).unwrap();
+        let sandbox = AlpacaSandbox::new(temp_dir.path());
+
+        assert!(sandbox.resolve("link/file.txt").is_err());
+    }
+
+    /// Tests changing the virtual current directory.
+    #[test]
+    fn test_set_current_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join("a/b")).unwrap();
+        let mut sandbox = AlpacaSandbox::new(temp_dir.path());
+
+        sandbox.set_current_dir("a/b").unwrap();
+        assert_eq!(
+            sandbox.display_path(&sandbox.resolve("c.txt").unwrap()),
+            "c.txt"
+        );
+        assert_eq!(sandbox.display_path(&sandbox.resolve("..").unwrap()), "/a");
+
+        assert!(sandbox.set_current_dir("../../..").is_err());
+        assert!(sandbox.set_current_dir("missing").is_err());
+    }
+}
@@ -0,0 +1,493 @@
+use crate::file_walk::AlpacaWalkOptions;
+use crate::rust_modules::blank_non_code;
+use crate::rust_outline::{AlpacaOutlineItem, outline};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::SystemTime;
+
+/// The largest source file indexed.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// The most files indexed under one root.
+const MAX_FILES: usize = 20_000;
+
+static IDENTIFIER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:r#)?([A-Za-z_][A-Za-z0-9_]*)").unwrap());
+
+// ===
+// AlpacaSymbolIndex
+// ===
+/// An in-memory index of the Rust items defined under a directory, and of
+/// every identifier used in its source files.
+///
+/// The index is refreshed incrementally: only files whose size or
+/// modification time changed since the last refresh are read again. Lookups
+/// are by name, so references are found textually rather than resolved.
+#[derive(Debug, Default)]
+pub struct AlpacaSymbolIndex {
+    root: Option<PathBuf>,
+    files: HashMap<PathBuf, IndexedFile>,
+}
+
+/// An item definition found by the index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaSymbol {
+    /// The name of the item; for an `impl` block, the implementing type without generics
+    pub name: String,
+    /// The path of the item within its file, such as `AlpacaActions::invoke`
+    /// or `<AlpacaActionList as AlpacaActionTrait>::invoke`
+    pub qualified_name: String,
+    pub kind: &'static str,
+    /// The trait of an `impl` block, without generics
+    pub trait_name: Option<String>,
+    pub file: PathBuf,
+    /// The line of the name, or of the item if the name was not found on its own
+    pub line: usize,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub signature: String,
+    pub doc: Option<String>,
+}
+
+/// A use of an identifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpacaReference {
+    pub file: PathBuf,
+    pub line: usize,
+    /// The one-based column, in characters
+    pub column: usize,
+    /// `definition`, `call` (followed by `(` or `!`) or `reference`
+    pub kind: &'static str,
+}
+
+/// What a refresh of the index did.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AlpacaIndexRefresh {
+    /// The number of files in the index
+    pub files: usize,
+    /// The number of files read again
+    pub updated: usize,
+    /// The number of files dropped from the index
+    pub removed: usize,
+    /// The files that could not be parsed, with the error; their identifiers
+    /// are indexed but not their items
+    pub parse_errors: Vec<(PathBuf, String)>,
+}
+
+#[derive(Debug)]
+struct IndexedFile {
+    modified: Option<SystemTime>,
+    size: u64,
+    symbols: Vec<AlpacaSymbol>,
+    /// Each identifier, with its one-based line and column and whether it is a call
+    identifiers: HashMap<String, Vec<(usize, usize, bool)>>,
+    parse_error: Option<String>,
+}
+
+impl AlpacaSymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Brings the index up to date with the Rust files under `root`.
+    ///
+    /// Files ignored by `.gitignore` and `target` directories are skipped. An
+    /// index built for another root is discarded.
+    pub fn refresh(&mut self, root: &Path) -> Result<AlpacaIndexRefresh, String> {
+        if self.root.as_deref() != Some(root) {
+            self.root = Some(root.to_path_buf());
+            self.files.clear();
+        }
+
+        let options = AlpacaWalkOptions {
+            include: vec!["*.rs".to_string()],
+            exclude: vec!["target".to_string()],
+            ..AlpacaWalkOptions::default()
+        };
+
+        let mut refresh = AlpacaIndexRefresh::default();
+        let mut seen = HashSet::new();
+        for entry in options.walk(root)?.flatten() {
+            if seen.len() >= MAX_FILES {
+                break;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !metadata.is_file() || metadata.len() > MAX_FILE_SIZE {
+                continue;
+            }
+
+            let path = entry.into_path();
+            seen.insert(path.clone());
+            let modified = metadata.modified().ok();
+            if self
+                .files
+                .get(&path)
+                .is_some_and(|file| file.modified == modified && file.size == metadata.len())
+            {
+                continue;
+            }
+
+            let Ok(text) = fs::read_to_string(&path) else {
+                self.files.remove(&path);
+                continue;
+            };
+            let mut file = index_file(&path, &text);
+            file.modified = modified;
+            file.size = metadata.len();
+            self.files.insert(path, file);
+            refresh.updated += 1;
+        }
+
+        let before = self.files.len();
+        self.files.retain(|path, _| seen.contains(path));
+        refresh.removed = before - self.files.len();
+        refresh.files = self.files.len();
+
+        let mut errors: Vec<(PathBuf, String)> = self
+            .files
+            .iter()
+            .filter_map(|(path, file)| Some((path.clone(), file.parse_error.clone()?)))
+            .collect();
+        errors.sort();
+        refresh.parse_errors = errors;
+
+        Ok(refresh)
+    }
+
+    /// Finds the items named `name`, or whose qualified name ends with `name`,
+    /// as in `AlpacaActions::invoke`. `impl` blocks are left out.
+    pub fn definitions(&self, name: &str) -> Vec<&AlpacaSymbol> {
+        let suffix = format!("::{}", name);
+        let mut found: Vec<&AlpacaSymbol> = self
+            .symbols()
+            .filter(|symbol| symbol.kind != "impl")
+            .filter(|symbol| {
+                symbol.name == name
+                    || symbol.qualified_name == name
+                    || symbol.qualified_name.ends_with(&suffix)
+            })
+            .collect();
+        sort_symbols(&mut found);
+        found
+    }
+
+    /// Finds the `impl` blocks of a trait, or for a type.
+    pub fn implementations(&self, name: &str) -> Vec<&AlpacaSymbol> {
+        let mut found: Vec<&AlpacaSymbol> = self
+            .symbols()
+            .filter(|symbol| symbol.kind == "impl")
+            .filter(|symbol| symbol.name == name || symbol.trait_name.as_deref() == Some(name))
+            .collect();
+        sort_symbols(&mut found);
+        found
+    }
+
+    /// Lists the names of items that contain `name`, ignoring case, for suggestions.
+    pub fn similar_names(&self, name: &str, limit: usize) -> Vec<String> {
+        let lowercase = name.to_lowercase();
+        let mut names: Vec<String> = self
+            .symbols()
+            .filter(|symbol| symbol.kind != "impl")
+            .filter(|symbol| symbol.name.to_lowercase().contains(&lowercase))
+            .map(|symbol| symbol.name.clone())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        names.sort_by_key(|candidate| (candidate.len(), candidate.clone()));
+        names.truncate(limit);
+        names
+    }
+
+    /// Finds every use of the identifier `name`, sorted by file and position.
+    pub fn references(&self, name: &str) -> Vec<AlpacaReference> {
+        let mut references = Vec::new();
+        for (path, file) in &self.files {
+            let Some(occurrences) = file.identifiers.get(name) else {
+                continue;
+            };
+            let definitions: HashSet<usize> = file
+                .symbols
+                .iter()
+                .filter(|symbol| symbol.name == name && symbol.kind != "impl")
+                .map(|symbol| symbol.line)
+                .collect();
+
+            let mut defined = HashSet::new();
+            for &(line, column, call) in occurrences {
+                // The first use on a definition line is the definition itself
+                let kind = if definitions.contains(&line) && defined.insert(line) {
+                    "definition"
+                } else if call {
+                    "call"
+                } else {
+                    "reference"
+                };
+                references.push(AlpacaReference {
+                    file: path.clone(),
+                    line,
+                    column,
+                    kind,
+                });
+            }
+        }
+        references.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+        references
+    }
+
+    fn symbols(&self) -> impl Iterator<Item = &AlpacaSymbol> {
+        self.files.values().flat_map(|file| file.symbols.iter())
+    }
+}
+
+fn sort_symbols(symbols: &mut [&AlpacaSymbol]) {
+    symbols.sort_by(|a, b| (&a.file, a.start_line).cmp(&(&b.file, b.start_line)));
+}
+
+fn index_file(path: &Path, text: &str) -> IndexedFile {
+    let identifiers = identifiers(text);
+
+    let (items, parse_error) = match outline(text) {
+        Ok(items) => (items, None),
+        Err(error) => (Vec::new(), Some(error)),
+    };
+    let mut symbols = Vec::new();
+    for item in &items {
+        collect_symbols(item, "", path, &identifiers, &mut symbols);
+    }
+
+    IndexedFile {
+        modified: None,
+        size: 0,
+        symbols,
+        identifiers,
+        parse_error,
+    }
+}
+
+/// Finds the identifiers outside comments and literals.
+fn identifiers(text: &str) -> HashMap<String, Vec<(usize, usize, bool)>> {
+    let mut identifiers: HashMap<String, Vec<(usize, usize, bool)>> = HashMap::new();
+
+    for (index, line) in blank_non_code(text).lines().enumerate() {
+        for captures in IDENTIFIER.captures_iter(line) {
+            let name = captures.get(1).unwrap();
+            // Skip the tails of numbers, such as `u8` in `1u8`
+            if line[..captures.get(0).unwrap().start()]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_ascii_digit())
+            {
+                continue;
+            }
+            let call = line[name.end()..].trim_start().starts_with(['(', '!']);
+            let column = line[..name.start()].chars().count() + 1;
+            identifiers
+                .entry(name.as_str().to_string())
+                .or_default()
+                .push((index + 1, column, call));
+        }
+    }
+
+    identifiers
+}
+
+fn collect_symbols(
+    item: &AlpacaOutlineItem,
+    container: &str,
+    file: &Path,
+    identifiers: &HashMap<String, Vec<(usize, usize, bool)>>,
+    symbols: &mut Vec<AlpacaSymbol>,
+) {
+    let bare = |name: &str| {
+        name.split('<')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    };
+    let name = if item.kind == "impl" {
+        bare(&item.name)
+    } else {
+        item.name.clone()
+    };
+    let trait_name = item.trait_name.as_deref().map(bare);
+    let qualified_name = match (item.kind, &trait_name) {
+        ("impl", Some(trait_name)) => format!("<{} as {}>", name, trait_name),
+        _ if container.is_empty() => name.clone(),
+        _ => format!("{}::{}", container, name),
+    };
+
+    let line = identifiers
+        .get(&name)
+        .and_then(|occurrences| {
+            occurrences
+                .iter()
+                .map(|&(line, _, _)| line)
+                .find(|line| (item.start_line..=item.end_line).contains(line))
+        })
+        .unwrap_or(item.start_line);
+
+    symbols.push(AlpacaSymbol {
+        name,
+        qualified_name: qualified_name.clone(),
+        kind: item.kind,
+        trait_name,
+        file: file.to_path_buf(),
+        line,
+        start_line: item.start_line,
+        end_line: item.end_line,
+        signature: item.signature.clone(),
+        doc: item.doc.clone(),
+    });
+
+    // The children of an `impl` block belong to the type, not to a module
+    let container = if item.kind == "impl" && item.trait_name.is_none() {
+        symbols.last().unwrap().name.clone()
+    } else {
+        qualified_name
+    };
+    for child in &item.children {
+        collect_symbols(child, &container, file, identifiers, symbols);
+    }
+}
+
+// ===
+// AlpacaSymbolIndex Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIB: &str = r#"
+pub trait Shape {
+    fn area(&self) -> f64;
+}
+
+pub struct Square(pub f64);
+
+impl Shape for Square {
+    fn area(&self) -> f64 {
+        self.0 * self.0
+    }
+}
+
+impl Square {
+    /// Makes a unit square.
+    pub fn unit() -> Self {
+        Square(1.0)
+    }
+}
+"#;
+
+    const MAIN: &str = r#"
+use crate::Square;
+
+fn main() {
+    // Square in a comment, and "Square" in a string
+    let square = Square::unit();
+    println!("{}", square.area());
+}
+"#;
+
+    /// Tests indexing definitions and implementations.
+    #[test]
+    fn test_definitions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("lib.rs"), LIB).unwrap();
+        fs::write(temp_dir.path().join("main.rs"), MAIN).unwrap();
+
+        let mut index = AlpacaSymbolIndex::new();
+        let refresh = index.refresh(temp_dir.path()).unwrap();
+        assert_eq!((refresh.files, refresh.updated), (2, 2));
+
+        let unit = index.definitions("unit");
+        assert_eq!(unit.len(), 1);
+        assert_eq!(unit[0].qualified_name, "Square::unit");
+        assert_eq!((unit[0].line, unit[0].start_line), (16, 15));
+        assert_eq!(unit[0].doc.as_deref(), Some("Makes a unit square."));
+        assert_eq!(index.definitions("Square::unit").len(), 1);
+
+        let area: Vec<&str> = index
+            .definitions("area")
+            .iter()
+            .map(|symbol| symbol.qualified_name.as_str())
+            .collect();
+        assert_eq!(area, ["Shape::area", "<Square as Shape>::area"]);
+
+        let implementations = index.implementations("Shape");
+        assert_eq!(implementations.len(), 1);
+        assert_eq!(implementations[0].line, 8);
+        assert_eq!(index.implementations("Square").len(), 2);
+    }
+
+    /// Tests finding references outside comments and strings.
+    #[test]
+    fn test_references() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("lib.rs"), LIB).unwrap();
+        fs::write(temp_dir.path().join("main.rs"), MAIN).unwrap();
+        let mut index = AlpacaSymbolIndex::new();
+        index.refresh(temp_dir.path()).unwrap();
+
+        let references: Vec<(String, usize, &str)> = index
+            .references("Square")
+            .iter()
+            .map(|reference| {
+                (
+                    reference
+                        .file
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string(),
+                    reference.line,
+                    reference.kind,
+                )
+            })
+            .collect();
+        assert_eq!(
+            references,
+            [
+                ("lib.rs".to_string(), 6, "definition"),
+                ("lib.rs".to_string(), 8, "reference"),
+                ("lib.rs".to_string(), 14, "reference"),
+                ("lib.rs".to_string(), 17, "call"),
+                ("main.rs".to_string(), 2, "reference"),
+                ("main.rs".to_string(), 6, "reference"),
+            ]
+        );
+    }
+
+    /// Tests that a refresh only reads files that changed.
+    #[test]
+    fn test_incremental_refresh() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("lib.rs"), LIB).unwrap();
+        fs::write(temp_dir.path().join("main.rs"), MAIN).unwrap();
+        let mut index = AlpacaSymbolIndex::new();
+        index.refresh(temp_dir.path()).unwrap();
+
+        let refresh = index.refresh(temp_dir.path()).unwrap();
+        assert_eq!((refresh.files, refresh.updated, refresh.removed), (2, 0, 0));
+
+        fs::write(
+            temp_dir.path().join("lib.rs"),
+            format!("{}\npub fn circle() {{}}\n", LIB),
+        )
+        .unwrap();
+        fs::remove_file(temp_dir.path().join("main.rs")).unwrap();
+        fs::write(temp_dir.path().join("broken.rs"), "fn broken( {}\n").unwrap();
+
+        let refresh = index.refresh(temp_dir.path()).unwrap();
+        assert_eq!((refresh.files, refresh.updated, refresh.removed), (2, 2, 1));
+        assert_eq!(refresh.parse_errors.len(), 1);
+        assert_eq!(index.definitions("circle").len(), 1);
+        assert!(index.references("main").is_empty());
+        assert_eq!(index.references("broken").len(), 1);
+    }
+}
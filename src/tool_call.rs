@@ -1,3 +1,4 @@
+use crate::tool_error::AlpacaToolParseError;
 use serde_json::Value;
 
 // ===
@@ -31,14 +32,56 @@ impl AlpacaToolCall {
     ///
     /// # Returns
     ///
-    /// * `Ok(AlpacaToolCall)` - If the JSON was successfully parsed
-    /// * `Err(())` - If the JSON parsing failed
-    pub fn from_str(json: &str) -> Result<AlpacaToolCall, ()> {
-        let object = serde_json::from_str(json);
-        match object {
-            Ok(object) => Ok(AlpacaToolCall { object }),
-            Err(_) => Err(()),
+    /// * `Ok(AlpacaToolCall)` - If the JSON was successfully parsed and has the tool call shape
+    /// * `Err(AlpacaToolParseError)` - If the JSON is invalid or does not describe a tool call
+    pub fn from_str(json: &str) -> Result<AlpacaToolCall, AlpacaToolParseError> {
+        let object: Value =
+            serde_json::from_str(json).map_err(|e| AlpacaToolParseError::from_json_error(&e))?;
+
+        Self::from_value(object)
+    }
+
+    /// Creates an `AlpacaToolCall` from an already parsed JSON value.
+    ///
+    /// # Arguments
+    ///
+    /// * `object` - A JSON value representing a tool call
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AlpacaToolCall)` - If the value has the tool call shape
+    /// * `Err(AlpacaToolParseError)` - Describing the first problem found otherwise
+    pub fn from_value(object: Value) -> Result<AlpacaToolCall, AlpacaToolParseError> {
+        if !object.is_object() {
+            return Err(AlpacaToolParseError::NotAnObject);
         }
+
+        // The function name is required and must be a string
+        match object.get("function") {
+            None => return Err(AlpacaToolParseError::MissingFunction),
+            Some(function) if !function.is_string() => {
+                return Err(AlpacaToolParseError::WrongType {
+                    field: "function".to_string(),
+                    expected: "string",
+                });
+            }
+            Some(_) => {}
+        }
+
+        // The arguments are optional, but must be an object when present
+        if object
+            .get("arguments")
+            .is_some_and(|args| !args.is_object())
+        {
+            return Err(AlpacaToolParseError::ArgumentsNotObject);
+        }
+
+        Ok(AlpacaToolCall { object })
+    }
+
+    /// Gets the underlying JSON representation of the tool call.
+    pub fn as_value(&self) -> &Value {
+        &self.object
     }
 
     /// Converts the tool call to a formatted JSON string.
@@ -166,6 +209,57 @@ mod tests {
         assert!(result.is_err());
     }
 
+    /// Tests that invalid JSON reports the position of the error.
+    ///
+    /// Verifies that the line and column of the parse failure are preserved.
+    #[test]
+    fn test_from_string_invalid_position() {
+        let invalid_json = "{\n  \"function\": \"search\",\n  \"arguments\": {\"query\"}\n}";
+        let result = AlpacaToolCall::from_str(invalid_json);
+
+        match result {
+            Err(AlpacaToolParseError::InvalidJson { line, column, .. }) => {
+                assert_eq!(line, 3);
+                assert!(column > 0);
+            }
+            _ => panic!("Expected an InvalidJson error"),
+        }
+    }
+
+    /// Tests that a JSON value which is not an object is rejected.
+    #[test]
+    fn test_from_string_not_object() {
+        let result = AlpacaToolCall::from_str(r#"["search"]"#);
+        assert_eq!(result.err(), Some(AlpacaToolParseError::NotAnObject));
+    }
+
+    /// Tests that a tool call without a function name is rejected.
+    #[test]
+    fn test_from_string_missing_function() {
+        let result = AlpacaToolCall::from_str(r#"{"arguments":{"query":"rust"}}"#);
+        assert_eq!(result.err(), Some(AlpacaToolParseError::MissingFunction));
+    }
+
+    /// Tests that a function name which is not a string is rejected.
+    #[test]
+    fn test_from_string_function_wrong_type() {
+        let result = AlpacaToolCall::from_str(r#"{"function":42}"#);
+        assert_eq!(
+            result.err(),
+            Some(AlpacaToolParseError::WrongType {
+                field: "function".to_string(),
+                expected: "string",
+            })
+        );
+    }
+
+    /// Tests that an `arguments` field which is not an object is rejected.
+    #[test]
+    fn test_from_string_arguments_not_object() {
+        let result = AlpacaToolCall::from_str(r#"{"function":"search","arguments":"rust"}"#);
+        assert_eq!(result.err(), Some(AlpacaToolParseError::ArgumentsNotObject));
+    }
+
     /// Tests the retrieval of a function name from an `AlpacaToolCall`.
     ///
     /// Verifies that function() returns None for a new instance and
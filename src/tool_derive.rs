@@ -0,0 +1,221 @@
+//! Runtime support for the `#[alpaca_tool]` attribute macro.
+//!
+//! The macro turns a plain Rust function into a type that implements both
+//! `AlpacaFunction` and `AlpacaActionTrait`. The code it generates calls into
+//! the helpers below, so that the argument handling and the response formats
+//! stay identical to the hand-written functions and actions.
+
+use crate::action::AlpacaActions;
+use crate::function::AlpacaFunctions;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+pub use alpaca_macros::alpaca_tool;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+    pub use serde_json;
+}
+
+/// Deserializes tool arguments into the typed argument struct of a tool.
+///
+/// # Arguments
+///
+/// * `arguments` - The arguments object passed by the model, if any
+///
+/// # Returns
+///
+/// * `Ok(T)` - The typed arguments
+/// * `Err(String)` - A message describing which argument is missing or malformed
+pub fn parse_arguments<T: DeserializeOwned>(arguments: Option<&Value>) -> Result<T, String> {
+    let arguments = match arguments {
+        Some(arguments) => arguments.clone(),
+        None => json!({}),
+    };
+
+    serde_json::from_value(arguments).map_err(|e| format!("Invalid arguments: {}.", e))
+}
+
+/// Converts the return value of a tool into a JSON value.
+///
+/// # Returns
+///
+/// * `Ok(Value)` - The serialized output
+/// * `Err(String)` - A message if the output could not be serialized
+pub fn to_output<T: Serialize>(output: T) -> Result<Value, String> {
+    serde_json::to_value(output).map_err(|e| format!("Failed to serialize the output: {}.", e))
+}
+
+/// Formats the result of a tool invoked as an `AlpacaFunction`.
+///
+/// # Arguments
+///
+/// * `name` - The name of the function
+/// * `info` - The usage information appended to argument errors
+/// * `result` - The output of the function, or the error it reported
+pub fn function_response(name: &str, info: &str, result: Result<Value, ToolFailure>) -> String {
+    match result {
+        Ok(output) => AlpacaFunctions::ok(name, &output),
+        Err(ToolFailure::Arguments(error)) => {
+            format!("{}{}\n", AlpacaFunctions::error(name, &error), info)
+        }
+        Err(ToolFailure::Execution(error)) => AlpacaFunctions::error(name, &error),
+    }
+}
+
+/// Formats the result of a tool invoked as an action.
+///
+/// # Arguments
+///
+/// * `description` - The action description appended to argument errors
+/// * `result` - The output of the action, or the error it reported
+pub fn action_response(description: &str, result: Result<Value, ToolFailure>) -> String {
+    match result {
+        Ok(output) => format!("## Success\n\n{}\n", AlpacaActions::blockify(&output)),
+        Err(ToolFailure::Arguments(error)) => {
+            format!("## Error\n\n{}\n\n## Help\n{}", error, description)
+        }
+        Err(ToolFailure::Execution(error)) => format!("## Error\n\n{}\n", error),
+    }
+}
+
+// ===
+// ToolFailure
+// ===
+/// The ways a generated tool invocation can fail.
+pub enum ToolFailure {
+    /// The arguments supplied by the model did not match the function signature
+    Arguments(String),
+    /// The function itself returned an error
+    Execution(String),
+}
+
+// ===
+// Tool Derive Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use crate::action::{AlpacaActionTrait, AlpacaActions};
+    use crate::function::{AlpacaFunction, AlpacaFunctions};
+    use crate::tool_derive::alpaca_tool;
+    use crate::tool_proto::AlpacaToolParameterType;
+    use serde_json::json;
+
+    /// Repeats a word a number of times.
+    ///
+    /// The words are separated by a single space.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The word to repeat.
+    /// * `count` - How many times to repeat it.
+    /// * `separator` - The separator to use instead of a space.
+    #[alpaca_tool]
+    fn repeat_word(word: String, count: u32, separator: Option<String>) -> String {
+        let separator = separator.unwrap_or_else(|| " ".to_string());
+        vec![word; count as usize].join(&separator)
+    }
+
+    /// Divides one number by another.
+    #[alpaca_tool(name = "divide")]
+    fn checked_divide(numerator: f64, denominator: f64) -> Result<f64, String> {
+        if denominator == 0.0 {
+            return Err("Cannot divide by zero.".to_string());
+        }
+        Ok(numerator / denominator)
+    }
+
+    /// Tests that the original function is still callable.
+    #[test]
+    fn test_function_is_preserved() {
+        assert_eq!(repeat_word("a".to_string(), 2, None), "a a");
+    }
+
+    /// Tests the generated name, description and info.
+    #[test]
+    fn test_generated_metadata() {
+        let tool = RepeatWordTool::new();
+        assert_eq!(AlpacaFunction::name(&tool), "repeat_word");
+        assert_eq!(
+            AlpacaFunction::description(&tool),
+            "Repeats a word a number of times."
+        );
+        assert!(
+            tool.info()
+                .contains("The words are separated by a single space.")
+        );
+        assert!(tool.info().contains("\"function\": \"repeat_word\""));
+        assert!(AlpacaActionTrait::description(&tool).contains("\"action\": \"repeat_word\""));
+
+        assert_eq!(AlpacaFunction::name(&CheckedDivideTool::new()), "divide");
+    }
+
+    /// Tests the generated tool prototype.
+    #[test]
+    fn test_generated_definition() {
+        let definition = RepeatWordTool::definition();
+        let tool = definition.to_ollama_tool();
+        let properties = &tool["function"]["parameters"]["properties"];
+
+        assert_eq!(properties["word"]["type"], json!("string"));
+        assert_eq!(
+            properties["word"]["description"],
+            json!("The word to repeat.")
+        );
+        assert_eq!(properties["count"]["type"], json!("integer"));
+        assert_eq!(
+            tool["function"]["parameters"]["required"],
+            json!(["count", "word"])
+        );
+
+        let proto = RepeatWordTool::tool_proto();
+        assert_eq!(proto.function(), Some("repeat_word"));
+        assert_eq!(
+            proto.parameters().unwrap()["count"]["type"],
+            json!(AlpacaToolParameterType::Integer)
+        );
+    }
+
+    /// Tests calling the generated function with typed arguments.
+    #[test]
+    fn test_execute_function() {
+        let mut functions = AlpacaFunctions::new();
+        RepeatWordTool::register_function(&mut functions);
+        CheckedDivideTool::register_function(&mut functions);
+
+        let args = json!({"word": "hi", "count": 3, "separator": "-"});
+        let output = functions.call_function("repeat_word", Some(&args)).unwrap();
+        assert!(output.contains("\"ok\": \"hi-hi-hi\""));
+
+        let args = json!({"numerator": 1.0, "denominator": 0.0});
+        let output = functions.call_function("divide", Some(&args)).unwrap();
+        assert!(output.contains("Cannot divide by zero."));
+    }
+
+    /// Tests that argument errors are reported together with the usage information.
+    #[test]
+    fn test_execute_function_invalid_arguments() {
+        let mut functions = AlpacaFunctions::new();
+        RepeatWordTool::register_function(&mut functions);
+
+        let args = json!({"word": "hi", "count": "three"});
+        let output = functions.call_function("repeat_word", Some(&args)).unwrap();
+        assert!(output.contains("Invalid arguments"));
+        assert!(output.contains("# `repeat_word`"));
+    }
+
+    /// Tests invoking the generated tool as an action.
+    #[test]
+    fn test_invoke_action() {
+        let mut actions = AlpacaActions::new();
+        RepeatWordTool::register_action(&mut actions);
+
+        let message = "```json\n{\"action\": \"repeat_word\", \"word\": \"yo\", \"count\": 2}\n```";
+        let response = actions.invoke(message).unwrap();
+        assert!(response.contains("## Success"));
+        assert!(response.contains("\"yo yo\""));
+    }
+}
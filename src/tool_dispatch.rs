@@ -1,10 +1,48 @@
 use crate::tool_call::AlpacaToolCall;
+use crate::tool_error::AlpacaToolParseError;
+
+// ===
+// AlpacaRejectedToolCall
+// ===
+/// A tool call candidate found in a message that could not be parsed.
+///
+/// Keeping the rejected text together with the reason allows the host to tell
+/// the model what was wrong, rather than silently dropping its call.
+pub struct AlpacaRejectedToolCall {
+    text: String,
+    error: AlpacaToolParseError,
+}
+
+impl AlpacaRejectedToolCall {
+    /// Gets the text of the rejected tool call candidate.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Gets the reason the candidate was rejected.
+    pub fn error(&self) -> &AlpacaToolParseError {
+        &self.error
+    }
+
+    /// Creates a message that can be sent back to the model asking it to fix the call.
+    ///
+    /// # Returns
+    ///
+    /// A markdown string quoting the rejected tool call and explaining the error.
+    pub fn correction(&self) -> String {
+        format!(
+            "The following tool call could not be processed:\n```json\n{}\n```\nError: {}\nPlease correct the tool call and try again.\n",
+            self.text, self.error
+        )
+    }
+}
 
 // ===
 // AlapaToolDispatch
 // ===
 pub struct AlapacaToolDispatch {
     tool_calls: Vec<AlpacaToolCall>,
+    rejected: Vec<AlpacaRejectedToolCall>,
 }
 
 // ---
@@ -12,28 +50,46 @@ pub struct AlapacaToolDispatch {
 // ---
 impl AlapacaToolDispatch {
     pub fn new(message: &str) -> Self {
-        let tool_calls = Self::create_tool_calls(message);
+        let (tool_calls, rejected) = Self::create_tool_calls(message);
 
-        AlapacaToolDispatch { tool_calls }
+        AlapacaToolDispatch {
+            tool_calls,
+            rejected,
+        }
     }
 
     pub fn tool_calls(&self) -> &Vec<AlpacaToolCall> {
         &self.tool_calls
     }
+
+    /// Gets the tool call candidates that could not be parsed, along with their errors.
+    pub fn rejected(&self) -> &Vec<AlpacaRejectedToolCall> {
+        &self.rejected
+    }
 }
 
 // ---
 // AlapaToolDispatch: Private Methods
 // ---
 impl AlapacaToolDispatch {
-    fn create_tool_calls(message: &str) -> Vec<AlpacaToolCall> {
-        Self::find_tool_calls(message)
-            .iter()
-            .filter_map(|tool_call_text| AlpacaToolCall::from_str(*tool_call_text).ok())
-            .collect()
+    fn create_tool_calls(message: &str) -> (Vec<AlpacaToolCall>, Vec<AlpacaRejectedToolCall>) {
+        let mut tool_calls = Vec::new();
+        let mut rejected = Vec::new();
+
+        for tool_call_text in Self::find_tool_calls(message) {
+            match AlpacaToolCall::from_str(tool_call_text) {
+                Ok(tool_call) => tool_calls.push(tool_call),
+                Err(error) => rejected.push(AlpacaRejectedToolCall {
+                    text: tool_call_text.to_string(),
+                    error,
+                }),
+            }
+        }
+
+        (tool_calls, rejected)
     }
 
-    fn find_tool_calls<'a>(message: &'a str) -> Vec<&'a str> {
+    fn find_tool_calls(message: &str) -> Vec<&str> {
         const START_MARKER: &str = "```json";
         const END_MARKER: &str = "```";
 
@@ -63,3 +119,49 @@ impl AlapacaToolDispatch {
         results
     }
 }
+
+// ===
+// AlapaToolDispatch Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that valid tool calls are dispatched and invalid ones are kept as rejected.
+    #[test]
+    fn test_rejected_tool_calls() {
+        let message = r#"
+Let me look at the directory first.
+```json
+{"function": "dir", "arguments": {}}
+```
+And then read the file.
+```json
+{"arguments": {"file_name": "Cargo.toml"}}
+```
+"#;
+
+        let dispatch = AlapacaToolDispatch::new(message);
+        assert_eq!(dispatch.tool_calls().len(), 1);
+        assert_eq!(dispatch.tool_calls()[0].function(), Some("dir"));
+
+        assert_eq!(dispatch.rejected().len(), 1);
+        let rejected = &dispatch.rejected()[0];
+        assert_eq!(rejected.error(), &AlpacaToolParseError::MissingFunction);
+        assert!(rejected.text().contains("Cargo.toml"));
+        assert!(
+            rejected
+                .correction()
+                .contains("Missing required field 'function'")
+        );
+    }
+
+    /// Tests that a message without any JSON blocks produces no calls and no rejections.
+    #[test]
+    fn test_no_tool_calls() {
+        let dispatch = AlapacaToolDispatch::new("There is nothing to do here.");
+        assert!(dispatch.tool_calls().is_empty());
+        assert!(dispatch.rejected().is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+// ===
+// AlpacaToolParseError
+// ===
+/// Describes why a piece of text could not be parsed as a tool call or tool prototype.
+///
+/// Each variant carries enough detail to explain the problem back to the model,
+/// so that it can correct its output and try again.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlpacaToolParseError {
+    /// The text is not valid JSON.
+    InvalidJson {
+        /// The 1-based line on which the error was detected
+        line: usize,
+        /// The 1-based column at which the error was detected
+        column: usize,
+        /// The message reported by the JSON parser
+        message: String,
+    },
+    /// The JSON is valid, but the top-level value is not an object.
+    NotAnObject,
+    /// The object does not contain a `function` field.
+    MissingFunction,
+    /// The `arguments` field is present but is not an object.
+    ArgumentsNotObject,
+    /// A field is present but has the wrong type.
+    WrongType {
+        /// The name of the offending field
+        field: String,
+        /// The JSON type that was expected for the field
+        expected: &'static str,
+    },
+}
+
+impl AlpacaToolParseError {
+    /// Creates an `InvalidJson` error from a `serde_json` parse error.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error returned by `serde_json`
+    ///
+    /// # Returns
+    ///
+    /// An `AlpacaToolParseError::InvalidJson` carrying the error position and message.
+    pub fn from_json_error(error: &serde_json::Error) -> Self {
+        AlpacaToolParseError::InvalidJson {
+            line: error.line(),
+            column: error.column(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AlpacaToolParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlpacaToolParseError::InvalidJson {
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Invalid JSON at line {}, column {}: {}.",
+                line, column, message
+            ),
+            AlpacaToolParseError::NotAnObject => {
+                write!(f, "The tool call must be a JSON object.")
+            }
+            AlpacaToolParseError::MissingFunction => {
+                write!(f, "Missing required field 'function'.")
+            }
+            AlpacaToolParseError::ArgumentsNotObject => {
+                write!(f, "The 'arguments' field must be a JSON object.")
+            }
+            AlpacaToolParseError::WrongType { field, expected } => {
+                write!(f, "The '{}' field must be a {}.", field, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AlpacaToolParseError {}
+
+// ===
+// AlpacaToolParseError Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that a `serde_json` error is converted with its position preserved.
+    #[test]
+    fn test_from_json_error() {
+        let error = serde_json::from_str::<serde_json::Value>("{\n  \"function\": }").unwrap_err();
+        let parse_error = AlpacaToolParseError::from_json_error(&error);
+
+        match parse_error {
+            AlpacaToolParseError::InvalidJson { line, column, .. } => {
+                assert_eq!(line, 2);
+                assert!(column > 0);
+            }
+            _ => panic!("Expected an InvalidJson error"),
+        }
+    }
+
+    /// Tests the human-readable messages produced for each error variant.
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            AlpacaToolParseError::MissingFunction.to_string(),
+            "Missing required field 'function'."
+        );
+        assert_eq!(
+            AlpacaToolParseError::WrongType {
+                field: "function".to_string(),
+                expected: "string",
+            }
+            .to_string(),
+            "The 'function' field must be a string."
+        );
+    }
+}
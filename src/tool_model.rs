@@ -0,0 +1,803 @@
+use crate::tool_call::AlpacaToolCall;
+use crate::tool_error::AlpacaToolParseError;
+use crate::tool_proto::{AlpacaToolParameterType, AlpacaToolProto};
+use schemars::JsonSchema;
+use schemars::generate::SchemaSettings;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+// ===
+// ToolParameter
+// ===
+/// The declaration of a single tool parameter.
+///
+/// A parameter is either a bare type name (`"string"`), which is the format used by
+/// `AlpacaToolProto::add_parameter`, or a full schema that can carry a description
+/// and nested properties or array items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ToolParameter {
+    /// A parameter declared only by its type
+    Type(AlpacaToolParameterType),
+    /// A parameter declared with a full schema
+    Schema(ToolParameterSchema),
+}
+
+impl ToolParameter {
+    /// Gets the type of the parameter.
+    pub fn kind(&self) -> AlpacaToolParameterType {
+        match self {
+            ToolParameter::Type(kind) => *kind,
+            ToolParameter::Schema(schema) => schema.kind,
+        }
+    }
+
+    /// Returns `true` if the model must always provide this parameter.
+    pub fn is_required(&self) -> bool {
+        match self {
+            ToolParameter::Type(_) => true,
+            ToolParameter::Schema(schema) => !schema.optional,
+        }
+    }
+
+    /// Creates a parameter declaration from a JSON schema.
+    ///
+    /// Only the parts of the schema that a parameter can express are kept: the type,
+    /// the description, nested object properties and array items.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - A JSON schema object with all references already resolved
+    /// * `required` - Whether the model must always provide the parameter
+    pub fn from_json_schema(schema: &Value, required: bool) -> ToolParameter {
+        schema_parameter(schema, required)
+    }
+
+    /// Converts the parameter to the JSON schema format used by Ollama tool requests.
+    pub fn to_json_schema(&self) -> Value {
+        match self {
+            ToolParameter::Type(kind) => json!({ "type": json_schema_type(*kind) }),
+            ToolParameter::Schema(schema) => schema.to_json_schema(),
+        }
+    }
+}
+
+impl From<AlpacaToolParameterType> for ToolParameter {
+    fn from(kind: AlpacaToolParameterType) -> Self {
+        ToolParameter::Type(kind)
+    }
+}
+
+impl From<ToolParameterSchema> for ToolParameter {
+    fn from(schema: ToolParameterSchema) -> Self {
+        ToolParameter::Schema(schema)
+    }
+}
+
+// ===
+// ToolParameterSchema
+// ===
+/// A detailed parameter declaration, built fluently.
+///
+/// ```
+/// use alpaca_rs::tool_model::ToolParameterSchema;
+/// use alpaca_rs::tool_proto::AlpacaToolParameterType;
+///
+/// let range = ToolParameterSchema::object()
+///     .description("The lines to read.")
+///     .property("start", AlpacaToolParameterType::Integer)
+///     .property("end", AlpacaToolParameterType::Integer)
+///     .optional();
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolParameterSchema {
+    #[serde(rename = "type")]
+    kind: AlpacaToolParameterType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    optional: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<String, ToolParameter>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    items: Option<Box<ToolParameter>>,
+}
+
+impl ToolParameterSchema {
+    /// Creates a required parameter schema of the given type.
+    pub fn new(kind: AlpacaToolParameterType) -> Self {
+        ToolParameterSchema {
+            kind,
+            description: None,
+            optional: false,
+            properties: BTreeMap::new(),
+            items: None,
+        }
+    }
+
+    /// Creates an object parameter schema with no properties.
+    pub fn object() -> Self {
+        Self::new(AlpacaToolParameterType::Object)
+    }
+
+    /// Creates an array parameter schema whose elements follow `items`.
+    pub fn array(items: impl Into<ToolParameter>) -> Self {
+        let mut schema = Self::new(AlpacaToolParameterType::Array);
+        schema.items = Some(Box::new(items.into()));
+        schema
+    }
+
+    /// Sets the description shown to the model for this parameter.
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Marks the parameter as optional.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Adds a nested property to an object parameter.
+    pub fn property(mut self, name: &str, parameter: impl Into<ToolParameter>) -> Self {
+        self.properties.insert(name.to_string(), parameter.into());
+        self
+    }
+
+    /// Gets the type of the parameter.
+    pub fn kind(&self) -> AlpacaToolParameterType {
+        self.kind
+    }
+
+    /// Gets the description of the parameter, if one was set.
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Gets the nested properties of an object parameter.
+    pub fn properties(&self) -> &BTreeMap<String, ToolParameter> {
+        &self.properties
+    }
+
+    /// Gets the element declaration of an array parameter.
+    pub fn items(&self) -> Option<&ToolParameter> {
+        self.items.as_deref()
+    }
+
+    fn to_json_schema(&self) -> Value {
+        let mut schema = json!({ "type": json_schema_type(self.kind) });
+
+        if let Some(description) = &self.description {
+            schema["description"] = json!(description);
+        }
+
+        if !self.properties.is_empty() {
+            schema["properties"] = properties_to_json_schema(&self.properties);
+            schema["required"] = json!(required_names(&self.properties));
+        }
+
+        if let Some(items) = &self.items {
+            schema["items"] = items.to_json_schema();
+        }
+
+        schema
+    }
+}
+
+// ===
+// ToolDefinition
+// ===
+/// A strongly typed tool prototype.
+///
+/// Serializes to the same JSON as `AlpacaToolProto`, so the two can be converted
+/// back and forth without losing information. Fields this struct does not know
+/// about are preserved in `extra`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub function: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<BTreeMap<String, ToolParameter>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ToolDefinition {
+    /// Starts building a tool definition for the named function.
+    pub fn builder(function: &str) -> ToolDefinitionBuilder {
+        ToolDefinitionBuilder::new(function)
+    }
+
+    /// Creates a tool definition from the JSON schema of an argument type.
+    ///
+    /// The tool description and the parameter descriptions are taken from the doc
+    /// comments on `T` and its fields. `Option` fields become optional parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `function` - The name of the tool
+    pub fn from_arguments<T: JsonSchema>(function: &str) -> ToolDefinition {
+        let schema = argument_schema::<T>();
+        let mut builder = ToolDefinitionBuilder::new(function);
+
+        if let Some(description) = schema.get("description").and_then(Value::as_str) {
+            builder = builder.description(description);
+        }
+
+        for (name, parameter) in schema_properties(&schema) {
+            builder = builder.parameter(&name, parameter);
+        }
+
+        builder.build()
+    }
+
+    /// Converts the definition to its JSON representation.
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    /// Converts the definition to the tool format used by Ollama chat requests.
+    ///
+    /// # Returns
+    ///
+    /// A JSON value of the form `{"type": "function", "function": {...}}` that can be
+    /// passed in the `tools` array of an `ollie-rs` request.
+    pub fn to_ollama_tool(&self) -> Value {
+        let empty = BTreeMap::new();
+        let parameters = self.parameters.as_ref().unwrap_or(&empty);
+
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.function,
+                "description": self.description.clone().unwrap_or_default(),
+                "parameters": {
+                    "type": "object",
+                    "properties": properties_to_json_schema(parameters),
+                    "required": required_names(parameters),
+                }
+            }
+        })
+    }
+}
+
+impl FromStr for ToolDefinition {
+    type Err = AlpacaToolParseError;
+
+    /// Parses a tool definition from a JSON string.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ToolDefinition)` if the JSON describes a tool prototype
+    /// * `Err(AlpacaToolParseError)` describing the first problem found otherwise
+    fn from_str(json: &str) -> Result<Self, Self::Err> {
+        let proto = AlpacaToolProto::from_string(json)?;
+        ToolDefinition::try_from(&proto)
+    }
+}
+
+impl TryFrom<&AlpacaToolProto> for ToolDefinition {
+    type Error = AlpacaToolParseError;
+
+    fn try_from(proto: &AlpacaToolProto) -> Result<Self, Self::Error> {
+        // Re-validate the top level so that a default constructed prototype is rejected
+        let proto = AlpacaToolProto::from_value(proto.as_value().clone())?;
+
+        serde_json::from_value(proto.as_value().clone()).map_err(|_| {
+            AlpacaToolParseError::WrongType {
+                field: "parameters".to_string(),
+                expected: "map of parameter types or schemas",
+            }
+        })
+    }
+}
+
+impl From<&ToolDefinition> for AlpacaToolProto {
+    fn from(definition: &ToolDefinition) -> Self {
+        // A serialized definition always has the prototype shape
+        AlpacaToolProto::from_value(definition.to_value())
+            .unwrap_or_else(|_| AlpacaToolProto::new())
+    }
+}
+
+// ===
+// ToolDefinitionBuilder
+// ===
+/// Fluent builder for `ToolDefinition`.
+///
+/// ```
+/// use alpaca_rs::tool_model::{ToolDefinition, ToolParameterSchema};
+/// use alpaca_rs::tool_proto::AlpacaToolParameterType;
+///
+/// let definition = ToolDefinition::builder("read_file")
+///     .description("Outputs the contents of the specified text file.")
+///     .parameter("file_name", AlpacaToolParameterType::String)
+///     .parameter(
+///         "range",
+///         ToolParameterSchema::object()
+///             .property("start_line", AlpacaToolParameterType::Integer)
+///             .property("end_line", AlpacaToolParameterType::Integer)
+///             .optional(),
+///     )
+///     .build();
+///
+/// assert_eq!(definition.function, "read_file");
+/// ```
+pub struct ToolDefinitionBuilder {
+    definition: ToolDefinition,
+}
+
+impl ToolDefinitionBuilder {
+    /// Creates a builder for the named function.
+    pub fn new(function: &str) -> Self {
+        ToolDefinitionBuilder {
+            definition: ToolDefinition {
+                function: function.to_string(),
+                description: None,
+                parameters: None,
+                extra: Map::new(),
+            },
+        }
+    }
+
+    /// Sets the description of the tool.
+    pub fn description(mut self, description: &str) -> Self {
+        self.definition.description = Some(description.to_string());
+        self
+    }
+
+    /// Adds a parameter to the tool.
+    pub fn parameter(mut self, name: &str, parameter: impl Into<ToolParameter>) -> Self {
+        self.definition
+            .parameters
+            .get_or_insert_with(BTreeMap::new)
+            .insert(name.to_string(), parameter.into());
+        self
+    }
+
+    /// Finishes building the tool definition.
+    pub fn build(self) -> ToolDefinition {
+        self.definition
+    }
+}
+
+// ===
+// ToolCall
+// ===
+/// A strongly typed tool call.
+///
+/// Serializes to the same JSON as `AlpacaToolCall`. Fields this struct does not
+/// know about are preserved in `extra`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub function: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Map<String, Value>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ToolCall {
+    /// Creates a tool call for the named function with no arguments.
+    pub fn new(function: &str) -> Self {
+        ToolCall {
+            function: function.to_string(),
+            arguments: None,
+            extra: Map::new(),
+        }
+    }
+
+    /// Adds an argument to the tool call.
+    pub fn argument(mut self, name: &str, value: Value) -> Self {
+        self.arguments
+            .get_or_insert_with(Map::new)
+            .insert(name.to_string(), value);
+        self
+    }
+
+    /// Converts the tool call to its JSON representation.
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    /// Converts the tool call to the format used in Ollama chat messages.
+    ///
+    /// # Returns
+    ///
+    /// A JSON value of the form `{"function": {"name": ..., "arguments": {...}}}`.
+    pub fn to_ollama_tool_call(&self) -> Value {
+        json!({
+            "function": {
+                "name": self.function,
+                "arguments": self.arguments.clone().unwrap_or_default(),
+            }
+        })
+    }
+
+    /// Creates a tool call from an entry of the `tool_calls` array in an Ollama response.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A JSON value of the form `{"function": {"name": ..., "arguments": {...}}}`
+    pub fn from_ollama_tool_call(value: &Value) -> Result<ToolCall, AlpacaToolParseError> {
+        let function = value
+            .get("function")
+            .ok_or(AlpacaToolParseError::MissingFunction)?;
+
+        if !function.is_object() {
+            return Err(AlpacaToolParseError::WrongType {
+                field: "function".to_string(),
+                expected: "object",
+            });
+        }
+
+        let mut object = json!({ "function": function["name"] });
+        if let Some(arguments) = function.get("arguments") {
+            object["arguments"] = arguments.clone();
+        }
+
+        let tool_call = AlpacaToolCall::from_value(object)?;
+        Ok(ToolCall::from(&tool_call))
+    }
+}
+
+impl FromStr for ToolCall {
+    type Err = AlpacaToolParseError;
+
+    /// Parses a tool call from a JSON string.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ToolCall)` if the JSON describes a tool call
+    /// * `Err(AlpacaToolParseError)` describing the first problem found otherwise
+    fn from_str(json: &str) -> Result<Self, Self::Err> {
+        let tool_call = AlpacaToolCall::from_str(json)?;
+        Ok(ToolCall::from(&tool_call))
+    }
+}
+
+impl From<&AlpacaToolCall> for ToolCall {
+    fn from(tool_call: &AlpacaToolCall) -> Self {
+        // `AlpacaToolCall` only holds validated objects, so the fields are always present
+        let mut extra = tool_call
+            .as_value()
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+        let function = extra
+            .remove("function")
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let arguments = extra
+            .remove("arguments")
+            .and_then(|value| value.as_object().cloned());
+
+        ToolCall {
+            function,
+            arguments,
+            extra,
+        }
+    }
+}
+
+impl From<&ToolCall> for AlpacaToolCall {
+    fn from(tool_call: &ToolCall) -> Self {
+        // A serialized tool call always has the expected shape
+        AlpacaToolCall::from_value(tool_call.to_value()).unwrap_or_else(|_| AlpacaToolCall::new())
+    }
+}
+
+// ---
+
+/// Generates the JSON schema of an argument type, with all subschemas inlined.
+///
+/// # Returns
+///
+/// A JSON schema object describing `T`, as produced by `schemars`.
+pub fn argument_schema<T: JsonSchema>() -> Value {
+    let generator = SchemaSettings::draft07()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+
+    generator.into_root_schema_for::<T>().to_value()
+}
+
+fn schema_properties(schema: &Value) -> BTreeMap<String, ToolParameter> {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    schema["properties"]
+        .as_object()
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, property)| {
+                    let parameter = schema_parameter(property, required.contains(&name.as_str()));
+                    (name.clone(), parameter)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn schema_parameter(schema: &Value, required: bool) -> ToolParameter {
+    let kind = schema_kind(schema);
+    let mut parameter = match kind {
+        AlpacaToolParameterType::Array => match schema.get("items") {
+            Some(items) => ToolParameterSchema::array(schema_parameter(items, true)),
+            None => ToolParameterSchema::array(AlpacaToolParameterType::Object),
+        },
+        _ => ToolParameterSchema::new(kind),
+    };
+
+    if let Some(description) = schema.get("description").and_then(Value::as_str) {
+        parameter = parameter.description(description);
+    }
+
+    if kind == AlpacaToolParameterType::Object {
+        parameter.properties = schema_properties(schema);
+    }
+
+    if !required {
+        parameter = parameter.optional();
+    }
+
+    // Use the compact form when the schema carries nothing but the type
+    if parameter == ToolParameterSchema::new(kind) {
+        return ToolParameter::Type(kind);
+    }
+
+    ToolParameter::Schema(parameter)
+}
+
+fn schema_kind(schema: &Value) -> AlpacaToolParameterType {
+    // Optional values are declared as `["string", "null"]`
+    let kind = match &schema["type"] {
+        Value::String(kind) => Some(kind.as_str()),
+        Value::Array(kinds) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|kind| *kind != "null"),
+        _ => None,
+    };
+
+    match kind {
+        Some("string") => AlpacaToolParameterType::String,
+        Some("integer") => AlpacaToolParameterType::Integer,
+        Some("number") => AlpacaToolParameterType::Float,
+        Some("boolean") => AlpacaToolParameterType::Boolean,
+        Some("array") => AlpacaToolParameterType::Array,
+        _ => AlpacaToolParameterType::Object,
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn json_schema_type(kind: AlpacaToolParameterType) -> &'static str {
+    match kind {
+        AlpacaToolParameterType::String => "string",
+        AlpacaToolParameterType::Integer => "integer",
+        AlpacaToolParameterType::Float => "number",
+        AlpacaToolParameterType::Boolean => "boolean",
+        AlpacaToolParameterType::Object => "object",
+        AlpacaToolParameterType::Array => "array",
+    }
+}
+
+fn properties_to_json_schema(properties: &BTreeMap<String, ToolParameter>) -> Value {
+    let schema: Map<String, Value> = properties
+        .iter()
+        .map(|(name, parameter)| (name.clone(), parameter.to_json_schema()))
+        .collect();
+
+    Value::Object(schema)
+}
+
+fn required_names(properties: &BTreeMap<String, ToolParameter>) -> Vec<&str> {
+    properties
+        .iter()
+        .filter(|(_, parameter)| parameter.is_required())
+        .map(|(name, _)| name.as_str())
+        .collect()
+}
+
+// ===
+// Tool Model Tests
+// ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that a prototype built with `AlpacaToolProto` round-trips through `ToolDefinition`.
+    #[test]
+    fn test_definition_round_trip() {
+        let mut proto = AlpacaToolProto::new();
+        proto.set_function("file_info");
+        proto.set_description("Returns information about files.");
+        proto.add_parameter("path", AlpacaToolParameterType::String);
+        proto.add_parameter("limit", AlpacaToolParameterType::Integer);
+
+        let definition = ToolDefinition::try_from(&proto).unwrap();
+        assert_eq!(definition.function, "file_info");
+        assert_eq!(
+            definition.parameters.as_ref().unwrap()["limit"],
+            ToolParameter::Type(AlpacaToolParameterType::Integer)
+        );
+
+        let round_trip = AlpacaToolProto::from(&definition);
+        assert_eq!(round_trip.as_value(), proto.as_value());
+    }
+
+    /// Tests that unknown fields survive a round trip.
+    #[test]
+    fn test_definition_preserves_extra_fields() {
+        let json = r#"{"function":"search","parameters":{"query":"string"},"version":2}"#;
+        let definition = ToolDefinition::from_str(json).unwrap();
+        assert_eq!(definition.extra["version"], json!(2));
+
+        let expected: Value = serde_json::from_str(json).unwrap();
+        assert_eq!(definition.to_value(), expected);
+    }
+
+    /// Tests building a definition with nested parameters.
+    #[test]
+    fn test_builder_nested_parameters() {
+        let definition = ToolDefinition::builder("search")
+            .description("Searches files.")
+            .parameter("pattern", AlpacaToolParameterType::String)
+            .parameter(
+                "options",
+                ToolParameterSchema::object()
+                    .description("Search options.")
+                    .property("case_sensitive", AlpacaToolParameterType::Boolean)
+                    .property(
+                        "globs",
+                        ToolParameterSchema::array(AlpacaToolParameterType::String),
+                    )
+                    .optional(),
+            )
+            .build();
+
+        let value = definition.to_value();
+        assert_eq!(value["parameters"]["pattern"], json!("string"));
+        assert_eq!(value["parameters"]["options"]["type"], json!("object"));
+        assert_eq!(value["parameters"]["options"]["optional"], json!(true));
+        assert_eq!(
+            value["parameters"]["options"]["properties"]["globs"]["items"],
+            json!("string")
+        );
+
+        // The JSON representation parses back into the same definition
+        let parsed = ToolDefinition::from_str(&value.to_string()).unwrap();
+        assert_eq!(parsed, definition);
+    }
+
+    /// Tests the conversion of a definition into the Ollama tool format.
+    #[test]
+    fn test_to_ollama_tool() {
+        let definition = ToolDefinition::builder("read_file")
+            .description("Reads a file.")
+            .parameter("file_name", AlpacaToolParameterType::String)
+            .parameter(
+                "scale",
+                ToolParameterSchema::new(AlpacaToolParameterType::Float).optional(),
+            )
+            .build();
+
+        let tool = definition.to_ollama_tool();
+        assert_eq!(tool["type"], json!("function"));
+        assert_eq!(tool["function"]["name"], json!("read_file"));
+        assert_eq!(
+            tool["function"]["parameters"]["properties"]["scale"]["type"],
+            json!("number")
+        );
+        assert_eq!(
+            tool["function"]["parameters"]["required"],
+            json!(["file_name"])
+        );
+    }
+
+    /// Tests that a malformed nested parameter is reported as a type error.
+    #[test]
+    fn test_definition_invalid_parameter() {
+        let json = r#"{"function":"search","parameters":{"query":"text"}}"#;
+        let result = ToolDefinition::from_str(json);
+        assert!(matches!(
+            result,
+            Err(AlpacaToolParseError::WrongType { .. })
+        ));
+    }
+
+    /// Searches the files in a directory.
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct SearchArguments {
+        /// The pattern to search for.
+        pattern: String,
+        /// The maximum number of results.
+        max_results: Option<u32>,
+        /// The globs of the files to include.
+        include: Vec<String>,
+        options: SearchOptions,
+    }
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct SearchOptions {
+        case_sensitive: bool,
+    }
+
+    /// Tests generating a definition from the schema of an argument type.
+    #[test]
+    fn test_from_arguments() {
+        let definition = ToolDefinition::from_arguments::<SearchArguments>("search");
+        assert_eq!(
+            definition.description.as_deref(),
+            Some("Searches the files in a directory.")
+        );
+
+        let parameters = definition.parameters.as_ref().unwrap();
+        assert_eq!(
+            parameters["pattern"],
+            ToolParameter::Schema(
+                ToolParameterSchema::new(AlpacaToolParameterType::String)
+                    .description("The pattern to search for.")
+            )
+        );
+        assert!(!parameters["max_results"].is_required());
+        assert_eq!(
+            parameters["max_results"].kind(),
+            AlpacaToolParameterType::Integer
+        );
+        assert_eq!(parameters["include"].kind(), AlpacaToolParameterType::Array);
+
+        let tool = definition.to_ollama_tool();
+        assert_eq!(
+            tool["function"]["parameters"]["properties"]["options"]["properties"]["case_sensitive"]
+                ["type"],
+            json!("boolean")
+        );
+        assert_eq!(
+            tool["function"]["parameters"]["required"],
+            json!(["include", "options", "pattern"])
+        );
+    }
+
+    /// Tests that tool calls round-trip through `AlpacaToolCall` and the Ollama format.
+    #[test]
+    fn test_tool_call_round_trip() {
+        let json = r#"{"function":"read_file","arguments":{"file_name":"Cargo.toml"}}"#;
+        let tool_call = ToolCall::from_str(json).unwrap();
+        assert_eq!(tool_call.function, "read_file");
+
+        let alpaca_call = AlpacaToolCall::from(&tool_call);
+        assert_eq!(alpaca_call.function(), Some("read_file"));
+        assert_eq!(
+            alpaca_call.as_value(),
+            &serde_json::from_str::<Value>(json).unwrap()
+        );
+
+        let ollama_call = tool_call.to_ollama_tool_call();
+        assert_eq!(ollama_call["function"]["name"], json!("read_file"));
+        assert_eq!(
+            ToolCall::from_ollama_tool_call(&ollama_call).unwrap(),
+            tool_call
+        );
+    }
+
+    /// Tests that a malformed Ollama tool call is rejected.
+    #[test]
+    fn test_from_ollama_tool_call_invalid() {
+        let result = ToolCall::from_ollama_tool_call(&json!({"name": "read_file"}));
+        assert_eq!(result.err(), Some(AlpacaToolParseError::MissingFunction));
+    }
+}
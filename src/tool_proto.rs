@@ -1,3 +1,5 @@
+use crate::tool_error::AlpacaToolParseError;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 // ===
@@ -7,6 +9,8 @@ use serde_json::Value;
 ///
 /// This enum defines the standard data types that can be used for tool parameters
 /// when interacting with Alpaca language models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AlpacaToolParameterType {
     /// String data type
     String,
@@ -74,13 +78,70 @@ impl AlpacaToolProto {
     /// # Returns
     ///
     /// * `Ok(AlpacaToolProto)` if parsing was successful
-    /// * `Err(())` if the string could not be parsed as valid JSON
-    pub fn from_string(json: &str) -> Result<AlpacaToolProto, ()> {
-        let object = serde_json::from_str(json);
-        match object {
-            Ok(object) => Ok(AlpacaToolProto { object }),
-            Err(_) => Err(()),
+    /// * `Err(AlpacaToolParseError)` if the string is not valid JSON or does not
+    ///   have the shape of a tool prototype
+    pub fn from_string(json: &str) -> Result<AlpacaToolProto, AlpacaToolParseError> {
+        let object: Value =
+            serde_json::from_str(json).map_err(|e| AlpacaToolParseError::from_json_error(&e))?;
+
+        Self::from_value(object)
+    }
+
+    /// Creates a tool prototype from an already parsed JSON value.
+    ///
+    /// # Arguments
+    ///
+    /// * `object` - A JSON value that represents a tool prototype
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AlpacaToolProto)` if the value has the shape of a tool prototype
+    /// * `Err(AlpacaToolParseError)` describing the first problem found otherwise
+    pub fn from_value(object: Value) -> Result<AlpacaToolProto, AlpacaToolParseError> {
+        if !object.is_object() {
+            return Err(AlpacaToolParseError::NotAnObject);
+        }
+
+        // The function name is required and must be a string
+        match object.get(FUNCTION) {
+            None => return Err(AlpacaToolParseError::MissingFunction),
+            Some(function) if !function.is_string() => {
+                return Err(AlpacaToolParseError::WrongType {
+                    field: FUNCTION.to_string(),
+                    expected: "string",
+                });
+            }
+            Some(_) => {}
+        }
+
+        // The description is optional, but must be a string when present
+        if object
+            .get(DESCRIPTION)
+            .is_some_and(|value| !value.is_string())
+        {
+            return Err(AlpacaToolParseError::WrongType {
+                field: DESCRIPTION.to_string(),
+                expected: "string",
+            });
         }
+
+        // The parameters are optional, but must be an object when present
+        if object
+            .get(PARAMETERS)
+            .is_some_and(|value| !value.is_object())
+        {
+            return Err(AlpacaToolParseError::WrongType {
+                field: PARAMETERS.to_string(),
+                expected: "object",
+            });
+        }
+
+        Ok(AlpacaToolProto { object })
+    }
+
+    /// Gets the underlying JSON representation of the tool prototype.
+    pub fn as_value(&self) -> &Value {
+        &self.object
     }
 
     /// Serializes the tool prototype to a pretty-printed JSON string.
@@ -184,6 +245,28 @@ mod tests {
         assert!(result.is_err());
     }
 
+    /// Tests that a prototype without a function name is rejected.
+    #[test]
+    fn test_from_string_missing_function() {
+        let json = r#"{"parameters":{"param1":"string"}}"#;
+        let result = AlpacaToolProto::from_string(json);
+        assert_eq!(result.err(), Some(AlpacaToolParseError::MissingFunction));
+    }
+
+    /// Tests that a prototype whose parameters are not an object is rejected.
+    #[test]
+    fn test_from_string_parameters_wrong_type() {
+        let json = r#"{"function":"test_func","parameters":["param1"]}"#;
+        let result = AlpacaToolProto::from_string(json);
+        assert_eq!(
+            result.err(),
+            Some(AlpacaToolParseError::WrongType {
+                field: PARAMETERS.to_string(),
+                expected: "object",
+            })
+        );
+    }
+
     /// Tests the pretty-printing JSON serialization of an `AlpacaToolProto`.
     ///
     /// Verifies that the to_string_pretty method produces a properly formatted JSON string